@@ -0,0 +1,400 @@
+//! AUTOSAR ARXML (4.x) communication matrix parser
+//!
+//! Frames come from `CAN-FRAME-TRIGGERING`s, signals from the
+//! `I-SIGNAL-TO-I-PDU-MAPPING`s of the PDUs mapped into each frame.
+//! `MULTIPLEXED-I-PDU`s become a selector signal with multiplexed signals,
+//! and compu-methods provide scaling, units and value tables.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use roxmltree::{Document, Node};
+
+use crate::signal_db::{
+    AttributeValue, ByteOrder, Message, Multiplex, Signal, SignalDatabase, ValueType,
+    GEN_SIG_START_VALUE, MAX_MESSAGE_SIZE, MAX_SIGNAL_BITS,
+};
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn descendant<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    node.descendants()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &'static str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+        None => text.parse().ok(),
+    }
+}
+
+fn child_number(node: Node, name: &'static str) -> Option<f64> {
+    child_text(node, name).and_then(parse_number)
+}
+
+fn short_name<'a>(node: Node<'a, '_>) -> &'a str {
+    child_text(node, "SHORT-NAME").unwrap_or("")
+}
+
+fn description(node: Node) -> Option<String> {
+    let desc = child(node, "DESC")?;
+    descendant(desc, "L-2")
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// Convert the LSB position used by AUTOSAR into the DBC start bit (MSB)
+fn msb_from_lsb(lsb: u32, length: u32) -> Option<u32> {
+    let mut pos = lsb;
+    for _ in 1..length {
+        pos = if pos % 8 == 7 {
+            pos.checked_sub(15)?
+        } else {
+            pos + 1
+        };
+    }
+    Some(pos)
+}
+
+struct Arxml<'a, 'input> {
+    by_path: HashMap<String, Node<'a, 'input>>,
+}
+
+impl<'a, 'input> Arxml<'a, 'input> {
+    fn index(node: Node<'a, 'input>, path: &str, by_path: &mut HashMap<String, Node<'a, 'input>>) {
+        for element in node.children().filter(|n| n.is_element()) {
+            match child_text(element, "SHORT-NAME") {
+                Some(name) => {
+                    let element_path = format!("{}/{}", path, name);
+                    Self::index(element, &element_path, by_path);
+                    by_path.insert(element_path, element);
+                }
+                None => Self::index(element, path, by_path),
+            }
+        }
+    }
+
+    /// Follow the reference held by the `name` child of `node`
+    fn reference(&self, node: Node, name: &'static str) -> Option<Node<'a, 'input>> {
+        self.resolve(child(node, name)?)
+    }
+
+    fn resolve(&self, reference: Node) -> Option<Node<'a, 'input>> {
+        self.by_path.get(reference.text()?.trim()).copied()
+    }
+
+    /// First `name` reference found anywhere below `node`
+    fn deep_reference(&self, node: Node, name: &'static str) -> Option<Node<'a, 'input>> {
+        self.resolve(descendant(node, name)?)
+    }
+
+    fn apply_compu_method(&self, signal: &mut Signal, method: Node) {
+        if let Some(unit) = self.reference(method, "UNIT-REF") {
+            signal.unit = child_text(unit, "DISPLAY-NAME")
+                .unwrap_or(short_name(unit))
+                .to_string();
+        }
+        let Some(scales) =
+            child(method, "COMPU-INTERNAL-TO-PHYS").and_then(|n| child(n, "COMPU-SCALES"))
+        else {
+            return;
+        };
+
+        let mut table = BTreeMap::new();
+        for scale in children(scales, "COMPU-SCALE") {
+            let lower = child_number(scale, "LOWER-LIMIT");
+            let upper = child_number(scale, "UPPER-LIMIT").or(lower);
+
+            if let Some(text) = child(scale, "COMPU-CONST").and_then(|n| child_text(n, "VT")) {
+                if let (Some(lower), Some(upper)) = (lower, upper) {
+                    let (lower, upper) = (lower as i64, upper as i64);
+                    // Plage étendue : seule la borne basse reçoit le libellé
+                    let upper = if upper - lower > 255 { lower } else { upper };
+                    for value in lower..=upper {
+                        table.insert(value, text.to_string());
+                    }
+                }
+            }
+
+            if let Some(coeffs) = child(scale, "COMPU-RATIONAL-COEFFS") {
+                let values = |name| -> Vec<f64> {
+                    child(coeffs, name)
+                        .into_iter()
+                        .flat_map(|n| children(n, "V"))
+                        .filter_map(|v| v.text().and_then(parse_number))
+                        .collect()
+                };
+                let numerator = values("COMPU-NUMERATOR");
+                let denominator = values("COMPU-DENOMINATOR").first().copied().unwrap_or(1.0);
+                if denominator != 0.0 {
+                    signal.offset = numerator.first().copied().unwrap_or(0.0) / denominator;
+                    signal.factor = numerator.get(1).copied().unwrap_or(1.0) / denominator;
+                }
+                if let (Some(lower), Some(upper)) = (lower, upper) {
+                    let (a, b) = (signal.physical(lower), signal.physical(upper));
+                    signal.minimum = a.min(b);
+                    signal.maximum = a.max(b);
+                }
+            }
+        }
+        signal.value_table = table;
+    }
+
+    fn load_signal(
+        &self,
+        isignal: Node,
+        lsb: u32,
+        byte_order: ByteOrder,
+    ) -> Result<Signal, String> {
+        let name = short_name(isignal);
+        let length = child_number(isignal, "LENGTH")
+            .ok_or_else(|| format!("{}: missing LENGTH", name))? as u32;
+        if length == 0 || length > 64 {
+            return Err(format!("{}: invalid length {}", name, length));
+        }
+        if lsb >= MAX_SIGNAL_BITS {
+            return Err(format!("{}: invalid start position {}", name, lsb));
+        }
+        let start_bit = match byte_order {
+            ByteOrder::LittleEndian => lsb,
+            ByteOrder::BigEndian => msb_from_lsb(lsb, length)
+                .ok_or_else(|| format!("{}: invalid start position {}", name, lsb))?,
+        };
+
+        let mut signal = Signal::new(name, start_bit, length, byte_order);
+        let system_signal = self.reference(isignal, "SYSTEM-SIGNAL-REF");
+        let network_props = child(isignal, "NETWORK-REPRESENTATION-PROPS");
+
+        if let Some(base_type) = network_props.and_then(|p| self.deep_reference(p, "BASE-TYPE-REF"))
+        {
+            signal.value_type = match child_text(base_type, "BASE-TYPE-ENCODING") {
+                Some("2C") => ValueType::Signed,
+                Some("IEEE754") if length == 32 => ValueType::Float32,
+                Some("IEEE754") if length == 64 => ValueType::Float64,
+                _ => ValueType::Unsigned,
+            };
+        }
+
+        let physical_props = system_signal.and_then(|s| child(s, "PHYSICAL-PROPS"));
+        let compu_method = network_props
+            .and_then(|p| self.deep_reference(p, "COMPU-METHOD-REF"))
+            .or_else(|| physical_props.and_then(|p| self.deep_reference(p, "COMPU-METHOD-REF")));
+        if let Some(method) = compu_method {
+            self.apply_compu_method(&mut signal, method);
+        }
+        if signal.unit.is_empty() {
+            if let Some(unit) = physical_props.and_then(|p| self.deep_reference(p, "UNIT-REF")) {
+                signal.unit = child_text(unit, "DISPLAY-NAME")
+                    .unwrap_or(short_name(unit))
+                    .to_string();
+            }
+        }
+
+        if let Some(init) = child(isignal, "INIT-VALUE")
+            .and_then(|n| descendant(n, "VALUE"))
+            .and_then(|n| n.text())
+            .and_then(parse_number)
+        {
+            signal.attributes.insert(
+                GEN_SIG_START_VALUE.to_string(),
+                AttributeValue::Number(init),
+            );
+        }
+
+        signal.comment = system_signal
+            .and_then(description)
+            .or_else(|| description(isignal));
+        Ok(signal)
+    }
+
+    /// Append the signals of `pdu`, placed at `offset` bits in the frame
+    fn load_pdu(
+        &self,
+        pdu: Node,
+        offset: u32,
+        multiplex: Multiplex,
+        signals: &mut Vec<Signal>,
+    ) -> Result<(), String> {
+        if pdu.tag_name().name() == "MULTIPLEXED-I-PDU" {
+            // Pas de multiplexage imbriqué
+            if multiplex != Multiplex::None {
+                return Ok(());
+            }
+            let position = child_number(pdu, "SELECTOR-FIELD-START-POSITION").unwrap_or(0.0);
+            let length = child_number(pdu, "SELECTOR-FIELD-LENGTH").unwrap_or(0.0) as u32;
+            if length > 0 {
+                let byte_order = byte_order(child_text(pdu, "SELECTOR-FIELD-BYTE-ORDER"));
+                let lsb = offset.saturating_add(position as u32);
+                let start_bit = match byte_order {
+                    _ if lsb >= MAX_SIGNAL_BITS => None,
+                    ByteOrder::LittleEndian => Some(lsb),
+                    ByteOrder::BigEndian => msb_from_lsb(lsb, length),
+                };
+                let name = format!("{}_Selector", short_name(pdu));
+                let start_bit =
+                    start_bit.ok_or_else(|| format!("{}: invalid start position", name))?;
+                let mut selector = Signal::new(&name, start_bit, length, byte_order);
+                selector.multiplex = Multiplex::Multiplexor;
+                signals.push(selector);
+            }
+
+            for part in pdu
+                .descendants()
+                .filter(|n| n.tag_name().name() == "STATIC-PART")
+            {
+                if let Some(static_pdu) = self.reference(part, "I-PDU-REF") {
+                    self.load_pdu(static_pdu, offset, Multiplex::None, signals)?;
+                }
+            }
+            for alternative in pdu
+                .descendants()
+                .filter(|n| n.tag_name().name() == "DYNAMIC-PART-ALTERNATIVE")
+            {
+                let code = child_number(alternative, "SELECTOR-FIELD-CODE").unwrap_or(0.0);
+                if let Some(dynamic_pdu) = self.reference(alternative, "I-PDU-REF") {
+                    let multiplex = if length > 0 {
+                        Multiplex::Multiplexed(code as u64)
+                    } else {
+                        Multiplex::None
+                    };
+                    self.load_pdu(dynamic_pdu, offset, multiplex, signals)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let Some(mappings) = child(pdu, "I-SIGNAL-TO-PDU-MAPPINGS") else {
+            return Ok(());
+        };
+        for mapping in children(mappings, "I-SIGNAL-TO-I-PDU-MAPPING") {
+            // Les groupes de signaux n'ont pas de position propre
+            let Some(isignal) = self.reference(mapping, "I-SIGNAL-REF") else {
+                continue;
+            };
+            let position = child_number(mapping, "START-POSITION").unwrap_or(0.0) as u32;
+            let byte_order = byte_order(child_text(mapping, "PACKING-BYTE-ORDER"));
+            let mut signal =
+                self.load_signal(isignal, offset.saturating_add(position), byte_order)?;
+            signal.multiplex = multiplex;
+            signals.push(signal);
+        }
+        Ok(())
+    }
+
+    /// Transmitting and receiving ECUs of a frame triggering
+    fn frame_ecus(&self, triggering: Node) -> (Option<String>, Vec<String>) {
+        let mut transmitter = None;
+        let mut receivers = Vec::new();
+        let ports = child(triggering, "FRAME-PORT-REFS")
+            .into_iter()
+            .flat_map(|n| children(n, "FRAME-PORT-REF"))
+            .filter_map(|r| self.resolve(r));
+        for port in ports {
+            let Some(ecu) = port
+                .ancestors()
+                .find(|n| n.tag_name().name() == "ECU-INSTANCE")
+            else {
+                continue;
+            };
+            let ecu = short_name(ecu).to_string();
+            match child_text(port, "COMMUNICATION-DIRECTION") {
+                Some("OUT") => transmitter = Some(ecu),
+                Some("IN") if !receivers.contains(&ecu) => receivers.push(ecu),
+                _ => {}
+            }
+        }
+        (transmitter, receivers)
+    }
+}
+
+fn byte_order(text: Option<&str>) -> ByteOrder {
+    match text {
+        Some("MOST-SIGNIFICANT-BYTE-FIRST") => ByteOrder::BigEndian,
+        _ => ByteOrder::LittleEndian,
+    }
+}
+
+/// Parse an ARXML document
+pub fn parse(text: &str) -> Result<SignalDatabase, String> {
+    let doc = Document::parse(text).map_err(|e| e.to_string())?;
+    let mut by_path = HashMap::new();
+    Arxml::index(doc.root_element(), "", &mut by_path);
+    let arxml = Arxml { by_path };
+
+    let mut db = SignalDatabase::default();
+    db.nodes = doc
+        .descendants()
+        .filter(|n| n.tag_name().name() == "ECU-INSTANCE")
+        .map(|n| short_name(n).to_string())
+        .collect();
+
+    let mut seen = HashSet::new();
+    for triggering in doc
+        .descendants()
+        .filter(|n| n.tag_name().name() == "CAN-FRAME-TRIGGERING")
+    {
+        let name = short_name(triggering);
+        let id = child_number(triggering, "IDENTIFIER")
+            .ok_or_else(|| format!("{}: missing IDENTIFIER", name))? as u32;
+        let extended = child_text(triggering, "CAN-ADDRESSING-MODE") == Some("EXTENDED");
+        // Une trame déclenchée sur plusieurs canaux n'est chargée qu'une fois
+        if !seen.insert((id, extended)) {
+            continue;
+        }
+        let Some(frame) = arxml.reference(triggering, "FRAME-REF") else {
+            return Err(format!("{}: unresolved FRAME-REF", name));
+        };
+
+        let mut signals = Vec::new();
+        for mapping in frame
+            .descendants()
+            .filter(|n| n.tag_name().name() == "PDU-TO-FRAME-MAPPING")
+        {
+            let Some(pdu) = arxml.reference(mapping, "PDU-REF") else {
+                continue;
+            };
+            let offset = child_number(mapping, "START-POSITION").unwrap_or(0.0) as u32;
+            arxml.load_pdu(pdu, offset, Multiplex::None, &mut signals)?;
+        }
+
+        let size = child_number(frame, "FRAME-LENGTH").unwrap_or(0.0) as u32;
+        if size > MAX_MESSAGE_SIZE {
+            return Err(format!("{}: invalid FRAME-LENGTH {}", name, size));
+        }
+
+        let (transmitter, receivers) = arxml.frame_ecus(triggering);
+        for signal in &mut signals {
+            signal.receivers = receivers.clone();
+        }
+
+        db.messages.push(Message {
+            id,
+            extended,
+            name: short_name(frame).to_string(),
+            size,
+            transmitter,
+            signals,
+            comment: description(frame),
+            attributes: HashMap::new(),
+        });
+    }
+
+    db.finish();
+    Ok(db)
+}
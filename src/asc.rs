@@ -0,0 +1,385 @@
+//! Vector ASC text trace format
+//!
+//! Reads classic (`1  123  Rx  d 8 ...`), remote, `ErrorFrame` and `CANFD`
+//! event lines; other events (statistics, status, markers) are skipped.
+//! Timestamps are offsets from the `date` header, or from the previous
+//! event with `timestamps relative`.
+
+use neon::prelude::*;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::trace::{
+    dlc_to_len, frames_from_js, frames_to_js, ChannelMap, DateTime, Direction, TraceFrame,
+};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// CAN FD flags of the ASC `CANFD` event (EDL, BRS, ESI)
+const FD_FLAG_EDL: u32 = 0x1000;
+const FD_FLAG_BRS: u32 = 0x2000;
+const FD_FLAG_ESI: u32 = 0x4000;
+
+/// Parse a header date such as `Wed Oct 11 02:40:00.123 pm 2023`
+///
+/// Only English month names are understood; `None` otherwise.
+pub fn parse_date(text: &str) -> Option<DateTime> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    let (month, day, time, meridiem, year) = match fields[..] {
+        [_, month, day, time, meridiem, year] => (month, day, time, Some(meridiem), year),
+        [_, month, day, time, year] => (month, day, time, None, year),
+        _ => return None,
+    };
+
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? as u32 + 1;
+    let (hms, millis) = time.split_once('.').unwrap_or((time, "0"));
+    let mut hms = hms.split(':').map(|v| v.parse::<u32>().ok());
+    let (mut hour, minute, second) = (hms.next()??, hms.next()??, hms.next()??);
+    match meridiem.map(|m| m.to_ascii_lowercase()).as_deref() {
+        Some("am") if hour == 12 => hour = 0,
+        Some("pm") if hour < 12 => hour += 12,
+        Some("am") | Some("pm") | None => {}
+        Some(_) => return None,
+    }
+
+    Some(DateTime {
+        year: year.parse().ok()?,
+        month,
+        day: day.parse().ok()?,
+        hour,
+        minute,
+        second,
+        millisecond: format!("{:0<3}", millis).get(..3)?.parse().ok()?,
+    })
+}
+
+/// Format a header date, 12-hour clock as written by Vector tools
+pub fn format_date(date: DateTime) -> String {
+    let (hour, meridiem) = match date.hour {
+        0 => (12, "am"),
+        1..=11 => (date.hour, "am"),
+        12 => (12, "pm"),
+        _ => (date.hour - 12, "pm"),
+    };
+    format!(
+        "{} {} {:02} {:02}:{:02}:{:02}.{:03} {} {}",
+        WEEKDAYS[date.weekday() as usize],
+        MONTHS[date.month as usize - 1],
+        date.day,
+        hour,
+        date.minute,
+        date.second,
+        date.millisecond,
+        meridiem,
+        date.year
+    )
+}
+
+struct Parser {
+    radix: u32,
+    relative: bool,
+    start: Duration,
+    previous: Duration,
+}
+
+impl Parser {
+    fn number(&self, text: &str) -> Result<u32, String> {
+        u32::from_str_radix(text, self.radix).map_err(|_| format!("invalid number '{}'", text))
+    }
+
+    fn id(&self, text: &str) -> Result<(u32, bool), String> {
+        match text.strip_suffix(['x', 'X']) {
+            Some(id) => Ok((self.number(id)?, true)),
+            None => Ok((self.number(text)?, false)),
+        }
+    }
+
+    fn bytes(&self, fields: &[&str], len: usize) -> Result<Vec<u8>, String> {
+        if fields.len() < len {
+            return Err(format!("expected {} data bytes", len));
+        }
+        fields[..len]
+            .iter()
+            .map(|b| {
+                u8::from_str_radix(b, self.radix).map_err(|_| format!("invalid data byte '{}'", b))
+            })
+            .collect()
+    }
+
+    fn direction(text: &str) -> Option<Direction> {
+        match text {
+            "Rx" => Some(Direction::Rx),
+            "Tx" | "TxRq" => Some(Direction::Tx),
+            _ => None,
+        }
+    }
+
+    /// `<channel> <id> <dir> d <dlc> <data>` or `<channel> <id> <dir> r [<dlc>]`
+    fn classic(&self, fields: &[&str]) -> Result<Option<TraceFrame>, String> {
+        let [channel, id, direction, kind, rest @ ..] = fields else {
+            return Ok(None);
+        };
+        let Some(direction) = Self::direction(direction) else {
+            return Ok(None);
+        };
+        let (id, extended) = self.id(id)?;
+        let mut frame = TraceFrame {
+            interface: channel.to_string(),
+            id,
+            extended,
+            direction: Some(direction),
+            ..Default::default()
+        };
+        match *kind {
+            "d" | "D" => {
+                let dlc = self.number(rest.first().ok_or("missing DLC")?)? as u8;
+                frame.data = self.bytes(&rest[1..], dlc_to_len(dlc, false))?;
+            }
+            "r" | "R" => {
+                frame.remote = true;
+                frame.remote_len = match rest.first() {
+                    Some(dlc) => self.number(dlc).map(|d| d.min(8) as u8).unwrap_or(0),
+                    None => 0,
+                };
+            }
+            _ => return Ok(None),
+        }
+        Ok(Some(frame))
+    }
+
+    /// `CANFD <channel> <dir> <id> [<name>] <brs> <esi> <dlc> <len> <data> ...`
+    fn fd(&self, fields: &[&str]) -> Result<Option<TraceFrame>, String> {
+        let [channel, direction, id, rest @ ..] = fields else {
+            return Ok(None);
+        };
+        let Some(direction) = Self::direction(direction) else {
+            return Ok(None);
+        };
+        // Nom symbolique optionnel avant les indicateurs BRS/ESI
+        let rest = match rest.first() {
+            Some(&"0") | Some(&"1") => rest,
+            Some(_) => &rest[1..],
+            None => return Err("truncated CANFD event".to_string()),
+        };
+        let [brs, esi, _dlc, len, data @ ..] = rest else {
+            return Err("truncated CANFD event".to_string());
+        };
+        let len: usize = len
+            .parse()
+            .map_err(|_| format!("invalid data length '{}'", len))?;
+        if len > 64 {
+            return Err(format!("invalid data length {}", len));
+        }
+
+        let (id, extended) = self.id(id)?;
+        Ok(Some(TraceFrame {
+            interface: channel.to_string(),
+            id,
+            extended,
+            fd: true,
+            brs: *brs == "1",
+            esi: *esi == "1",
+            direction: Some(direction),
+            data: self.bytes(data, len)?,
+            ..Default::default()
+        }))
+    }
+
+    fn event(&mut self, line: &str) -> Result<Option<TraceFrame>, String> {
+        let mut fields = line.split_whitespace();
+        let Some(Ok(seconds)) = fields.next().map(str::parse::<f64>) else {
+            return Ok(None);
+        };
+        // En mode relatif, chaque événement (même ignoré) sert de référence au suivant
+        let offset = Duration::try_from_secs_f64(seconds)
+            .map_err(|_| format!("invalid timestamp {}", seconds))?;
+        let base = if self.relative {
+            self.previous
+        } else {
+            self.start
+        };
+        let timestamp = base
+            .checked_add(offset)
+            .ok_or_else(|| "timestamp out of range".to_string())?;
+        self.previous = timestamp;
+
+        let fields: Vec<&str> = fields.collect();
+        let frame = match fields[..] {
+            ["CANFD", ref rest @ ..] => self.fd(rest)?,
+            [channel, "ErrorFrame", ..] if channel.parse::<u16>().is_ok() => Some(TraceFrame {
+                interface: channel.to_string(),
+                error: true,
+                ..Default::default()
+            }),
+            [channel, ..] if channel.parse::<u16>().is_ok() => self.classic(&fields)?,
+            _ => None,
+        };
+        Ok(frame.map(|frame| TraceFrame { timestamp, ..frame }))
+    }
+}
+
+/// Parse an ASC trace
+pub fn parse(text: &str) -> Result<Vec<TraceFrame>, String> {
+    let mut parser = Parser {
+        radix: 16,
+        relative: false,
+        start: Duration::ZERO,
+        previous: Duration::ZERO,
+    };
+    let mut frames = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let lower = line.to_ascii_lowercase();
+        if let Some(date) = lower.strip_prefix("date ") {
+            if let Some(start) = parse_date(date).and_then(DateTime::to_unix) {
+                parser.start = start;
+                parser.previous = start;
+            }
+        } else if lower.starts_with("base ") {
+            let fields: Vec<&str> = lower.split_whitespace().collect();
+            parser.radix = if fields.get(1) == Some(&"dec") {
+                10
+            } else {
+                16
+            };
+            parser.relative = fields.get(3) == Some(&"relative");
+        } else {
+            match parser.event(line) {
+                Ok(Some(frame)) => frames.push(frame),
+                Ok(None) => {}
+                Err(e) => return Err(format!("line {}: {}", index + 1, e)),
+            }
+        }
+    }
+    Ok(frames)
+}
+
+fn format_id(frame: &TraceFrame) -> String {
+    if frame.extended {
+        format!("{:X}x", frame.id)
+    } else {
+        format!("{:X}", frame.id)
+    }
+}
+
+/// Format frames as an ASC trace, timestamps relative to the first frame
+///
+/// CAN XL frames have no ASC representation and are skipped.
+pub fn format(frames: &[TraceFrame]) -> String {
+    let start = frames.first().map_or(Duration::ZERO, |f| f.timestamp);
+    let date = format_date(DateTime::from_unix(start));
+    let mut out = String::new();
+    let _ = writeln!(out, "date {}", date);
+    out.push_str("base hex  timestamps absolute\n");
+    out.push_str("internal events logged\n");
+    let _ = writeln!(out, "Begin Triggerblock {}", date);
+    out.push_str("   0.000000 Start of measurement\n");
+
+    let mut channels = ChannelMap::default();
+    for frame in frames.iter().filter(|frame| frame.xl.is_none()) {
+        let seconds = frame.timestamp.saturating_sub(start).as_secs_f64();
+        let channel = channels.channel(&frame.interface);
+        let direction = match frame.direction {
+            Some(Direction::Tx) => "Tx",
+            _ => "Rx",
+        };
+        let data = frame
+            .data
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let _ = write!(out, "{:>11.6} ", seconds);
+        if frame.error {
+            let _ = writeln!(out, "{}  ErrorFrame", channel);
+        } else if frame.fd {
+            let flags = FD_FLAG_EDL
+                | if frame.brs { FD_FLAG_BRS } else { 0 }
+                | if frame.esi { FD_FLAG_ESI } else { 0 };
+            let _ = writeln!(
+                out,
+                "CANFD {:>3} {:<4} {:>8} {} {} {:x} {:>2} {} {:>8} {:>4} {:>8X} {:>8} {:>8} {:>8} {:>8} {:>8}",
+                channel,
+                direction,
+                format_id(frame),
+                frame.brs as u8,
+                frame.esi as u8,
+                frame.dlc(),
+                frame.data.len(),
+                data,
+                0,
+                0,
+                flags,
+                0,
+                0,
+                0,
+                0,
+                0
+            );
+        } else if frame.remote {
+            let _ = writeln!(
+                out,
+                "{}  {:<15} {:<4} r {:x}",
+                channel,
+                format_id(frame),
+                direction,
+                frame.remote_len
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "{}  {:<15} {:<4} d {:x} {}",
+                channel,
+                format_id(frame),
+                direction,
+                frame.data.len(),
+                data
+            );
+        }
+    }
+    out.push_str("End TriggerBlock\n");
+    out
+}
+
+/// Parse an ASC trace from JavaScript
+pub fn parse_asc_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let text = cx.argument::<JsString>(0)?.value(&mut cx);
+    match parse(&text) {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to parse ASC trace: {}", e)),
+    }
+}
+
+/// Read an ASC trace file from JavaScript
+pub fn read_asc_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let result = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| parse(&String::from_utf8_lossy(&bytes)));
+    match result {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+/// Format frames as an ASC trace from JavaScript
+pub fn format_asc_log(mut cx: FunctionContext) -> JsResult<JsString> {
+    let array = cx.argument::<JsArray>(0)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    Ok(cx.string(format(&frames)))
+}
+
+/// Write frames to an ASC trace file from JavaScript
+pub fn write_asc_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let array = cx.argument::<JsArray>(1)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    match std::fs::write(&path, format(&frames)) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to write {}: {}", path, e)),
+    }
+}
@@ -0,0 +1,448 @@
+//! Vector BLF binary trace format
+//!
+//! A 144-byte `LOGG` file header is followed by `LOBJ` log containers,
+//! uncompressed or zlib-compressed, whose concatenated payload is the
+//! stream of CAN objects. Written files use uncompressed containers.
+
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use std::time::Duration;
+
+use crate::trace::{
+    dlc_to_len, frames_from_js, frames_to_js, ChannelMap, DateTime, Direction, TraceFrame,
+};
+
+const FILE_SIGNATURE: &[u8; 4] = b"LOGG";
+const OBJECT_SIGNATURE: &[u8; 4] = b"LOBJ";
+const FILE_HEADER_SIZE: usize = 144;
+const OBJECT_HEADER_BASE_SIZE: usize = 16;
+const OBJECT_HEADER_V1_SIZE: usize = 16;
+const CONTAINER_HEADER_SIZE: usize = 16;
+/// Uncompressed payload of each written container
+const MAX_CONTAINER_SIZE: usize = 128 * 1024;
+
+const CAN_MESSAGE: u32 = 1;
+const LOG_CONTAINER: u32 = 10;
+const CAN_ERROR_EXT: u32 = 73;
+const CAN_MESSAGE2: u32 = 86;
+const CAN_FD_MESSAGE: u32 = 100;
+const CAN_FD_MESSAGE_64: u32 = 101;
+
+const NO_COMPRESSION: u16 = 0;
+const ZLIB_DEFLATE: u16 = 2;
+
+/// Object header flag: timestamps in units of 10 µs (otherwise 1 ns)
+const TIME_TEN_MICS: u32 = 0x0000_0001;
+const TIME_ONE_NANS: u32 = 0x0000_0002;
+
+const CAN_MSG_EXT: u32 = 0x8000_0000;
+const CAN_MSG_DIR_TX: u8 = 0x01;
+const CAN_MSG_REMOTE: u8 = 0x80;
+const FD_EDL: u8 = 0x01;
+const FD_BRS: u8 = 0x02;
+const FD_ESI: u8 = 0x04;
+const FD64_REMOTE: u32 = 0x0010;
+const FD64_EDL: u32 = 0x1000;
+const FD64_BRS: u32 = 0x2000;
+const FD64_ESI: u32 = 0x4000;
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+/// SYSTEMTIME at `pos`, dates before 1970 giving the epoch
+fn read_systemtime(data: &[u8], pos: usize) -> Result<Duration, String> {
+    let field = |i: usize| u16_at(data, pos + 2 * i) as u32;
+    let date = DateTime {
+        year: field(0) as i32,
+        month: field(1),
+        day: field(3),
+        hour: field(4),
+        minute: field(5),
+        second: field(6),
+        millisecond: field(7),
+    };
+    if !date.is_valid() {
+        return Err(format!("invalid SYSTEMTIME at offset {}", pos));
+    }
+    Ok(date.to_unix().unwrap_or_default())
+}
+
+fn write_systemtime(out: &mut Vec<u8>, time: Duration) {
+    let date = DateTime::from_unix(time);
+    let fields = [
+        date.year as u32,
+        date.month,
+        date.weekday(),
+        date.day,
+        date.hour,
+        date.minute,
+        date.second,
+        date.millisecond,
+    ];
+    for field in fields {
+        out.extend_from_slice(&(field as u16).to_le_bytes());
+    }
+}
+
+/// Split the CAN id word of BLF objects into id and extended flag
+fn split_id(word: u32) -> (u32, bool) {
+    (word & !CAN_MSG_EXT, word & CAN_MSG_EXT != 0)
+}
+
+fn direction(tx: bool) -> Option<Direction> {
+    Some(if tx { Direction::Tx } else { Direction::Rx })
+}
+
+/// Decode one CAN object body, `None` for other object types
+fn decode_object(kind: u32, body: &[u8]) -> Result<Option<TraceFrame>, String> {
+    let too_short = || format!("object type {} too short", kind);
+    let frame = match kind {
+        CAN_MESSAGE | CAN_MESSAGE2 => {
+            if body.len() < 16 {
+                return Err(too_short());
+            }
+            let flags = body[2];
+            let dlc = body[3];
+            let (id, extended) = split_id(u32_at(body, 4));
+            let remote = flags & CAN_MSG_REMOTE != 0;
+            TraceFrame {
+                interface: u16_at(body, 0).to_string(),
+                id,
+                extended,
+                remote,
+                remote_len: if remote { dlc.min(8) } else { 0 },
+                direction: direction(flags & CAN_MSG_DIR_TX != 0),
+                data: if remote {
+                    Vec::new()
+                } else {
+                    body[8..8 + dlc_to_len(dlc, false)].to_vec()
+                },
+                ..Default::default()
+            }
+        }
+        CAN_FD_MESSAGE => {
+            if body.len() < 84 {
+                return Err(too_short());
+            }
+            let flags = body[2];
+            let dlc = body[3];
+            let (id, extended) = split_id(u32_at(body, 4));
+            let fd_flags = body[13];
+            let fd = fd_flags & FD_EDL != 0;
+            let remote = flags & CAN_MSG_REMOTE != 0;
+            let len = (body[14] as usize).min(if fd { 64 } else { 8 });
+            TraceFrame {
+                interface: u16_at(body, 0).to_string(),
+                id,
+                extended,
+                fd,
+                brs: fd_flags & FD_BRS != 0,
+                esi: fd_flags & FD_ESI != 0,
+                remote,
+                remote_len: if remote { dlc.min(8) } else { 0 },
+                direction: direction(flags & CAN_MSG_DIR_TX != 0),
+                data: if remote {
+                    Vec::new()
+                } else {
+                    body[20..20 + len].to_vec()
+                },
+                ..Default::default()
+            }
+        }
+        CAN_FD_MESSAGE_64 => {
+            if body.len() < 40 {
+                return Err(too_short());
+            }
+            let dlc = body[1];
+            let len = body[2] as usize;
+            let (id, extended) = split_id(u32_at(body, 4));
+            let flags = u32_at(body, 12);
+            let fd = flags & FD64_EDL != 0;
+            let remote = flags & FD64_REMOTE != 0;
+            let data = body
+                .get(40..40 + len.min(64))
+                .ok_or_else(too_short)?
+                .to_vec();
+            TraceFrame {
+                interface: body[0].to_string(),
+                id,
+                extended,
+                fd,
+                brs: flags & FD64_BRS != 0,
+                esi: flags & FD64_ESI != 0,
+                remote,
+                remote_len: if remote { dlc.min(8) } else { 0 },
+                direction: direction(body[34] != 0),
+                data: if remote { Vec::new() } else { data },
+                ..Default::default()
+            }
+        }
+        CAN_ERROR_EXT => {
+            if body.len() < 32 {
+                return Err(too_short());
+            }
+            TraceFrame {
+                interface: u16_at(body, 0).to_string(),
+                error: true,
+                ..Default::default()
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(frame))
+}
+
+/// Parse a BLF file
+pub fn parse(data: &[u8]) -> Result<Vec<TraceFrame>, String> {
+    if data.len() < 72 || &data[..4] != FILE_SIGNATURE {
+        return Err("not a BLF file (missing LOGG signature)".to_string());
+    }
+    let header_size = u32_at(data, 4) as usize;
+    let start = read_systemtime(data, 40)?;
+
+    // Concaténation du contenu des containers : un objet peut être à cheval sur deux containers
+    let mut stream = Vec::new();
+    let mut pos = header_size;
+    while pos + OBJECT_HEADER_BASE_SIZE <= data.len() {
+        if &data[pos..pos + 4] != OBJECT_SIGNATURE {
+            return Err(format!("missing LOBJ signature at offset {}", pos));
+        }
+        let size = u32_at(data, pos + 8) as usize;
+        let kind = u32_at(data, pos + 12);
+        if size < OBJECT_HEADER_BASE_SIZE {
+            return Err(format!("invalid object size at offset {}", pos));
+        }
+        let object = data
+            .get(pos..pos + size)
+            .ok_or_else(|| format!("truncated object at offset {}", pos))?;
+        if kind == LOG_CONTAINER {
+            let header_size = u16_at(object, 4) as usize;
+            let payload = object
+                .get(header_size + CONTAINER_HEADER_SIZE..)
+                .ok_or("truncated log container")?;
+            let uncompressed_size = u32_at(object, header_size + 8) as usize;
+            match u16_at(object, header_size) {
+                NO_COMPRESSION => stream.extend_from_slice(payload),
+                ZLIB_DEFLATE => stream.extend_from_slice(
+                    &decompress_to_vec_zlib_with_limit(payload, uncompressed_size)
+                        .map_err(|e| format!("log container: {}", e))?,
+                ),
+                method => return Err(format!("unsupported compression method {}", method)),
+            }
+        } else {
+            stream.extend_from_slice(object);
+        }
+        pos += size + size % 4;
+    }
+
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while pos + OBJECT_HEADER_BASE_SIZE + OBJECT_HEADER_V1_SIZE <= stream.len() {
+        if &stream[pos..pos + 4] != OBJECT_SIGNATURE {
+            return Err(format!("missing LOBJ signature at stream offset {}", pos));
+        }
+        let header_size = u16_at(&stream, pos + 4) as usize;
+        let size = u32_at(&stream, pos + 8) as usize;
+        let kind = u32_at(&stream, pos + 12);
+        if header_size < OBJECT_HEADER_BASE_SIZE + OBJECT_HEADER_V1_SIZE || size < header_size {
+            return Err(format!("invalid object header at stream offset {}", pos));
+        }
+        if pos + size > stream.len() {
+            // Dernier objet tronqué (enregistrement interrompu)
+            break;
+        }
+
+        // Les en-têtes V1 et V2 ont les flags et l'horodatage aux mêmes positions
+        let flags = u32_at(&stream, pos + 16);
+        let ticks = u64_at(&stream, pos + 24);
+        let offset = match flags {
+            TIME_TEN_MICS => Duration::from_micros(ticks.saturating_mul(10)),
+            _ => Duration::from_nanos(ticks),
+        };
+        if let Some(mut frame) = decode_object(kind, &stream[pos + header_size..pos + size])? {
+            frame.timestamp = start + offset;
+            frames.push(frame);
+        }
+
+        pos += size;
+        if kind != CAN_FD_MESSAGE_64 {
+            pos += size % 4;
+        }
+    }
+    Ok(frames)
+}
+
+fn encode_object(frame: &TraceFrame, channel: u16) -> Result<(u32, Vec<u8>), String> {
+    let max_len = if frame.fd { 64 } else { 8 };
+    if !frame.error && frame.data.len() > max_len {
+        return Err(format!(
+            "frame 0x{:X}: {} data bytes exceed the {} of a {} frame",
+            frame.id,
+            frame.data.len(),
+            max_len,
+            if frame.fd { "CAN FD" } else { "classic CAN" }
+        ));
+    }
+    let mut body = Vec::with_capacity(84);
+    let direction = if frame.direction == Some(Direction::Tx) {
+        CAN_MSG_DIR_TX
+    } else {
+        0
+    };
+    let id = frame.id | if frame.extended { CAN_MSG_EXT } else { 0 };
+
+    if frame.error {
+        body.extend_from_slice(&channel.to_le_bytes());
+        body.resize(32, 0);
+        return Ok((CAN_ERROR_EXT, body));
+    }
+    if frame.fd {
+        let fd_flags =
+            FD_EDL | if frame.brs { FD_BRS } else { 0 } | if frame.esi { FD_ESI } else { 0 };
+        body.extend_from_slice(&channel.to_le_bytes());
+        body.push(direction);
+        body.push(frame.dlc());
+        body.extend_from_slice(&id.to_le_bytes());
+        body.extend_from_slice(&[0; 5]); // frame_length, bit_count
+        body.push(fd_flags);
+        body.push(frame.data.len() as u8);
+        body.extend_from_slice(&[0; 5]);
+        body.extend_from_slice(&frame.data);
+        body.resize(84, 0);
+        return Ok((CAN_FD_MESSAGE, body));
+    }
+
+    let flags = direction | if frame.remote { CAN_MSG_REMOTE } else { 0 };
+    body.extend_from_slice(&channel.to_le_bytes());
+    body.push(flags);
+    body.push(frame.dlc());
+    body.extend_from_slice(&id.to_le_bytes());
+    body.extend_from_slice(&frame.data);
+    body.resize(16, 0);
+    Ok((CAN_MESSAGE, body))
+}
+
+fn push_container(out: &mut Vec<u8>, payload: &[u8]) {
+    let size = OBJECT_HEADER_BASE_SIZE + CONTAINER_HEADER_SIZE + payload.len();
+    out.extend_from_slice(OBJECT_SIGNATURE);
+    out.extend_from_slice(&(OBJECT_HEADER_BASE_SIZE as u16).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&(size as u32).to_le_bytes());
+    out.extend_from_slice(&LOG_CONTAINER.to_le_bytes());
+    out.extend_from_slice(&NO_COMPRESSION.to_le_bytes());
+    out.extend_from_slice(&[0; 6]);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(payload);
+    out.resize(out.len() + size % 4, 0);
+}
+
+/// Format frames as a BLF file, the first frame giving the start time
+///
+/// CAN XL frames are skipped; payloads too long for their frame type are
+/// rejected.
+pub fn format(frames: &[TraceFrame]) -> Result<Vec<u8>, String> {
+    // SYSTEMTIME est à la milliseconde : les horodatages des objets partent de cette base
+    let first = frames.first().map_or(Duration::ZERO, |f| f.timestamp);
+    let start = Duration::from_millis(first.as_millis() as u64);
+    let stop = frames.last().map_or(Duration::ZERO, |f| f.timestamp);
+
+    let mut stream = Vec::new();
+    let mut channels = ChannelMap::default();
+    let mut count = 0u32;
+    for frame in frames.iter().filter(|frame| frame.xl.is_none()) {
+        count += 1;
+        let (kind, body) = encode_object(frame, channels.channel(&frame.interface))?;
+        let header_size = OBJECT_HEADER_BASE_SIZE + OBJECT_HEADER_V1_SIZE;
+        let size = header_size + body.len();
+        let ticks = frame.timestamp.saturating_sub(start).as_nanos() as u64;
+        stream.extend_from_slice(OBJECT_SIGNATURE);
+        stream.extend_from_slice(&(header_size as u16).to_le_bytes());
+        stream.extend_from_slice(&1u16.to_le_bytes());
+        stream.extend_from_slice(&(size as u32).to_le_bytes());
+        stream.extend_from_slice(&kind.to_le_bytes());
+        stream.extend_from_slice(&TIME_ONE_NANS.to_le_bytes());
+        stream.extend_from_slice(&[0; 4]); // client index, object version
+        stream.extend_from_slice(&ticks.to_le_bytes());
+        stream.extend_from_slice(&body);
+        stream.resize(stream.len() + size % 4, 0);
+    }
+
+    let mut containers = Vec::new();
+    for chunk in stream.chunks(MAX_CONTAINER_SIZE) {
+        push_container(&mut containers, chunk);
+    }
+    let uncompressed_size = FILE_HEADER_SIZE
+        + stream.len()
+        + stream.len().div_ceil(MAX_CONTAINER_SIZE)
+            * (OBJECT_HEADER_BASE_SIZE + CONTAINER_HEADER_SIZE);
+
+    let mut out = Vec::with_capacity(FILE_HEADER_SIZE + containers.len());
+    out.extend_from_slice(FILE_SIGNATURE);
+    out.extend_from_slice(&(FILE_HEADER_SIZE as u32).to_le_bytes());
+    // Application (5 = CANoe), version d'application puis version du format binaire
+    out.extend_from_slice(&[5, 0, 0, 0, 2, 6, 8, 1]);
+    out.extend_from_slice(&((FILE_HEADER_SIZE + containers.len()) as u64).to_le_bytes());
+    out.extend_from_slice(&(uncompressed_size as u64).to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    write_systemtime(&mut out, start);
+    write_systemtime(&mut out, stop);
+    out.resize(FILE_HEADER_SIZE, 0);
+    out.extend_from_slice(&containers);
+    Ok(out)
+}
+
+/// Parse a BLF buffer from JavaScript
+pub fn parse_blf_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let data = cx.argument::<JsBuffer>(0)?.as_slice(&cx).to_vec();
+    match parse(&data) {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to parse BLF trace: {}", e)),
+    }
+}
+
+/// Read a BLF file from JavaScript
+pub fn read_blf_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let result = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| parse(&data));
+    match result {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+/// Format frames as a BLF buffer from JavaScript
+pub fn format_blf_log(mut cx: FunctionContext) -> JsResult<JsBuffer> {
+    let array = cx.argument::<JsArray>(0)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    match format(&frames) {
+        Ok(data) => JsBuffer::from_slice(&mut cx, &data),
+        Err(e) => cx.throw_error(format!("Failed to format BLF trace: {}", e)),
+    }
+}
+
+/// Write frames to a BLF file from JavaScript
+pub fn write_blf_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let array = cx.argument::<JsArray>(1)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    let result =
+        format(&frames).and_then(|data| std::fs::write(&path, data).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to write {}: {}", path, e)),
+    }
+}
@@ -0,0 +1,538 @@
+//! Userspace bridge between two sockets
+//!
+//! A bridge thread reads frames from a source socket and forwards them to a
+//! destination after ID filtering, ID remapping, payload rewriting and rate
+//! limiting, for what the kernel gateway cannot express. An optional hook,
+//! typically a JavaScript callback, may drop or replace each frame. The
+//! thread counts forwarded and dropped frames and the forwarding latency.
+
+use neon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::replay::{self, ReplaySink};
+use crate::trace::{frame_from_js, frame_to_js, TraceFrame};
+use crate::CanSocketWrapper;
+
+/// Longest wait for a frame, bounds the stop latency
+const BRIDGE_POLL: Duration = Duration::from_millis(20);
+
+/// Upper bounds of the latency histogram buckets, in microseconds
+pub const LATENCY_BOUNDS_US: [u64; 10] = [10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Source of bridged frames
+pub trait BridgeSource: Send + 'static {
+    /// Next frame, `None` when `timeout` expires first
+    fn recv(&mut self, timeout: Duration) -> Result<Option<TraceFrame>, Box<dyn Error>>;
+}
+
+impl BridgeSource for CanSocketWrapper {
+    fn recv(&mut self, timeout: Duration) -> Result<Option<TraceFrame>, Box<dyn Error>> {
+        match self.read_frame(Some(timeout.as_millis().max(1) as u64)) {
+            Ok((id, data, extended, fd, remote, error)) => Ok(Some(TraceFrame {
+                id,
+                data,
+                extended,
+                fd,
+                remote,
+                error,
+                ..Default::default()
+            })),
+            Err(e) => match e.downcast_ref::<io::Error>().map(io::Error::kind) {
+                Some(io::ErrorKind::WouldBlock)
+                | Some(io::ErrorKind::TimedOut)
+                | Some(io::ErrorKind::Interrupted) => Ok(None),
+                _ => Err(e),
+            },
+        }
+    }
+}
+
+/// Payload rewrite of the frames matching an ID filter
+///
+/// The operands apply from `offset` in the kernel gateway order (and, or,
+/// xor, set); bytes past the end of the payload are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RewriteRule {
+    pub id: u32,
+    pub mask: u32,
+    pub offset: usize,
+    pub and: Vec<u8>,
+    pub or: Vec<u8>,
+    pub xor: Vec<u8>,
+    pub set: Vec<u8>,
+}
+
+impl RewriteRule {
+    pub fn matches(&self, id: u32) -> bool {
+        id & self.mask == self.id & self.mask
+    }
+
+    pub fn apply(&self, data: &mut [u8]) {
+        type Op = fn(u8, u8) -> u8;
+        let ops: [(&[u8], Op); 4] = [
+            (&self.and, |byte, operand| byte & operand),
+            (&self.or, |byte, operand| byte | operand),
+            (&self.xor, |byte, operand| byte ^ operand),
+            (&self.set, |_, operand| operand),
+        ];
+        for (operands, op) in ops {
+            let bytes = data.iter_mut().skip(self.offset);
+            for (byte, &operand) in bytes.zip(operands) {
+                *byte = op(*byte, operand);
+            }
+        }
+    }
+}
+
+/// Token bucket refilled at `rate` frames per second, holding `burst` frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub rate: f64,
+    pub burst: u32,
+}
+
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        TokenBucket {
+            limit,
+            tokens: limit.burst as f64,
+            last: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.limit.rate).min(self.limit.burst as f64);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BridgeOptions {
+    /// (id, mask) filters, a frame passes if it matches any, all if empty
+    pub filters: Vec<(u32, u32)>,
+    /// Identifiers to rewrite before forwarding
+    pub id_map: HashMap<u32, u32>,
+    /// Applied in order after the ID remapping
+    pub rewrite: Vec<RewriteRule>,
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// Custom logic per frame: `Some` forwards the returned frame, `None` drops it
+///
+/// `stop` is set when the bridge stops; a hook waiting on another thread
+/// must give up then.
+pub type FrameHook = Box<dyn FnMut(TraceFrame, &AtomicBool) -> Option<TraceFrame> + Send>;
+
+/// Histogram of the time from reception to forwarding
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyHistogram {
+    /// Counts per `LATENCY_BOUNDS_US` bucket, then above the last bound
+    pub counts: [u64; LATENCY_BOUNDS_US.len() + 1],
+    pub min: Duration,
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        let bucket = LATENCY_BOUNDS_US.partition_point(|&bound| bound < micros);
+        if self.count() == 0 || latency < self.min {
+            self.min = latency;
+        }
+        self.max = self.max.max(latency);
+        self.total = self.total.saturating_add(latency);
+        self.counts[bucket] += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.count() {
+            0 => Duration::ZERO,
+            count => {
+                // Division sur les nanosecondes : le compte dépasse u32 sur un bus chargé
+                let nanos = self.total.as_nanos() / count as u128;
+                Duration::new(
+                    (nanos / 1_000_000_000) as u64,
+                    (nanos % 1_000_000_000) as u32,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BridgeStats {
+    pub received: u64,
+    pub forwarded: u64,
+    /// Frames rejected by the ID filters, and error frames
+    pub filtered: u64,
+    pub rate_limited: u64,
+    /// Frames dropped by the hook
+    pub hook_dropped: u64,
+    pub send_errors: u64,
+    pub latency: LatencyHistogram,
+    /// Last send error, or the read error that stopped the bridge
+    pub error: Option<String>,
+    pub running: bool,
+}
+
+impl BridgeStats {
+    pub fn dropped(&self) -> u64 {
+        self.filtered + self.rate_limited + self.hook_dropped + self.send_errors
+    }
+}
+
+/// Filtering and rewriting stages before the hook
+fn transform(options: &BridgeOptions, mut frame: TraceFrame) -> Option<TraceFrame> {
+    if frame.error
+        || !(options.filters.is_empty()
+            || options
+                .filters
+                .iter()
+                .any(|&(id, mask)| frame.id & mask == id & mask))
+    {
+        return None;
+    }
+    if let Some(&id) = options.id_map.get(&frame.id) {
+        frame.id = id;
+        frame.extended |= id > 0x7FF;
+    }
+    for rule in options.rewrite.iter().filter(|rule| rule.matches(frame.id)) {
+        rule.apply(&mut frame.data);
+    }
+    Some(frame)
+}
+
+/// Bridge thread forwarding frames from a source to a sink
+pub struct Bridge {
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<BridgeStats>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Bridge {
+    pub fn start<S, D>(
+        mut source: S,
+        sink: D,
+        options: BridgeOptions,
+        mut hook: Option<FrameHook>,
+    ) -> Self
+    where
+        S: BridgeSource,
+        D: ReplaySink,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(Mutex::new(BridgeStats {
+            running: true,
+            ..Default::default()
+        }));
+        let stop_flag = Arc::clone(&stop);
+        let shared = Arc::clone(&stats);
+        let handle = thread::spawn(move || {
+            let mut bucket = options.rate_limit.map(TokenBucket::new);
+            while !stop_flag.load(Ordering::Relaxed) {
+                let frame = match source.recv(BRIDGE_POLL) {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        shared.lock().unwrap().error = Some(e.to_string());
+                        break;
+                    }
+                };
+                let received = Instant::now();
+                shared.lock().unwrap().received += 1;
+
+                let Some(frame) = transform(&options, frame) else {
+                    shared.lock().unwrap().filtered += 1;
+                    continue;
+                };
+                let frame = match hook.as_mut() {
+                    Some(hook) => match hook(frame, &stop_flag) {
+                        Some(frame) => frame,
+                        None => {
+                            shared.lock().unwrap().hook_dropped += 1;
+                            continue;
+                        }
+                    },
+                    None => frame,
+                };
+                if let Some(bucket) = bucket.as_mut() {
+                    if !bucket.take(Instant::now()) {
+                        shared.lock().unwrap().rate_limited += 1;
+                        continue;
+                    }
+                }
+
+                let result = replay::send(&sink, &frame);
+                let mut stats = shared.lock().unwrap();
+                match result {
+                    Ok(()) => {
+                        stats.forwarded += 1;
+                        stats.latency.record(received.elapsed());
+                    }
+                    Err(e) => {
+                        stats.send_errors += 1;
+                        stats.error = Some(e);
+                    }
+                }
+            }
+            shared.lock().unwrap().running = false;
+        });
+
+        Bridge {
+            stop,
+            stats,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stats(&self) -> BridgeStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Stop the thread and return the final counters
+    pub fn stop(mut self) -> BridgeStats {
+        self.halt();
+        self.stats()
+    }
+
+    fn halt(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref BRIDGE_REGISTRY: Arc<Mutex<HashMap<u32, Bridge>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Start a bridge between sockets and register it, returning its handle
+pub fn start_bridge(
+    source: CanSocketWrapper,
+    sink: CanSocketWrapper,
+    options: BridgeOptions,
+    hook: Option<FrameHook>,
+) -> u32 {
+    let bridge = Bridge::start(source, sink, options, hook);
+    let id = crate::next_handle_id();
+    BRIDGE_REGISTRY.lock().unwrap().insert(id, bridge);
+    id
+}
+
+fn bytes_from_js(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+    key: &str,
+) -> NeonResult<Vec<u8>> {
+    let Some(array) = obj.get_opt::<JsArray, _, _>(cx, key)? else {
+        return Ok(Vec::new());
+    };
+    let mut bytes = Vec::new();
+    for value in array.to_vec(cx)? {
+        bytes.push(value.downcast_or_throw::<JsNumber, _>(cx)?.value(cx) as u8);
+    }
+    Ok(bytes)
+}
+
+fn id_mask_from_js(cx: &mut FunctionContext, obj: Handle<JsObject>) -> NeonResult<(u32, u32)> {
+    let id = obj.get::<JsNumber, _, _>(cx, "id")?.value(cx) as u32;
+    let mask = obj
+        .get_opt::<JsNumber, _, _>(cx, "mask")?
+        .map_or(u32::MAX, |mask| mask.value(cx) as u32);
+    Ok((id, mask))
+}
+
+/// Read `{ filters, idMap, rewrite, rateLimit }`
+pub fn options_from_js(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+) -> NeonResult<BridgeOptions> {
+    let mut options = BridgeOptions::default();
+
+    if let Some(filters) = obj.get_opt::<JsArray, _, _>(cx, "filters")? {
+        for value in filters.to_vec(cx)? {
+            let filter = value.downcast_or_throw::<JsObject, _>(cx)?;
+            options.filters.push(id_mask_from_js(cx, filter)?);
+        }
+    }
+    if let Some(id_map) = obj.get_opt::<JsObject, _, _>(cx, "idMap")? {
+        let keys = id_map.get_own_property_names(cx)?.to_vec(cx)?;
+        for key in keys {
+            let key = key.downcast_or_throw::<JsString, _>(cx)?.value(cx);
+            let Some(from) = crate::parse_id_key(&key) else {
+                return cx.throw_error(format!("Invalid CAN ID in idMap: {}", key));
+            };
+            let to = id_map.get::<JsNumber, _, _>(cx, key.as_str())?;
+            options.id_map.insert(from, to.value(cx) as u32);
+        }
+    }
+    if let Some(rules) = obj.get_opt::<JsArray, _, _>(cx, "rewrite")? {
+        for value in rules.to_vec(cx)? {
+            let rule = value.downcast_or_throw::<JsObject, _>(cx)?;
+            let (id, mask) = id_mask_from_js(cx, rule)?;
+            let offset = rule
+                .get_opt::<JsNumber, _, _>(cx, "offset")?
+                .map_or(0, |offset| offset.value(cx) as usize);
+            options.rewrite.push(RewriteRule {
+                id,
+                mask,
+                offset,
+                and: bytes_from_js(cx, rule, "and")?,
+                or: bytes_from_js(cx, rule, "or")?,
+                xor: bytes_from_js(cx, rule, "xor")?,
+                set: bytes_from_js(cx, rule, "set")?,
+            });
+        }
+    }
+    if let Some(limit) = obj.get_opt::<JsObject, _, _>(cx, "rateLimit")? {
+        let rate = limit.get::<JsNumber, _, _>(cx, "rate")?.value(cx);
+        if !rate.is_finite() || rate <= 0.0 {
+            return cx.throw_error(format!("Invalid bridge rate {}", rate));
+        }
+        let burst = limit
+            .get_opt::<JsNumber, _, _>(cx, "burst")?
+            .map_or(1, |burst| (burst.value(cx) as u32).max(1));
+        options.rate_limit = Some(RateLimit { rate, burst });
+    }
+    Ok(options)
+}
+
+/// Hook calling a JavaScript function with each frame
+///
+/// The function returns `false` or `null` to drop the frame, a frame object
+/// to forward instead, anything else to forward it unchanged.
+pub fn js_hook(cx: &mut FunctionContext, callback: Handle<JsFunction>) -> FrameHook {
+    let callback = Arc::new(callback.root(cx));
+    let js_channel = cx.channel();
+    Box::new(move |frame, stop| {
+        let (tx, rx) = mpsc::channel();
+        let callback = Arc::clone(&callback);
+        js_channel.send(move |mut cx| {
+            let obj = frame_to_js(&mut cx, &frame)?;
+            let result: Handle<JsValue> = callback
+                .to_inner(&mut cx)
+                .call_with(&cx)
+                .arg(obj)
+                .apply(&mut cx)?;
+            let verdict = if result.is_a::<JsNull, _>(&mut cx) {
+                None
+            } else if let Ok(keep) = result.downcast::<JsBoolean, _>(&mut cx) {
+                keep.value(&mut cx).then_some(frame)
+            } else if let Ok(obj) = result.downcast::<JsObject, _>(&mut cx) {
+                Some(frame_from_js(&mut cx, obj)?)
+            } else {
+                Some(frame)
+            };
+            let _ = tx.send(verdict);
+            Ok(())
+        });
+        // Le thread JavaScript peut attendre l'arrêt du pont : ne pas bloquer
+        loop {
+            match rx.recv_timeout(BRIDGE_POLL) {
+                Ok(verdict) => return verdict,
+                Err(RecvTimeoutError::Timeout) if !stop.load(Ordering::Relaxed) => {}
+                Err(_) => return None,
+            }
+        }
+    })
+}
+
+fn stats_to_js<'a>(cx: &mut FunctionContext<'a>, stats: &BridgeStats) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    for (key, value) in [
+        ("received", stats.received),
+        ("forwarded", stats.forwarded),
+        ("dropped", stats.dropped()),
+        ("filtered", stats.filtered),
+        ("rateLimited", stats.rate_limited),
+        ("droppedByCallback", stats.hook_dropped),
+        ("sendErrors", stats.send_errors),
+    ] {
+        let value = cx.number(value as f64);
+        obj.set(cx, key, value)?;
+    }
+    let running = cx.boolean(stats.running);
+    obj.set(cx, "running", running)?;
+    if let Some(error) = &stats.error {
+        let error = cx.string(error);
+        obj.set(cx, "error", error)?;
+    }
+
+    let latency = cx.empty_object();
+    let micros = |d: Duration| d.as_secs_f64() * 1e6;
+    for (key, value) in [
+        ("min", micros(stats.latency.min)),
+        ("max", micros(stats.latency.max)),
+        ("mean", micros(stats.latency.mean())),
+    ] {
+        let value = cx.number(value);
+        latency.set(cx, key, value)?;
+    }
+    let buckets = cx.empty_array();
+    for (i, &count) in stats.latency.counts.iter().enumerate() {
+        let bucket = cx.empty_object();
+        let bound = LATENCY_BOUNDS_US
+            .get(i)
+            .map_or(f64::INFINITY, |&b| b as f64);
+        let bound = cx.number(bound);
+        bucket.set(cx, "le", bound)?;
+        let count = cx.number(count as f64);
+        bucket.set(cx, "count", count)?;
+        buckets.set(cx, i as u32, bucket)?;
+    }
+    latency.set(cx, "buckets", buckets)?;
+    obj.set(cx, "latency", latency)?;
+    Ok(obj)
+}
+
+/// Get the counters of a bridge from JavaScript
+pub fn get_bridge_stats(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let bridge_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let stats = match BRIDGE_REGISTRY.lock().unwrap().get(&bridge_id) {
+        Some(bridge) => bridge.stats(),
+        None => return cx.throw_error("Invalid bridge ID"),
+    };
+    stats_to_js(&mut cx, &stats)
+}
+
+/// Stop a bridge from JavaScript, returning its final counters
+pub fn stop_bridge(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let bridge_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(bridge) = BRIDGE_REGISTRY.lock().unwrap().remove(&bridge_id) else {
+        return cx.throw_error("Invalid bridge ID");
+    };
+    let stats = bridge.stop();
+    stats_to_js(&mut cx, &stats)
+}
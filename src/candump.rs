@@ -0,0 +1,402 @@
+//! can-utils `candump -l` log format
+//!
+//! One frame per line: `(1697040000.123456) can0 123#DEADBEEF`. Extended
+//! and error frames use 8 hex digits for the identifier, `##<flags>` marks
+//! CAN FD frames and `R` remote frames. A trailing `R`/`T` (candump `-x`)
+//! gives the direction.
+
+use neon::prelude::*;
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+use std::fmt::Write as _;
+#[cfg(target_os = "linux")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::trace::{frames_from_js, frames_to_js, Direction, TraceFrame};
+
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_EFF_MASK: u32 = 0x1FFF_FFFF;
+const CANFD_BRS: u8 = 0x01;
+const CANFD_ESI: u8 = 0x02;
+
+fn parse_timestamp(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid timestamp '{}'", text);
+    let (secs, frac) = text.split_once('.').unwrap_or((text, ""));
+    let secs: u64 = secs.parse().map_err(|_| invalid())?;
+    if frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let nanos = format!("{:0<9}", frac)
+        .parse::<u32>()
+        .map_err(|_| invalid())?;
+    Ok(Duration::new(secs, nanos))
+}
+
+fn parse_hex_data(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = text.bytes().filter(|&b| b != b'.').collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of data digits in '{}'", text));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| format!("invalid data '{}'", text))
+        })
+        .collect()
+}
+
+/// Parse a `<id>#<data>` frame as printed by can-utils
+pub fn parse_frame(text: &str) -> Result<TraceFrame, String> {
+    let (id_text, payload) = text
+        .split_once('#')
+        .ok_or_else(|| format!("missing '#' in frame '{}'", text))?;
+    let id = u32::from_str_radix(id_text, 16).map_err(|_| format!("invalid id '{}'", id_text))?;
+
+    let mut frame = TraceFrame::default();
+    match id_text.len() {
+        3 if id <= 0x7FF => frame.id = id,
+        8 if id & CAN_ERR_FLAG != 0 => {
+            frame.id = id & CAN_EFF_MASK;
+            frame.error = true;
+        }
+        8 if id <= CAN_EFF_MASK => {
+            frame.id = id;
+            frame.extended = true;
+        }
+        _ => return Err(format!("invalid id '{}'", id_text)),
+    }
+
+    if let Some(fd_payload) = payload.strip_prefix('#') {
+        let mut chars = fd_payload.chars();
+        let flags = chars
+            .next()
+            .and_then(|c| c.to_digit(16))
+            .ok_or_else(|| format!("missing CAN FD flags in '{}'", text))?
+            as u8;
+        frame.fd = true;
+        frame.brs = flags & CANFD_BRS != 0;
+        frame.esi = flags & CANFD_ESI != 0;
+        frame.data = parse_hex_data(chars.as_str())?;
+        if frame.data.len() > 64 {
+            return Err(format!("CAN FD payload too long in '{}'", text));
+        }
+    } else if let Some(len) = payload.strip_prefix('R') {
+        frame.remote = true;
+        frame.remote_len = match len {
+            "" => 0,
+            _ => len
+                .parse()
+                .ok()
+                .filter(|&l| l <= 8)
+                .ok_or_else(|| format!("invalid remote length in '{}'", text))?,
+        };
+    } else {
+        // Suffixe `_<dlc>` des trames classiques avec DLC > 8 : ignoré
+        let data = payload.split_once('_').map_or(payload, |(data, _)| data);
+        frame.data = parse_hex_data(data)?;
+        if frame.data.len() > 8 {
+            return Err(format!("classic CAN payload too long in '{}'", text));
+        }
+    }
+    Ok(frame)
+}
+
+/// Parse one log line, `None` for blank lines
+pub fn parse_line(line: &str) -> Result<Option<TraceFrame>, String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let (timestamp, rest) = line
+        .strip_prefix('(')
+        .and_then(|l| l.split_once(')'))
+        .ok_or("missing (timestamp)")?;
+
+    let mut fields = rest.split_whitespace();
+    let interface = fields.next().ok_or("missing interface")?;
+    let mut frame = parse_frame(fields.next().ok_or("missing frame")?)?;
+    frame.timestamp = parse_timestamp(timestamp)?;
+    frame.interface = interface.to_string();
+    frame.direction = match fields.next() {
+        None => None,
+        Some("R") => Some(Direction::Rx),
+        Some("T") => Some(Direction::Tx),
+        Some(other) => return Err(format!("unexpected '{}'", other)),
+    };
+    Ok(Some(frame))
+}
+
+/// Parse a whole log
+pub fn parse(text: &str) -> Result<Vec<TraceFrame>, String> {
+    let mut frames = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        match parse_line(line) {
+            Ok(Some(frame)) => frames.push(frame),
+            Ok(None) => {}
+            Err(e) => return Err(format!("line {}: {}", index + 1, e)),
+        }
+    }
+    Ok(frames)
+}
+
+/// Format the `<id>#<data>` part of a frame
+pub fn format_frame(frame: &TraceFrame) -> String {
+    let mut out = String::with_capacity(16 + 2 * frame.data.len());
+    if frame.error {
+        let _ = write!(out, "{:08X}#", frame.id & CAN_EFF_MASK | CAN_ERR_FLAG);
+    } else if frame.extended {
+        let _ = write!(out, "{:08X}#", frame.id & CAN_EFF_MASK);
+    } else {
+        let _ = write!(out, "{:03X}#", frame.id & 0x7FF);
+    }
+
+    if frame.fd {
+        let flags = if frame.brs { CANFD_BRS } else { 0 } | if frame.esi { CANFD_ESI } else { 0 };
+        let _ = write!(out, "#{:X}", flags);
+    } else if frame.remote {
+        out.push('R');
+        if (1..=8).contains(&frame.remote_len) {
+            let _ = write!(out, "{}", frame.remote_len);
+        }
+        return out;
+    }
+    for byte in &frame.data {
+        let _ = write!(out, "{:02X}", byte);
+    }
+    out
+}
+
+/// Format one log line, without the line feed
+pub fn format_line(frame: &TraceFrame) -> String {
+    let interface = if frame.interface.is_empty() {
+        "can0"
+    } else {
+        &frame.interface
+    };
+    let mut line = format!(
+        "({:010}.{:06}) {} {}",
+        frame.timestamp.as_secs(),
+        frame.timestamp.subsec_micros(),
+        interface,
+        format_frame(frame)
+    );
+    match frame.direction {
+        Some(Direction::Rx) => line.push_str(" R"),
+        Some(Direction::Tx) => line.push_str(" T"),
+        None => {}
+    }
+    line
+}
+
+/// Format a whole log, skipping CAN XL frames
+pub fn format(frames: &[TraceFrame]) -> String {
+    let frames = frames.iter().filter(|frame| frame.xl.is_none());
+    frames.fold(String::new(), |mut out, frame| {
+        out.push_str(&format_line(frame));
+        out.push('\n');
+        out
+    })
+}
+
+/// Parse a candump log from JavaScript
+pub fn parse_candump_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let text = cx.argument::<JsString>(0)?.value(&mut cx);
+    match parse(&text) {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to parse candump log: {}", e)),
+    }
+}
+
+/// Read a candump log file from JavaScript
+pub fn read_candump_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let result = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| parse(&text));
+    match result {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+/// Format frames as a candump log from JavaScript
+pub fn format_candump_log(mut cx: FunctionContext) -> JsResult<JsString> {
+    let array = cx.argument::<JsArray>(0)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    Ok(cx.string(format(&frames)))
+}
+
+/// Write frames to a candump log file from JavaScript
+pub fn write_candump_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let array = cx.argument::<JsArray>(1)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    match std::fs::write(&path, format(&frames)) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to write {}: {}", path, e)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod logger {
+    use socketcan::{CanFdSocket, Socket, SocketOptions};
+    use std::fs::File;
+    use std::io::{self, BufWriter, Write};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::pcap::PcapngWriter;
+    use crate::trace::TraceFrame;
+
+    /// Read timeout of the logging socket, also the stop latency
+    const POLL: Duration = Duration::from_millis(100);
+
+    /// File format written by a [`Logger`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LogFormat {
+        Candump,
+        Pcapng,
+    }
+
+    impl LogFormat {
+        /// Guess the format from the file extension, candump by default
+        pub fn from_path(path: &str) -> Self {
+            let extension = std::path::Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_ascii_lowercase);
+            match extension.as_deref() {
+                Some("pcap" | "pcapng") => LogFormat::Pcapng,
+                _ => LogFormat::Candump,
+            }
+        }
+    }
+
+    enum Sink {
+        Candump(BufWriter<File>),
+        Pcapng(PcapngWriter<BufWriter<File>>),
+    }
+
+    impl Sink {
+        fn write(&mut self, frame: &TraceFrame) -> io::Result<()> {
+            match self {
+                Sink::Candump(out) => writeln!(out, "{}", super::format_line(frame)),
+                Sink::Pcapng(out) => out.write_frame(frame),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            match self {
+                Sink::Candump(out) => out.flush(),
+                Sink::Pcapng(out) => out.flush(),
+            }
+        }
+    }
+
+    /// Background thread writing every frame of an interface to a log file
+    pub struct Logger {
+        stop: Arc<AtomicBool>,
+        handle: JoinHandle<io::Result<u64>>,
+    }
+
+    impl Logger {
+        /// Start logging `interface` to `path`
+        ///
+        /// The logger has its own socket, with error frames enabled and no
+        /// filter, so readers of the interface see the same frames as before.
+        pub fn start(
+            interface: &str,
+            path: &str,
+            format: LogFormat,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            let socket = CanFdSocket::open(interface)?;
+            socket.set_error_filter_accept_all()?;
+            socket.set_recv_timestamp(true)?;
+            socket.set_read_timeout(POLL)?;
+            let file = BufWriter::new(File::create(path)?);
+            let mut out = match format {
+                LogFormat::Candump => Sink::Candump(file),
+                LogFormat::Pcapng => Sink::Pcapng(PcapngWriter::new(file)?),
+            };
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread_stop = Arc::clone(&stop);
+            let interface = interface.to_string();
+            let handle = thread::spawn(move || {
+                let mut count = 0u64;
+                while !thread_stop.load(Ordering::Relaxed) {
+                    match socket.read_frame_with_timestamp() {
+                        Ok((frame, time)) => {
+                            let timestamp = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                            let trace =
+                                TraceFrame::from_socket_frame(&frame, timestamp, &interface);
+                            out.write(&trace)?;
+                            count += 1;
+                        }
+                        // Bus inactif : on en profite pour vider le tampon
+                        Err(e)
+                            if matches!(
+                                e.kind(),
+                                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                            ) =>
+                        {
+                            out.flush()?
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+                out.flush()?;
+                Ok(count)
+            });
+            Ok(Self { stop, handle })
+        }
+
+        /// Stop the thread and return the number of logged frames
+        pub fn stop(self) -> io::Result<u64> {
+            self.stop.store(true, Ordering::Relaxed);
+            self.handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("logger thread panicked")))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use logger::{LogFormat, Logger};
+
+#[cfg(target_os = "linux")]
+lazy_static::lazy_static! {
+    static ref LOGGER_REGISTRY: Arc<Mutex<HashMap<u32, Logger>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Start a logger and register it, returning its handle
+#[cfg(target_os = "linux")]
+pub fn start_logger(
+    interface: &str,
+    path: &str,
+    format: LogFormat,
+) -> Result<u32, Box<dyn std::error::Error>> {
+    let logger = Logger::start(interface, path, format)?;
+    let id = crate::next_handle_id();
+    LOGGER_REGISTRY.lock().unwrap().insert(id, logger);
+    Ok(id)
+}
+
+/// Stop a logger from JavaScript, returning the number of logged frames
+#[cfg(target_os = "linux")]
+pub fn stop_logging(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let logger_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(logger) = LOGGER_REGISTRY.lock().unwrap().remove(&logger_id) else {
+        return cx.throw_error("Invalid logger ID");
+    };
+    match logger.stop() {
+        Ok(count) => Ok(cx.number(count as f64)),
+        Err(e) => cx.throw_error(format!("Failed to write log: {}", e)),
+    }
+}
@@ -0,0 +1,1360 @@
+//! CANopen (CiA 301) master: NMT, heartbeat consumer and SDO client
+//!
+//! A dispatcher thread reads the bus and routes heartbeats to the node table
+//! and SDO responses to the pending transfer of the matching node.
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::canopen_od::{get_pdo_device, mapping_from_js, PdoKind, PdoMapping, PDO_DISABLED};
+use crate::isotp_engine::CanLink;
+use crate::{next_handle_id, CanSocketWrapper};
+
+const COB_NMT: u32 = 0x000;
+const COB_SYNC: u32 = 0x080;
+const COB_EMCY: u32 = 0x080;
+const COB_SDO_TX: u32 = 0x580;
+const COB_SDO_RX: u32 = 0x600;
+const COB_HEARTBEAT: u32 = 0x700;
+
+/// Poll interval of the dispatcher thread
+const DISPATCH_POLL: Duration = Duration::from_millis(20);
+
+/// Default number of segments per SDO block
+const DEFAULT_BLOCK_SIZE: u8 = 127;
+
+const ABORT_TOGGLE: u32 = 0x0503_0000;
+const ABORT_TIMEOUT: u32 = 0x0504_0000;
+const ABORT_COMMAND: u32 = 0x0504_0001;
+const ABORT_BLOCK_SIZE: u32 = 0x0504_0002;
+const ABORT_SEQUENCE: u32 = 0x0504_0003;
+const ABORT_CRC: u32 = 0x0504_0004;
+const ABORT_LENGTH: u32 = 0x0607_0010;
+
+/// Errors raised by SDO transfers
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SdoError {
+    #[error("SDO abort 0x{code:08X} ({}) on 0x{index:04X}:{subindex:02X}", abort_name(*code))]
+    Abort { index: u16, subindex: u8, code: u32 },
+    #[error("SDO timeout on 0x{index:04X}:{subindex:02X}")]
+    Timeout { index: u16, subindex: u8 },
+    #[error("SDO protocol error: {0}")]
+    Protocol(String),
+    #[error("Transport error: {0}")]
+    Transport(String),
+}
+
+impl SdoError {
+    /// Abort code to send to the server for a locally detected error
+    fn local_abort_code(&self) -> Option<u32> {
+        match self {
+            SdoError::Timeout { .. } => Some(ABORT_TIMEOUT),
+            SdoError::Protocol(_) => Some(ABORT_COMMAND),
+            _ => None,
+        }
+    }
+}
+
+/// Description of an SDO abort code
+pub fn abort_name(code: u32) -> &'static str {
+    match code {
+        0x0503_0000 => "Toggle bit not alternated",
+        0x0504_0000 => "SDO protocol timed out",
+        0x0504_0001 => "Client/server command specifier not valid or unknown",
+        0x0504_0002 => "Invalid block size",
+        0x0504_0003 => "Invalid sequence number",
+        0x0504_0004 => "CRC error",
+        0x0504_0005 => "Out of memory",
+        0x0601_0000 => "Unsupported access to an object",
+        0x0601_0001 => "Attempt to read a write only object",
+        0x0601_0002 => "Attempt to write a read only object",
+        0x0602_0000 => "Object does not exist in the object dictionary",
+        0x0604_0041 => "Object cannot be mapped to the PDO",
+        0x0604_0042 => "PDO length exceeded",
+        0x0604_0043 => "General parameter incompatibility",
+        0x0604_0047 => "General internal incompatibility in the device",
+        0x0606_0000 => "Access failed due to a hardware error",
+        0x0607_0010 => "Data type does not match, length of service parameter does not match",
+        0x0607_0012 => "Data type does not match, length of service parameter too high",
+        0x0607_0013 => "Data type does not match, length of service parameter too low",
+        0x0609_0011 => "Sub-index does not exist",
+        0x0609_0030 => "Invalid value for parameter",
+        0x0609_0031 => "Value of parameter written too high",
+        0x0609_0032 => "Value of parameter written too low",
+        0x0609_0036 => "Maximum value is less than minimum value",
+        0x060A_0023 => "Resource not available: SDO connection",
+        0x0800_0000 => "General error",
+        0x0800_0020 => "Data cannot be transferred or stored to the application",
+        0x0800_0021 => "Data cannot be transferred because of local control",
+        0x0800_0022 => "Data cannot be transferred because of the present device state",
+        0x0800_0023 => "Object dictionary not present",
+        0x0800_0024 => "No data available",
+        _ => "Unknown abort code",
+    }
+}
+
+/// CRC-16/XMODEM used by SDO block transfers
+pub fn sdo_crc(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// NMT command specifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtCommand {
+    Start = 0x01,
+    Stop = 0x02,
+    PreOperational = 0x80,
+    ResetNode = 0x81,
+    ResetCommunication = 0x82,
+}
+
+impl NmtCommand {
+    /// Parse a JS command name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "start" | "operational" => Some(NmtCommand::Start),
+            "stop" | "stopped" => Some(NmtCommand::Stop),
+            "preOperational" | "pre-operational" => Some(NmtCommand::PreOperational),
+            "resetNode" | "reset" => Some(NmtCommand::ResetNode),
+            "resetCommunication" => Some(NmtCommand::ResetCommunication),
+            _ => None,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x01 => Some(NmtCommand::Start),
+            0x02 => Some(NmtCommand::Stop),
+            0x80 => Some(NmtCommand::PreOperational),
+            0x81 => Some(NmtCommand::ResetNode),
+            0x82 => Some(NmtCommand::ResetCommunication),
+            _ => None,
+        }
+    }
+}
+
+/// NMT state reported in heartbeat messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtState {
+    Unknown,
+    BootUp,
+    Stopped,
+    Operational,
+    PreOperational,
+}
+
+impl NmtState {
+    fn from_heartbeat(value: u8) -> Self {
+        match value & 0x7F {
+            0x00 => NmtState::BootUp,
+            0x04 => NmtState::Stopped,
+            0x05 => NmtState::Operational,
+            0x7F => NmtState::PreOperational,
+            _ => NmtState::Unknown,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NmtState::Unknown => "unknown",
+            NmtState::BootUp => "bootUp",
+            NmtState::Stopped => "stopped",
+            NmtState::Operational => "operational",
+            NmtState::PreOperational => "preOperational",
+        }
+    }
+}
+
+/// Decoded emergency (EMCY) message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmcyMessage {
+    pub node_id: u8,
+    pub error_code: u16,
+    pub error_register: u8,
+    pub manufacturer_data: [u8; 5],
+}
+
+/// Decode an EMCY frame (COB-ID 0x081..=0x0FF)
+pub fn decode_emcy(id: u32, data: &[u8]) -> Option<EmcyMessage> {
+    if !(0x081..=0x0FF).contains(&id) || data.len() < 3 {
+        return None;
+    }
+    let mut manufacturer_data = [0u8; 5];
+    let extra = &data[3..data.len().min(8)];
+    manufacturer_data[..extra.len()].copy_from_slice(extra);
+    Some(EmcyMessage {
+        node_id: (id - COB_EMCY) as u8,
+        error_code: u16::from_le_bytes([data[0], data[1]]),
+        error_register: data[2],
+        manufacturer_data,
+    })
+}
+
+/// Description of an emergency error code (CiA 301 classes)
+pub fn emcy_description(code: u16) -> &'static str {
+    match code {
+        0x0000 => "Error reset or no error",
+        0x8110 => "CAN overrun (objects lost)",
+        0x8120 => "CAN in error passive mode",
+        0x8130 => "Life guard error or heartbeat error",
+        0x8140 => "Recovered from bus off",
+        0x8150 => "CAN-ID collision",
+        0x8210 => "PDO not processed due to length error",
+        0x8220 => "PDO length exceeded",
+        0x8230 => "DAM MPDO not processed, destination object not available",
+        0x8240 => "Unexpected SYNC data length",
+        0x8250 => "RPDO timeout",
+        _ => match code & 0xFF00 {
+            0x1000 => "Generic error",
+            0x2000 => "Current",
+            0x2100 => "Current, device input side",
+            0x2200 => "Current inside the device",
+            0x2300 => "Current, device output side",
+            0x3000 => "Voltage",
+            0x3100 => "Mains voltage",
+            0x3200 => "Voltage inside the device",
+            0x3300 => "Output voltage",
+            0x4000 => "Temperature",
+            0x4100 => "Ambient temperature",
+            0x4200 => "Device temperature",
+            0x5000 => "Device hardware",
+            0x6000 => "Device software",
+            0x6100 => "Internal software",
+            0x6200 => "User software",
+            0x6300 => "Data set",
+            0x7000 => "Additional modules",
+            0x8000 => "Monitoring",
+            0x8100 => "Communication",
+            0x8200 => "Protocol error",
+            0x9000 => "External error",
+            0xF000 => "Additional functions",
+            0xFF00 => "Device specific",
+            _ => "Unknown error",
+        },
+    }
+}
+
+/// Events reported by the dispatcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeEvent {
+    BootUp(u8),
+    StateChanged {
+        node_id: u8,
+        state: NmtState,
+    },
+    HeartbeatTimeout(u8),
+    /// SYNC seen on the bus, with its counter when present
+    Sync {
+        counter: Option<u8>,
+    },
+    Emergency(EmcyMessage),
+}
+
+/// Snapshot of a monitored node
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStatus {
+    pub node_id: u8,
+    pub state: NmtState,
+    /// Time since the last heartbeat, `None` if never seen
+    pub last_seen: Option<Duration>,
+    pub heartbeat_timeout: Option<Duration>,
+    pub timed_out: bool,
+}
+
+struct NodeEntry {
+    state: NmtState,
+    last_seen: Option<Instant>,
+    /// Start of the current supervision window
+    reference: Instant,
+    heartbeat_timeout: Option<Duration>,
+    timed_out: bool,
+}
+
+impl NodeEntry {
+    fn new() -> Self {
+        Self {
+            state: NmtState::Unknown,
+            last_seen: None,
+            reference: Instant::now(),
+            heartbeat_timeout: None,
+            timed_out: false,
+        }
+    }
+}
+
+type EventCallback = Box<dyn Fn(NodeEvent) + Send>;
+
+/// SDO transfer options
+#[derive(Debug, Clone, Copy)]
+pub struct SdoOptions {
+    pub timeout: Duration,
+    /// Use block transfer instead of expedited/segmented
+    pub block: bool,
+    pub block_size: u8,
+}
+
+impl Default for SdoOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            block: false,
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+/// State shared with the dispatcher thread
+struct Shared {
+    nodes: Mutex<HashMap<u8, NodeEntry>>,
+    sdo_routes: Mutex<HashMap<u8, Sender<Vec<u8>>>>,
+    on_event: Mutex<Option<EventCallback>>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn emit(&self, event: NodeEvent) {
+        if let Some(callback) = self.on_event.lock().unwrap().as_ref() {
+            callback(event);
+        }
+    }
+
+    fn handle_frame(&self, id: u32, data: &[u8]) {
+        match id {
+            0x701..=0x77F if !data.is_empty() => {
+                let node_id = (id - COB_HEARTBEAT) as u8;
+                let state = NmtState::from_heartbeat(data[0]);
+                let events = {
+                    let mut nodes = self.nodes.lock().unwrap();
+                    let entry = nodes.entry(node_id).or_insert_with(NodeEntry::new);
+                    let mut events = Vec::new();
+                    if state == NmtState::BootUp {
+                        events.push(NodeEvent::BootUp(node_id));
+                    } else if state != entry.state || entry.timed_out {
+                        events.push(NodeEvent::StateChanged { node_id, state });
+                    }
+                    entry.state = state;
+                    entry.last_seen = Some(Instant::now());
+                    entry.reference = Instant::now();
+                    entry.timed_out = false;
+                    events
+                };
+                for event in events {
+                    self.emit(event);
+                }
+            }
+            0x581..=0x5FF => {
+                let node_id = (id - COB_SDO_TX) as u8;
+                if let Some(route) = self.sdo_routes.lock().unwrap().get(&node_id) {
+                    let _ = route.send(data.to_vec());
+                }
+            }
+            COB_SYNC => self.emit(NodeEvent::Sync {
+                counter: data.first().copied(),
+            }),
+            0x081..=0x0FF => {
+                if let Some(emcy) = decode_emcy(id, data) {
+                    self.emit(NodeEvent::Emergency(emcy));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_timeouts(&self) {
+        let expired: Vec<u8> = {
+            let mut nodes = self.nodes.lock().unwrap();
+            nodes
+                .iter_mut()
+                .filter_map(|(&node_id, entry)| {
+                    let timeout = entry.heartbeat_timeout?;
+                    if !entry.timed_out && entry.reference.elapsed() > timeout {
+                        entry.timed_out = true;
+                        Some(node_id)
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+        for node_id in expired {
+            self.emit(NodeEvent::HeartbeatTimeout(node_id));
+        }
+    }
+}
+
+/// Removes the SDO route of a node when a transfer ends
+struct SdoRoute<'a> {
+    shared: &'a Shared,
+    node_id: u8,
+    rx: Receiver<Vec<u8>>,
+}
+
+impl Drop for SdoRoute<'_> {
+    fn drop(&mut self) {
+        self.shared.sdo_routes.lock().unwrap().remove(&self.node_id);
+    }
+}
+
+/// CANopen master bound to one bus
+pub struct CanOpenMaster<L: CanLink + Send + Sync + 'static = CanSocketWrapper> {
+    tx: Arc<L>,
+    shared: Arc<Shared>,
+    /// One SDO transfer at a time per node
+    sdo_locks: Mutex<HashMap<u8, Arc<Mutex<()>>>>,
+    dispatcher: Mutex<Option<JoinHandle<()>>>,
+    sync_producer: Mutex<Option<SyncProducer>>,
+}
+
+/// Running SYNC producer thread
+struct SyncProducer {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl CanOpenMaster<CanSocketWrapper> {
+    /// Open a master on `interface`
+    ///
+    /// Reception uses its own socket so the dispatcher never holds up sends.
+    pub fn open(interface: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let tx = CanSocketWrapper::new(interface.to_string())?;
+        let rx = CanSocketWrapper::new(interface.to_string())?;
+        Ok(Self::with_links(Arc::new(tx), Arc::new(rx)))
+    }
+}
+
+impl<L: CanLink + Send + Sync + 'static> CanOpenMaster<L> {
+    /// Build a master from explicit send and receive links
+    pub fn with_links(tx: Arc<L>, rx: Arc<L>) -> Self {
+        let shared = Arc::new(Shared {
+            nodes: Mutex::new(HashMap::new()),
+            sdo_routes: Mutex::new(HashMap::new()),
+            on_event: Mutex::new(None),
+            stop: AtomicBool::new(false),
+        });
+
+        let thread_shared = Arc::clone(&shared);
+        let dispatcher = thread::spawn(move || {
+            while !thread_shared.stop.load(Ordering::Relaxed) {
+                match rx.recv(DISPATCH_POLL) {
+                    Ok(Some((id, data, false))) => thread_shared.handle_frame(id, &data),
+                    Ok(_) => {}
+                    Err(_) => thread::sleep(DISPATCH_POLL),
+                }
+                thread_shared.check_timeouts();
+            }
+        });
+
+        Self {
+            tx,
+            shared,
+            sdo_locks: Mutex::new(HashMap::new()),
+            dispatcher: Mutex::new(Some(dispatcher)),
+            sync_producer: Mutex::new(None),
+        }
+    }
+
+    /// Produce SYNC every `period`, with a counter wrapping after `counter_overflow`
+    pub fn start_sync(&self, period: Duration, counter_overflow: Option<u8>) -> Result<(), String> {
+        if let Some(overflow) = counter_overflow {
+            if !(2..=240).contains(&overflow) {
+                return Err(format!("Invalid SYNC counter overflow {}", overflow));
+            }
+        }
+        self.stop_sync();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let tx = Arc::clone(&self.tx);
+        let handle = thread::spawn(move || {
+            let step = Duration::from_millis(1).min(period);
+            let mut counter = 1u8;
+            let mut next = Instant::now();
+            while !thread_stop.load(Ordering::Relaxed) {
+                if Instant::now() >= next {
+                    let data = match counter_overflow {
+                        Some(overflow) => {
+                            let value = counter;
+                            counter = if counter >= overflow { 1 } else { counter + 1 };
+                            vec![value]
+                        }
+                        None => Vec::new(),
+                    };
+                    let _ = tx.send(COB_SYNC, data, false, false);
+                    next += period;
+                }
+                thread::sleep(step);
+            }
+        });
+        *self.sync_producer.lock().unwrap() = Some(SyncProducer { stop, handle });
+        Ok(())
+    }
+
+    /// Stop the SYNC producer, if running
+    pub fn stop_sync(&self) {
+        if let Some(producer) = self.sync_producer.lock().unwrap().take() {
+            producer.stop.store(true, Ordering::Relaxed);
+            let _ = producer.handle.join();
+        }
+    }
+
+    /// Register the callback receiving node events
+    pub fn set_event_callback(&self, callback: Option<EventCallback>) {
+        *self.shared.on_event.lock().unwrap() = callback;
+    }
+
+    /// Send an NMT command to `node_id` (0 addresses all nodes)
+    pub fn nmt(&self, command: NmtCommand, node_id: u8) -> Result<(), String> {
+        if node_id > 127 {
+            return Err(format!("Invalid node ID {}", node_id));
+        }
+        self.tx
+            .send(COB_NMT, vec![command as u8, node_id], false, false)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Supervise the heartbeat of `node_id`, `None` disables supervision
+    pub fn set_heartbeat_timeout(&self, node_id: u8, timeout: Option<Duration>) {
+        let mut nodes = self.shared.nodes.lock().unwrap();
+        let entry = nodes.entry(node_id).or_insert_with(NodeEntry::new);
+        entry.heartbeat_timeout = timeout;
+        entry.reference = Instant::now();
+        entry.timed_out = false;
+    }
+
+    /// Current view of every known node, sorted by node ID
+    pub fn node_states(&self) -> Vec<NodeStatus> {
+        let nodes = self.shared.nodes.lock().unwrap();
+        let mut states: Vec<NodeStatus> = nodes
+            .iter()
+            .map(|(&node_id, entry)| NodeStatus {
+                node_id,
+                state: entry.state,
+                last_seen: entry.last_seen.map(|t| t.elapsed()),
+                heartbeat_timeout: entry.heartbeat_timeout,
+                timed_out: entry.timed_out,
+            })
+            .collect();
+        states.sort_by_key(|status| status.node_id);
+        states
+    }
+
+    /// Read an object from a node's dictionary
+    pub fn sdo_upload(
+        &self,
+        node_id: u8,
+        index: u16,
+        subindex: u8,
+        options: &SdoOptions,
+    ) -> Result<Vec<u8>, SdoError> {
+        self.transfer(node_id, index, subindex, options, |session| {
+            if options.block {
+                session.block_upload(options.block_size)
+            } else {
+                session.upload()
+            }
+        })
+    }
+
+    /// Write an object to a node's dictionary
+    pub fn sdo_download(
+        &self,
+        node_id: u8,
+        index: u16,
+        subindex: u8,
+        data: &[u8],
+        options: &SdoOptions,
+    ) -> Result<(), SdoError> {
+        self.transfer(node_id, index, subindex, options, |session| {
+            if options.block {
+                session.block_download(data)
+            } else {
+                session.download(data)
+            }
+        })
+    }
+
+    /// Write a PDO's communication and mapping parameters to a node
+    ///
+    /// Follows the CiA 301 sequence: disable the PDO, clear the mapping,
+    /// write the entries, set their count and enable the PDO again.
+    pub fn write_pdo_mapping(
+        &self,
+        node_id: u8,
+        mapping: &PdoMapping,
+        options: &SdoOptions,
+    ) -> Result<(), SdoError> {
+        let options = SdoOptions {
+            block: false,
+            ..*options
+        };
+        let communication = mapping.kind.communication_index() + mapping.number - 1;
+        let mapping_index = mapping.kind.mapping_index() + mapping.number - 1;
+        let write = |index: u16, subindex: u8, data: &[u8]| {
+            self.sdo_download(node_id, index, subindex, data, &options)
+        };
+
+        write(
+            communication,
+            1,
+            &(mapping.cob_id | PDO_DISABLED).to_le_bytes(),
+        )?;
+        write(mapping_index, 0, &[0])?;
+        for (i, entry) in mapping.entries.iter().enumerate() {
+            write(mapping_index, i as u8 + 1, &entry.to_u32().to_le_bytes())?;
+        }
+        write(mapping_index, 0, &[mapping.entries.len() as u8])?;
+        write(communication, 2, &[mapping.transmission_type])?;
+        if mapping.enabled() {
+            write(communication, 1, &mapping.cob_id.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn transfer<T>(
+        &self,
+        node_id: u8,
+        index: u16,
+        subindex: u8,
+        options: &SdoOptions,
+        run: impl FnOnce(&SdoSession<L>) -> Result<T, SdoError>,
+    ) -> Result<T, SdoError> {
+        if !(1..=127).contains(&node_id) {
+            return Err(SdoError::Protocol(format!("Invalid node ID {}", node_id)));
+        }
+        if options.block && !(1..=127).contains(&options.block_size) {
+            return Err(SdoError::Protocol(format!(
+                "Invalid block size {}",
+                options.block_size
+            )));
+        }
+
+        let node_lock = Arc::clone(
+            self.sdo_locks
+                .lock()
+                .unwrap()
+                .entry(node_id)
+                .or_insert_with(|| Arc::new(Mutex::new(()))),
+        );
+        let _guard = node_lock.lock().unwrap();
+
+        let (route_tx, route_rx) = channel();
+        self.shared
+            .sdo_routes
+            .lock()
+            .unwrap()
+            .insert(node_id, route_tx);
+        let session = SdoSession {
+            tx: &*self.tx,
+            route: SdoRoute {
+                shared: &self.shared,
+                node_id,
+                rx: route_rx,
+            },
+            node_id,
+            index,
+            subindex,
+            timeout: options.timeout,
+        };
+
+        let result = run(&session);
+        if let Err(e) = &result {
+            if let Some(code) = e.local_abort_code() {
+                session.abort(code);
+            }
+        }
+        result
+    }
+}
+
+impl<L: CanLink + Send + Sync + 'static> Drop for CanOpenMaster<L> {
+    fn drop(&mut self) {
+        self.stop_sync();
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.dispatcher.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// One SDO transfer with a node
+struct SdoSession<'a, L: CanLink> {
+    tx: &'a L,
+    route: SdoRoute<'a>,
+    node_id: u8,
+    index: u16,
+    subindex: u8,
+    timeout: Duration,
+}
+
+impl<L: CanLink> SdoSession<'_, L> {
+    fn send(&self, frame: [u8; 8]) -> Result<(), SdoError> {
+        self.tx
+            .send(
+                COB_SDO_RX + self.node_id as u32,
+                frame.to_vec(),
+                false,
+                false,
+            )
+            .map_err(|e| SdoError::Transport(e.to_string()))
+    }
+
+    /// Wait for the next server response, surfacing aborts
+    fn recv(&self) -> Result<[u8; 8], SdoError> {
+        let data = self
+            .route
+            .rx
+            .recv_timeout(self.timeout)
+            .map_err(|_| SdoError::Timeout {
+                index: self.index,
+                subindex: self.subindex,
+            })?;
+        if data.len() < 8 {
+            return Err(SdoError::Protocol(format!(
+                "response too short ({} bytes)",
+                data.len()
+            )));
+        }
+        let mut frame = [0u8; 8];
+        frame.copy_from_slice(&data[..8]);
+        if frame[0] == 0x80 {
+            return Err(SdoError::Abort {
+                index: u16::from_le_bytes([frame[1], frame[2]]),
+                subindex: frame[3],
+                code: u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]),
+            });
+        }
+        Ok(frame)
+    }
+
+    fn abort(&self, code: u32) {
+        let mut frame = self.initiate_frame(0x80);
+        frame[4..8].copy_from_slice(&code.to_le_bytes());
+        let _ = self.send(frame);
+    }
+
+    fn initiate_frame(&self, command: u8) -> [u8; 8] {
+        let index = self.index.to_le_bytes();
+        [command, index[0], index[1], self.subindex, 0, 0, 0, 0]
+    }
+
+    /// Check the command specifier and multiplexer of an initiate response
+    fn expect_initiate(&self, frame: &[u8; 8], scs: u8) -> Result<(), SdoError> {
+        if frame[0] >> 5 != scs {
+            return Err(SdoError::Protocol(format!(
+                "unexpected command 0x{:02X}",
+                frame[0]
+            )));
+        }
+        if u16::from_le_bytes([frame[1], frame[2]]) != self.index || frame[3] != self.subindex {
+            return Err(SdoError::Protocol("multiplexer mismatch".to_string()));
+        }
+        Ok(())
+    }
+
+    fn fail(&self, code: u32, message: &str) -> SdoError {
+        self.abort(code);
+        SdoError::Protocol(message.to_string())
+    }
+
+    fn upload(&self) -> Result<Vec<u8>, SdoError> {
+        self.send(self.initiate_frame(0x40))?;
+        let response = self.recv()?;
+        self.expect_initiate(&response, 2)?;
+
+        let expedited = response[0] & 0x02 != 0;
+        let size_indicated = response[0] & 0x01 != 0;
+        if expedited {
+            let len = if size_indicated {
+                4 - ((response[0] >> 2) & 0x03) as usize
+            } else {
+                4
+            };
+            return Ok(response[4..4 + len].to_vec());
+        }
+
+        let expected_size = size_indicated
+            .then(|| u32::from_le_bytes([response[4], response[5], response[6], response[7]]));
+        let mut data = Vec::new();
+        let mut toggle = 0u8;
+        loop {
+            self.send([0x60 | (toggle << 4), 0, 0, 0, 0, 0, 0, 0])?;
+            let segment = self.recv()?;
+            if segment[0] >> 5 != 0 {
+                return Err(SdoError::Protocol(format!(
+                    "unexpected command 0x{:02X}",
+                    segment[0]
+                )));
+            }
+            if (segment[0] >> 4) & 0x01 != toggle {
+                return Err(self.fail(ABORT_TOGGLE, "toggle bit not alternated"));
+            }
+            let unused = ((segment[0] >> 1) & 0x07) as usize;
+            data.extend_from_slice(&segment[1..8 - unused]);
+            if segment[0] & 0x01 != 0 {
+                break;
+            }
+            toggle ^= 1;
+        }
+
+        if let Some(size) = expected_size {
+            if size as usize != data.len() {
+                return Err(self.fail(ABORT_LENGTH, "received length does not match size"));
+            }
+        }
+        Ok(data)
+    }
+
+    fn download(&self, data: &[u8]) -> Result<(), SdoError> {
+        if !data.is_empty() && data.len() <= 4 {
+            let mut frame = self.initiate_frame(0x23 | (((4 - data.len()) as u8) << 2));
+            frame[4..4 + data.len()].copy_from_slice(data);
+            self.send(frame)?;
+            let response = self.recv()?;
+            return self.expect_initiate(&response, 3);
+        }
+
+        let mut frame = self.initiate_frame(0x21);
+        frame[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        self.send(frame)?;
+        let response = self.recv()?;
+        self.expect_initiate(&response, 3)?;
+
+        let mut toggle = 0u8;
+        let mut chunks = data.chunks(7).peekable();
+        if chunks.peek().is_none() {
+            // Zero-length object: one empty last segment
+            self.send([0x0F, 0, 0, 0, 0, 0, 0, 0])?;
+            self.recv_download_ack(0)?;
+            return Ok(());
+        }
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            let mut segment = [0u8; 8];
+            segment[0] = (toggle << 4) | (((7 - chunk.len()) as u8) << 1) | last as u8;
+            segment[1..1 + chunk.len()].copy_from_slice(chunk);
+            self.send(segment)?;
+            self.recv_download_ack(toggle)?;
+            toggle ^= 1;
+        }
+        Ok(())
+    }
+
+    fn recv_download_ack(&self, toggle: u8) -> Result<(), SdoError> {
+        let response = self.recv()?;
+        if response[0] >> 5 != 1 {
+            return Err(SdoError::Protocol(format!(
+                "unexpected command 0x{:02X}",
+                response[0]
+            )));
+        }
+        if (response[0] >> 4) & 0x01 != toggle {
+            return Err(self.fail(ABORT_TOGGLE, "toggle bit not alternated"));
+        }
+        Ok(())
+    }
+
+    fn block_upload(&self, block_size: u8) -> Result<Vec<u8>, SdoError> {
+        // ccs=5, CRC supported, protocol switch disabled
+        let mut frame = self.initiate_frame(0xA4);
+        frame[4] = block_size;
+        self.send(frame)?;
+        let response = self.recv()?;
+        self.expect_initiate(&response, 6)?;
+        let server_crc = response[0] & 0x04 != 0;
+        let expected_size = (response[0] & 0x02 != 0)
+            .then(|| u32::from_le_bytes([response[4], response[5], response[6], response[7]]));
+
+        self.send([0xA3, 0, 0, 0, 0, 0, 0, 0])?;
+
+        let mut data = Vec::new();
+        let mut expected_seq = 1u8;
+        loop {
+            let segment = self.recv()?;
+            let seq = segment[0] & 0x7F;
+            let last = segment[0] & 0x80 != 0;
+            let in_order = seq == expected_seq;
+            if in_order {
+                data.extend_from_slice(&segment[1..8]);
+                expected_seq += 1;
+            }
+            if seq == block_size || last {
+                // Acknowledge what arrived in order, the server resends the rest
+                self.send([0xA2, expected_seq - 1, block_size, 0, 0, 0, 0, 0])?;
+                expected_seq = 1;
+                if last && in_order {
+                    break;
+                }
+            }
+        }
+
+        let end = self.recv()?;
+        if end[0] >> 5 != 6 || end[0] & 0x03 != 0x01 {
+            return Err(self.fail(ABORT_COMMAND, "expected block upload end"));
+        }
+        let unused = ((end[0] >> 2) & 0x07) as usize;
+        data.truncate(data.len().saturating_sub(unused));
+
+        if server_crc && sdo_crc(&data) != u16::from_le_bytes([end[1], end[2]]) {
+            return Err(self.fail(ABORT_CRC, "CRC mismatch"));
+        }
+        if let Some(size) = expected_size {
+            if size as usize != data.len() {
+                return Err(self.fail(ABORT_LENGTH, "received length does not match size"));
+            }
+        }
+        self.send([0xA1, 0, 0, 0, 0, 0, 0, 0])?;
+        Ok(data)
+    }
+
+    fn block_download(&self, data: &[u8]) -> Result<(), SdoError> {
+        // ccs=6, CRC supported, size indicated
+        let mut frame = self.initiate_frame(0xC6);
+        frame[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        self.send(frame)?;
+        let response = self.recv()?;
+        self.expect_initiate(&response, 5)?;
+        let server_crc = response[0] & 0x04 != 0;
+        let mut block_size = response[4];
+
+        let segments = data.len().div_ceil(7).max(1);
+        let mut next = 0usize;
+        while next < segments {
+            if !(1..=127).contains(&block_size) {
+                return Err(self.fail(ABORT_BLOCK_SIZE, "invalid block size"));
+            }
+            let block_start = next;
+            let mut seq = 0u8;
+            while seq < block_size && next < segments {
+                seq += 1;
+                let chunk = &data[(next * 7).min(data.len())..((next + 1) * 7).min(data.len())];
+                let last = next + 1 == segments;
+                let mut segment = [0u8; 8];
+                segment[0] = ((last as u8) << 7) | seq;
+                segment[1..1 + chunk.len()].copy_from_slice(chunk);
+                self.send(segment)?;
+                next += 1;
+            }
+
+            let ack = self.recv()?;
+            if ack[0] != 0xA2 {
+                return Err(self.fail(ABORT_COMMAND, "expected block acknowledge"));
+            }
+            let ack_seq = ack[1];
+            if ack_seq > seq {
+                return Err(self.fail(ABORT_SEQUENCE, "invalid acknowledged sequence"));
+            }
+            // Resume after the last segment the server confirmed
+            next = block_start + ack_seq as usize;
+            block_size = ack[2];
+        }
+
+        let last_len = data.len() - (segments - 1) * 7;
+        let mut end = [0u8; 8];
+        end[0] = 0xC1 | (((7 - last_len) as u8) << 2);
+        if server_crc {
+            end[1..3].copy_from_slice(&sdo_crc(data).to_le_bytes());
+        }
+        self.send(end)?;
+        let response = self.recv()?;
+        if response[0] != 0xA1 {
+            return Err(SdoError::Protocol(format!(
+                "unexpected command 0x{:02X}",
+                response[0]
+            )));
+        }
+        Ok(())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CANOPEN_REGISTRY: Arc<Mutex<HashMap<u32, Arc<CanOpenMaster>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+fn get_master(master_id: u32) -> Option<Arc<CanOpenMaster>> {
+    CANOPEN_REGISTRY.lock().unwrap().get(&master_id).cloned()
+}
+
+/// Create a CANopen master on an interface
+pub fn create_canopen_master(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let interface = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    match CanOpenMaster::open(&interface) {
+        Ok(master) => {
+            let id = next_handle_id();
+            CANOPEN_REGISTRY
+                .lock()
+                .unwrap()
+                .insert(id, Arc::new(master));
+            Ok(cx.number(id as f64))
+        }
+        Err(e) => cx.throw_error(format!("Failed to create CANopen master: {}", e)),
+    }
+}
+
+/// Send an NMT command from JavaScript
+pub fn canopen_nmt(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let command_arg = cx.argument::<JsValue>(1)?;
+    let node_id = match cx.argument_opt(2) {
+        Some(v) => v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u8,
+        None => 0,
+    };
+
+    let command = if let Ok(name) = command_arg.downcast::<JsString, _>(&mut cx) {
+        NmtCommand::from_name(&name.value(&mut cx))
+    } else {
+        let code = command_arg
+            .downcast_or_throw::<JsNumber, _>(&mut cx)?
+            .value(&mut cx);
+        NmtCommand::from_code(code as u8)
+    };
+    let Some(command) = command else {
+        return cx.throw_error("Invalid NMT command");
+    };
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+
+    match master.nmt(command, node_id) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to send NMT command: {}", e)),
+    }
+}
+
+/// Supervise a node's heartbeat (0 disables supervision)
+pub fn canopen_set_heartbeat_timeout(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let node_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let timeout_ms = cx.argument::<JsNumber>(2)?.value(&mut cx) as u64;
+
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+    let timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms));
+    master.set_heartbeat_timeout(node_id, timeout);
+    Ok(cx.undefined())
+}
+
+/// List known nodes and their NMT state
+pub fn canopen_get_node_states(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+
+    let result = cx.empty_array();
+    for (i, status) in master.node_states().into_iter().enumerate() {
+        let obj = cx.empty_object();
+
+        let node_val = cx.number(status.node_id as f64);
+        obj.set(&mut cx, "nodeId", node_val)?;
+
+        let state_val = cx.string(status.state.as_str());
+        obj.set(&mut cx, "state", state_val)?;
+
+        if let Some(last_seen) = status.last_seen {
+            let last_val = cx.number(last_seen.as_millis() as f64);
+            obj.set(&mut cx, "lastSeenMs", last_val)?;
+        }
+
+        if let Some(timeout) = status.heartbeat_timeout {
+            let timeout_val = cx.number(timeout.as_millis() as f64);
+            obj.set(&mut cx, "heartbeatTimeout", timeout_val)?;
+        }
+
+        let timed_out_val = cx.boolean(status.timed_out);
+        obj.set(&mut cx, "timedOut", timed_out_val)?;
+
+        result.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(result)
+}
+
+fn emcy_to_js<'a, C: Context<'a>>(cx: &mut C, emcy: &EmcyMessage) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+
+    let node_val = cx.number(emcy.node_id as f64);
+    obj.set(cx, "nodeId", node_val)?;
+
+    let code_val = cx.number(emcy.error_code as f64);
+    obj.set(cx, "errorCode", code_val)?;
+
+    let register_val = cx.number(emcy.error_register as f64);
+    obj.set(cx, "errorRegister", register_val)?;
+
+    let data_val = JsBuffer::from_slice(cx, &emcy.manufacturer_data)?;
+    obj.set(cx, "manufacturerData", data_val)?;
+
+    let description_val = cx.string(emcy_description(emcy.error_code));
+    obj.set(cx, "description", description_val)?;
+    Ok(obj)
+}
+
+fn event_to_js<'a, C: Context<'a>>(cx: &mut C, event: NodeEvent) -> JsResult<'a, JsObject> {
+    let obj = match &event {
+        NodeEvent::Emergency(emcy) => emcy_to_js(cx, emcy)?,
+        _ => cx.empty_object(),
+    };
+    let (kind, node_id, state) = match event {
+        NodeEvent::BootUp(node_id) => ("bootUp", Some(node_id), Some(NmtState::BootUp)),
+        NodeEvent::StateChanged { node_id, state } => ("stateChanged", Some(node_id), Some(state)),
+        NodeEvent::HeartbeatTimeout(node_id) => ("heartbeatTimeout", Some(node_id), None),
+        NodeEvent::Sync { counter } => {
+            if let Some(counter) = counter {
+                let counter_val = cx.number(counter as f64);
+                obj.set(cx, "counter", counter_val)?;
+            }
+            ("sync", None, None)
+        }
+        NodeEvent::Emergency(_) => ("emergency", None, None),
+    };
+    let type_val = cx.string(kind);
+    obj.set(cx, "type", type_val)?;
+    if let Some(node_id) = node_id {
+        let node_val = cx.number(node_id as f64);
+        obj.set(cx, "nodeId", node_val)?;
+    }
+    if let Some(state) = state {
+        let state_val = cx.string(state.as_str());
+        obj.set(cx, "state", state_val)?;
+    }
+    Ok(obj)
+}
+
+/// Register a JS callback for node, SYNC and EMCY events
+pub fn canopen_on_node_event(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let callback = cx
+        .argument_opt(1)
+        .and_then(|v| v.downcast::<JsFunction, _>(&mut cx).ok());
+
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+    let Some(callback) = callback else {
+        master.set_event_callback(None);
+        return Ok(cx.undefined());
+    };
+
+    let callback = Arc::new(callback.root(&mut cx));
+    let js_channel = cx.channel();
+    master.set_event_callback(Some(Box::new(move |event| {
+        let callback = Arc::clone(&callback);
+        js_channel.send(move |mut cx| {
+            let obj = event_to_js(&mut cx, event)?;
+            callback
+                .to_inner(&mut cx)
+                .call_with(&cx)
+                .arg(obj)
+                .exec(&mut cx)
+        });
+    })));
+    Ok(cx.undefined())
+}
+
+/// Read SDO options `{ timeout, block, blockSize }`
+fn sdo_options_arg(cx: &mut FunctionContext, index: usize) -> NeonResult<SdoOptions> {
+    let mut options = SdoOptions::default();
+    if let Some(obj) = cx
+        .argument_opt(index)
+        .and_then(|v| v.downcast::<JsObject, _>(cx).ok())
+    {
+        if let Some(timeout) = obj.get_opt::<JsNumber, _, _>(cx, "timeout")? {
+            options.timeout = Duration::from_millis(timeout.value(cx) as u64);
+        }
+        if let Some(block) = obj.get_opt::<JsBoolean, _, _>(cx, "block")? {
+            options.block = block.value(cx);
+        }
+        if let Some(block_size) = obj.get_opt::<JsNumber, _, _>(cx, "blockSize")? {
+            options.block_size = block_size.value(cx) as u8;
+        }
+    }
+    Ok(options)
+}
+
+/// Reject with `abortCode`/`abortName` properties for SDO aborts
+fn throw_sdo_error<'a, T: Value>(cx: &mut TaskContext<'a>, e: SdoError) -> JsResult<'a, T> {
+    let err = cx.error(e.to_string())?;
+    if let SdoError::Abort { code, .. } = e {
+        let v = cx.number(code as f64);
+        err.set(cx, "abortCode", v)?;
+        let v = cx.string(abort_name(code));
+        err.set(cx, "abortName", v)?;
+    }
+    cx.throw(err)
+}
+
+/// SDO upload from JavaScript, resolves with a Buffer
+pub fn canopen_sdo_upload(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let node_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let index = cx.argument::<JsNumber>(2)?.value(&mut cx) as u16;
+    let subindex = cx.argument::<JsNumber>(3)?.value(&mut cx) as u8;
+    let options = sdo_options_arg(&mut cx, 4)?;
+
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+
+    let promise = cx
+        .task(move || master.sdo_upload(node_id, index, subindex, &options))
+        .promise(move |mut cx, result| match result {
+            Ok(data) => JsBuffer::from_slice(&mut cx, &data),
+            Err(e) => throw_sdo_error(&mut cx, e),
+        });
+    Ok(promise)
+}
+
+/// SDO download from JavaScript
+pub fn canopen_sdo_download(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let node_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let index = cx.argument::<JsNumber>(2)?.value(&mut cx) as u16;
+    let subindex = cx.argument::<JsNumber>(3)?.value(&mut cx) as u8;
+    let data = cx.argument::<JsBuffer>(4)?.as_slice(&cx).to_vec();
+    let options = sdo_options_arg(&mut cx, 5)?;
+
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+
+    let promise = cx
+        .task(move || master.sdo_download(node_id, index, subindex, &data, &options))
+        .promise(move |mut cx, result| match result {
+            Ok(()) => Ok(cx.undefined()),
+            Err(e) => throw_sdo_error(&mut cx, e),
+        });
+    Ok(promise)
+}
+
+/// Start producing SYNC from JavaScript
+pub fn canopen_start_sync(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let period_ms = cx.argument::<JsNumber>(1)?.value(&mut cx) as u64;
+    let counter_overflow = match cx.argument_opt(2) {
+        Some(v) if !v.is_a::<JsUndefined, _>(&mut cx) => {
+            Some(v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u8)
+        }
+        _ => None,
+    };
+
+    if period_ms == 0 {
+        return cx.throw_error("SYNC period must be greater than 0");
+    }
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+    match master.start_sync(Duration::from_millis(period_ms), counter_overflow) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to start SYNC: {}", e)),
+    }
+}
+
+/// Stop producing SYNC
+pub fn canopen_stop_sync(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+    master.stop_sync();
+    Ok(cx.undefined())
+}
+
+/// Decode an EMCY frame `{ id, data }`, `null` for other frames
+pub fn canopen_decode_emcy(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let frame = cx.argument::<JsObject>(0)?;
+    let id = frame.get::<JsNumber, _, _>(&mut cx, "id")?.value(&mut cx) as u32;
+    let data_array = frame.get::<JsArray, _, _>(&mut cx, "data")?;
+    let mut data = Vec::new();
+    for i in 0..data_array.len(&mut cx) {
+        let val = data_array.get::<JsNumber, _, _>(&mut cx, i)?.value(&mut cx) as u8;
+        data.push(val);
+    }
+
+    match decode_emcy(id, &data) {
+        Some(emcy) => Ok(emcy_to_js(&mut cx, &emcy)?.upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+/// Write a PDO mapping to a node through SDO
+///
+/// The mapping is either given inline or taken from an object dictionary.
+pub fn canopen_write_pdo_mapping(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let node_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let device_id = cx.argument::<JsNumber>(2)?.value(&mut cx) as u32;
+    let spec = cx.argument::<JsValue>(3)?;
+    let options = sdo_options_arg(&mut cx, 4)?;
+
+    let Some(master) = get_master(master_id) else {
+        return cx.throw_error("Invalid CANopen master ID");
+    };
+    let Some(device) = get_pdo_device(device_id) else {
+        return cx.throw_error("Invalid object dictionary ID");
+    };
+
+    let mapping = {
+        let device = device.lock().unwrap();
+        if let Ok(obj) = spec.downcast::<JsObject, _>(&mut cx) {
+            let mapping = mapping_from_js(&mut cx, &device.od, obj)?;
+            if let Err(e) = mapping.validate(&device.od) {
+                return cx.throw_error(format!("Invalid PDO mapping: {}", e));
+            }
+            mapping
+        } else {
+            // "tpdo1", "rpdo2"...
+            let name = spec
+                .downcast_or_throw::<JsString, _>(&mut cx)?
+                .value(&mut cx);
+            // Byte 4 may fall inside a multi-byte character
+            let kind = name.get(..4).and_then(PdoKind::from_name);
+            let number = name.get(4..).and_then(|number| number.parse::<u16>().ok());
+            let found = kind
+                .zip(number)
+                .and_then(|(kind, number)| device.mapping(kind, number));
+            match found {
+                Some(mapping) => mapping.clone(),
+                None => return cx.throw_error(format!("Unknown PDO {}", name)),
+            }
+        }
+    };
+
+    let promise = cx
+        .task(move || master.write_pdo_mapping(node_id, &mapping, &options))
+        .promise(move |mut cx, result| match result {
+            Ok(()) => Ok(cx.undefined()),
+            Err(e) => throw_sdo_error(&mut cx, e),
+        });
+    Ok(promise)
+}
+
+/// Stop a CANopen master
+pub fn close_canopen_master(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let master_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match CANOPEN_REGISTRY.lock().unwrap().remove(&master_id) {
+        Some(master) => {
+            master.set_event_callback(None);
+            Ok(cx.undefined())
+        }
+        None => cx.throw_error("Invalid CANopen master ID"),
+    }
+}
@@ -0,0 +1,1135 @@
+//! CANopen object dictionary loaded from EDS/DCF files (CiA 306)
+//!
+//! Also holds the RPDO/TPDO mappings used to decode received PDO frames into
+//! named object values and to encode outgoing ones.
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use neon::types::JsBigInt;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::next_handle_id;
+
+const RPDO_COMMUNICATION: u16 = 0x1400;
+const RPDO_MAPPING: u16 = 0x1600;
+const TPDO_COMMUNICATION: u16 = 0x1800;
+const TPDO_MAPPING: u16 = 0x1A00;
+
+/// Bit 31 of a PDO COB-ID: the PDO does not exist / is disabled
+pub const PDO_DISABLED: u32 = 0x8000_0000;
+
+/// CiA 301 basic data types
+pub mod data_type {
+    pub const BOOLEAN: u16 = 0x0001;
+    pub const INTEGER8: u16 = 0x0002;
+    pub const INTEGER16: u16 = 0x0003;
+    pub const INTEGER32: u16 = 0x0004;
+    pub const UNSIGNED8: u16 = 0x0005;
+    pub const UNSIGNED16: u16 = 0x0006;
+    pub const UNSIGNED32: u16 = 0x0007;
+    pub const REAL32: u16 = 0x0008;
+    pub const VISIBLE_STRING: u16 = 0x0009;
+    pub const OCTET_STRING: u16 = 0x000A;
+    pub const UNICODE_STRING: u16 = 0x000B;
+    pub const DOMAIN: u16 = 0x000F;
+    pub const INTEGER24: u16 = 0x0010;
+    pub const REAL64: u16 = 0x0011;
+    pub const INTEGER40: u16 = 0x0012;
+    pub const INTEGER48: u16 = 0x0013;
+    pub const INTEGER56: u16 = 0x0014;
+    pub const INTEGER64: u16 = 0x0015;
+    pub const UNSIGNED24: u16 = 0x0016;
+    pub const UNSIGNED40: u16 = 0x0018;
+    pub const UNSIGNED48: u16 = 0x0019;
+    pub const UNSIGNED56: u16 = 0x001A;
+    pub const UNSIGNED64: u16 = 0x001B;
+}
+
+/// Size in bits of a fixed-size data type
+pub fn data_type_bits(data_type: u16) -> Option<u32> {
+    use data_type::*;
+    match data_type {
+        BOOLEAN => Some(1),
+        INTEGER8 | UNSIGNED8 => Some(8),
+        INTEGER16 | UNSIGNED16 => Some(16),
+        INTEGER24 | UNSIGNED24 => Some(24),
+        INTEGER32 | UNSIGNED32 | REAL32 => Some(32),
+        INTEGER40 | UNSIGNED40 => Some(40),
+        INTEGER48 | UNSIGNED48 => Some(48),
+        INTEGER56 | UNSIGNED56 => Some(56),
+        INTEGER64 | UNSIGNED64 | REAL64 => Some(64),
+        _ => None,
+    }
+}
+
+fn is_signed(data_type: u16) -> bool {
+    use data_type::*;
+    matches!(
+        data_type,
+        INTEGER8
+            | INTEGER16
+            | INTEGER24
+            | INTEGER32
+            | INTEGER40
+            | INTEGER48
+            | INTEGER56
+            | INTEGER64
+    )
+}
+
+/// Value of a dictionary object
+#[derive(Debug, Clone, PartialEq)]
+pub enum OdValue {
+    Boolean(bool),
+    Integer(i64),
+    Unsigned(u64),
+    Real(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl OdValue {
+    /// Decode `bits` raw bits according to `data_type`
+    pub fn from_raw(data_type: u16, raw: u64, bits: u32) -> Self {
+        match data_type {
+            data_type::BOOLEAN => OdValue::Boolean(raw != 0),
+            data_type::REAL32 => OdValue::Real(f32::from_bits(raw as u32) as f64),
+            data_type::REAL64 => OdValue::Real(f64::from_bits(raw)),
+            t if is_signed(t) && bits > 0 && bits < 64 => {
+                let shift = 64 - bits;
+                OdValue::Integer(((raw << shift) as i64) >> shift)
+            }
+            t if is_signed(t) => OdValue::Integer(raw as i64),
+            _ => OdValue::Unsigned(raw),
+        }
+    }
+
+    /// Raw bits of a numeric value
+    pub fn to_raw(&self, data_type: u16) -> Result<u64, String> {
+        match (self, data_type) {
+            (OdValue::Real(v), data_type::REAL32) => Ok((*v as f32).to_bits() as u64),
+            (OdValue::Real(v), data_type::REAL64) => Ok(v.to_bits()),
+            (OdValue::Integer(v), data_type::REAL32) => Ok((*v as f32).to_bits() as u64),
+            (OdValue::Unsigned(v), data_type::REAL32) => Ok((*v as f32).to_bits() as u64),
+            (OdValue::Integer(v), data_type::REAL64) => Ok((*v as f64).to_bits()),
+            (OdValue::Unsigned(v), data_type::REAL64) => Ok((*v as f64).to_bits()),
+            (OdValue::Boolean(v), _) => Ok(*v as u64),
+            (OdValue::Integer(v), _) => Ok(*v as u64),
+            (OdValue::Unsigned(v), _) => Ok(*v),
+            (OdValue::Real(v), _) => Ok(v.round() as i64 as u64),
+            _ => Err("value is not numeric".to_string()),
+        }
+    }
+
+    /// Parse an EDS value string
+    fn parse(data_type: u16, text: &str) -> Option<Self> {
+        match data_type {
+            data_type::VISIBLE_STRING | data_type::UNICODE_STRING => {
+                Some(OdValue::Text(text.to_string()))
+            }
+            data_type::OCTET_STRING | data_type::DOMAIN => {
+                let text = text.trim();
+                let text = text.strip_prefix("0x").unwrap_or(text);
+                let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+                if !digits.len().is_multiple_of(2) {
+                    return None;
+                }
+                digits
+                    .chunks(2)
+                    .map(|pair| {
+                        std::str::from_utf8(pair)
+                            .ok()
+                            .and_then(|s| u8::from_str_radix(s, 16).ok())
+                    })
+                    .collect::<Option<Vec<u8>>>()
+                    .map(OdValue::Bytes)
+            }
+            data_type::REAL32 | data_type::REAL64 => text.parse::<f64>().ok().map(OdValue::Real),
+            data_type::BOOLEAN => parse_integer(text).map(|v| OdValue::Boolean(v != 0)),
+            t if is_signed(t) => parse_integer(text).map(OdValue::Integer),
+            _ => parse_unsigned(text).map(OdValue::Unsigned),
+        }
+    }
+}
+
+/// Parse an EDS integer: decimal, `0x` hexadecimal or leading-zero octal
+pub fn parse_integer(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::try_from(u64::from_str_radix(hex, 16).ok()?).ok()?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8).ok()?
+    } else {
+        digits.parse::<i64>().ok()?
+    };
+    if negative {
+        value.checked_neg()
+    } else {
+        Some(value)
+    }
+}
+
+/// Parse an EDS unsigned value, hexadecimal ones over the full 64 bits
+fn parse_unsigned(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => parse_integer(text)
+            .and_then(|v| u64::try_from(v).ok())
+            .or_else(|| text.parse().ok()),
+    }
+}
+
+/// Replace `$NODEID` and evaluate the `a+b` form used by EDS defaults
+fn resolve_node_id(text: &str, node_id: Option<u8>) -> Option<String> {
+    if !text.to_ascii_uppercase().contains("$NODEID") {
+        return Some(text.to_string());
+    }
+    let node_id = node_id? as i64;
+    let mut total = 0i64;
+    for term in text.split('+') {
+        let term = term.trim();
+        let value = if term.eq_ignore_ascii_case("$NODEID") {
+            node_id
+        } else {
+            parse_integer(term)?
+        };
+        total = total.checked_add(value)?;
+    }
+    Some(total.to_string())
+}
+
+/// Object dictionary entry (one sub-index)
+#[derive(Debug, Clone, PartialEq)]
+pub struct OdEntry {
+    pub index: u16,
+    pub subindex: u8,
+    /// `Parameter` or `Record.Parameter` for sub-objects
+    pub name: String,
+    pub data_type: u16,
+    pub access: String,
+    pub pdo_mappable: bool,
+    pub default_value: Option<String>,
+    /// DCF `ParameterValue`, takes precedence over the default
+    pub parameter_value: Option<String>,
+}
+
+/// Object dictionary of one node
+#[derive(Debug, Clone, Default)]
+pub struct ObjectDictionary {
+    pub entries: BTreeMap<(u16, u8), OdEntry>,
+    pub node_id: Option<u8>,
+}
+
+type IniSections = Vec<(String, HashMap<String, String>)>;
+
+fn parse_ini(text: &str) -> IniSections {
+    let mut sections: IniSections = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), HashMap::new()));
+        } else if let (Some((key, value)), Some((_, keys))) =
+            (line.split_once('='), sections.last_mut())
+        {
+            keys.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+/// Split an object section name: `1018` or `1018sub2`
+fn object_section(name: &str) -> Option<(u16, Option<u8>)> {
+    let lower = name.to_ascii_lowercase();
+    match lower.split_once("sub") {
+        Some((index, sub)) => Some((
+            u16::from_str_radix(index, 16).ok()?,
+            Some(u8::from_str_radix(sub, 16).ok()?),
+        )),
+        None if lower.len() == 4 => Some((u16::from_str_radix(&lower, 16).ok()?, None)),
+        None => None,
+    }
+}
+
+impl ObjectDictionary {
+    /// Parse an EDS or DCF file
+    ///
+    /// `node_id` resolves `$NODEID` defaults; the DCF `[DeviceComissioning]`
+    /// node ID is used when it is omitted.
+    pub fn parse(text: &str, node_id: Option<u8>) -> Result<Self, String> {
+        let sections = parse_ini(text);
+        let mut od = ObjectDictionary {
+            entries: BTreeMap::new(),
+            node_id,
+        };
+        if od.node_id.is_none() {
+            od.node_id = sections
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("DeviceComissioning"))
+                .and_then(|(_, keys)| keys.get("nodeid"))
+                .and_then(|v| parse_integer(v))
+                .map(|v| v as u8);
+        }
+
+        let objects: HashMap<(u16, Option<u8>), &HashMap<String, String>> = sections
+            .iter()
+            .filter_map(|(name, keys)| object_section(name).map(|key| (key, keys)))
+            .collect();
+        if objects.is_empty() {
+            return Err("no object sections found".to_string());
+        }
+
+        for (&(index, sub), keys) in &objects {
+            if sub.is_some() {
+                continue;
+            }
+            let name = keys.get("parametername").cloned().unwrap_or_default();
+            let object_type = keys
+                .get("objecttype")
+                .and_then(|v| parse_integer(v))
+                .unwrap_or(0x7);
+
+            if object_type == 0x7 {
+                od.insert_entry(index, 0, name, keys)?;
+                continue;
+            }
+
+            // ARRAY/RECORD: sub-objects have their own sections
+            let mut found = false;
+            for (&(sub_index, sub), sub_keys) in &objects {
+                if let (true, Some(subindex)) = (sub_index == index, sub) {
+                    let sub_name = sub_keys.get("parametername").cloned().unwrap_or_default();
+                    od.insert_entry(index, subindex, format!("{}.{}", name, sub_name), sub_keys)?;
+                    found = true;
+                }
+            }
+
+            // CompactSubObj arrays list no sub sections
+            if let (false, Some(count)) = (
+                found,
+                keys.get("compactsubobj").and_then(|v| parse_integer(v)),
+            ) {
+                let mut count_keys = HashMap::new();
+                count_keys.insert("datatype".to_string(), "0x0005".to_string());
+                count_keys.insert("accesstype".to_string(), "ro".to_string());
+                count_keys.insert("defaultvalue".to_string(), count.to_string());
+                od.insert_entry(index, 0, format!("{}.NrOfObjects", name), &count_keys)?;
+                for subindex in 1..=count.clamp(0, 254) as u8 {
+                    od.insert_entry(index, subindex, format!("{}{}", name, subindex), keys)?;
+                }
+            }
+        }
+        Ok(od)
+    }
+
+    fn insert_entry(
+        &mut self,
+        index: u16,
+        subindex: u8,
+        name: String,
+        keys: &HashMap<String, String>,
+    ) -> Result<(), String> {
+        let data_type = match keys.get("datatype") {
+            Some(v) => parse_integer(v).ok_or_else(|| {
+                format!(
+                    "object {:04X}sub{:X}: invalid DataType {}",
+                    index, subindex, v
+                )
+            })? as u16,
+            None => data_type::DOMAIN,
+        };
+        let non_empty = |key: &str| keys.get(key).filter(|v| !v.is_empty()).cloned();
+        self.entries.insert(
+            (index, subindex),
+            OdEntry {
+                index,
+                subindex,
+                name,
+                data_type,
+                access: keys
+                    .get("accesstype")
+                    .cloned()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase(),
+                pdo_mappable: keys
+                    .get("pdomapping")
+                    .and_then(|v| parse_integer(v))
+                    .unwrap_or(0)
+                    != 0,
+                default_value: non_empty("defaultvalue"),
+                parameter_value: non_empty("parametervalue"),
+            },
+        );
+        Ok(())
+    }
+
+    /// Look up an entry by index and sub-index
+    pub fn entry(&self, index: u16, subindex: u8) -> Option<&OdEntry> {
+        self.entries.get(&(index, subindex))
+    }
+
+    /// Look up an entry by name
+    pub fn find(&self, name: &str) -> Option<&OdEntry> {
+        self.entries.values().find(|entry| entry.name == name)
+    }
+
+    /// Configured value of an entry (DCF value, else EDS default)
+    pub fn value(&self, index: u16, subindex: u8) -> Option<OdValue> {
+        let entry = self.entry(index, subindex)?;
+        let text = entry
+            .parameter_value
+            .as_ref()
+            .or(entry.default_value.as_ref())?;
+        let text = resolve_node_id(text, self.node_id)?;
+        OdValue::parse(entry.data_type, &text)
+    }
+
+    fn unsigned(&self, index: u16, subindex: u8) -> Option<u64> {
+        match self.value(index, subindex)? {
+            OdValue::Unsigned(v) => Some(v),
+            OdValue::Integer(v) => Some(v as u64),
+            _ => None,
+        }
+    }
+
+    /// PDO mappings configured in the dictionary
+    pub fn pdo_mappings(&self) -> Vec<PdoMapping> {
+        let mut mappings = Vec::new();
+        for kind in [PdoKind::Rpdo, PdoKind::Tpdo] {
+            for offset in 0..512u16 {
+                let communication = kind.communication_index() + offset;
+                let Some(cob_id) = self.unsigned(communication, 1) else {
+                    continue;
+                };
+                let mapping_index = kind.mapping_index() + offset;
+                let count = self.unsigned(mapping_index, 0).unwrap_or(0) as u8;
+                let entries = (1..=count)
+                    .filter_map(|sub| self.unsigned(mapping_index, sub))
+                    .filter(|&value| value != 0)
+                    .map(|value| PdoEntry::from_u32(value as u32))
+                    .collect();
+                mappings.push(PdoMapping {
+                    kind,
+                    number: offset + 1,
+                    cob_id: cob_id as u32,
+                    transmission_type: self.unsigned(communication, 2).unwrap_or(0xFF) as u8,
+                    entries,
+                });
+            }
+        }
+        mappings
+    }
+
+    /// Name of an object, or `IIII:SS` when unknown
+    fn object_name(&self, index: u16, subindex: u8) -> String {
+        match self.entry(index, subindex) {
+            Some(entry) => entry.name.clone(),
+            None => format!("{:04X}:{:02X}", index, subindex),
+        }
+    }
+
+    /// Data type of a mapped object; dummy entries use their index as type
+    fn mapped_type(&self, index: u16, subindex: u8) -> u16 {
+        match self.entry(index, subindex) {
+            Some(entry) => entry.data_type,
+            None if index < 0x20 => index,
+            None => data_type::DOMAIN,
+        }
+    }
+}
+
+/// Direction of a PDO, seen from the device owning the dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdoKind {
+    /// Received by the device
+    Rpdo,
+    /// Transmitted by the device
+    Tpdo,
+}
+
+impl PdoKind {
+    pub fn communication_index(self) -> u16 {
+        match self {
+            PdoKind::Rpdo => RPDO_COMMUNICATION,
+            PdoKind::Tpdo => TPDO_COMMUNICATION,
+        }
+    }
+
+    pub fn mapping_index(self) -> u16 {
+        match self {
+            PdoKind::Rpdo => RPDO_MAPPING,
+            PdoKind::Tpdo => TPDO_MAPPING,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PdoKind::Rpdo => "rpdo",
+            PdoKind::Tpdo => "tpdo",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rpdo" => Some(PdoKind::Rpdo),
+            "tpdo" => Some(PdoKind::Tpdo),
+            _ => None,
+        }
+    }
+}
+
+/// One object mapped into a PDO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PdoEntry {
+    pub index: u16,
+    pub subindex: u8,
+    pub bits: u8,
+}
+
+impl PdoEntry {
+    /// Decode a mapping parameter `IIIISSLL`
+    pub fn from_u32(value: u32) -> Self {
+        Self {
+            index: (value >> 16) as u16,
+            subindex: (value >> 8) as u8,
+            bits: value as u8,
+        }
+    }
+
+    /// Encode as a mapping parameter `IIIISSLL`
+    pub fn to_u32(self) -> u32 {
+        ((self.index as u32) << 16) | ((self.subindex as u32) << 8) | self.bits as u32
+    }
+}
+
+/// Decoded object value carried by a PDO
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdoValue {
+    pub index: u16,
+    pub subindex: u8,
+    pub name: String,
+    pub value: OdValue,
+}
+
+/// PDO communication and mapping parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdoMapping {
+    pub kind: PdoKind,
+    /// PDO number, starting at 1
+    pub number: u16,
+    pub cob_id: u32,
+    pub transmission_type: u8,
+    pub entries: Vec<PdoEntry>,
+}
+
+impl PdoMapping {
+    /// Whether the PDO is enabled (COB-ID bit 31 clear)
+    pub fn enabled(&self) -> bool {
+        self.cob_id & PDO_DISABLED == 0
+    }
+
+    /// CAN identifier of the PDO
+    pub fn can_id(&self) -> u32 {
+        self.cob_id & 0x1FFF_FFFF
+    }
+
+    /// Total mapped length in bits
+    pub fn bit_length(&self) -> u32 {
+        self.entries.iter().map(|e| e.bits as u32).sum()
+    }
+
+    /// Check the mapping against the dictionary and the 64-bit PDO limit
+    pub fn validate(&self, od: &ObjectDictionary) -> Result<(), String> {
+        if self.bit_length() > 64 {
+            return Err(format!(
+                "{} {} maps {} bits (maximum 64)",
+                self.kind.as_str(),
+                self.number,
+                self.bit_length()
+            ));
+        }
+        for entry in &self.entries {
+            if entry.index < 0x20 {
+                continue;
+            }
+            let Some(object) = od.entry(entry.index, entry.subindex) else {
+                return Err(format!(
+                    "object {:04X}:{:02X} does not exist",
+                    entry.index, entry.subindex
+                ));
+            };
+            if !object.pdo_mappable {
+                return Err(format!("object {} is not PDO mappable", object.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode a PDO payload into object values (dummy entries are skipped)
+    pub fn decode(&self, od: &ObjectDictionary, data: &[u8]) -> Result<Vec<PdoValue>, String> {
+        let needed = self.bit_length().div_ceil(8) as usize;
+        if data.len() < needed {
+            return Err(format!(
+                "{} {} expects {} bytes, got {}",
+                self.kind.as_str(),
+                self.number,
+                needed,
+                data.len()
+            ));
+        }
+        let mut values = Vec::new();
+        let mut offset = 0u32;
+        for entry in &self.entries {
+            let bits = entry.bits as u32;
+            if entry.index >= 0x20 {
+                let data_type = od.mapped_type(entry.index, entry.subindex);
+                let value = if data_type_bits(data_type).is_some() {
+                    OdValue::from_raw(data_type, read_bits(data, offset, bits), bits)
+                } else {
+                    let start = (offset / 8) as usize;
+                    let bytes = data[start..start + (bits / 8) as usize].to_vec();
+                    match data_type {
+                        data_type::VISIBLE_STRING => {
+                            OdValue::Text(String::from_utf8_lossy(&bytes).into_owned())
+                        }
+                        _ => OdValue::Bytes(bytes),
+                    }
+                };
+                values.push(PdoValue {
+                    index: entry.index,
+                    subindex: entry.subindex,
+                    name: od.object_name(entry.index, entry.subindex),
+                    value,
+                });
+            }
+            offset += bits;
+        }
+        Ok(values)
+    }
+
+    /// Encode a PDO payload; missing objects fall back to their dictionary value
+    pub fn encode(
+        &self,
+        od: &ObjectDictionary,
+        values: &HashMap<String, OdValue>,
+    ) -> Result<Vec<u8>, String> {
+        let mut data = vec![0u8; self.bit_length().div_ceil(8) as usize];
+        let mut offset = 0u32;
+        for entry in &self.entries {
+            let bits = entry.bits as u32;
+            if entry.index >= 0x20 {
+                let name = od.object_name(entry.index, entry.subindex);
+                let value = values
+                    .get(&name)
+                    .cloned()
+                    .or_else(|| od.value(entry.index, entry.subindex));
+                let data_type = od.mapped_type(entry.index, entry.subindex);
+                match value {
+                    Some(OdValue::Bytes(bytes)) if offset.is_multiple_of(8) => {
+                        let start = (offset / 8) as usize;
+                        let len = ((bits / 8) as usize).min(bytes.len());
+                        data[start..start + len].copy_from_slice(&bytes[..len]);
+                    }
+                    Some(OdValue::Text(text)) if offset.is_multiple_of(8) => {
+                        let start = (offset / 8) as usize;
+                        let len = ((bits / 8) as usize).min(text.len());
+                        data[start..start + len].copy_from_slice(&text.as_bytes()[..len]);
+                    }
+                    Some(value) => {
+                        let raw = value
+                            .to_raw(data_type)
+                            .map_err(|e| format!("{}: {}", name, e))?;
+                        check_range(&name, data_type, raw, bits, &value)?;
+                        write_bits(&mut data, offset, bits, raw);
+                    }
+                    None => {}
+                }
+            }
+            offset += bits;
+        }
+        Ok(data)
+    }
+}
+
+/// Reject integers that do not fit in the mapped length
+fn check_range(
+    name: &str,
+    data_type: u16,
+    raw: u64,
+    bits: u32,
+    value: &OdValue,
+) -> Result<(), String> {
+    if bits >= 64 || matches!(data_type, data_type::REAL32 | data_type::REAL64) {
+        return Ok(());
+    }
+    let fits = match value {
+        _ if bits == 0 => raw == 0,
+        OdValue::Integer(v) if is_signed(data_type) => {
+            let limit = 1i64 << (bits - 1);
+            (-limit..limit).contains(v)
+        }
+        OdValue::Integer(v) => *v >= 0 && (*v as u64) >> bits == 0,
+        _ => raw >> bits == 0,
+    };
+    if fits {
+        Ok(())
+    } else {
+        Err(format!("{}: value out of range for {} bits", name, bits))
+    }
+}
+
+/// Read `len` little-endian bits starting at bit `start`
+pub fn read_bits(data: &[u8], start: u32, len: u32) -> u64 {
+    let mut raw = 0u64;
+    for i in 0..len {
+        let bit = start + i;
+        if data[(bit / 8) as usize] >> (bit % 8) & 1 != 0 {
+            raw |= 1 << i;
+        }
+    }
+    raw
+}
+
+/// Write `len` little-endian bits starting at bit `start`
+pub fn write_bits(data: &mut [u8], start: u32, len: u32, value: u64) {
+    for i in 0..len {
+        let bit = start + i;
+        let mask = 1 << (bit % 8);
+        if value >> i & 1 != 0 {
+            data[(bit / 8) as usize] |= mask;
+        } else {
+            data[(bit / 8) as usize] &= !mask;
+        }
+    }
+}
+
+/// Dictionary and active PDO mappings of one node
+pub struct PdoDevice {
+    pub od: ObjectDictionary,
+    pub mappings: Vec<PdoMapping>,
+}
+
+impl PdoDevice {
+    /// Device with the mappings configured in the dictionary
+    pub fn new(od: ObjectDictionary) -> Result<Self, String> {
+        let mappings = od.pdo_mappings();
+        for mapping in &mappings {
+            mapping.validate(&od)?;
+        }
+        Ok(Self { od, mappings })
+    }
+
+    /// Add or replace the mapping with the same kind and number
+    pub fn set_mapping(&mut self, mapping: PdoMapping) -> Result<(), String> {
+        mapping.validate(&self.od)?;
+        self.mappings
+            .retain(|m| !(m.kind == mapping.kind && m.number == mapping.number));
+        self.mappings.push(mapping);
+        Ok(())
+    }
+
+    pub fn mapping(&self, kind: PdoKind, number: u16) -> Option<&PdoMapping> {
+        self.mappings
+            .iter()
+            .find(|m| m.kind == kind && m.number == number)
+    }
+
+    /// Decode a frame if its identifier matches an enabled PDO
+    pub fn decode_frame(
+        &self,
+        id: u32,
+        data: &[u8],
+    ) -> Option<Result<(&PdoMapping, Vec<PdoValue>), String>> {
+        let mapping = self
+            .mappings
+            .iter()
+            .find(|m| m.enabled() && m.can_id() == id)?;
+        Some(
+            mapping
+                .decode(&self.od, data)
+                .map(|values| (mapping, values)),
+        )
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref PDO_DEVICE_REGISTRY: Arc<Mutex<HashMap<u32, Arc<Mutex<PdoDevice>>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+pub(crate) fn get_pdo_device(device_id: u32) -> Option<Arc<Mutex<PdoDevice>>> {
+    PDO_DEVICE_REGISTRY.lock().unwrap().get(&device_id).cloned()
+}
+
+/// Convert an object value to JS (64-bit integers beyond 2^53 become bigints)
+fn value_to_js<'a>(cx: &mut FunctionContext<'a>, value: &OdValue) -> JsResult<'a, JsValue> {
+    const SAFE: u64 = 1 << 53;
+    Ok(match value {
+        OdValue::Boolean(v) => cx.boolean(*v).upcast(),
+        OdValue::Integer(v) if v.unsigned_abs() < SAFE => cx.number(*v as f64).upcast(),
+        OdValue::Integer(v) => JsBigInt::from_i64(cx, *v).upcast(),
+        OdValue::Unsigned(v) if *v < SAFE => cx.number(*v as f64).upcast(),
+        OdValue::Unsigned(v) => JsBigInt::from_u64(cx, *v).upcast(),
+        OdValue::Real(v) => cx.number(*v).upcast(),
+        OdValue::Text(v) => cx.string(v).upcast(),
+        OdValue::Bytes(v) => JsBuffer::from_slice(cx, v)?.upcast(),
+    })
+}
+
+/// Convert a JS value to an object value
+fn value_from_js(cx: &mut FunctionContext, value: Handle<JsValue>) -> NeonResult<OdValue> {
+    if let Ok(v) = value.downcast::<JsBoolean, _>(cx) {
+        return Ok(OdValue::Boolean(v.value(cx)));
+    }
+    if let Ok(v) = value.downcast::<JsNumber, _>(cx) {
+        let v = v.value(cx);
+        return Ok(if v.fract() == 0.0 {
+            OdValue::Integer(v as i64)
+        } else {
+            OdValue::Real(v)
+        });
+    }
+    if let Ok(v) = value.downcast::<JsBigInt, _>(cx) {
+        return match v.to_i64(cx) {
+            Ok(v) => Ok(OdValue::Integer(v)),
+            Err(_) => match v.to_u64(cx) {
+                Ok(v) => Ok(OdValue::Unsigned(v)),
+                Err(_) => cx.throw_range_error("BigInt value does not fit in 64 bits"),
+            },
+        };
+    }
+    if let Ok(v) = value.downcast::<JsString, _>(cx) {
+        return Ok(OdValue::Text(v.value(cx)));
+    }
+    if let Ok(v) = value.downcast::<JsBuffer, _>(cx) {
+        return Ok(OdValue::Bytes(v.as_slice(cx).to_vec()));
+    }
+    cx.throw_type_error("Unsupported object value type")
+}
+
+fn mapping_to_js<'a>(
+    cx: &mut FunctionContext<'a>,
+    od: &ObjectDictionary,
+    mapping: &PdoMapping,
+) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+
+    let kind_val = cx.string(mapping.kind.as_str());
+    obj.set(cx, "kind", kind_val)?;
+
+    let number_val = cx.number(mapping.number as f64);
+    obj.set(cx, "number", number_val)?;
+
+    let cob_val = cx.number(mapping.can_id() as f64);
+    obj.set(cx, "cobId", cob_val)?;
+
+    let enabled_val = cx.boolean(mapping.enabled());
+    obj.set(cx, "enabled", enabled_val)?;
+
+    let type_val = cx.number(mapping.transmission_type as f64);
+    obj.set(cx, "transmissionType", type_val)?;
+
+    let entries = cx.empty_array();
+    for (i, entry) in mapping.entries.iter().enumerate() {
+        let entry_obj = cx.empty_object();
+        let index_val = cx.number(entry.index as f64);
+        entry_obj.set(cx, "index", index_val)?;
+        let sub_val = cx.number(entry.subindex as f64);
+        entry_obj.set(cx, "subindex", sub_val)?;
+        let bits_val = cx.number(entry.bits as f64);
+        entry_obj.set(cx, "bits", bits_val)?;
+        let name_val = cx.string(od.object_name(entry.index, entry.subindex));
+        entry_obj.set(cx, "name", name_val)?;
+        entries.set(cx, i as u32, entry_obj)?;
+    }
+    obj.set(cx, "entries", entries)?;
+    Ok(obj)
+}
+
+/// Load an EDS/DCF file
+pub fn load_canopen_eds(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let node_id = match cx.argument_opt(1) {
+        Some(v) if !v.is_a::<JsUndefined, _>(&mut cx) => {
+            Some(v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u8)
+        }
+        _ => None,
+    };
+
+    let text = match std::fs::read(&path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => return cx.throw_error(format!("Failed to read EDS file {}: {}", path, e)),
+    };
+    let od = match ObjectDictionary::parse(&text, node_id) {
+        Ok(od) => od,
+        Err(e) => return cx.throw_error(format!("Failed to parse EDS file: {}", e)),
+    };
+
+    let device = match PdoDevice::new(od) {
+        Ok(device) => device,
+        Err(e) => return cx.throw_error(format!("Invalid PDO mapping in EDS file: {}", e)),
+    };
+
+    let id = next_handle_id();
+    PDO_DEVICE_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(id, Arc::new(Mutex::new(device)));
+    Ok(cx.number(id as f64))
+}
+
+/// Describe one dictionary entry by name or by index/sub-index
+pub fn canopen_get_object(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let device_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let key = cx.argument::<JsValue>(1)?;
+    let subindex = match cx.argument_opt(2) {
+        Some(v) => v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u8,
+        None => 0,
+    };
+    let name = match key.downcast::<JsString, _>(&mut cx) {
+        Ok(name) => Some(name.value(&mut cx)),
+        Err(_) => None,
+    };
+    let index = match name {
+        Some(_) => 0,
+        None => key
+            .downcast_or_throw::<JsNumber, _>(&mut cx)?
+            .value(&mut cx) as u16,
+    };
+
+    let Some(device) = get_pdo_device(device_id) else {
+        return cx.throw_error("Invalid object dictionary ID");
+    };
+    let device = device.lock().unwrap();
+    let entry = match &name {
+        Some(name) => device.od.find(name),
+        None => device.od.entry(index, subindex),
+    };
+    let Some(entry) = entry else {
+        return Ok(cx.undefined().upcast());
+    };
+
+    let obj = cx.empty_object();
+    let index_val = cx.number(entry.index as f64);
+    obj.set(&mut cx, "index", index_val)?;
+    let sub_val = cx.number(entry.subindex as f64);
+    obj.set(&mut cx, "subindex", sub_val)?;
+    let name_val = cx.string(&entry.name);
+    obj.set(&mut cx, "name", name_val)?;
+    let type_val = cx.number(entry.data_type as f64);
+    obj.set(&mut cx, "dataType", type_val)?;
+    let access_val = cx.string(&entry.access);
+    obj.set(&mut cx, "access", access_val)?;
+    let mappable_val = cx.boolean(entry.pdo_mappable);
+    obj.set(&mut cx, "pdoMappable", mappable_val)?;
+    if let Some(value) = device.od.value(entry.index, entry.subindex) {
+        let value_val = value_to_js(&mut cx, &value)?;
+        obj.set(&mut cx, "value", value_val)?;
+    }
+    Ok(obj.upcast())
+}
+
+/// List the PDO mappings of a device
+pub fn canopen_get_pdo_mappings(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let device_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(device) = get_pdo_device(device_id) else {
+        return cx.throw_error("Invalid object dictionary ID");
+    };
+    let device = device.lock().unwrap();
+
+    let result = cx.empty_array();
+    for (i, mapping) in device.mappings.iter().enumerate() {
+        let obj = mapping_to_js(&mut cx, &device.od, mapping)?;
+        result.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(result)
+}
+
+/// Read a mapping object `{ kind, number, cobId, transmissionType?, entries }`
+///
+/// Entries are object names or `{ index, subindex, bits? }`.
+pub(crate) fn mapping_from_js(
+    cx: &mut FunctionContext,
+    od: &ObjectDictionary,
+    obj: Handle<JsObject>,
+) -> NeonResult<PdoMapping> {
+    let kind_name = obj.get::<JsString, _, _>(cx, "kind")?.value(cx);
+    let Some(kind) = PdoKind::from_name(&kind_name) else {
+        return cx.throw_error("PDO kind must be 'rpdo' or 'tpdo'");
+    };
+    let number = obj.get::<JsNumber, _, _>(cx, "number")?.value(cx) as u16;
+    if !(1..=512).contains(&number) {
+        return cx.throw_error("PDO number must be between 1 and 512");
+    }
+    let cob_id = obj.get::<JsNumber, _, _>(cx, "cobId")?.value(cx) as u32;
+    let transmission_type = match obj.get_opt::<JsNumber, _, _>(cx, "transmissionType")? {
+        Some(v) => v.value(cx) as u8,
+        None => 0xFF,
+    };
+
+    let list = obj.get::<JsArray, _, _>(cx, "entries")?;
+    let mut entries = Vec::new();
+    for i in 0..list.len(cx) {
+        let item = list.get::<JsValue, _, _>(cx, i)?;
+        let (index, subindex, bits) = if let Ok(name) = item.downcast::<JsString, _>(cx) {
+            let name = name.value(cx);
+            let Some(entry) = od.find(&name) else {
+                return cx.throw_error(format!("Unknown object {}", name));
+            };
+            (entry.index, entry.subindex, None)
+        } else {
+            let item = item.downcast_or_throw::<JsObject, _>(cx)?;
+            let index = item.get::<JsNumber, _, _>(cx, "index")?.value(cx) as u16;
+            let subindex = match item.get_opt::<JsNumber, _, _>(cx, "subindex")? {
+                Some(v) => v.value(cx) as u8,
+                None => 0,
+            };
+            let bits = item
+                .get_opt::<JsNumber, _, _>(cx, "bits")?
+                .map(|v| v.value(cx) as u8);
+            (index, subindex, bits)
+        };
+        let bits = match bits
+            .or_else(|| data_type_bits(od.mapped_type(index, subindex)).map(|b| b as u8))
+        {
+            Some(bits) => bits,
+            None => {
+                return cx.throw_error(format!(
+                    "Length of object {:04X}:{:02X} is unknown, pass bits",
+                    index, subindex
+                ))
+            }
+        };
+        entries.push(PdoEntry {
+            index,
+            subindex,
+            bits,
+        });
+    }
+
+    Ok(PdoMapping {
+        kind,
+        number,
+        cob_id,
+        transmission_type,
+        entries,
+    })
+}
+
+/// Add or replace a PDO mapping of a device
+pub fn canopen_set_pdo_mapping(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let device_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let obj = cx.argument::<JsObject>(1)?;
+    let Some(device) = get_pdo_device(device_id) else {
+        return cx.throw_error("Invalid object dictionary ID");
+    };
+    let mut device = device.lock().unwrap();
+
+    let mapping = mapping_from_js(&mut cx, &device.od, obj)?;
+    match device.set_mapping(mapping) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Invalid PDO mapping: {}", e)),
+    }
+}
+
+/// Decode a frame `{ id, data }` into named values, `null` if it is not a PDO
+pub fn canopen_decode_pdo(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let device_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let frame = cx.argument::<JsObject>(1)?;
+    let id = frame.get::<JsNumber, _, _>(&mut cx, "id")?.value(&mut cx) as u32;
+    let data_array = frame.get::<JsArray, _, _>(&mut cx, "data")?;
+    let mut data = Vec::new();
+    for i in 0..data_array.len(&mut cx) {
+        let val = data_array.get::<JsNumber, _, _>(&mut cx, i)?.value(&mut cx) as u8;
+        data.push(val);
+    }
+
+    let Some(device) = get_pdo_device(device_id) else {
+        return cx.throw_error("Invalid object dictionary ID");
+    };
+    let device = device.lock().unwrap();
+    let (mapping, values) = match device.decode_frame(id, &data) {
+        None => return Ok(cx.null().upcast()),
+        Some(Ok(decoded)) => decoded,
+        Some(Err(e)) => return cx.throw_error(format!("Failed to decode PDO: {}", e)),
+    };
+
+    let obj = cx.empty_object();
+    let kind_val = cx.string(mapping.kind.as_str());
+    obj.set(&mut cx, "kind", kind_val)?;
+    let number_val = cx.number(mapping.number as f64);
+    obj.set(&mut cx, "number", number_val)?;
+    let cob_val = cx.number(mapping.can_id() as f64);
+    obj.set(&mut cx, "cobId", cob_val)?;
+
+    let values_obj = cx.empty_object();
+    for value in &values {
+        let v = value_to_js(&mut cx, &value.value)?;
+        values_obj.set(&mut cx, value.name.as_str(), v)?;
+    }
+    obj.set(&mut cx, "values", values_obj)?;
+    Ok(obj.upcast())
+}
+
+/// Encode a PDO from named values, returns a frame `{ id, data }`
+pub fn canopen_encode_pdo(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let device_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let kind_name = cx.argument::<JsString>(1)?.value(&mut cx);
+    let number = cx.argument::<JsNumber>(2)?.value(&mut cx) as u16;
+    let values_obj = cx.argument::<JsObject>(3)?;
+
+    let Some(kind) = PdoKind::from_name(&kind_name) else {
+        return cx.throw_error("PDO kind must be 'rpdo' or 'tpdo'");
+    };
+    let keys = values_obj.get_own_property_names(&mut cx)?;
+    let mut values = HashMap::new();
+    for i in 0..keys.len(&mut cx) {
+        let key = keys.get::<JsString, _, _>(&mut cx, i)?;
+        let value = values_obj.get_value(&mut cx, key)?;
+        let value = value_from_js(&mut cx, value)?;
+        values.insert(key.value(&mut cx), value);
+    }
+
+    let Some(device) = get_pdo_device(device_id) else {
+        return cx.throw_error("Invalid object dictionary ID");
+    };
+    let device = device.lock().unwrap();
+    let Some(mapping) = device.mapping(kind, number) else {
+        return cx.throw_error(format!("{} {} is not mapped", kind_name, number));
+    };
+    let data = match mapping.encode(&device.od, &values) {
+        Ok(data) => data,
+        Err(e) => return cx.throw_error(format!("Failed to encode PDO: {}", e)),
+    };
+
+    let obj = cx.empty_object();
+    let id_val = cx.number(mapping.can_id() as f64);
+    obj.set(&mut cx, "id", id_val)?;
+    let data_array = cx.empty_array();
+    for (i, byte) in data.iter().enumerate() {
+        let byte_val = cx.number(*byte as f64);
+        data_array.set(&mut cx, i as u32, byte_val)?;
+    }
+    obj.set(&mut cx, "data", data_array)?;
+    Ok(obj)
+}
+
+/// Release an object dictionary
+pub fn close_canopen_eds(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let device_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match PDO_DEVICE_REGISTRY.lock().unwrap().remove(&device_id) {
+        Some(_) => Ok(cx.undefined()),
+        None => cx.throw_error("Invalid object dictionary ID"),
+    }
+}
@@ -0,0 +1,744 @@
+//! CAN controller configuration over rtnetlink (`IFLA_CAN_*`)
+//!
+//! Settings are sent like `ip link set <dev> type can ...`: an
+//! `RTM_NEWLINK` request whose `IFLA_INFO_DATA` carries the CAN attributes.
+//! Bit rates are checked against the device constants beforehand so that
+//! unreachable settings are reported with a usable message. Controller
+//! state and error counters are read back from the same link attributes.
+
+use neon::prelude::*;
+
+use crate::interfaces::{interface_index, InterfaceError};
+use crate::netlink::{
+    attr_string, attr_u32, attributes, Link, LinkStats, Message, Netlink, ARPHRD_CAN,
+    IFLA_INFO_DATA, IFLA_INFO_KIND, IFLA_LINKINFO, RTM_NEWLINK,
+};
+
+const IFLA_CAN_BITTIMING: u16 = 1;
+const IFLA_CAN_BITTIMING_CONST: u16 = 2;
+const IFLA_CAN_CLOCK: u16 = 3;
+const IFLA_CAN_STATE: u16 = 4;
+const IFLA_CAN_CTRLMODE: u16 = 5;
+const IFLA_CAN_RESTART_MS: u16 = 6;
+const IFLA_CAN_RESTART: u16 = 7;
+const IFLA_CAN_BERR_COUNTER: u16 = 8;
+const IFLA_CAN_DATA_BITTIMING: u16 = 9;
+const IFLA_CAN_DATA_BITTIMING_CONST: u16 = 10;
+const IFLA_CAN_TERMINATION: u16 = 11;
+const IFLA_CAN_TERMINATION_CONST: u16 = 12;
+const IFLA_CAN_BITRATE_CONST: u16 = 13;
+const IFLA_CAN_DATA_BITRATE_CONST: u16 = 14;
+const IFLA_CAN_BITRATE_MAX: u16 = 15;
+const IFLA_CAN_CTRLMODE_EXT: u16 = 17;
+const IFLA_CAN_CTRLMODE_SUPPORTED: u16 = 1;
+
+const CAN_CTRLMODE_LOOPBACK: u32 = 0x01;
+const CAN_CTRLMODE_LISTENONLY: u32 = 0x02;
+const CAN_CTRLMODE_ONE_SHOT: u32 = 0x08;
+const CAN_CTRLMODE_BERR_REPORTING: u32 = 0x10;
+const CAN_CTRLMODE_FD: u32 = 0x20;
+
+/// Largest bit rate deviation accepted by the kernel, in tenths of a percent
+const MAX_BITRATE_ERROR: u64 = 50;
+
+/// Bit timing limits of a controller (`struct can_bittiming_const`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BittimingConst {
+    pub name: String,
+    pub tseg1_min: u32,
+    pub tseg1_max: u32,
+    pub tseg2_min: u32,
+    pub tseg2_max: u32,
+    pub sjw_max: u32,
+    pub brp_min: u32,
+    pub brp_max: u32,
+    pub brp_inc: u32,
+}
+
+impl BittimingConst {
+    fn parse(value: &[u8]) -> Option<Self> {
+        let field = |i: usize| attr_u32(value.get(16 + i * 4..20 + i * 4)?);
+        Some(BittimingConst {
+            name: attr_string(value.get(..16)?),
+            tseg1_min: field(0)?,
+            tseg1_max: field(1)?,
+            tseg2_min: field(2)?,
+            tseg2_max: field(3)?,
+            sjw_max: field(4)?,
+            brp_min: field(5)?,
+            brp_max: field(6)?,
+            brp_inc: field(7)?,
+        })
+    }
+}
+
+/// Bit timing capabilities reported by a CAN device
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceLimits {
+    /// CAN system clock in Hz
+    pub clock: Option<u32>,
+    pub bittiming_const: Option<BittimingConst>,
+    pub data_bittiming_const: Option<BittimingConst>,
+    /// Fixed bit rates of devices without bit timing registers
+    pub bitrate_const: Vec<u32>,
+    pub data_bitrate_const: Vec<u32>,
+    pub bitrate_max: Option<u32>,
+    /// Selectable termination resistors in Ohm
+    pub termination_const: Vec<u16>,
+    /// `CAN_CTRLMODE_*` bits the device supports
+    pub ctrlmode_supported: Option<u32>,
+}
+
+impl DeviceLimits {
+    /// Read the limits from the `IFLA_INFO_DATA` attributes of a link
+    pub fn parse(info_data: &[u8]) -> Self {
+        let mut limits = DeviceLimits::default();
+        for (kind, value) in attributes(info_data) {
+            match kind {
+                IFLA_CAN_CLOCK => limits.clock = attr_u32(value),
+                IFLA_CAN_BITTIMING_CONST => limits.bittiming_const = BittimingConst::parse(value),
+                IFLA_CAN_DATA_BITTIMING_CONST => {
+                    limits.data_bittiming_const = BittimingConst::parse(value)
+                }
+                IFLA_CAN_BITRATE_CONST => limits.bitrate_const = u32_array(value),
+                IFLA_CAN_DATA_BITRATE_CONST => limits.data_bitrate_const = u32_array(value),
+                IFLA_CAN_BITRATE_MAX => limits.bitrate_max = attr_u32(value),
+                IFLA_CAN_TERMINATION_CONST => {
+                    limits.termination_const = value
+                        .chunks_exact(2)
+                        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+                        .collect()
+                }
+                IFLA_CAN_CTRLMODE_EXT => {
+                    for (ext, value) in attributes(value) {
+                        if ext == IFLA_CAN_CTRLMODE_SUPPORTED {
+                            limits.ctrlmode_supported = attr_u32(value);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        limits
+    }
+}
+
+fn u32_array(value: &[u8]) -> Vec<u32> {
+    value
+        .chunks_exact(4)
+        .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Settings of `configureInterface`, `None` leaves a setting unchanged
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CanConfig {
+    pub bitrate: Option<u32>,
+    /// Sample point as a fraction of the bit time (0.875)
+    pub sample_point: Option<f64>,
+    pub dbitrate: Option<u32>,
+    pub dsample_point: Option<f64>,
+    pub fd: Option<bool>,
+    pub listen_only: Option<bool>,
+    pub loopback: Option<bool>,
+    pub one_shot: Option<bool>,
+    pub berr_reporting: Option<bool>,
+    pub restart_ms: Option<u32>,
+    /// Termination resistor in Ohm, 0 to disable
+    pub termination: Option<u16>,
+}
+
+impl CanConfig {
+    /// `struct can_ctrlmode` as (mask, flags)
+    fn ctrlmode(&self) -> (u32, u32) {
+        let modes = [
+            (self.loopback, CAN_CTRLMODE_LOOPBACK),
+            (self.listen_only, CAN_CTRLMODE_LISTENONLY),
+            (self.one_shot, CAN_CTRLMODE_ONE_SHOT),
+            (self.berr_reporting, CAN_CTRLMODE_BERR_REPORTING),
+            (self.fd, CAN_CTRLMODE_FD),
+        ];
+        modes
+            .iter()
+            .fold((0, 0), |(mask, flags), (value, bit)| match value {
+                Some(true) => (mask | bit, flags | bit),
+                Some(false) => (mask | bit, flags),
+                None => (mask, flags),
+            })
+    }
+}
+
+/// Default sample point of the kernel for a bit rate, in tenths of a percent
+fn default_sample_point(bitrate: u32) -> u32 {
+    if bitrate > 800_000 {
+        750
+    } else if bitrate > 500_000 {
+        800
+    } else {
+        875
+    }
+}
+
+/// Closest sample point reachable with `tseg` time quanta (sync excluded)
+fn update_sample_point(btc: &BittimingConst, nominal: u32, tseg: u32) -> u32 {
+    let mut best_error = u32::MAX;
+    let mut best = 0;
+    for i in 0..=1 {
+        let tseg2 = (tseg + 1)
+            .saturating_sub(nominal * (tseg + 1) / 1000)
+            .saturating_sub(i)
+            .clamp(btc.tseg2_min, btc.tseg2_max);
+        let tseg2 = if tseg.saturating_sub(tseg2) > btc.tseg1_max {
+            tseg - btc.tseg1_max
+        } else {
+            tseg2
+        };
+        let sample_point = 1000 * (tseg + 1 - tseg2.min(tseg + 1)) / (tseg + 1);
+        let error = nominal.abs_diff(sample_point);
+        if sample_point <= nominal && error < best_error {
+            best = sample_point;
+            best_error = error;
+        }
+    }
+    best
+}
+
+/// Bit rate nearest to `bitrate` the controller can produce, as computed
+/// by the kernel (`can_calc_bittiming`), with its sample point
+pub fn calc_bitrate(
+    bitrate: u32,
+    sample_point: Option<u32>,
+    clock: u32,
+    btc: &BittimingConst,
+) -> Option<(u32, u32)> {
+    if bitrate == 0 {
+        return None;
+    }
+    let nominal = sample_point.unwrap_or_else(|| default_sample_point(bitrate));
+    let mut best: Option<(u32, u32, u32, u32)> = None; // (rate error, sp error, rate, sp)
+    let brp_inc = btc.brp_inc.max(1);
+
+    let mut tseg = (btc.tseg1_max + btc.tseg2_max) * 2 + 1;
+    while tseg >= (btc.tseg1_min + btc.tseg2_min) * 2 {
+        let tsegall = 1 + tseg / 2;
+        let brp = (clock as u64 / (tsegall as u64 * bitrate as u64)) as u32 + tseg % 2;
+        let brp = brp / brp_inc * brp_inc;
+        if brp >= btc.brp_min.max(1) && brp <= btc.brp_max {
+            let rate = clock / (brp * tsegall);
+            let rate_error = bitrate.abs_diff(rate);
+            let sample_point = update_sample_point(btc, nominal, tseg / 2);
+            let sp_error = nominal.abs_diff(sample_point);
+            let better = match best {
+                None => true,
+                Some((best_rate, best_sp, _, _)) => {
+                    rate_error < best_rate || (rate_error == best_rate && sp_error < best_sp)
+                }
+            };
+            if better {
+                best = Some((rate_error, sp_error, rate, sample_point));
+                if rate_error == 0 && sp_error == 0 {
+                    break;
+                }
+            }
+        }
+        if tseg == 0 {
+            break;
+        }
+        tseg -= 1;
+    }
+    best.map(|(_, _, rate, sample_point)| (rate, sample_point))
+}
+
+/// Sample point fraction to tenths of a percent
+fn sample_point_permill(value: f64, what: &str) -> Result<u32, InterfaceError> {
+    if !(value > 0.0 && value < 1.0) {
+        return Err(InterfaceError::Invalid(format!(
+            "Invalid {}: {} (expected a fraction between 0 and 1)",
+            what, value
+        )));
+    }
+    Ok((value * 1000.0).round() as u32)
+}
+
+fn check_bitrate(
+    bitrate: u32,
+    sample_point: Option<u32>,
+    clock: Option<u32>,
+    btc: Option<&BittimingConst>,
+    fixed: &[u32],
+    what: &str,
+) -> Result<(), InterfaceError> {
+    if !fixed.is_empty() {
+        if fixed.contains(&bitrate) {
+            return Ok(());
+        }
+        let supported: Vec<String> = fixed.iter().map(|rate| rate.to_string()).collect();
+        return Err(InterfaceError::Invalid(format!(
+            "Unsupported {} {} (supported: {})",
+            what,
+            bitrate,
+            supported.join(", ")
+        )));
+    }
+    let (Some(clock), Some(btc)) = (clock, btc) else {
+        return Ok(());
+    };
+    let unreachable = || {
+        InterfaceError::Invalid(format!(
+            "{} {} is not reachable with the {} Hz clock of {}",
+            what, bitrate, clock, btc.name
+        ))
+    };
+    let (rate, _) = calc_bitrate(bitrate, sample_point, clock, btc).ok_or_else(unreachable)?;
+    if bitrate.abs_diff(rate) as u64 * 1000 / bitrate as u64 > MAX_BITRATE_ERROR {
+        return Err(unreachable());
+    }
+    Ok(())
+}
+
+/// Check a configuration against the device limits
+pub fn validate(config: &CanConfig, limits: &DeviceLimits) -> Result<(), InterfaceError> {
+    let sample_point = config
+        .sample_point
+        .map(|sp| sample_point_permill(sp, "sample point"))
+        .transpose()?;
+    let dsample_point = config
+        .dsample_point
+        .map(|sp| sample_point_permill(sp, "data sample point"))
+        .transpose()?;
+    if sample_point.is_some() && config.bitrate.is_none() {
+        return Err(InterfaceError::Invalid(
+            "samplePoint requires bitrate".to_string(),
+        ));
+    }
+    if dsample_point.is_some() && config.dbitrate.is_none() {
+        return Err(InterfaceError::Invalid(
+            "dsamplePoint requires dbitrate".to_string(),
+        ));
+    }
+
+    if let Some(bitrate) = config.bitrate {
+        if let Some(max) = limits.bitrate_max {
+            if bitrate > max {
+                return Err(InterfaceError::Invalid(format!(
+                    "Bitrate {} exceeds the maximum of {}",
+                    bitrate, max
+                )));
+            }
+        }
+        check_bitrate(
+            bitrate,
+            sample_point,
+            limits.clock,
+            limits.bittiming_const.as_ref(),
+            &limits.bitrate_const,
+            "Bitrate",
+        )?;
+    }
+
+    if let Some(dbitrate) = config.dbitrate {
+        if config.fd != Some(true) {
+            return Err(InterfaceError::Invalid(
+                "dbitrate requires fd: true".to_string(),
+            ));
+        }
+        if limits.data_bittiming_const.is_none() && limits.data_bitrate_const.is_empty() {
+            return Err(InterfaceError::Invalid(
+                "Device does not support CAN FD data bit timing".to_string(),
+            ));
+        }
+        if let Some(bitrate) = config.bitrate {
+            if dbitrate < bitrate {
+                return Err(InterfaceError::Invalid(format!(
+                    "Data bitrate {} is lower than the bitrate {}",
+                    dbitrate, bitrate
+                )));
+            }
+        }
+        check_bitrate(
+            dbitrate,
+            dsample_point,
+            limits.clock,
+            limits.data_bittiming_const.as_ref(),
+            &limits.data_bitrate_const,
+            "Data bitrate",
+        )?;
+    }
+
+    let (_, flags) = config.ctrlmode();
+    if let Some(supported) = limits.ctrlmode_supported {
+        if flags & !supported != 0 {
+            return Err(InterfaceError::Invalid(format!(
+                "Unsupported control mode 0x{:X} (supported: 0x{:X})",
+                flags & !supported,
+                supported
+            )));
+        }
+    }
+
+    if let Some(termination) = config.termination {
+        if limits.termination_const.is_empty() {
+            return Err(InterfaceError::Invalid(
+                "Device has no configurable termination".to_string(),
+            ));
+        }
+        if !limits.termination_const.contains(&termination) {
+            let supported: Vec<String> = limits
+                .termination_const
+                .iter()
+                .map(|ohms| ohms.to_string())
+                .collect();
+            return Err(InterfaceError::Invalid(format!(
+                "Unsupported termination {} Ohm (supported: {})",
+                termination,
+                supported.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// `struct can_bittiming` with only the bit rate and sample point set,
+/// the kernel computes the segments
+fn bittiming(bitrate: u32, sample_point: Option<u32>) -> [u8; 32] {
+    let mut data = [0u8; 32];
+    data[0..4].copy_from_slice(&bitrate.to_ne_bytes());
+    data[4..8].copy_from_slice(&sample_point.unwrap_or(0).to_ne_bytes());
+    data
+}
+
+/// `RTM_NEWLINK` request applying a configuration to interface `index`
+pub fn config_message(index: u32, config: &CanConfig) -> Message {
+    let mut message = Message::link(RTM_NEWLINK, 0, index);
+    message.nest(IFLA_LINKINFO);
+    message.attr_str(IFLA_INFO_KIND, "can");
+    message.nest(IFLA_INFO_DATA);
+    if let Some(bitrate) = config.bitrate {
+        let sample_point = config.sample_point.map(|sp| (sp * 1000.0).round() as u32);
+        message.attr(IFLA_CAN_BITTIMING, &bittiming(bitrate, sample_point));
+    }
+    if let Some(dbitrate) = config.dbitrate {
+        let sample_point = config.dsample_point.map(|sp| (sp * 1000.0).round() as u32);
+        message.attr(IFLA_CAN_DATA_BITTIMING, &bittiming(dbitrate, sample_point));
+    }
+    let (mask, flags) = config.ctrlmode();
+    if mask != 0 {
+        let mut ctrlmode = [0u8; 8];
+        ctrlmode[0..4].copy_from_slice(&mask.to_ne_bytes());
+        ctrlmode[4..8].copy_from_slice(&flags.to_ne_bytes());
+        message.attr(IFLA_CAN_CTRLMODE, &ctrlmode);
+    }
+    if let Some(restart_ms) = config.restart_ms {
+        message.attr_u32(IFLA_CAN_RESTART_MS, restart_ms);
+    }
+    if let Some(termination) = config.termination {
+        message.attr(IFLA_CAN_TERMINATION, &termination.to_ne_bytes());
+    }
+    message.end();
+    message.end();
+    message
+}
+
+/// Link of a CAN controller, `NotCan` for virtual and other interfaces
+pub fn can_link(socket: &mut Netlink, name: &str) -> Result<Link, InterfaceError> {
+    let index = interface_index(name)?;
+    let link = socket
+        .link(index)
+        .map_err(|e| InterfaceError::from_os(name, e))?;
+    if link.kind.as_deref() != Some("can") {
+        return Err(InterfaceError::NotCan(name.to_string()));
+    }
+    Ok(link)
+}
+
+/// Apply a configuration to a CAN controller
+pub fn configure(name: &str, config: &CanConfig) -> Result<(), InterfaceError> {
+    let mut socket = Netlink::open()?;
+    let link = can_link(&mut socket, name)?;
+    validate(config, &DeviceLimits::parse(&link.info_data))?;
+    socket
+        .request(config_message(link.index, config))
+        .map_err(|e| InterfaceError::from_os(name, e))?;
+    Ok(())
+}
+
+/// Restart a controller in bus-off state (`ip link set <dev> type can restart`)
+pub fn restart(name: &str) -> Result<(), InterfaceError> {
+    let mut socket = Netlink::open()?;
+    let link = can_link(&mut socket, name)?;
+    let mut message = Message::link(RTM_NEWLINK, 0, link.index);
+    message.nest(IFLA_LINKINFO);
+    message.attr_str(IFLA_INFO_KIND, "can");
+    message.nest(IFLA_INFO_DATA);
+    message.attr_u32(IFLA_CAN_RESTART, 1);
+    message.end();
+    message.end();
+    match socket.request(message) {
+        Ok(_) => Ok(()),
+        // The kernel only restarts a running controller that is bus-off
+        Err(e) if e.raw_os_error() == Some(libc::EBUSY) => {
+            Err(InterfaceError::NotBusOff(name.to_string()))
+        }
+        Err(e) if e.raw_os_error() == Some(libc::EINVAL) => Err(restart_refusal(&link)),
+        Err(e) => Err(InterfaceError::from_os(name, e)),
+    }
+}
+
+/// Cause of an `EINVAL` restart refusal: the controller is down, or
+/// `restart-ms` is set and the kernel refuses manual restarts
+pub fn restart_refusal(link: &Link) -> InterfaceError {
+    let restart_ms = attributes(&link.info_data)
+        .into_iter()
+        .find(|(kind, _)| *kind == IFLA_CAN_RESTART_MS)
+        .and_then(|(_, value)| attr_u32(value));
+    if link.flags & libc::IFF_UP as u32 == 0 {
+        InterfaceError::Invalid(format!("{} is down", link.name))
+    } else if restart_ms.is_some_and(|ms| ms != 0) {
+        InterfaceError::AutoRestart(link.name.clone())
+    } else {
+        InterfaceError::Invalid(format!("{} refused the restart", link.name))
+    }
+}
+
+/// Error state of a CAN controller (`enum can_state`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanState {
+    ErrorActive,
+    ErrorWarning,
+    ErrorPassive,
+    BusOff,
+    Stopped,
+    Sleeping,
+}
+
+impl CanState {
+    pub fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(CanState::ErrorActive),
+            1 => Some(CanState::ErrorWarning),
+            2 => Some(CanState::ErrorPassive),
+            3 => Some(CanState::BusOff),
+            4 => Some(CanState::Stopped),
+            5 => Some(CanState::Sleeping),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CanState::ErrorActive => "error-active",
+            CanState::ErrorWarning => "error-warning",
+            CanState::ErrorPassive => "error-passive",
+            CanState::BusOff => "bus-off",
+            CanState::Stopped => "stopped",
+            CanState::Sleeping => "sleeping",
+        }
+    }
+}
+
+/// Controller event counters (`struct can_device_stats`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeviceStats {
+    pub bus_error: u32,
+    pub error_warning: u32,
+    pub error_passive: u32,
+    pub bus_off: u32,
+    pub arbitration_lost: u32,
+    pub restarts: u32,
+}
+
+impl DeviceStats {
+    fn parse(value: &[u8]) -> Option<Self> {
+        let field = |i: usize| attr_u32(value.get(i * 4..i * 4 + 4)?);
+        Some(DeviceStats {
+            bus_error: field(0)?,
+            error_warning: field(1)?,
+            error_passive: field(2)?,
+            bus_off: field(3)?,
+            arbitration_lost: field(4)?,
+            restarts: field(5)?,
+        })
+    }
+}
+
+/// Statistics of a CAN interface; the controller fields are `None` for
+/// virtual interfaces and drivers that do not report them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterfaceStats {
+    pub state: Option<CanState>,
+    /// Transmit and receive error counters (`struct can_berr_counter`)
+    pub error_counters: Option<(u16, u16)>,
+    pub device: Option<DeviceStats>,
+    pub link: LinkStats,
+}
+
+impl InterfaceStats {
+    pub fn from_link(link: &Link) -> Self {
+        let mut stats = InterfaceStats {
+            link: link.stats.unwrap_or_default(),
+            ..Default::default()
+        };
+        if link.kind.as_deref() != Some("can") {
+            return stats;
+        }
+        for (kind, value) in attributes(&link.info_data) {
+            match kind {
+                IFLA_CAN_STATE => stats.state = attr_u32(value).and_then(CanState::from_raw),
+                IFLA_CAN_BERR_COUNTER if value.len() >= 4 => {
+                    stats.error_counters = Some((
+                        u16::from_ne_bytes([value[0], value[1]]),
+                        u16::from_ne_bytes([value[2], value[3]]),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        stats.device = DeviceStats::parse(&link.xstats);
+        stats
+    }
+}
+
+/// Read the statistics of a CAN interface
+pub fn interface_stats(name: &str) -> Result<InterfaceStats, InterfaceError> {
+    let index = interface_index(name)?;
+    let link = Netlink::open()?
+        .link(index)
+        .map_err(|e| InterfaceError::from_os(name, e))?;
+    if link.link_type != ARPHRD_CAN {
+        return Err(InterfaceError::NotCan(name.to_string()));
+    }
+    Ok(InterfaceStats::from_link(&link))
+}
+
+fn config_from_js(cx: &mut FunctionContext, obj: Handle<JsObject>) -> NeonResult<CanConfig> {
+    let number = |cx: &mut FunctionContext, key: &str| -> NeonResult<Option<f64>> {
+        Ok(obj
+            .get_opt::<JsNumber, _, _>(cx, key)?
+            .map(|value| value.value(cx)))
+    };
+    let boolean = |cx: &mut FunctionContext, key: &str| -> NeonResult<Option<bool>> {
+        Ok(obj
+            .get_opt::<JsBoolean, _, _>(cx, key)?
+            .map(|value| value.value(cx)))
+    };
+    Ok(CanConfig {
+        bitrate: number(cx, "bitrate")?.map(|v| v as u32),
+        sample_point: number(cx, "samplePoint")?,
+        dbitrate: number(cx, "dbitrate")?.map(|v| v as u32),
+        dsample_point: number(cx, "dsamplePoint")?,
+        fd: boolean(cx, "fd")?,
+        listen_only: boolean(cx, "listenOnly")?,
+        loopback: boolean(cx, "loopback")?,
+        one_shot: boolean(cx, "oneShot")?,
+        berr_reporting: boolean(cx, "berrReporting")?,
+        restart_ms: number(cx, "restartMs")?.map(|v| v as u32),
+        termination: number(cx, "termination")?.map(|v| v as u16),
+    })
+}
+
+/// Configure bit timing and control modes of a CAN controller
+pub fn configure_interface(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+    let options = cx.argument::<JsObject>(1)?;
+    let config = config_from_js(&mut cx, options)?;
+
+    match configure(&name, &config) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to configure interface: {}", e)),
+    }
+}
+
+fn stats_to_js<'a>(cx: &mut FunctionContext<'a>, stats: &InterfaceStats) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+
+    let state: Handle<JsValue> = match stats.state {
+        Some(state) => cx.string(state.as_str()).upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "state", state)?;
+
+    let counters: Handle<JsValue> = match stats.error_counters {
+        Some((tx, rx)) => {
+            let counters = cx.empty_object();
+            let tx = cx.number(tx);
+            counters.set(cx, "tx", tx)?;
+            let rx = cx.number(rx);
+            counters.set(cx, "rx", rx)?;
+            counters.upcast()
+        }
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "errorCounters", counters)?;
+
+    let device: Handle<JsValue> = match stats.device {
+        Some(device) => {
+            let fields = [
+                ("busError", device.bus_error),
+                ("errorWarning", device.error_warning),
+                ("errorPassive", device.error_passive),
+                ("busOff", device.bus_off),
+                ("arbitrationLost", device.arbitration_lost),
+                ("restarts", device.restarts),
+            ];
+            let device = cx.empty_object();
+            for (key, value) in fields {
+                let value = cx.number(value);
+                device.set(cx, key, value)?;
+            }
+            device.upcast()
+        }
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "controller", device)?;
+
+    let link = &stats.link;
+    let directions = [
+        (
+            "rx",
+            [
+                link.rx_packets,
+                link.rx_bytes,
+                link.rx_errors,
+                link.rx_dropped,
+            ],
+        ),
+        (
+            "tx",
+            [
+                link.tx_packets,
+                link.tx_bytes,
+                link.tx_errors,
+                link.tx_dropped,
+            ],
+        ),
+    ];
+    for (key, values) in directions {
+        let counters = cx.empty_object();
+        for (name, value) in ["packets", "bytes", "errors", "dropped"].iter().zip(values) {
+            let value = cx.number(value as f64);
+            counters.set(cx, *name, value)?;
+        }
+        obj.set(cx, key, counters)?;
+    }
+    Ok(obj)
+}
+
+/// Controller state, error counters and traffic statistics of an interface
+pub fn get_interface_stats(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    match interface_stats(&name) {
+        Ok(stats) => stats_to_js(&mut cx, &stats),
+        Err(e) => cx.throw_error(format!("Failed to get interface statistics: {}", e)),
+    }
+}
+
+/// Restart a CAN controller after bus-off
+pub fn restart_interface(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    match restart(&name) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to restart interface: {}", e)),
+    }
+}
@@ -0,0 +1,577 @@
+//! DBC file parser
+//!
+//! Builds a [`SignalDatabase`] from the DBC sections used for decoding:
+//! `BU_`, `BO_`, `SG_`, `CM_`, `BA_DEF_DEF_`, `BA_`, `VAL_`,
+//! `SIG_VALTYPE_` and `SG_MUL_VAL_`. Other sections are skipped.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::signal_db::{
+    AttributeValue, ByteOrder, Message, Multiplex, MuxCondition, Signal, SignalDatabase, ValueType,
+    MAX_MESSAGE_SIZE, MAX_SIGNAL_BITS,
+};
+
+/// Flag marking an extended identifier in DBC message IDs
+const DBC_EXTENDED_FLAG: u32 = 0x8000_0000;
+
+/// Pseudo-message holding signals not attached to a message
+const INDEPENDENT_SIGNALS_ID: u32 = 0xC000_0000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(char),
+}
+
+struct Lexed {
+    token: Token,
+    line: usize,
+    /// First token of its line: statements start here
+    line_start: bool,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Lexed>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            chars.next();
+            line += 1;
+            line_start = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let token_line = line;
+        let token = if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => {
+                        if let Some(escaped) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    Some(ch) => {
+                        if ch == '\n' {
+                            line += 1;
+                        }
+                        value.push(ch);
+                    }
+                    None => return Err(format!("line {}: unterminated string", token_line)),
+                }
+            }
+            Token::Str(value)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut value = String::new();
+            while let Some(&ch) = chars.peek() {
+                if !(ch.is_ascii_alphanumeric() || ch == '_') {
+                    break;
+                }
+                value.push(ch);
+                chars.next();
+            }
+            Token::Ident(value)
+        } else if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' {
+            chars.next();
+            let signed = c == '-' || c == '+';
+            if signed
+                && !chars
+                    .peek()
+                    .is_some_and(|ch| ch.is_ascii_digit() || *ch == '.')
+            {
+                Token::Punct(c)
+            } else {
+                let mut value = String::from(c);
+                while let Some(&ch) = chars.peek() {
+                    let exponent_sign = (ch == '-' || ch == '+') && value.ends_with(['e', 'E']);
+                    if !(ch.is_ascii_digit()
+                        || ch == '.'
+                        || ch == 'e'
+                        || ch == 'E'
+                        || exponent_sign)
+                    {
+                        break;
+                    }
+                    value.push(ch);
+                    chars.next();
+                }
+                Token::Number(value)
+            }
+        } else {
+            chars.next();
+            Token::Punct(c)
+        };
+
+        tokens.push(Lexed {
+            token,
+            line: token_line,
+            line_start,
+        });
+        line_start = false;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Lexed>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(0, |t| t.line)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line(), message))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|t| t.token.clone());
+        self.pos += 1;
+        token
+    }
+
+    /// Whether the current token starts a new statement
+    fn at_statement(&self) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_none_or(|t| t.line_start && matches!(t.token, Token::Ident(_)))
+    }
+
+    fn skip_statement(&mut self) {
+        self.pos += 1;
+        while !self.at_statement() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect_punct(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(Token::Punct(c)) if *c == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => self.error(&format!("expected '{}'", expected)),
+        }
+    }
+
+    fn accept_punct(&mut self, expected: char) -> bool {
+        if self.peek() == Some(&Token::Punct(expected)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Ident(value)) => {
+                let value = value.clone();
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => self.error("expected identifier"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Str(value)) => {
+                let value = value.clone();
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => self.error("expected string"),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some(Token::Number(value)) => match value.parse() {
+                Ok(number) => {
+                    self.pos += 1;
+                    Ok(number)
+                }
+                Err(_) => self.error(&format!("invalid number '{}'", value)),
+            },
+            _ => self.error("expected number"),
+        }
+    }
+
+    fn unsigned(&mut self) -> Result<u64, String> {
+        match self.peek() {
+            Some(Token::Number(value)) => match value.parse() {
+                Ok(number) => {
+                    self.pos += 1;
+                    Ok(number)
+                }
+                Err(_) => self.error(&format!("invalid integer '{}'", value)),
+            },
+            _ => self.error("expected integer"),
+        }
+    }
+
+    /// Value/description pairs up to the closing ';'
+    fn value_descriptions(&mut self) -> Result<BTreeMap<i64, String>, String> {
+        let mut table = BTreeMap::new();
+        while !self.accept_punct(';') {
+            if self.at_statement() {
+                break;
+            }
+            let value = self.number()? as i64;
+            let label = self.string()?;
+            table.insert(value, label);
+        }
+        Ok(table)
+    }
+}
+
+struct Builder {
+    db: SignalDatabase,
+    messages: HashMap<u32, usize>,
+}
+
+impl Builder {
+    fn signal_mut(&mut self, dbc_id: u32, name: &str) -> Option<&mut Signal> {
+        let index = *self.messages.get(&dbc_id)?;
+        self.db.messages[index]
+            .signals
+            .iter_mut()
+            .find(|s| s.name == name)
+    }
+
+    fn message_mut(&mut self, dbc_id: u32) -> Option<&mut Message> {
+        let index = *self.messages.get(&dbc_id)?;
+        Some(&mut self.db.messages[index])
+    }
+}
+
+fn parse_multiplex(indicator: &str) -> Option<Multiplex> {
+    if indicator == "M" {
+        return Some(Multiplex::Multiplexor);
+    }
+    let rest = indicator.strip_prefix('m')?;
+    match rest.strip_suffix('M') {
+        Some(value) => value.parse().ok().map(Multiplex::MultiplexedMultiplexor),
+        None => rest.parse().ok().map(Multiplex::Multiplexed),
+    }
+}
+
+fn parse_signal(p: &mut Parser) -> Result<Signal, String> {
+    let name = p.ident()?;
+    let multiplex = match p.peek() {
+        Some(Token::Ident(indicator)) => {
+            let Some(multiplex) = parse_multiplex(indicator) else {
+                return p.error(&format!("invalid multiplexer indicator '{}'", indicator));
+            };
+            p.pos += 1;
+            multiplex
+        }
+        _ => Multiplex::None,
+    };
+    p.expect_punct(':')?;
+    let start_bit = p.unsigned()?;
+    p.expect_punct('|')?;
+    let length = p.unsigned()?;
+    p.expect_punct('@')?;
+    let byte_order = match p.unsigned()? {
+        0 => ByteOrder::BigEndian,
+        1 => ByteOrder::LittleEndian,
+        _ => return p.error("invalid byte order"),
+    };
+    let value_type = match p.next() {
+        Some(Token::Punct('+')) => ValueType::Unsigned,
+        Some(Token::Punct('-')) => ValueType::Signed,
+        _ => return p.error("expected value type '+' or '-'"),
+    };
+    if length == 0 || length > 64 {
+        return p.error(&format!("signal {}: invalid length {}", name, length));
+    }
+    if start_bit >= MAX_SIGNAL_BITS as u64 {
+        return p.error(&format!("signal {}: invalid start bit {}", name, start_bit));
+    }
+    let (start_bit, length) = (start_bit as u32, length as u32);
+
+    p.expect_punct('(')?;
+    let factor = p.number()?;
+    p.expect_punct(',')?;
+    let offset = p.number()?;
+    p.expect_punct(')')?;
+    p.expect_punct('[')?;
+    let minimum = p.number()?;
+    p.expect_punct('|')?;
+    let maximum = p.number()?;
+    p.expect_punct(']')?;
+    let unit = p.string()?;
+
+    let mut receivers = Vec::new();
+    if !p.at_statement() {
+        receivers.push(p.ident()?);
+        while p.accept_punct(',') {
+            receivers.push(p.ident()?);
+        }
+    }
+
+    let mut signal = Signal::new(&name, start_bit, length, byte_order);
+    signal.value_type = value_type;
+    signal.factor = factor;
+    signal.offset = offset;
+    signal.minimum = minimum;
+    signal.maximum = maximum;
+    signal.unit = unit;
+    signal.receivers = receivers;
+    signal.multiplex = multiplex;
+    Ok(signal)
+}
+
+fn parse_attribute_value(p: &mut Parser) -> Result<AttributeValue, String> {
+    match p.peek() {
+        Some(Token::Str(_)) => Ok(AttributeValue::Text(p.string()?)),
+        _ => Ok(AttributeValue::Number(p.number()?)),
+    }
+}
+
+/// Parse the text of a DBC file
+pub fn parse(text: &str) -> Result<SignalDatabase, String> {
+    let mut p = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    let mut b = Builder {
+        db: SignalDatabase::default(),
+        messages: HashMap::new(),
+    };
+    let mut current: Option<u32> = None;
+
+    while p.pos < p.tokens.len() {
+        let Some(Token::Ident(keyword)) = p.peek().cloned() else {
+            p.skip_statement();
+            continue;
+        };
+        if !p.at_statement() {
+            p.skip_statement();
+            continue;
+        }
+        p.pos += 1;
+
+        match keyword.as_str() {
+            "VERSION" => b.db.version = p.string()?,
+            "NS_" => {
+                // La liste des mots-clés est indentée : on saute jusqu'à BS_
+                while p.pos < p.tokens.len() {
+                    if p.at_statement()
+                        && matches!(p.peek(), Some(Token::Ident(k)) if k == "BS_" || k == "BU_")
+                    {
+                        break;
+                    }
+                    p.pos += 1;
+                }
+            }
+            "BU_" => {
+                p.expect_punct(':')?;
+                while !p.at_statement() {
+                    let node = p.ident()?;
+                    b.db.nodes.push(node);
+                }
+            }
+            "BO_" => {
+                let dbc_id = p.unsigned()? as u32;
+                let name = p.ident()?;
+                p.expect_punct(':')?;
+                let size = p.unsigned()?;
+                if size > MAX_MESSAGE_SIZE as u64 {
+                    return p.error(&format!("message {}: invalid size {}", name, size));
+                }
+                let size = size as u32;
+                let transmitter = p.ident()?;
+                current = None;
+                if dbc_id == INDEPENDENT_SIGNALS_ID {
+                    continue;
+                }
+                if b.messages.contains_key(&dbc_id) {
+                    return p.error(&format!("duplicate message ID {}", dbc_id));
+                }
+                let extended = dbc_id & DBC_EXTENDED_FLAG != 0;
+                b.messages.insert(dbc_id, b.db.messages.len());
+                b.db.messages.push(Message {
+                    id: dbc_id & !DBC_EXTENDED_FLAG,
+                    extended,
+                    name,
+                    size,
+                    transmitter: (transmitter != "Vector__XXX").then_some(transmitter),
+                    signals: Vec::new(),
+                    comment: None,
+                    attributes: HashMap::new(),
+                });
+                current = Some(dbc_id);
+            }
+            "SG_" => {
+                let signal = parse_signal(&mut p)?;
+                if let Some(message) = current.and_then(|id| b.message_mut(id)) {
+                    message.signals.push(signal);
+                }
+            }
+            "CM_" => {
+                match p.peek().cloned() {
+                    Some(Token::Ident(kind)) if kind == "BO_" => {
+                        p.pos += 1;
+                        let dbc_id = p.unsigned()? as u32;
+                        let comment = p.string()?;
+                        if let Some(message) = b.message_mut(dbc_id) {
+                            message.comment = Some(comment);
+                        }
+                    }
+                    Some(Token::Ident(kind)) if kind == "SG_" => {
+                        p.pos += 1;
+                        let dbc_id = p.unsigned()? as u32;
+                        let name = p.ident()?;
+                        let comment = p.string()?;
+                        if let Some(signal) = b.signal_mut(dbc_id, &name) {
+                            signal.comment = Some(comment);
+                        }
+                    }
+                    _ => {
+                        p.skip_statement();
+                        continue;
+                    }
+                }
+                p.accept_punct(';');
+            }
+            "BA_DEF_DEF_" => {
+                let name = p.string()?;
+                let value = parse_attribute_value(&mut p)?;
+                b.db.attribute_defaults.insert(name, value);
+                p.accept_punct(';');
+            }
+            "BA_" => {
+                let name = p.string()?;
+                match p.peek().cloned() {
+                    Some(Token::Ident(kind)) if kind == "BO_" => {
+                        p.pos += 1;
+                        let dbc_id = p.unsigned()? as u32;
+                        let value = parse_attribute_value(&mut p)?;
+                        if let Some(message) = b.message_mut(dbc_id) {
+                            message.attributes.insert(name, value);
+                        }
+                    }
+                    Some(Token::Ident(kind)) if kind == "SG_" => {
+                        p.pos += 1;
+                        let dbc_id = p.unsigned()? as u32;
+                        let signal_name = p.ident()?;
+                        let value = parse_attribute_value(&mut p)?;
+                        if let Some(signal) = b.signal_mut(dbc_id, &signal_name) {
+                            signal.attributes.insert(name, value);
+                        }
+                    }
+                    _ => {
+                        p.skip_statement();
+                        continue;
+                    }
+                }
+                p.accept_punct(';');
+            }
+            "VAL_" => {
+                let Some(Token::Number(_)) = p.peek() else {
+                    // Tables de variables d'environnement
+                    p.skip_statement();
+                    continue;
+                };
+                let dbc_id = p.unsigned()? as u32;
+                let name = p.ident()?;
+                let table = p.value_descriptions()?;
+                if let Some(signal) = b.signal_mut(dbc_id, &name) {
+                    signal.value_table = table;
+                }
+            }
+            "SIG_VALTYPE_" => {
+                let dbc_id = p.unsigned()? as u32;
+                let name = p.ident()?;
+                p.accept_punct(':');
+                let value_type = match p.unsigned()? {
+                    1 => ValueType::Float32,
+                    2 => ValueType::Float64,
+                    _ => ValueType::Unsigned,
+                };
+                p.accept_punct(';');
+                if let Some(signal) = b.signal_mut(dbc_id, &name) {
+                    let expected = match value_type {
+                        ValueType::Float32 => 32,
+                        ValueType::Float64 => 64,
+                        _ => signal.length,
+                    };
+                    if signal.length != expected {
+                        return p.error(&format!(
+                            "signal {}: float signal must be {} bits",
+                            name, expected
+                        ));
+                    }
+                    if value_type != ValueType::Unsigned {
+                        signal.value_type = value_type;
+                    }
+                }
+            }
+            "SG_MUL_VAL_" => {
+                let dbc_id = p.unsigned()? as u32;
+                let name = p.ident()?;
+                let multiplexor = p.ident()?;
+                let mut ranges = Vec::new();
+                while !p.accept_punct(';') {
+                    if p.at_statement() {
+                        break;
+                    }
+                    let low = p.unsigned()?;
+                    // "3-5" est lu comme "3" puis "-5"
+                    let high = match p.peek() {
+                        Some(Token::Number(n)) if n.starts_with('-') => {
+                            let high = n[1..].parse().ok();
+                            p.pos += 1;
+                            match high {
+                                Some(high) => high,
+                                None => return p.error("invalid multiplexer range"),
+                            }
+                        }
+                        _ => {
+                            p.expect_punct('-')?;
+                            p.unsigned()?
+                        }
+                    };
+                    ranges.push((low, high));
+                    p.accept_punct(',');
+                }
+                if let Some(signal) = b.signal_mut(dbc_id, &name) {
+                    signal.mux_conditions.push(MuxCondition {
+                        multiplexor,
+                        ranges,
+                    });
+                }
+            }
+            _ => {
+                p.pos -= 1;
+                p.skip_statement();
+            }
+        }
+    }
+
+    b.db.finish();
+    Ok(b.db)
+}
@@ -0,0 +1,759 @@
+//! Kernel CAN gateway (can-gw) rules over rtnetlink
+//!
+//! Rules are `RTM_NEWROUTE`/`RTM_DELROUTE` requests of family `AF_CAN`
+//! with an `rtcanmsg` header and `CGW_*` attributes, like `cangw -A/-D`.
+//! Matching frames are then forwarded, modified and checksummed in the
+//! kernel without a round trip through userspace.
+
+use neon::prelude::*;
+use std::io;
+
+use crate::interfaces::{interface_index, interface_name, InterfaceError};
+use crate::netlink::{attr_u32, attributes, Message, Netlink, NLM_F_CREATE, NLM_F_DUMP};
+
+pub const RTM_NEWROUTE: u16 = 24;
+pub const RTM_DELROUTE: u16 = 25;
+const RTM_GETROUTE: u16 = 26;
+
+const AF_CAN: u8 = 29;
+const CGW_TYPE_CAN_CAN: u8 = 1;
+
+const CGW_FLAGS_CAN_ECHO: u16 = 0x01;
+const CGW_FLAGS_CAN_SRC_TSTAMP: u16 = 0x02;
+const CGW_FLAGS_CAN_IIF_TX_OK: u16 = 0x04;
+const CGW_FLAGS_CAN_FD: u16 = 0x08;
+
+const CGW_MOD_AND: u16 = 1;
+const CGW_MOD_OR: u16 = 2;
+const CGW_MOD_XOR: u16 = 3;
+const CGW_MOD_SET: u16 = 4;
+const CGW_CS_XOR: u16 = 5;
+const CGW_CS_CRC8: u16 = 6;
+const CGW_HANDLED: u16 = 7;
+const CGW_DROPPED: u16 = 8;
+const CGW_SRC_IF: u16 = 9;
+const CGW_DST_IF: u16 = 10;
+const CGW_FILTER: u16 = 11;
+const CGW_DELETED: u16 = 12;
+const CGW_LIM_HOPS: u16 = 13;
+const CGW_MOD_UID: u16 = 14;
+/// `CGW_FDMOD_AND` .. `CGW_FDMOD_SET` follow the classic attributes
+const CGW_FDMOD_OFFSET: u16 = 14;
+
+const CGW_MOD_ID: u8 = 0x01;
+const CGW_MOD_DLC: u8 = 0x02;
+const CGW_MOD_DATA: u8 = 0x04;
+const CGW_MOD_FLAGS: u8 = 0x08;
+
+/// `struct can_frame` and `struct canfd_frame` sizes
+const CAN_FRAME_LEN: usize = 16;
+const CANFD_FRAME_LEN: usize = 72;
+
+/// `struct cgw_csum_crc8`: indexes, init/final values, table, profile
+const CRC8_LEN: usize = 282;
+const CRC8_PROFILE_DATA_LEN: usize = 20;
+
+/// Frame modification operation, applied by the kernel in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModOp {
+    And,
+    Or,
+    Xor,
+    Set,
+}
+
+impl ModOp {
+    const ALL: [ModOp; 4] = [ModOp::And, ModOp::Or, ModOp::Xor, ModOp::Set];
+
+    fn attr(self, fd: bool) -> u16 {
+        let attr = match self {
+            ModOp::And => CGW_MOD_AND,
+            ModOp::Or => CGW_MOD_OR,
+            ModOp::Xor => CGW_MOD_XOR,
+            ModOp::Set => CGW_MOD_SET,
+        };
+        if fd {
+            attr + CGW_FDMOD_OFFSET
+        } else {
+            attr
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModOp::And => "and",
+            ModOp::Or => "or",
+            ModOp::Xor => "xor",
+            ModOp::Set => "set",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ModOp::ALL.into_iter().find(|op| op.as_str() == name)
+    }
+}
+
+/// Operand of a modification, `None` fields are left untouched
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameMod {
+    /// Raw `can_id`, including the EFF/RTR/ERR flag bits
+    pub id: Option<u32>,
+    /// DLC of classic frames, length of CAN FD frames
+    pub dlc: Option<u8>,
+    /// CAN FD frame flags (BRS, ESI)
+    pub flags: Option<u8>,
+    pub data: Option<Vec<u8>>,
+}
+
+impl FrameMod {
+    /// `struct cgw_frame_mod` or `struct cgw_fdframe_mod`
+    fn encode(&self, fd: bool) -> Vec<u8> {
+        let frame_len = if fd { CANFD_FRAME_LEN } else { CAN_FRAME_LEN };
+        let mut out = vec![0u8; frame_len + 1];
+        let mut modtype = 0;
+        if let Some(id) = self.id {
+            out[0..4].copy_from_slice(&id.to_ne_bytes());
+            modtype |= CGW_MOD_ID;
+        }
+        if let Some(dlc) = self.dlc {
+            out[4] = dlc;
+            modtype |= CGW_MOD_DLC;
+        }
+        if let Some(flags) = self.flags {
+            out[5] = flags;
+            modtype |= CGW_MOD_FLAGS;
+        }
+        if let Some(data) = &self.data {
+            out[8..8 + data.len()].copy_from_slice(data);
+            modtype |= CGW_MOD_DATA;
+        }
+        out[frame_len] = modtype;
+        out
+    }
+
+    fn decode(value: &[u8], fd: bool) -> Option<Self> {
+        let frame_len = if fd { CANFD_FRAME_LEN } else { CAN_FRAME_LEN };
+        let modtype = *value.get(frame_len)?;
+        let flag = |bit: u8| modtype & bit != 0;
+        Some(FrameMod {
+            id: flag(CGW_MOD_ID).then(|| u32::from_ne_bytes(value[0..4].try_into().unwrap())),
+            dlc: flag(CGW_MOD_DLC).then_some(value[4]),
+            flags: flag(CGW_MOD_FLAGS).then_some(value[5]),
+            data: flag(CGW_MOD_DATA).then(|| value[8..frame_len].to_vec()),
+        })
+    }
+}
+
+/// XOR checksum over `from..=to` stored at `result` (`struct cgw_csum_xor`)
+///
+/// Negative indexes count from the end of the payload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XorChecksum {
+    pub from: i8,
+    pub to: i8,
+    pub result: i8,
+    pub init: u8,
+}
+
+/// CRC8 checksum over `from..=to` stored at `result` (`struct cgw_csum_crc8`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crc8Checksum {
+    pub from: i8,
+    pub to: i8,
+    pub result: i8,
+    pub init: u8,
+    pub final_xor: u8,
+    pub table: Vec<u8>,
+    /// `CGW_CRC8PRF_*`: 0 none, 1 one extra byte, 2 extra byte per
+    /// 16-value counter, 3 SFF identifier XOR
+    pub profile: u8,
+    pub profile_data: Vec<u8>,
+}
+
+/// Lookup table of the MSB-first CRC8 with polynomial `poly`
+pub fn crc8_table(poly: u8) -> Vec<u8> {
+    (0..=255u8)
+        .map(|byte| {
+            (0..8).fold(byte, |crc, _| {
+                if crc & 0x80 != 0 {
+                    (crc << 1) ^ poly
+                } else {
+                    crc << 1
+                }
+            })
+        })
+        .collect()
+}
+
+/// Gateway rule between two CAN interfaces
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GatewayRule {
+    pub src: String,
+    pub dst: String,
+    /// Receive filter (id, mask), all frames when `None`
+    pub filter: Option<(u32, u32)>,
+    /// Keep the frame visible as local echo on the destination
+    pub echo: bool,
+    /// Keep the source timestamp
+    pub src_timestamp: bool,
+    /// Allow sending back on the source interface
+    pub iif_tx_ok: bool,
+    /// Route CAN FD frames (modifications then use CAN FD frames)
+    pub fd: bool,
+    /// Hop limit of the frames routed by this rule
+    pub hops: Option<u8>,
+    /// Identifier of the modification set, used to delete the rule
+    pub uid: Option<u32>,
+    /// At most one modification per operation
+    pub modifications: Vec<(ModOp, FrameMod)>,
+    pub xor: Option<XorChecksum>,
+    pub crc8: Option<Crc8Checksum>,
+}
+
+/// Counters reported with the rules
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleStats {
+    pub handled: u32,
+    pub dropped: u32,
+    pub deleted: u32,
+}
+
+fn invalid(message: impl Into<String>) -> InterfaceError {
+    InterfaceError::Invalid(message.into())
+}
+
+/// Check a rule against the limits enforced by the kernel
+pub fn validate(rule: &GatewayRule) -> Result<(), InterfaceError> {
+    let max_data = if rule.fd { 64 } else { 8 };
+    for (i, (op, modification)) in rule.modifications.iter().enumerate() {
+        if rule.modifications[..i].iter().any(|(other, _)| other == op) {
+            return Err(invalid(format!(
+                "Only one '{}' modification per rule",
+                op.as_str()
+            )));
+        }
+        if modification.flags.is_some() && !rule.fd {
+            return Err(invalid("Flags modifications require an fd rule"));
+        }
+        if let Some(data) = &modification.data {
+            if data.len() > max_data {
+                return Err(invalid(format!(
+                    "Modification data longer than {} bytes",
+                    max_data
+                )));
+            }
+        }
+    }
+
+    // Checksum indexes address the payload from either end
+    let max_index = max_data as i8;
+    let valid = |index: i8| index >= -max_index && index < max_index;
+    let checksums = rule
+        .xor
+        .iter()
+        .map(|xor| ("XOR", [xor.from, xor.to, xor.result]))
+        .chain(
+            rule.crc8
+                .iter()
+                .map(|crc| ("CRC8", [crc.from, crc.to, crc.result])),
+        );
+    for (name, indexes) in checksums {
+        if !indexes.into_iter().all(valid) {
+            return Err(invalid(format!(
+                "{} checksum index outside -{}..{}",
+                name,
+                max_index,
+                max_index - 1
+            )));
+        }
+    }
+    if let Some(crc) = &rule.crc8 {
+        if crc.table.len() != 256 {
+            return Err(invalid("CRC8 table must have 256 entries"));
+        }
+        if crc.profile_data.len() > CRC8_PROFILE_DATA_LEN {
+            return Err(invalid(format!(
+                "CRC8 profile data longer than {} bytes",
+                CRC8_PROFILE_DATA_LEN
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn rule_flags(rule: &GatewayRule) -> u16 {
+    [
+        (rule.echo, CGW_FLAGS_CAN_ECHO),
+        (rule.src_timestamp, CGW_FLAGS_CAN_SRC_TSTAMP),
+        (rule.iif_tx_ok, CGW_FLAGS_CAN_IIF_TX_OK),
+        (rule.fd, CGW_FLAGS_CAN_FD),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .fold(0, |flags, (_, bit)| flags | bit)
+}
+
+/// `rtcanmsg` header
+fn rtcanmsg(flags: u16) -> [u8; 4] {
+    let flags = flags.to_ne_bytes();
+    [AF_CAN, CGW_TYPE_CAN_CAN, flags[0], flags[1]]
+}
+
+/// Request adding (`RTM_NEWROUTE`) or deleting (`RTM_DELROUTE`) a rule
+pub fn rule_message(kind: u16, rule: &GatewayRule, src: u32, dst: u32) -> Message {
+    let flags = if kind == RTM_NEWROUTE {
+        NLM_F_CREATE
+    } else {
+        0
+    };
+    let mut message = Message::new(kind, flags, &rtcanmsg(rule_flags(rule)));
+
+    for (op, modification) in &rule.modifications {
+        message.attr(op.attr(rule.fd), &modification.encode(rule.fd));
+    }
+    if let Some(xor) = &rule.xor {
+        message.attr(
+            CGW_CS_XOR,
+            &[xor.from as u8, xor.to as u8, xor.result as u8, xor.init],
+        );
+    }
+    if let Some(crc) = &rule.crc8 {
+        let mut data = vec![
+            crc.from as u8,
+            crc.to as u8,
+            crc.result as u8,
+            crc.init,
+            crc.final_xor,
+        ];
+        data.extend_from_slice(&crc.table);
+        data.push(crc.profile);
+        data.extend_from_slice(&crc.profile_data);
+        data.resize(CRC8_LEN, 0);
+        message.attr(CGW_CS_CRC8, &data);
+    }
+    if let Some(uid) = rule.uid {
+        message.attr_u32(CGW_MOD_UID, uid);
+    }
+    if let Some(hops) = rule.hops {
+        message.attr(CGW_LIM_HOPS, &[hops]);
+    }
+    if let Some((id, mask)) = rule.filter {
+        message.attr(CGW_FILTER, &[id.to_ne_bytes(), mask.to_ne_bytes()].concat());
+    }
+    message.attr_u32(CGW_SRC_IF, src);
+    message.attr_u32(CGW_DST_IF, dst);
+    message
+}
+
+/// Decode a rule of a dump, `None` for other route families
+pub fn parse_rule(payload: &[u8]) -> Option<(GatewayRule, RuleStats)> {
+    let header = payload.get(..4)?;
+    if header[0] != AF_CAN || header[1] != CGW_TYPE_CAN_CAN {
+        return None;
+    }
+    let flags = u16::from_ne_bytes([header[2], header[3]]);
+    let mut rule = GatewayRule {
+        echo: flags & CGW_FLAGS_CAN_ECHO != 0,
+        src_timestamp: flags & CGW_FLAGS_CAN_SRC_TSTAMP != 0,
+        iif_tx_ok: flags & CGW_FLAGS_CAN_IIF_TX_OK != 0,
+        fd: flags & CGW_FLAGS_CAN_FD != 0,
+        ..Default::default()
+    };
+    let mut stats = RuleStats::default();
+    let index_name = |value: &[u8]| {
+        let index = attr_u32(value).unwrap_or(0);
+        interface_name(index).unwrap_or_else(|| index.to_string())
+    };
+
+    for (kind, value) in attributes(&payload[4..]) {
+        if let Some(op) = ModOp::ALL.into_iter().find(|op| op.attr(rule.fd) == kind) {
+            if let Some(modification) = FrameMod::decode(value, rule.fd) {
+                rule.modifications.push((op, modification));
+            }
+            continue;
+        }
+        match kind {
+            CGW_CS_XOR if value.len() >= 4 => {
+                rule.xor = Some(XorChecksum {
+                    from: value[0] as i8,
+                    to: value[1] as i8,
+                    result: value[2] as i8,
+                    init: value[3],
+                })
+            }
+            CGW_CS_CRC8 if value.len() >= CRC8_LEN => {
+                rule.crc8 = Some(Crc8Checksum {
+                    from: value[0] as i8,
+                    to: value[1] as i8,
+                    result: value[2] as i8,
+                    init: value[3],
+                    final_xor: value[4],
+                    table: value[5..261].to_vec(),
+                    profile: value[261],
+                    profile_data: value[262..CRC8_LEN].to_vec(),
+                })
+            }
+            CGW_HANDLED => stats.handled = attr_u32(value).unwrap_or(0),
+            CGW_DROPPED => stats.dropped = attr_u32(value).unwrap_or(0),
+            CGW_DELETED => stats.deleted = attr_u32(value).unwrap_or(0),
+            CGW_SRC_IF => rule.src = index_name(value),
+            CGW_DST_IF => rule.dst = index_name(value),
+            CGW_FILTER if value.len() >= 8 => {
+                rule.filter = Some((
+                    u32::from_ne_bytes(value[0..4].try_into().unwrap()),
+                    u32::from_ne_bytes(value[4..8].try_into().unwrap()),
+                ))
+            }
+            CGW_LIM_HOPS => rule.hops = value.first().copied(),
+            CGW_MOD_UID => rule.uid = attr_u32(value),
+            _ => {}
+        }
+    }
+    Some((rule, stats))
+}
+
+fn gateway_error(rule: Option<&GatewayRule>, kind: u16, e: io::Error) -> InterfaceError {
+    match e.raw_os_error() {
+        Some(libc::EOPNOTSUPP) | Some(libc::EPROTONOSUPPORT) | Some(libc::EAFNOSUPPORT) => {
+            invalid("CAN gateway is not supported (is the can-gw module loaded?)")
+        }
+        Some(libc::ENODEV) => match rule {
+            Some(rule) => invalid(format!(
+                "{} and {} must be CAN interfaces",
+                rule.src, rule.dst
+            )),
+            None => InterfaceError::Io(e),
+        },
+        // The kernel answers EINVAL when no rule matches a deletion
+        Some(libc::EINVAL) if kind == RTM_DELROUTE => invalid("No matching gateway rule"),
+        Some(libc::EINVAL) => invalid("Invalid gateway rule"),
+        _ => InterfaceError::from_os("can-gw", e),
+    }
+}
+
+fn send_rule(kind: u16, rule: &GatewayRule) -> Result<(), InterfaceError> {
+    validate(rule)?;
+    let src = interface_index(&rule.src)?;
+    let dst = interface_index(&rule.dst)?;
+    Netlink::open()?
+        .request(rule_message(kind, rule, src, dst))
+        .map_err(|e| gateway_error(Some(rule), kind, e))?;
+    Ok(())
+}
+
+/// Add a gateway rule
+pub fn add_rule(rule: &GatewayRule) -> Result<(), InterfaceError> {
+    send_rule(RTM_NEWROUTE, rule)
+}
+
+/// Delete the rule with the same interfaces, modifications and checksums,
+/// or with the same `uid` when set
+pub fn delete_rule(rule: &GatewayRule) -> Result<(), InterfaceError> {
+    send_rule(RTM_DELROUTE, rule)
+}
+
+/// Delete all gateway rules
+pub fn flush_rules() -> Result<(), InterfaceError> {
+    let message = Message::new(RTM_DELROUTE, 0, &rtcanmsg(0));
+    Netlink::open()?
+        .request(message)
+        .map_err(|e| gateway_error(None, RTM_DELROUTE, e))?;
+    Ok(())
+}
+
+/// List the gateway rules with their counters
+pub fn list_rules() -> Result<Vec<(GatewayRule, RuleStats)>, InterfaceError> {
+    let message = Message::new(RTM_GETROUTE, NLM_F_DUMP, &rtcanmsg(0));
+    let replies = Netlink::open()?
+        .request(message)
+        .map_err(|e| gateway_error(None, RTM_GETROUTE, e))?;
+    // Without can-gw the kernel falls back to dumping the routes of all families
+    Ok(replies
+        .iter()
+        .filter(|(kind, _)| *kind == RTM_NEWROUTE)
+        .filter_map(|(_, payload)| parse_rule(payload))
+        .collect())
+}
+
+fn bytes_from_js(cx: &mut FunctionContext, array: Handle<JsArray>) -> NeonResult<Vec<u8>> {
+    let values = array.to_vec(cx)?;
+    let mut bytes = Vec::with_capacity(values.len());
+    for value in values {
+        let value = value.downcast_or_throw::<JsNumber, _>(cx)?.value(cx);
+        bytes.push(value as i64 as u8);
+    }
+    Ok(bytes)
+}
+
+fn bytes_to_js<'a>(cx: &mut FunctionContext<'a>, bytes: &[u8]) -> JsResult<'a, JsArray> {
+    let array = cx.empty_array();
+    for (i, &byte) in bytes.iter().enumerate() {
+        let value = cx.number(byte);
+        array.set(cx, i as u32, value)?;
+    }
+    Ok(array)
+}
+
+fn index_from_js(cx: &mut FunctionContext, obj: Handle<JsObject>, key: &str) -> NeonResult<i8> {
+    Ok(obj.get::<JsNumber, _, _>(cx, key)?.value(cx) as i8)
+}
+
+fn byte_from_js(cx: &mut FunctionContext, obj: Handle<JsObject>, key: &str) -> NeonResult<u8> {
+    Ok(obj
+        .get_opt::<JsNumber, _, _>(cx, key)?
+        .map_or(0, |value| value.value(cx) as u8))
+}
+
+/// Read a rule object (see `GatewayRule` in the type definitions)
+fn rule_from_js(cx: &mut FunctionContext, obj: Handle<JsObject>) -> NeonResult<GatewayRule> {
+    let flag = |cx: &mut FunctionContext, key: &str| -> NeonResult<bool> {
+        Ok(obj
+            .get_opt::<JsBoolean, _, _>(cx, key)?
+            .is_some_and(|value| value.value(cx)))
+    };
+    let mut rule = GatewayRule {
+        src: obj.get::<JsString, _, _>(cx, "src")?.value(cx),
+        dst: obj.get::<JsString, _, _>(cx, "dst")?.value(cx),
+        echo: flag(cx, "echo")?,
+        src_timestamp: flag(cx, "srcTimestamp")?,
+        iif_tx_ok: flag(cx, "allowIifTx")?,
+        fd: flag(cx, "fd")?,
+        ..Default::default()
+    };
+
+    if let Some(filter) = obj.get_opt::<JsObject, _, _>(cx, "filter")? {
+        let id = filter.get::<JsNumber, _, _>(cx, "id")?.value(cx) as u32;
+        let mask = filter.get::<JsNumber, _, _>(cx, "mask")?.value(cx) as u32;
+        rule.filter = Some((id, mask));
+    }
+    if let Some(hops) = obj.get_opt::<JsNumber, _, _>(cx, "hops")? {
+        rule.hops = Some(hops.value(cx) as u8);
+    }
+    if let Some(uid) = obj.get_opt::<JsNumber, _, _>(cx, "uid")? {
+        rule.uid = Some(uid.value(cx) as u32);
+    }
+
+    if let Some(modifications) = obj.get_opt::<JsArray, _, _>(cx, "modifications")? {
+        for value in modifications.to_vec(cx)? {
+            let modification = value.downcast_or_throw::<JsObject, _>(cx)?;
+            let name = modification.get::<JsString, _, _>(cx, "op")?.value(cx);
+            let Some(op) = ModOp::from_name(&name) else {
+                return cx.throw_error(format!("Unknown modification operation: {}", name));
+            };
+            let number = |cx: &mut FunctionContext, key: &str| -> NeonResult<Option<f64>> {
+                Ok(modification
+                    .get_opt::<JsNumber, _, _>(cx, key)?
+                    .map(|value| value.value(cx)))
+            };
+            let data = match modification.get_opt::<JsArray, _, _>(cx, "data")? {
+                Some(array) => Some(bytes_from_js(cx, array)?),
+                None => None,
+            };
+            let frame_mod = FrameMod {
+                id: number(cx, "id")?.map(|v| v as u32),
+                dlc: number(cx, "dlc")?.map(|v| v as u8),
+                flags: number(cx, "flags")?.map(|v| v as u8),
+                data,
+            };
+            rule.modifications.push((op, frame_mod));
+        }
+    }
+
+    if let Some(xor) = obj.get_opt::<JsObject, _, _>(cx, "xorChecksum")? {
+        rule.xor = Some(XorChecksum {
+            from: index_from_js(cx, xor, "from")?,
+            to: index_from_js(cx, xor, "to")?,
+            result: index_from_js(cx, xor, "result")?,
+            init: byte_from_js(cx, xor, "init")?,
+        });
+    }
+    if let Some(crc) = obj.get_opt::<JsObject, _, _>(cx, "crc8")? {
+        let table = match crc.get_opt::<JsArray, _, _>(cx, "table")? {
+            Some(array) => bytes_from_js(cx, array)?,
+            None => match crc.get_opt::<JsNumber, _, _>(cx, "polynomial")? {
+                Some(poly) => crc8_table(poly.value(cx) as u8),
+                None => return cx.throw_error("crc8 needs a polynomial or a table"),
+            },
+        };
+        let profile = match crc.get_opt::<JsString, _, _>(cx, "profile")? {
+            Some(name) => match name.value(cx).as_str() {
+                "none" => 0,
+                "1u8" => 1,
+                "16u8" => 2,
+                "sffid-xor" => 3,
+                other => return cx.throw_error(format!("Unknown CRC8 profile: {}", other)),
+            },
+            None => 0,
+        };
+        let profile_data = match crc.get_opt::<JsArray, _, _>(cx, "profileData")? {
+            Some(array) => bytes_from_js(cx, array)?,
+            None => Vec::new(),
+        };
+        rule.crc8 = Some(Crc8Checksum {
+            from: index_from_js(cx, crc, "from")?,
+            to: index_from_js(cx, crc, "to")?,
+            result: index_from_js(cx, crc, "result")?,
+            init: byte_from_js(cx, crc, "init")?,
+            final_xor: byte_from_js(cx, crc, "finalXor")?,
+            table,
+            profile,
+            profile_data,
+        });
+    }
+    Ok(rule)
+}
+
+fn rule_to_js<'a>(
+    cx: &mut FunctionContext<'a>,
+    rule: &GatewayRule,
+    stats: &RuleStats,
+) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let src = cx.string(&rule.src);
+    obj.set(cx, "src", src)?;
+    let dst = cx.string(&rule.dst);
+    obj.set(cx, "dst", dst)?;
+    for (key, value) in [
+        ("echo", rule.echo),
+        ("srcTimestamp", rule.src_timestamp),
+        ("allowIifTx", rule.iif_tx_ok),
+        ("fd", rule.fd),
+    ] {
+        let value = cx.boolean(value);
+        obj.set(cx, key, value)?;
+    }
+    if let Some((id, mask)) = rule.filter {
+        let filter = cx.empty_object();
+        let id = cx.number(id as f64);
+        filter.set(cx, "id", id)?;
+        let mask = cx.number(mask as f64);
+        filter.set(cx, "mask", mask)?;
+        obj.set(cx, "filter", filter)?;
+    }
+    if let Some(hops) = rule.hops {
+        let hops = cx.number(hops);
+        obj.set(cx, "hops", hops)?;
+    }
+    if let Some(uid) = rule.uid {
+        let uid = cx.number(uid as f64);
+        obj.set(cx, "uid", uid)?;
+    }
+
+    let modifications = cx.empty_array();
+    for (i, (op, modification)) in rule.modifications.iter().enumerate() {
+        let entry = cx.empty_object();
+        let name = cx.string(op.as_str());
+        entry.set(cx, "op", name)?;
+        if let Some(id) = modification.id {
+            let id = cx.number(id as f64);
+            entry.set(cx, "id", id)?;
+        }
+        if let Some(dlc) = modification.dlc {
+            let dlc = cx.number(dlc);
+            entry.set(cx, "dlc", dlc)?;
+        }
+        if let Some(flags) = modification.flags {
+            let flags = cx.number(flags);
+            entry.set(cx, "flags", flags)?;
+        }
+        if let Some(data) = &modification.data {
+            let data = bytes_to_js(cx, data)?;
+            entry.set(cx, "data", data)?;
+        }
+        modifications.set(cx, i as u32, entry)?;
+    }
+    obj.set(cx, "modifications", modifications)?;
+
+    if let Some(xor) = &rule.xor {
+        let entry = cx.empty_object();
+        for (key, value) in [
+            ("from", xor.from as f64),
+            ("to", xor.to as f64),
+            ("result", xor.result as f64),
+            ("init", xor.init as f64),
+        ] {
+            let value = cx.number(value);
+            entry.set(cx, key, value)?;
+        }
+        obj.set(cx, "xorChecksum", entry)?;
+    }
+    if let Some(crc) = &rule.crc8 {
+        let entry = cx.empty_object();
+        for (key, value) in [
+            ("from", crc.from as f64),
+            ("to", crc.to as f64),
+            ("result", crc.result as f64),
+            ("init", crc.init as f64),
+            ("finalXor", crc.final_xor as f64),
+        ] {
+            let value = cx.number(value);
+            entry.set(cx, key, value)?;
+        }
+        let table = bytes_to_js(cx, &crc.table)?;
+        entry.set(cx, "table", table)?;
+        let profile = cx.string(match crc.profile {
+            1 => "1u8",
+            2 => "16u8",
+            3 => "sffid-xor",
+            _ => "none",
+        });
+        entry.set(cx, "profile", profile)?;
+        let profile_data = bytes_to_js(cx, &crc.profile_data)?;
+        entry.set(cx, "profileData", profile_data)?;
+        obj.set(cx, "crc8", entry)?;
+    }
+
+    for (key, value) in [
+        ("handled", stats.handled),
+        ("dropped", stats.dropped),
+        ("deleted", stats.deleted),
+    ] {
+        let value = cx.number(value);
+        obj.set(cx, key, value)?;
+    }
+    Ok(obj)
+}
+
+/// Add a kernel gateway rule
+pub fn add_gateway_rule(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let obj = cx.argument::<JsObject>(0)?;
+    let rule = rule_from_js(&mut cx, obj)?;
+
+    match add_rule(&rule) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to add gateway rule: {}", e)),
+    }
+}
+
+/// Delete a kernel gateway rule
+pub fn delete_gateway_rule(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let obj = cx.argument::<JsObject>(0)?;
+    let rule = rule_from_js(&mut cx, obj)?;
+
+    match delete_rule(&rule) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to delete gateway rule: {}", e)),
+    }
+}
+
+/// Delete all kernel gateway rules
+pub fn flush_gateway_rules(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    match flush_rules() {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to flush gateway rules: {}", e)),
+    }
+}
+
+/// List the kernel gateway rules with their counters
+pub fn list_gateway_rules(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let rules = match list_rules() {
+        Ok(rules) => rules,
+        Err(e) => return cx.throw_error(format!("Failed to list gateway rules: {}", e)),
+    };
+    let array = cx.empty_array();
+    for (i, (rule, stats)) in rules.iter().enumerate() {
+        let obj = rule_to_js(&mut cx, rule, stats)?;
+        array.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(array)
+}
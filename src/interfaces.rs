@@ -0,0 +1,720 @@
+//! Discovery and link-state monitoring of CAN network interfaces
+//!
+//! Interfaces are listed with an `RTM_GETLINK` dump filtered on
+//! `ARPHRD_CAN`. The watcher listens to the `RTMGRP_LINK` multicast group
+//! and turns link notifications into added/removed/up/down events.
+//! Virtual vcan and vxcan interfaces are created and deleted with
+//! `RTM_NEWLINK`/`RTM_DELLINK` requests, like `ip link add ... type vcan`.
+
+use neon::prelude::*;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::netlink::{
+    parse_link, Link, Message, Netlink, ARPHRD_CAN, IFLA_IFNAME, IFLA_INFO_DATA, IFLA_INFO_KIND,
+    IFLA_LINKINFO, IFLA_MTU, NLM_F_CREATE, NLM_F_EXCL, RTMGRP_LINK, RTM_DELLINK, RTM_NEWLINK,
+};
+use crate::next_handle_id;
+
+/// Receive timeout of the watcher thread, bounds the stop latency
+const WATCH_POLL: Duration = Duration::from_millis(100);
+
+const CAN_MTU: u32 = 16;
+const CANFD_MTU: u32 = 72;
+const CANXL_MIN_MTU: u32 = 76;
+const CANXL_MAX_MTU: u32 = 2060;
+
+const IFF_ECHO: u32 = 0x40000;
+
+/// Longest interface name (`IFNAMSIZ` without the terminating NUL)
+const MAX_NAME_LEN: usize = 15;
+
+const VXCAN_INFO_PEER: u16 = 1;
+
+/// Interface flags reported to JS, in `ip link` order
+const FLAG_NAMES: &[(u32, &str)] = &[
+    (libc::IFF_UP as u32, "UP"),
+    (libc::IFF_BROADCAST as u32, "BROADCAST"),
+    (libc::IFF_LOOPBACK as u32, "LOOPBACK"),
+    (libc::IFF_POINTOPOINT as u32, "POINTOPOINT"),
+    (libc::IFF_RUNNING as u32, "RUNNING"),
+    (libc::IFF_NOARP as u32, "NOARP"),
+    (libc::IFF_PROMISC as u32, "PROMISC"),
+    (libc::IFF_MULTICAST as u32, "MULTICAST"),
+    (libc::IFF_LOWER_UP as u32, "LOWER_UP"),
+    (libc::IFF_DORMANT as u32, "DORMANT"),
+    (IFF_ECHO, "ECHO"),
+];
+
+/// Errors raised while creating or configuring an interface
+#[derive(Debug, thiserror::Error)]
+pub enum InterfaceError {
+    #[error("No such interface: {0}")]
+    NotFound(String),
+    #[error("{0} is not a CAN controller")]
+    NotCan(String),
+    #[error("Operation not permitted on {0}: CAP_NET_ADMIN is required")]
+    PermissionDenied(String),
+    #[error("Interface {0} already exists")]
+    Exists(String),
+    #[error("{0} must be down to change its configuration")]
+    Busy(String),
+    #[error("{0} is not in bus-off state")]
+    NotBusOff(String),
+    #[error("{0} restarts automatically after bus-off (restart-ms is set)")]
+    AutoRestart(String),
+    #[error("{0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl InterfaceError {
+    pub fn from_os(interface: &str, e: io::Error) -> Self {
+        match e.raw_os_error() {
+            Some(libc::EPERM) | Some(libc::EACCES) => {
+                InterfaceError::PermissionDenied(interface.to_string())
+            }
+            Some(libc::EEXIST) => InterfaceError::Exists(interface.to_string()),
+            Some(libc::EBUSY) => InterfaceError::Busy(interface.to_string()),
+            Some(libc::ENODEV) => InterfaceError::NotFound(interface.to_string()),
+            Some(libc::EOPNOTSUPP) => InterfaceError::Invalid(format!(
+                "{} does not support the requested settings",
+                interface
+            )),
+            _ => InterfaceError::Io(e),
+        }
+    }
+}
+
+/// Interface index of `name`
+pub fn interface_index(name: &str) -> Result<u32, InterfaceError> {
+    let c_name = CString::new(name).map_err(|_| InterfaceError::NotFound(name.to_string()))?;
+    match unsafe { libc::if_nametoindex(c_name.as_ptr()) } {
+        0 => Err(InterfaceError::NotFound(name.to_string())),
+        index => Ok(index),
+    }
+}
+
+/// Name of interface `index`, `None` if it no longer exists
+pub fn interface_name(index: u32) -> Option<String> {
+    let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let ptr = unsafe { libc::if_indextoname(index, name.as_mut_ptr()) };
+    if ptr.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// Frame format accepted by an interface, derived from its MTU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtuMode {
+    Classic,
+    Fd,
+    Xl,
+}
+
+impl MtuMode {
+    pub fn from_mtu(mtu: u32) -> Option<Self> {
+        match mtu {
+            CAN_MTU => Some(MtuMode::Classic),
+            CANFD_MTU => Some(MtuMode::Fd),
+            CANXL_MIN_MTU..=CANXL_MAX_MTU => Some(MtuMode::Xl),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MtuMode::Classic => "classic",
+            MtuMode::Fd => "fd",
+            MtuMode::Xl => "xl",
+        }
+    }
+}
+
+/// RFC 2863 operational state name of `IFLA_OPERSTATE`
+pub fn operstate_name(state: u8) -> &'static str {
+    match state {
+        1 => "notpresent",
+        2 => "down",
+        3 => "lowerlayerdown",
+        4 => "testing",
+        5 => "dormant",
+        6 => "up",
+        _ => "unknown",
+    }
+}
+
+/// CAN network interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub index: u32,
+    /// "can", "vcan", "vxcan" or "slcan"
+    pub kind: String,
+    pub mtu: u32,
+    pub operstate: u8,
+    /// `IFF_*` flags
+    pub flags: u32,
+}
+
+impl InterfaceInfo {
+    /// CAN interface of a link, `None` for other link types
+    pub fn from_link(link: &Link) -> Option<Self> {
+        if link.link_type != ARPHRD_CAN {
+            return None;
+        }
+        // slcan registers no rtnl link ops and therefore reports no kind
+        let kind = match &link.kind {
+            Some(kind) => kind.clone(),
+            None if link.name.starts_with("slcan") => "slcan".to_string(),
+            None => "can".to_string(),
+        };
+        Some(InterfaceInfo {
+            name: link.name.clone(),
+            index: link.index,
+            kind,
+            mtu: link.mtu,
+            operstate: link.operstate,
+            flags: link.flags,
+        })
+    }
+
+    pub fn mode(&self) -> Option<MtuMode> {
+        MtuMode::from_mtu(self.mtu)
+    }
+
+    /// Administratively up with carrier
+    pub fn is_up(&self) -> bool {
+        let up = (libc::IFF_UP | libc::IFF_RUNNING) as u32;
+        self.flags & up == up
+    }
+
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        FLAG_NAMES
+            .iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// List the CAN interfaces of the network namespace
+pub fn list_can_interfaces() -> io::Result<Vec<InterfaceInfo>> {
+    let mut interfaces: Vec<InterfaceInfo> = Netlink::open()?
+        .links()?
+        .iter()
+        .filter_map(InterfaceInfo::from_link)
+        .collect();
+    interfaces.sort_by_key(|info| info.index);
+    Ok(interfaces)
+}
+
+fn check_name(name: &str) -> Result<(), InterfaceError> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN || name.contains(['/', ' ', ':']) {
+        return Err(InterfaceError::Invalid(format!(
+            "Invalid interface name: {:?}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn check_mtu(mtu: Option<u32>) -> Result<(), InterfaceError> {
+    match mtu {
+        Some(mtu) if MtuMode::from_mtu(mtu).is_none() => Err(InterfaceError::Invalid(format!(
+            "Invalid CAN MTU {}: expected {} (classic), {} (FD) or {}..{} (XL)",
+            mtu, CAN_MTU, CANFD_MTU, CANXL_MIN_MTU, CANXL_MAX_MTU
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// `RTM_NEWLINK` request creating a vcan interface
+pub fn vcan_message(name: &str, mtu: Option<u32>) -> Message {
+    let mut message = Message::link(RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL, 0);
+    message.attr_str(IFLA_IFNAME, name);
+    if let Some(mtu) = mtu {
+        message.attr_u32(IFLA_MTU, mtu);
+    }
+    message.nest(IFLA_LINKINFO);
+    message.attr_str(IFLA_INFO_KIND, "vcan");
+    message.end();
+    message
+}
+
+/// `RTM_NEWLINK` request creating a vxcan pair, both ends with `mtu`
+pub fn vxcan_message(name: &str, peer: &str, mtu: Option<u32>) -> Message {
+    let mut message = Message::link(RTM_NEWLINK, NLM_F_CREATE | NLM_F_EXCL, 0);
+    message.attr_str(IFLA_IFNAME, name);
+    if let Some(mtu) = mtu {
+        message.attr_u32(IFLA_MTU, mtu);
+    }
+    message.nest(IFLA_LINKINFO);
+    message.attr_str(IFLA_INFO_KIND, "vxcan");
+    message.nest(IFLA_INFO_DATA);
+    message.nest(VXCAN_INFO_PEER);
+    message.ifinfomsg();
+    message.attr_str(IFLA_IFNAME, peer);
+    if let Some(mtu) = mtu {
+        message.attr_u32(IFLA_MTU, mtu);
+    }
+    message.end();
+    message.end();
+    message.end();
+    message
+}
+
+fn create_link(
+    socket: &mut Netlink,
+    name: &str,
+    kind: &str,
+    message: Message,
+) -> Result<(), InterfaceError> {
+    match socket.request(message) {
+        Ok(_) => Ok(()),
+        // The kernel answers EOPNOTSUPP when no driver registers the kind
+        Err(e) if e.raw_os_error() == Some(libc::EOPNOTSUPP) => {
+            Err(InterfaceError::Invalid(format!(
+                "{} interfaces are not supported (is the {} module loaded?)",
+                kind, kind
+            )))
+        }
+        Err(e) => Err(InterfaceError::from_os(name, e)),
+    }
+}
+
+fn interface_info(socket: &mut Netlink, name: &str) -> Result<InterfaceInfo, InterfaceError> {
+    let index = interface_index(name)?;
+    let link = socket
+        .link(index)
+        .map_err(|e| InterfaceError::from_os(name, e))?;
+    InterfaceInfo::from_link(&link).ok_or_else(|| InterfaceError::NotCan(name.to_string()))
+}
+
+fn set_up(socket: &mut Netlink, name: &str, up: bool) -> Result<(), InterfaceError> {
+    let index = interface_index(name)?;
+    let iff_up = libc::IFF_UP as u32;
+    let mut message = Message::link(RTM_NEWLINK, 0, index);
+    message.link_flags(if up { iff_up } else { 0 }, iff_up);
+    socket
+        .request(message)
+        .map_err(|e| InterfaceError::from_os(name, e))?;
+    Ok(())
+}
+
+/// Create a vcan interface, brought up if `up`
+pub fn create_vcan(
+    name: &str,
+    mtu: Option<u32>,
+    up: bool,
+) -> Result<InterfaceInfo, InterfaceError> {
+    check_name(name)?;
+    check_mtu(mtu)?;
+    let mut socket = Netlink::open()?;
+    create_link(&mut socket, name, "vcan", vcan_message(name, mtu))?;
+    if up {
+        set_up(&mut socket, name, true)?;
+    }
+    interface_info(&mut socket, name)
+}
+
+/// Create a pair of connected vxcan interfaces, brought up if `up`
+pub fn create_vxcan(
+    name: &str,
+    peer: &str,
+    mtu: Option<u32>,
+    up: bool,
+) -> Result<(InterfaceInfo, InterfaceInfo), InterfaceError> {
+    check_name(name)?;
+    check_name(peer)?;
+    check_mtu(mtu)?;
+    let mut socket = Netlink::open()?;
+    create_link(&mut socket, name, "vxcan", vxcan_message(name, peer, mtu))?;
+    if up {
+        set_up(&mut socket, name, true)?;
+        set_up(&mut socket, peer, true)?;
+    }
+    Ok((
+        interface_info(&mut socket, name)?,
+        interface_info(&mut socket, peer)?,
+    ))
+}
+
+/// Bring an interface up or down
+pub fn set_interface_up(name: &str, up: bool) -> Result<(), InterfaceError> {
+    set_up(&mut Netlink::open()?, name, up)
+}
+
+/// Only virtual CAN links may be deleted, not other virtual links
+pub fn check_deletable(link: &Link) -> Result<(), InterfaceError> {
+    match link.kind.as_deref() {
+        Some("vcan" | "vxcan") if link.link_type == ARPHRD_CAN => Ok(()),
+        _ => Err(InterfaceError::Invalid(format!(
+            "{} is not a vcan or vxcan interface",
+            link.name
+        ))),
+    }
+}
+
+/// Delete a virtual interface, vxcan peers go with it
+pub fn delete_interface(name: &str) -> Result<(), InterfaceError> {
+    let index = interface_index(name)?;
+    let mut socket = Netlink::open()?;
+    let link = socket
+        .link(index)
+        .map_err(|e| InterfaceError::from_os(name, e))?;
+    check_deletable(&link)?;
+    socket
+        .request(Message::link(RTM_DELLINK, 0, index))
+        .map_err(|e| InterfaceError::from_os(name, e))?;
+    Ok(())
+}
+
+/// Link-state change of a CAN interface
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterfaceEvent {
+    Added(InterfaceInfo),
+    Removed(InterfaceInfo),
+    Up(InterfaceInfo),
+    Down(InterfaceInfo),
+}
+
+impl InterfaceEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            InterfaceEvent::Added(_) => "added",
+            InterfaceEvent::Removed(_) => "removed",
+            InterfaceEvent::Up(_) => "up",
+            InterfaceEvent::Down(_) => "down",
+        }
+    }
+
+    pub fn interface(&self) -> &InterfaceInfo {
+        match self {
+            InterfaceEvent::Added(info)
+            | InterfaceEvent::Removed(info)
+            | InterfaceEvent::Up(info)
+            | InterfaceEvent::Down(info) => info,
+        }
+    }
+}
+
+/// Known interfaces, turns link notifications into events
+#[derive(Debug, Default)]
+pub struct LinkTracker {
+    known: HashMap<u32, InterfaceInfo>,
+}
+
+impl LinkTracker {
+    pub fn new(interfaces: &[InterfaceInfo]) -> Self {
+        LinkTracker {
+            known: interfaces
+                .iter()
+                .map(|info| (info.index, info.clone()))
+                .collect(),
+        }
+    }
+
+    /// Events of an `RTM_NEWLINK`/`RTM_DELLINK` message
+    pub fn update(&mut self, message: u16, link: &Link) -> Vec<InterfaceEvent> {
+        let Some(info) = InterfaceInfo::from_link(link) else {
+            return Vec::new();
+        };
+        match message {
+            RTM_DELLINK => match self.known.remove(&info.index) {
+                Some(_) => vec![InterfaceEvent::Removed(info)],
+                None => Vec::new(),
+            },
+            RTM_NEWLINK => self.apply(info),
+            _ => Vec::new(),
+        }
+    }
+
+    fn apply(&mut self, info: InterfaceInfo) -> Vec<InterfaceEvent> {
+        let up = info.is_up();
+        match self.known.insert(info.index, info.clone()) {
+            None if up => vec![
+                InterfaceEvent::Added(info.clone()),
+                InterfaceEvent::Up(info),
+            ],
+            None => vec![InterfaceEvent::Added(info)],
+            Some(old) if old.is_up() != up => {
+                if up {
+                    vec![InterfaceEvent::Up(info)]
+                } else {
+                    vec![InterfaceEvent::Down(info)]
+                }
+            }
+            Some(_) => Vec::new(),
+        }
+    }
+
+    /// Events between the tracked state and a fresh listing
+    pub fn resync(&mut self, interfaces: &[InterfaceInfo]) -> Vec<InterfaceEvent> {
+        let gone: Vec<u32> = self
+            .known
+            .keys()
+            .filter(|index| !interfaces.iter().any(|info| info.index == **index))
+            .copied()
+            .collect();
+        let mut events: Vec<InterfaceEvent> = gone
+            .iter()
+            .filter_map(|index| self.known.remove(index))
+            .map(InterfaceEvent::Removed)
+            .collect();
+        for info in interfaces {
+            events.extend(self.apply(info.clone()));
+        }
+        events
+    }
+}
+
+type EventCallback = Box<dyn Fn(InterfaceEvent) + Send>;
+
+/// Background thread reporting link-state changes of CAN interfaces
+pub struct InterfaceWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl InterfaceWatcher {
+    pub fn start(callback: EventCallback) -> io::Result<Self> {
+        // Subscribe before the initial listing so that no change is missed
+        let socket = Netlink::subscribe(RTMGRP_LINK)?;
+        socket.set_read_timeout(WATCH_POLL)?;
+        let mut tracker = LinkTracker::new(&list_can_interfaces()?);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            while !stop_flag.load(Ordering::Relaxed) {
+                let events = match socket.recv() {
+                    Ok(messages) => messages
+                        .iter()
+                        .filter_map(|(kind, payload)| parse_link(payload).map(|link| (*kind, link)))
+                        .flat_map(|(kind, link)| tracker.update(kind, &link))
+                        .collect(),
+                    Err(e)
+                        if e.kind() == io::ErrorKind::WouldBlock
+                            || e.kind() == io::ErrorKind::Interrupted =>
+                    {
+                        continue
+                    }
+                    // Notifications were dropped: compare with a new listing
+                    Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                        match list_can_interfaces() {
+                            Ok(interfaces) => tracker.resync(&interfaces),
+                            Err(_) => continue,
+                        }
+                    }
+                    Err(_) => break,
+                };
+                for event in events {
+                    callback(event);
+                }
+            }
+        });
+
+        Ok(InterfaceWatcher {
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn stop(mut self) {
+        self.halt();
+    }
+
+    fn halt(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for InterfaceWatcher {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref WATCHER_REGISTRY: Arc<Mutex<HashMap<u32, InterfaceWatcher>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+fn interface_to_js<'a, C: Context<'a>>(cx: &mut C, info: &InterfaceInfo) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let name = cx.string(&info.name);
+    obj.set(cx, "name", name)?;
+    let index = cx.number(info.index as f64);
+    obj.set(cx, "index", index)?;
+    let kind = cx.string(&info.kind);
+    obj.set(cx, "type", kind)?;
+    let mtu = cx.number(info.mtu as f64);
+    obj.set(cx, "mtu", mtu)?;
+    let mode: Handle<JsValue> = match info.mode() {
+        Some(mode) => cx.string(mode.as_str()).upcast(),
+        None => cx.null().upcast(),
+    };
+    obj.set(cx, "mode", mode)?;
+    let state = cx.string(operstate_name(info.operstate));
+    obj.set(cx, "state", state)?;
+    let up = cx.boolean(info.is_up());
+    obj.set(cx, "up", up)?;
+    let names = info.flag_names();
+    let flags = cx.empty_array();
+    for (i, name) in names.iter().enumerate() {
+        let name = cx.string(*name);
+        flags.set(cx, i as u32, name)?;
+    }
+    obj.set(cx, "flags", flags)?;
+    Ok(obj)
+}
+
+/// List the CAN interfaces with their type, MTU and state
+pub fn list_interfaces(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let interfaces = match list_can_interfaces() {
+        Ok(interfaces) => interfaces,
+        Err(e) => return cx.throw_error(format!("Failed to list interfaces: {}", e)),
+    };
+    let array = cx.empty_array();
+    for (i, info) in interfaces.iter().enumerate() {
+        let obj = interface_to_js(&mut cx, info)?;
+        array.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(array)
+}
+
+/// Call a JS callback on each link-state change of a CAN interface
+pub fn watch_interfaces(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let callback = cx.argument::<JsFunction>(0)?;
+    let callback = Arc::new(callback.root(&mut cx));
+    let js_channel = cx.channel();
+
+    let watcher = InterfaceWatcher::start(Box::new(move |event| {
+        let callback = Arc::clone(&callback);
+        js_channel.send(move |mut cx| {
+            let obj = cx.empty_object();
+            let kind = cx.string(event.kind());
+            obj.set(&mut cx, "type", kind)?;
+            let info = interface_to_js(&mut cx, event.interface())?;
+            obj.set(&mut cx, "interface", info)?;
+            callback
+                .to_inner(&mut cx)
+                .call_with(&cx)
+                .arg(obj)
+                .exec(&mut cx)
+        });
+    }));
+
+    match watcher {
+        Ok(watcher) => {
+            let id = next_handle_id();
+            WATCHER_REGISTRY.lock().unwrap().insert(id, watcher);
+            Ok(cx.number(id as f64))
+        }
+        Err(e) => cx.throw_error(format!("Failed to watch interfaces: {}", e)),
+    }
+}
+
+/// Stop an interface watcher
+pub fn unwatch_interfaces(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let watcher_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    let watcher = WATCHER_REGISTRY.lock().unwrap().remove(&watcher_id);
+    match watcher {
+        Some(watcher) => {
+            watcher.stop();
+            Ok(cx.undefined())
+        }
+        None => cx.throw_error("Invalid interface watcher ID"),
+    }
+}
+
+/// Read `{ mtu, up }` creation options; `mtu` also accepts "classic", "fd"
+/// or "xl"
+fn virtual_options(cx: &mut FunctionContext, index: usize) -> NeonResult<(Option<u32>, bool)> {
+    let mut mtu = None;
+    let mut up = true;
+    if let Some(obj) = cx
+        .argument_opt(index)
+        .and_then(|v| v.downcast::<JsObject, _>(cx).ok())
+    {
+        if let Some(value) = obj.get_opt::<JsValue, _, _>(cx, "mtu")? {
+            if let Ok(number) = value.downcast::<JsNumber, _>(cx) {
+                mtu = Some(number.value(cx) as u32);
+            } else if let Ok(mode) = value.downcast::<JsString, _>(cx) {
+                mtu = match mode.value(cx).as_str() {
+                    "classic" => Some(CAN_MTU),
+                    "fd" => Some(CANFD_MTU),
+                    "xl" => Some(CANXL_MAX_MTU),
+                    other => return cx.throw_error(format!("Unknown CAN mode: {}", other)),
+                };
+            }
+        }
+        if let Some(value) = obj.get_opt::<JsBoolean, _, _>(cx, "up")? {
+            up = value.value(cx);
+        }
+    }
+    Ok((mtu, up))
+}
+
+/// Create a vcan interface
+pub fn create_vcan_interface(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+    let (mtu, up) = virtual_options(&mut cx, 1)?;
+
+    match create_vcan(&name, mtu, up) {
+        Ok(info) => interface_to_js(&mut cx, &info),
+        Err(e) => cx.throw_error(format!("Failed to create interface: {}", e)),
+    }
+}
+
+/// Create a vxcan pair, returns both ends
+pub fn create_vxcan_interface(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+    let peer = cx.argument::<JsString>(1)?.value(&mut cx);
+    let (mtu, up) = virtual_options(&mut cx, 2)?;
+
+    match create_vxcan(&name, &peer, mtu, up) {
+        Ok((info, peer_info)) => {
+            let array = cx.empty_array();
+            let obj = interface_to_js(&mut cx, &info)?;
+            array.set(&mut cx, 0, obj)?;
+            let obj = interface_to_js(&mut cx, &peer_info)?;
+            array.set(&mut cx, 1, obj)?;
+            Ok(array)
+        }
+        Err(e) => cx.throw_error(format!("Failed to create interface: {}", e)),
+    }
+}
+
+/// Bring an interface up or down
+pub fn set_interface_state(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+    let up = cx.argument::<JsBoolean>(1)?.value(&mut cx);
+
+    match set_interface_up(&name, up) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to set interface state: {}", e)),
+    }
+}
+
+/// Delete a vcan or vxcan interface
+pub fn delete_virtual_interface(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    match delete_interface(&name) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to delete interface: {}", e)),
+    }
+}
@@ -0,0 +1,494 @@
+//! ISO-TP (ISO 15765-2) transport sockets backed by the kernel `CAN_ISOTP` protocol
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::{Arc, Mutex};
+
+use crate::isotp_engine::{IsoTpTimeouts, UserIsoTp};
+use crate::next_handle_id;
+
+const SOL_CAN_ISOTP: libc::c_int = libc::SOL_CAN_BASE + libc::CAN_ISOTP;
+
+const CAN_ISOTP_OPTS: libc::c_int = 1;
+const CAN_ISOTP_RECV_FC: libc::c_int = 2;
+const CAN_ISOTP_LL_OPTS: libc::c_int = 5;
+
+const CAN_ISOTP_EXTEND_ADDR: u32 = 0x002;
+const CAN_ISOTP_TX_PADDING: u32 = 0x004;
+const CAN_ISOTP_RX_PADDING: u32 = 0x008;
+const CAN_ISOTP_RX_EXT_ADDR: u32 = 0x200;
+const CAN_ISOTP_WAIT_TX_DONE: u32 = 0x400;
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_MTU: u8 = 16;
+const CANFD_MTU: u8 = 72;
+const CANFD_BRS: u8 = 0x01;
+
+/// Largest payload of a classic ISO-TP message (12-bit FF_DL)
+pub const MAX_CLASSIC_PAYLOAD: usize = 4095;
+
+/// Largest payload of an FD ISO-TP message accepted by the kernel (MAX_MSG_LENGTH)
+pub const MAX_FD_PAYLOAD: usize = 8200;
+
+/// Receive buffer size, large enough for escape-sequence FD messages
+const RECV_BUFFER_SIZE: usize = 65536;
+
+#[repr(C)]
+struct CanIsoTpOptions {
+    flags: u32,
+    frame_txtime: u32,
+    ext_address: u8,
+    txpad_content: u8,
+    rxpad_content: u8,
+    rx_ext_address: u8,
+}
+
+#[repr(C)]
+struct CanIsoTpFcOptions {
+    bs: u8,
+    stmin: u8,
+    wftmax: u8,
+}
+
+#[repr(C)]
+struct CanIsoTpLlOptions {
+    mtu: u8,
+    tx_dl: u8,
+    tx_flags: u8,
+}
+
+/// ISO-TP channel configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsoTpOptions {
+    /// Use 29-bit identifiers for tx/rx IDs
+    pub extended_id: bool,
+    /// Pad transmitted frames with this byte
+    pub tx_padding: Option<u8>,
+    /// Expect received frames padded with this byte
+    pub rx_padding: Option<u8>,
+    /// Block size announced in our flow control frames (0 = no limit)
+    pub block_size: u8,
+    /// Raw STmin byte announced in our flow control frames
+    pub st_min: u8,
+    /// Maximum number of FC.WAIT frames accepted before aborting
+    pub wft_max: u8,
+    /// Extended addressing: address byte prepended to transmitted frames
+    pub tx_ext_address: Option<u8>,
+    /// Extended addressing: address byte expected on received frames
+    pub rx_ext_address: Option<u8>,
+    /// Use CAN FD as link layer
+    pub fd: bool,
+    /// Transmit data length for FD link layer (8, 12, 16, 20, 24, 32, 48 or 64)
+    pub tx_dl: u8,
+    /// Enable bit rate switch on FD frames
+    pub brs: bool,
+}
+
+impl Default for IsoTpOptions {
+    fn default() -> Self {
+        Self {
+            extended_id: false,
+            tx_padding: None,
+            rx_padding: None,
+            block_size: 0,
+            st_min: 0,
+            wft_max: 0,
+            tx_ext_address: None,
+            rx_ext_address: None,
+            fd: false,
+            tx_dl: 8,
+            brs: false,
+        }
+    }
+}
+
+impl IsoTpOptions {
+    /// Largest message accepted by `send` with these options
+    pub fn max_payload(&self) -> usize {
+        if self.fd {
+            MAX_FD_PAYLOAD
+        } else {
+            MAX_CLASSIC_PAYLOAD
+        }
+    }
+
+    /// Check option consistency before opening a socket
+    pub fn validate(&self) -> Result<(), String> {
+        if self.fd {
+            if ![8, 12, 16, 20, 24, 32, 48, 64].contains(&self.tx_dl) {
+                return Err(format!("Invalid FD tx data length: {}", self.tx_dl));
+            }
+        } else if self.tx_dl != 8 {
+            return Err("tx data length other than 8 requires an FD link layer".to_string());
+        }
+        if self.rx_ext_address.is_some() && self.tx_ext_address.is_none() {
+            return Err("rx extended address requires a tx extended address".to_string());
+        }
+        Ok(())
+    }
+
+    fn kernel_flags(&self) -> u32 {
+        let mut flags = CAN_ISOTP_WAIT_TX_DONE;
+        if self.tx_padding.is_some() {
+            flags |= CAN_ISOTP_TX_PADDING;
+        }
+        if self.rx_padding.is_some() {
+            flags |= CAN_ISOTP_RX_PADDING;
+        }
+        if self.tx_ext_address.is_some() {
+            flags |= CAN_ISOTP_EXTEND_ADDR;
+        }
+        if self.rx_ext_address.is_some() {
+            flags |= CAN_ISOTP_RX_EXT_ADDR;
+        }
+        flags
+    }
+}
+
+/// Encode a CAN ID for the kernel, tagging 29-bit IDs with `CAN_EFF_FLAG`
+pub fn kernel_can_id(id: u32, extended: bool) -> Result<u32, String> {
+    if extended {
+        if id > 0x1FFF_FFFF {
+            return Err(format!("Invalid extended CAN ID: 0x{:X}", id));
+        }
+        Ok(id | CAN_EFF_FLAG)
+    } else {
+        if id > 0x7FF {
+            return Err(format!("Invalid standard CAN ID: 0x{:X}", id));
+        }
+        Ok(id)
+    }
+}
+
+/// Kernel ISO-TP socket bound to a tx/rx ID pair
+pub struct IsoTpSocket {
+    fd: OwnedFd,
+    max_payload: usize,
+}
+
+impl IsoTpSocket {
+    /// Open and bind an ISO-TP socket on `interface`
+    pub fn open(
+        interface: &str,
+        tx_id: u32,
+        rx_id: u32,
+        options: &IsoTpOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        options.validate()?;
+        let tx_id = kernel_can_id(tx_id, options.extended_id)?;
+        let rx_id = kernel_can_id(rx_id, options.extended_id)?;
+
+        let ifname = CString::new(interface)?;
+        let ifindex = unsafe { libc::if_nametoindex(ifname.as_ptr()) };
+        if ifindex == 0 {
+            return Err(format!("Interface not found: {}", interface).into());
+        }
+
+        let raw = unsafe {
+            libc::socket(
+                libc::PF_CAN,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::CAN_ISOTP,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let opts = CanIsoTpOptions {
+            flags: options.kernel_flags(),
+            frame_txtime: 0,
+            ext_address: options.tx_ext_address.unwrap_or(0),
+            txpad_content: options.tx_padding.unwrap_or(0),
+            rxpad_content: options.rx_padding.unwrap_or(0),
+            rx_ext_address: options.rx_ext_address.unwrap_or(0),
+        };
+        set_option(&fd, CAN_ISOTP_OPTS, &opts)?;
+
+        let fc = CanIsoTpFcOptions {
+            bs: options.block_size,
+            stmin: options.st_min,
+            wftmax: options.wft_max,
+        };
+        set_option(&fd, CAN_ISOTP_RECV_FC, &fc)?;
+
+        if options.fd {
+            let ll = CanIsoTpLlOptions {
+                mtu: CANFD_MTU,
+                tx_dl: options.tx_dl,
+                tx_flags: if options.brs { CANFD_BRS } else { 0 },
+            };
+            set_option(&fd, CAN_ISOTP_LL_OPTS, &ll)?;
+        } else {
+            let ll = CanIsoTpLlOptions {
+                mtu: CAN_MTU,
+                tx_dl: 8,
+                tx_flags: 0,
+            };
+            set_option(&fd, CAN_ISOTP_LL_OPTS, &ll)?;
+        }
+
+        let mut addr: libc::sockaddr_can = unsafe { std::mem::zeroed() };
+        addr.can_family = libc::AF_CAN as libc::sa_family_t;
+        addr.can_ifindex = ifindex as libc::c_int;
+        addr.can_addr.tp = libc::__c_anonymous_sockaddr_can_tp { rx_id, tx_id };
+
+        let ret = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(Self {
+            fd,
+            max_payload: options.max_payload(),
+        })
+    }
+
+    /// Send a complete ISO-TP message (segmented by the kernel)
+    pub fn send(&self, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        if data.is_empty() {
+            return Err("Cannot send an empty ISO-TP message".into());
+        }
+        if data.len() > self.max_payload {
+            return Err(format!(
+                "ISO-TP message too long ({} bytes, max {})",
+                data.len(),
+                self.max_payload
+            )
+            .into());
+        }
+        let written = unsafe {
+            libc::write(
+                self.fd.as_raw_fd(),
+                data.as_ptr() as *const libc::c_void,
+                data.len(),
+            )
+        };
+        if written < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Receive a complete ISO-TP message, waiting at most `timeout_ms`
+    pub fn recv(&self, timeout_ms: Option<u64>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout_ms.map_or(-1, |t| t.min(i32::MAX as u64) as libc::c_int);
+        let ready = unsafe { libc::poll(&mut pfd, 1, timeout) };
+        if ready < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        if ready == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "ISO-TP receive timed out").into());
+        }
+
+        let mut buffer = vec![0u8; RECV_BUFFER_SIZE];
+        let read = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+            )
+        };
+        if read < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        buffer.truncate(read as usize);
+        Ok(buffer)
+    }
+}
+
+fn set_option<T>(fd: &OwnedFd, name: libc::c_int, value: &T) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            SOL_CAN_ISOTP,
+            name,
+            value as *const T as *const libc::c_void,
+            std::mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// ISO-TP channel backed either by the kernel or by the userspace engine
+pub enum IsoTpChannel {
+    Kernel(IsoTpSocket),
+    Userspace(UserIsoTp),
+}
+
+impl IsoTpChannel {
+    /// Send a complete ISO-TP message
+    pub fn send(&self, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            IsoTpChannel::Kernel(socket) => socket.send(data),
+            IsoTpChannel::Userspace(engine) => engine.send(data),
+        }
+    }
+
+    /// Receive a complete ISO-TP message
+    pub fn recv(&self, timeout_ms: Option<u64>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self {
+            IsoTpChannel::Kernel(socket) => socket.recv(timeout_ms),
+            IsoTpChannel::Userspace(engine) => {
+                engine.recv(timeout_ms.map(std::time::Duration::from_millis))
+            }
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref ISOTP_REGISTRY: Arc<Mutex<HashMap<u32, Arc<IsoTpChannel>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Read ISO-TP options from an optional JS object
+fn options_from_js(
+    cx: &mut FunctionContext,
+    obj: Option<Handle<JsObject>>,
+) -> NeonResult<IsoTpOptions> {
+    let mut options = IsoTpOptions::default();
+    let Some(obj) = obj else {
+        return Ok(options);
+    };
+
+    let byte = |cx: &mut FunctionContext, key: &str| -> NeonResult<Option<u8>> {
+        Ok(obj
+            .get_opt::<JsNumber, _, _>(cx, key)?
+            .map(|v| v.value(cx) as u8))
+    };
+    let flag = |cx: &mut FunctionContext, key: &str| -> NeonResult<Option<bool>> {
+        Ok(obj
+            .get_opt::<JsBoolean, _, _>(cx, key)?
+            .map(|v| v.value(cx)))
+    };
+
+    options.extended_id = flag(cx, "extended")?.unwrap_or(false);
+    options.tx_padding = byte(cx, "txPadding")?;
+    options.rx_padding = byte(cx, "rxPadding")?;
+    options.block_size = byte(cx, "blockSize")?.unwrap_or(0);
+    options.st_min = byte(cx, "stMin")?.unwrap_or(0);
+    options.wft_max = byte(cx, "wftMax")?.unwrap_or(0);
+    options.tx_ext_address = byte(cx, "extAddress")?;
+    options.rx_ext_address = byte(cx, "rxExtAddress")?;
+    options.fd = flag(cx, "fd")?.unwrap_or(false);
+    options.tx_dl = byte(cx, "txDataLength")?.unwrap_or(if options.fd { 64 } else { 8 });
+    options.brs = flag(cx, "brs")?.unwrap_or(false);
+    Ok(options)
+}
+
+/// Read userspace engine settings (`userspace` flag and N_As/N_Bs/N_Cr in ms)
+fn engine_settings_from_js(
+    cx: &mut FunctionContext,
+    obj: Option<Handle<JsObject>>,
+) -> NeonResult<(bool, IsoTpTimeouts)> {
+    let mut timeouts = IsoTpTimeouts::default();
+    let Some(obj) = obj else {
+        return Ok((false, timeouts));
+    };
+
+    let userspace = obj
+        .get_opt::<JsBoolean, _, _>(cx, "userspace")?
+        .map(|v| v.value(cx))
+        .unwrap_or(false);
+    for (key, slot) in [
+        ("nAs", &mut timeouts.n_as),
+        ("nBs", &mut timeouts.n_bs),
+        ("nCr", &mut timeouts.n_cr),
+    ] {
+        if let Some(ms) = obj.get_opt::<JsNumber, _, _>(cx, key)? {
+            *slot = std::time::Duration::from_millis(ms.value(cx) as u64);
+        }
+    }
+    Ok((userspace, timeouts))
+}
+
+pub(crate) fn get_isotp_socket(socket_id: u32) -> Option<Arc<IsoTpChannel>> {
+    ISOTP_REGISTRY.lock().unwrap().get(&socket_id).cloned()
+}
+
+/// Create an ISO-TP socket from JavaScript
+pub fn create_isotp_socket(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let interface = cx.argument::<JsString>(0)?.value(&mut cx);
+    let tx_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
+    let rx_id = cx.argument::<JsNumber>(2)?.value(&mut cx) as u32;
+    let options_obj = cx
+        .argument_opt(3)
+        .and_then(|v| v.downcast::<JsObject, _>(&mut cx).ok());
+    let options = options_from_js(&mut cx, options_obj)?;
+    let (userspace, timeouts) = engine_settings_from_js(&mut cx, options_obj)?;
+
+    let channel = if userspace {
+        UserIsoTp::open(&interface, tx_id, rx_id, &options, timeouts).map(IsoTpChannel::Userspace)
+    } else {
+        IsoTpSocket::open(&interface, tx_id, rx_id, &options).map(IsoTpChannel::Kernel)
+    };
+
+    match channel {
+        Ok(socket) => {
+            let id = next_handle_id();
+            ISOTP_REGISTRY.lock().unwrap().insert(id, Arc::new(socket));
+            Ok(cx.number(id as f64))
+        }
+        Err(e) => cx.throw_error(format!("Failed to create ISO-TP socket: {}", e)),
+    }
+}
+
+/// Send an ISO-TP message from JavaScript
+pub fn isotp_send(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let data = cx.argument::<JsBuffer>(1)?.as_slice(&cx).to_vec();
+
+    let Some(socket) = get_isotp_socket(socket_id) else {
+        return cx.throw_error("Invalid ISO-TP socket ID");
+    };
+    match socket.send(&data) {
+        Ok(_) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to send ISO-TP message: {}", e)),
+    }
+}
+
+/// Receive an ISO-TP message from JavaScript
+pub fn isotp_recv(mut cx: FunctionContext) -> JsResult<JsBuffer> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let timeout = if cx.len() > 1 {
+        Some(cx.argument::<JsNumber>(1)?.value(&mut cx) as u64)
+    } else {
+        Some(1000)
+    };
+
+    let Some(socket) = get_isotp_socket(socket_id) else {
+        return cx.throw_error("Invalid ISO-TP socket ID");
+    };
+    match socket.recv(timeout) {
+        Ok(data) => JsBuffer::from_slice(&mut cx, &data),
+        Err(e) => cx.throw_error(format!("Failed to receive ISO-TP message: {}", e)),
+    }
+}
+
+/// Close an ISO-TP socket from JavaScript
+pub fn close_isotp_socket(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match ISOTP_REGISTRY.lock().unwrap().remove(&socket_id) {
+        Some(_) => Ok(cx.undefined()),
+        None => cx.throw_error("Invalid ISO-TP socket ID"),
+    }
+}
@@ -0,0 +1,511 @@
+//! Userspace ISO-TP (ISO 15765-2) engine layered on raw CAN frames
+//!
+//! Used when the kernel `can-isotp` module is unavailable. Segmentation,
+//! flow control and the N_As/N_Bs/N_Cr timers are handled here on top of
+//! `CanSocketWrapper::send_frame`/`read_frame`.
+
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::isotp::IsoTpOptions;
+use crate::CanSocketWrapper;
+
+/// Default padding byte for FD frames that must be stretched to a valid DLC
+const DEFAULT_PAD_CONTENT: u8 = 0xCC;
+
+/// Valid CAN FD payload lengths
+const FD_LENGTHS: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+/// Frame as seen by the engine: `(id, data, extended)`
+pub type LinkFrame = (u32, Vec<u8>, bool);
+
+/// Minimal frame transport used by the engine
+pub trait CanLink {
+    /// Send one frame on the link
+    fn send(
+        &self,
+        id: u32,
+        data: Vec<u8>,
+        extended: bool,
+        fd: bool,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Receive one frame, `None` on timeout
+    fn recv(&self, timeout: Duration) -> Result<Option<LinkFrame>, Box<dyn std::error::Error>>;
+}
+
+impl CanLink for CanSocketWrapper {
+    fn send(
+        &self,
+        id: u32,
+        data: Vec<u8>,
+        extended: bool,
+        fd: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send_frame(id, data, extended, fd, false)
+    }
+
+    fn recv(&self, timeout: Duration) -> Result<Option<LinkFrame>, Box<dyn std::error::Error>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let timeout_ms = (remaining.as_millis() as u64).max(1);
+            match self.read_frame(Some(timeout_ms)) {
+                Ok((id, data, extended, _, is_remote, is_error)) => {
+                    if !is_remote && !is_error {
+                        return Ok(Some((id, data, extended)));
+                    }
+                    if Instant::now() >= deadline {
+                        return Ok(None);
+                    }
+                }
+                Err(e) => {
+                    return match e.downcast_ref::<io::Error>() {
+                        Some(io_err)
+                            if matches!(
+                                io_err.kind(),
+                                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                            ) =>
+                        {
+                            Ok(None)
+                        }
+                        _ => Err(e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// ISO-TP network layer timeouts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsoTpTimeouts {
+    /// Maximum time to hand one frame to the link
+    pub n_as: Duration,
+    /// Maximum time the sender waits for a flow control frame
+    pub n_bs: Duration,
+    /// Maximum time the receiver waits for the next consecutive frame
+    pub n_cr: Duration,
+}
+
+impl Default for IsoTpTimeouts {
+    fn default() -> Self {
+        Self {
+            n_as: Duration::from_millis(1000),
+            n_bs: Duration::from_millis(1000),
+            n_cr: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// Flow status carried by a flow control frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowStatus {
+    ContinueToSend,
+    Wait,
+    Overflow,
+}
+
+/// Decoded ISO-TP protocol data unit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IsoTpFrame<'a> {
+    Single(&'a [u8]),
+    First {
+        total: usize,
+        data: &'a [u8],
+    },
+    Consecutive {
+        sequence: u8,
+        data: &'a [u8],
+    },
+    FlowControl {
+        status: FlowStatus,
+        block_size: u8,
+        st_min: u8,
+    },
+}
+
+/// Decode the PCI of a frame payload (extended address byte already stripped)
+pub fn decode_frame(payload: &[u8]) -> Result<IsoTpFrame<'_>, String> {
+    let pci = *payload.first().ok_or("Empty ISO-TP frame")?;
+    match pci >> 4 {
+        0x0 => {
+            let len = (pci & 0x0F) as usize;
+            if len == 0 {
+                // CAN FD escape sequence: length in the second byte
+                let len = *payload.get(1).ok_or("Truncated single frame")? as usize;
+                if len == 0 || payload.len() < 2 + len {
+                    return Err("Invalid FD single frame length".to_string());
+                }
+                Ok(IsoTpFrame::Single(&payload[2..2 + len]))
+            } else {
+                if payload.len() < 1 + len {
+                    return Err("Truncated single frame".to_string());
+                }
+                Ok(IsoTpFrame::Single(&payload[1..1 + len]))
+            }
+        }
+        0x1 => {
+            if payload.len() < 2 {
+                return Err("Truncated first frame".to_string());
+            }
+            let total = (((pci & 0x0F) as usize) << 8) | payload[1] as usize;
+            if total == 0 {
+                // Escape sequence for messages longer than 4095 bytes
+                if payload.len() < 6 {
+                    return Err("Truncated first frame".to_string());
+                }
+                let total =
+                    u32::from_be_bytes([payload[2], payload[3], payload[4], payload[5]]) as usize;
+                Ok(IsoTpFrame::First {
+                    total,
+                    data: &payload[6..],
+                })
+            } else {
+                Ok(IsoTpFrame::First {
+                    total,
+                    data: &payload[2..],
+                })
+            }
+        }
+        0x2 => Ok(IsoTpFrame::Consecutive {
+            sequence: pci & 0x0F,
+            data: &payload[1..],
+        }),
+        0x3 => {
+            if payload.len() < 3 {
+                return Err("Truncated flow control frame".to_string());
+            }
+            let status = match pci & 0x0F {
+                0 => FlowStatus::ContinueToSend,
+                1 => FlowStatus::Wait,
+                2 => FlowStatus::Overflow,
+                other => return Err(format!("Invalid flow status: {}", other)),
+            };
+            Ok(IsoTpFrame::FlowControl {
+                status,
+                block_size: payload[1],
+                st_min: payload[2],
+            })
+        }
+        other => Err(format!("Invalid ISO-TP PCI type: 0x{:X}", other)),
+    }
+}
+
+/// Convert a raw STmin byte into a delay
+pub fn st_min_duration(raw: u8) -> Duration {
+    match raw {
+        0x00..=0x7F => Duration::from_millis(raw as u64),
+        0xF1..=0xF9 => Duration::from_micros((raw - 0xF0) as u64 * 100),
+        // Reserved values are treated as the maximum of 127 ms
+        _ => Duration::from_millis(0x7F),
+    }
+}
+
+/// Userspace ISO-TP channel over a raw CAN link
+pub struct UserIsoTp<L: CanLink = CanSocketWrapper> {
+    link: L,
+    tx_id: u32,
+    rx_id: u32,
+    options: IsoTpOptions,
+    timeouts: IsoTpTimeouts,
+}
+
+impl UserIsoTp<CanSocketWrapper> {
+    /// Open a raw socket on `interface` and run ISO-TP over it
+    pub fn open(
+        interface: &str,
+        tx_id: u32,
+        rx_id: u32,
+        options: &IsoTpOptions,
+        timeouts: IsoTpTimeouts,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let socket = if options.fd {
+            CanSocketWrapper::new_fd(interface.to_string())?
+        } else {
+            CanSocketWrapper::new(interface.to_string())?
+        };
+        Self::with_link(socket, tx_id, rx_id, options, timeouts)
+    }
+}
+
+impl<L: CanLink> UserIsoTp<L> {
+    /// Run ISO-TP over an existing link
+    pub fn with_link(
+        link: L,
+        tx_id: u32,
+        rx_id: u32,
+        options: &IsoTpOptions,
+        timeouts: IsoTpTimeouts,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        options.validate()?;
+        if options.brs {
+            return Err("Bit rate switch is not supported by the userspace ISO-TP engine".into());
+        }
+        crate::isotp::kernel_can_id(tx_id, options.extended_id)?;
+        crate::isotp::kernel_can_id(rx_id, options.extended_id)?;
+        Ok(Self {
+            link,
+            tx_id,
+            rx_id,
+            options: options.clone(),
+            timeouts,
+        })
+    }
+
+    fn header_len(&self) -> usize {
+        usize::from(self.options.tx_ext_address.is_some())
+    }
+
+    /// Payload capacity of one link-layer frame, PCI included
+    fn frame_capacity(&self) -> usize {
+        self.options.tx_dl as usize - self.header_len()
+    }
+
+    fn rx_ext_address(&self) -> Option<u8> {
+        self.options.rx_ext_address.or(self.options.tx_ext_address)
+    }
+
+    /// Send one ISO-TP PDU, adding addressing and padding
+    fn send_pdu(&self, pdu: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut frame = Vec::with_capacity(self.options.tx_dl as usize);
+        if let Some(address) = self.options.tx_ext_address {
+            frame.push(address);
+        }
+        frame.extend_from_slice(pdu);
+
+        if self.options.fd {
+            let target = FD_LENGTHS
+                .iter()
+                .copied()
+                .find(|&len| len >= frame.len())
+                .ok_or("ISO-TP frame exceeds 64 bytes")?;
+            let pad = self.options.tx_padding.unwrap_or(DEFAULT_PAD_CONTENT);
+            frame.resize(target, pad);
+        } else if let Some(pad) = self.options.tx_padding {
+            frame.resize(8, pad);
+        }
+
+        let started = Instant::now();
+        self.link
+            .send(self.tx_id, frame, self.options.extended_id, self.options.fd)?;
+        if started.elapsed() > self.timeouts.n_as {
+            return Err("N_As timeout: frame transmission took too long".into());
+        }
+        Ok(())
+    }
+
+    /// Wait for the next PDU addressed to us, `None` once `deadline` passes
+    fn recv_pdu(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        loop {
+            let remaining = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(None);
+                    }
+                    deadline - now
+                }
+                None => Duration::from_secs(3600),
+            };
+
+            let Some((id, data, extended)) = self.link.recv(remaining)? else {
+                if deadline.is_some() {
+                    return Ok(None);
+                }
+                continue;
+            };
+            if id != self.rx_id || extended != self.options.extended_id {
+                continue;
+            }
+            match self.rx_ext_address() {
+                Some(address) => {
+                    if data.first() == Some(&address) {
+                        return Ok(Some(data[1..].to_vec()));
+                    }
+                }
+                None => return Ok(Some(data)),
+            }
+        }
+    }
+
+    fn send_flow_control(&self, status: FlowStatus) -> Result<(), Box<dyn std::error::Error>> {
+        let code = match status {
+            FlowStatus::ContinueToSend => 0x30,
+            FlowStatus::Wait => 0x31,
+            FlowStatus::Overflow => 0x32,
+        };
+        self.send_pdu(&[code, self.options.block_size, self.options.st_min])
+    }
+
+    /// Wait for a clear-to-send flow control, returning `(block_size, st_min)`
+    fn wait_flow_control(&self) -> Result<(u8, Duration), Box<dyn std::error::Error>> {
+        let mut waits = 0u32;
+        loop {
+            let deadline = Instant::now() + self.timeouts.n_bs;
+            let (status, block_size, st_min) = loop {
+                let pdu = self
+                    .recv_pdu(Some(deadline))?
+                    .ok_or("N_Bs timeout: no flow control received")?;
+                if let Ok(IsoTpFrame::FlowControl {
+                    status,
+                    block_size,
+                    st_min,
+                }) = decode_frame(&pdu)
+                {
+                    break (status, block_size, st_min);
+                }
+            };
+            match status {
+                FlowStatus::ContinueToSend => return Ok((block_size, st_min_duration(st_min))),
+                FlowStatus::Wait => {
+                    waits += 1;
+                    // wft_max of 0 accepts any number of wait frames, like the kernel
+                    if self.options.wft_max != 0 && waits > self.options.wft_max as u32 {
+                        return Err("Too many flow control WAIT frames".into());
+                    }
+                }
+                FlowStatus::Overflow => return Err("Receiver reported buffer overflow".into()),
+            }
+        }
+    }
+
+    /// Largest message accepted by `send`
+    pub fn max_payload(&self) -> usize {
+        self.options.max_payload()
+    }
+
+    /// Send a complete ISO-TP message, segmenting it as needed
+    pub fn send(&self, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        if data.is_empty() {
+            return Err("Cannot send an empty ISO-TP message".into());
+        }
+        if data.len() > self.max_payload() {
+            return Err(format!(
+                "ISO-TP message too long ({} bytes, max {})",
+                data.len(),
+                self.max_payload()
+            )
+            .into());
+        }
+
+        let capacity = self.frame_capacity();
+        if data.len() < capacity.min(8) {
+            let mut pdu = vec![data.len() as u8];
+            pdu.extend_from_slice(data);
+            return self.send_pdu(&pdu);
+        }
+        if self.options.fd && data.len() <= capacity - 2 {
+            let mut pdu = vec![0x00, data.len() as u8];
+            pdu.extend_from_slice(data);
+            return self.send_pdu(&pdu);
+        }
+
+        let mut pdu = if data.len() <= 4095 {
+            vec![0x10 | (data.len() >> 8) as u8, data.len() as u8]
+        } else {
+            let mut pdu = vec![0x10, 0x00];
+            pdu.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            pdu
+        };
+        let mut offset = capacity - pdu.len();
+        pdu.extend_from_slice(&data[..offset]);
+        self.send_pdu(&pdu)?;
+
+        let mut sequence = 1u8;
+        while offset < data.len() {
+            let (block_size, st_min) = self.wait_flow_control()?;
+            let mut sent_in_block = 0u8;
+            while offset < data.len() {
+                if sent_in_block > 0 && !st_min.is_zero() {
+                    thread::sleep(st_min);
+                }
+                let end = (offset + capacity - 1).min(data.len());
+                let mut pdu = vec![0x20 | sequence];
+                pdu.extend_from_slice(&data[offset..end]);
+                self.send_pdu(&pdu)?;
+
+                offset = end;
+                sequence = (sequence + 1) & 0x0F;
+                sent_in_block = sent_in_block.wrapping_add(1);
+                if block_size != 0 && sent_in_block == block_size {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Receive a complete ISO-TP message, waiting at most `timeout` for it to start
+    pub fn recv(&self, timeout: Option<Duration>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut pending = self
+            .recv_pdu(deadline)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "ISO-TP receive timed out"))?;
+
+        'message: loop {
+            let (total, first) = match decode_frame(&pending) {
+                Ok(IsoTpFrame::Single(data)) => return Ok(data.to_vec()),
+                Ok(IsoTpFrame::First { total, data }) => (total, data.to_vec()),
+                // Stray consecutive/flow control frames are ignored while idle
+                _ => {
+                    pending = self.recv_pdu(deadline)?.ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::TimedOut, "ISO-TP receive timed out")
+                    })?;
+                    continue;
+                }
+            };
+
+            if total > self.max_payload() {
+                self.send_flow_control(FlowStatus::Overflow)?;
+                return Err(format!("Incoming ISO-TP message too long ({} bytes)", total).into());
+            }
+
+            let mut message = first;
+            message.truncate(total);
+            self.send_flow_control(FlowStatus::ContinueToSend)?;
+
+            let mut expected = 1u8;
+            let mut received_in_block = 0u8;
+            while message.len() < total {
+                let pdu = self
+                    .recv_pdu(Some(Instant::now() + self.timeouts.n_cr))?
+                    .ok_or("N_Cr timeout: consecutive frame not received")?;
+                match decode_frame(&pdu) {
+                    Ok(IsoTpFrame::Consecutive { sequence, data }) => {
+                        if sequence != expected {
+                            return Err(format!(
+                                "Wrong consecutive frame sequence number (expected {}, got {})",
+                                expected, sequence
+                            )
+                            .into());
+                        }
+                        let take = (total - message.len()).min(data.len());
+                        message.extend_from_slice(&data[..take]);
+                        expected = (expected + 1) & 0x0F;
+                        received_in_block = received_in_block.wrapping_add(1);
+
+                        if self.options.block_size != 0
+                            && received_in_block == self.options.block_size
+                            && message.len() < total
+                        {
+                            self.send_flow_control(FlowStatus::ContinueToSend)?;
+                            received_in_block = 0;
+                        }
+                    }
+                    // A new single or first frame aborts the current reception
+                    Ok(IsoTpFrame::Single(_)) | Ok(IsoTpFrame::First { .. }) => {
+                        pending = pdu;
+                        continue 'message;
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(message);
+        }
+    }
+}
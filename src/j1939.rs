@@ -0,0 +1,624 @@
+//! SAE J1939 sockets backed by the kernel `CAN_J1939` protocol
+//!
+//! The kernel handles the TP/ETP transport protocols, so payloads larger
+//! than 8 bytes are segmented and reassembled transparently.
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use neon::types::JsBigInt;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::next_handle_id;
+
+/// Time to wait for contending address claims (J1939-81)
+const ADDRESS_CLAIM_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// First address of the dynamic range used by arbitrary-address-capable ECUs
+const DYNAMIC_ADDRESS_START: u8 = 128;
+const DYNAMIC_ADDRESS_END: u8 = 247;
+
+/// J1939 NAME fields (J1939-81)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct J1939Name {
+    pub identity_number: u32,
+    pub manufacturer_code: u16,
+    pub ecu_instance: u8,
+    pub function_instance: u8,
+    pub function: u8,
+    pub vehicle_system: u8,
+    pub vehicle_system_instance: u8,
+    pub industry_group: u8,
+    pub arbitrary_address_capable: bool,
+}
+
+impl J1939Name {
+    /// Pack the NAME into its 64-bit representation
+    pub fn to_u64(self) -> u64 {
+        (self.identity_number as u64 & 0x1F_FFFF)
+            | (self.manufacturer_code as u64 & 0x7FF) << 21
+            | (self.ecu_instance as u64 & 0x07) << 32
+            | (self.function_instance as u64 & 0x1F) << 35
+            | (self.function as u64) << 40
+            | (self.vehicle_system as u64 & 0x7F) << 49
+            | (self.vehicle_system_instance as u64 & 0x0F) << 56
+            | (self.industry_group as u64 & 0x07) << 60
+            | (self.arbitrary_address_capable as u64) << 63
+    }
+
+    /// Unpack a 64-bit NAME
+    pub fn from_u64(name: u64) -> Self {
+        Self {
+            identity_number: (name & 0x1F_FFFF) as u32,
+            manufacturer_code: ((name >> 21) & 0x7FF) as u16,
+            ecu_instance: ((name >> 32) & 0x07) as u8,
+            function_instance: ((name >> 35) & 0x1F) as u8,
+            function: ((name >> 40) & 0xFF) as u8,
+            vehicle_system: ((name >> 49) & 0x7F) as u8,
+            vehicle_system_instance: ((name >> 56) & 0x0F) as u8,
+            industry_group: ((name >> 60) & 0x07) as u8,
+            arbitrary_address_capable: name >> 63 != 0,
+        }
+    }
+}
+
+/// Whether our NAME keeps an address contested by `other` (lower NAME wins)
+pub fn claim_wins(ours: u64, other: u64) -> bool {
+    ours < other
+}
+
+/// First dynamic address not tried yet, for arbitrary-address-capable ECUs
+pub fn next_dynamic_address(tried: &[u8]) -> Option<u8> {
+    (DYNAMIC_ADDRESS_START..=DYNAMIC_ADDRESS_END).find(|address| !tried.contains(address))
+}
+
+/// J1939 socket configuration
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct J1939Options {
+    /// 64-bit NAME used for address claiming (0 = static addressing)
+    pub name: u64,
+    /// Source address (`0xFF` = receive only)
+    pub address: Option<u8>,
+    /// Only receive this PGN (applied once the address is claimed)
+    pub pgn: Option<u32>,
+    /// Receive every message on the bus, not only those addressed to us
+    pub promiscuous: bool,
+    /// Default send priority (0 = highest, 7 = lowest)
+    pub priority: Option<u8>,
+}
+
+/// Received J1939 message
+#[derive(Debug, Clone, PartialEq)]
+pub struct J1939Message {
+    pub pgn: u32,
+    pub sa: u8,
+    pub da: u8,
+    pub priority: u8,
+    pub data: Vec<u8>,
+    pub src_name: u64,
+}
+
+/// Send priority of J1939 sockets unless configured otherwise
+const DEFAULT_PRIORITY: u8 = 6;
+
+/// Kernel J1939 socket
+pub struct J1939Socket {
+    fd: OwnedFd,
+    ifindex: libc::c_int,
+    name: u64,
+    address: Mutex<u8>,
+    /// Priority of sends without their own
+    default_priority: u8,
+    /// Priority currently set on the socket, held while sending
+    send_priority: Mutex<u8>,
+}
+
+fn j1939_addr(ifindex: libc::c_int, name: u64, pgn: u32, addr: u8) -> libc::sockaddr_can {
+    let mut sockaddr: libc::sockaddr_can = unsafe { std::mem::zeroed() };
+    sockaddr.can_family = libc::AF_CAN as libc::sa_family_t;
+    sockaddr.can_ifindex = ifindex;
+    sockaddr.can_addr.j1939 = libc::__c_anonymous_sockaddr_can_j1939 { name, pgn, addr };
+    sockaddr
+}
+
+fn set_option<T>(fd: &OwnedFd, level: libc::c_int, name: libc::c_int, value: &T) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            level,
+            name,
+            value as *const T as *const libc::c_void,
+            std::mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl J1939Socket {
+    /// Open a J1939 socket on `interface`, claiming an address when a NAME is set
+    pub fn open(
+        interface: &str,
+        options: &J1939Options,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(priority) = options.priority {
+            if priority > 7 {
+                return Err(format!("Invalid J1939 priority: {}", priority).into());
+            }
+        }
+        if let Some(pgn) = options.pgn {
+            if pgn > libc::J1939_PGN_MAX {
+                return Err(format!("Invalid PGN: 0x{:X}", pgn).into());
+            }
+        }
+
+        let ifname = CString::new(interface)?;
+        let ifindex = unsafe { libc::if_nametoindex(ifname.as_ptr()) };
+        if ifindex == 0 {
+            return Err(format!("Interface not found: {}", interface).into());
+        }
+
+        let raw = unsafe {
+            libc::socket(
+                libc::PF_CAN,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::CAN_J1939,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let enable: libc::c_int = 1;
+        set_option(&fd, libc::SOL_SOCKET, libc::SO_BROADCAST, &enable)?;
+        if options.promiscuous {
+            set_option(&fd, libc::SOL_CAN_J1939, libc::SO_J1939_PROMISC, &enable)?;
+        }
+        if let Some(priority) = options.priority {
+            let priority = priority as libc::c_int;
+            set_option(
+                &fd,
+                libc::SOL_CAN_J1939,
+                libc::SO_J1939_SEND_PRIO,
+                &priority,
+            )?;
+        }
+
+        let socket = Self {
+            fd,
+            ifindex: ifindex as libc::c_int,
+            name: options.name,
+            address: Mutex::new(libc::J1939_NO_ADDR),
+            default_priority: options.priority.unwrap_or(DEFAULT_PRIORITY),
+            send_priority: Mutex::new(options.priority.unwrap_or(DEFAULT_PRIORITY)),
+        };
+        let address = options.address.unwrap_or(libc::J1939_NO_ADDR);
+        socket.bind(address)?;
+        if options.name != 0 && address != libc::J1939_NO_ADDR {
+            socket.claim_address(address)?;
+        }
+        // Filter after claiming so contending claims were still observed
+        if let Some(pgn) = options.pgn {
+            let filter = libc::j1939_filter {
+                name: 0,
+                name_mask: 0,
+                pgn,
+                pgn_mask: libc::J1939_PGN_MAX,
+                addr: 0,
+                addr_mask: 0,
+            };
+            set_option(
+                &socket.fd,
+                libc::SOL_CAN_J1939,
+                libc::SO_J1939_FILTER,
+                &filter,
+            )?;
+        }
+        Ok(socket)
+    }
+
+    fn bind(&self, address: u8) -> io::Result<()> {
+        let sockaddr = j1939_addr(self.ifindex, self.name, libc::J1939_NO_PGN, address);
+        let ret = unsafe {
+            libc::bind(
+                self.fd.as_raw_fd(),
+                &sockaddr as *const libc::sockaddr_can as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        *self.address.lock().unwrap() = address;
+        Ok(())
+    }
+
+    /// Current source address
+    pub fn address(&self) -> u8 {
+        *self.address.lock().unwrap()
+    }
+
+    fn send_address_claim(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.send(
+            libc::J1939_PGN_ADDRESS_CLAIMED,
+            &self.name.to_le_bytes(),
+            libc::J1939_NO_ADDR,
+            None,
+        )
+    }
+
+    /// Run the J1939-81 address claim procedure, returning the claimed address
+    ///
+    /// Arbitrary-address-capable NAMEs move through the dynamic range
+    /// (128-247) when they lose arbitration; others report "cannot claim".
+    pub fn claim_address(&self, preferred: u8) -> Result<u8, Box<dyn std::error::Error>> {
+        let arbitrary = J1939Name::from_u64(self.name).arbitrary_address_capable;
+        let mut address = preferred;
+        let mut tried = Vec::new();
+        loop {
+            tried.push(address);
+            if address != self.address() {
+                self.bind(address)?;
+            }
+            self.send_address_claim()?;
+
+            let deadline = Instant::now() + ADDRESS_CLAIM_TIMEOUT;
+            let mut lost = false;
+            while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+                let message = match self.recv(Some(remaining.as_millis().max(1) as u64)) {
+                    Ok(message) => message,
+                    Err(e) if is_timeout(e.as_ref()) => break,
+                    Err(e) => return Err(e),
+                };
+                if message.pgn != libc::J1939_PGN_ADDRESS_CLAIMED
+                    || message.sa != address
+                    || message.data.len() < 8
+                {
+                    continue;
+                }
+                let mut name_bytes = [0u8; 8];
+                name_bytes.copy_from_slice(&message.data[..8]);
+                let other = u64::from_le_bytes(name_bytes);
+                if other == self.name {
+                    continue;
+                }
+                if claim_wins(self.name, other) {
+                    // Defend the address by repeating our claim
+                    self.send_address_claim()?;
+                } else {
+                    lost = true;
+                    break;
+                }
+            }
+            if !lost {
+                return Ok(address);
+            }
+
+            match next_dynamic_address(&tried).filter(|_| arbitrary) {
+                Some(next) => address = next,
+                None => {
+                    // Announce "cannot claim address" from the null address
+                    self.bind(libc::J1939_IDLE_ADDR)?;
+                    let _ = self.send_address_claim();
+                    return Err(format!(
+                        "Cannot claim J1939 address 0x{:02X}: lost arbitration",
+                        preferred
+                    )
+                    .into());
+                }
+            }
+        }
+    }
+
+    /// Send `data` on `pgn` to destination `da` (0xFF = global)
+    pub fn send(
+        &self,
+        pgn: u32,
+        data: &[u8],
+        da: u8,
+        priority: Option<u8>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if pgn > libc::J1939_PGN_MAX {
+            return Err(format!("Invalid PGN: 0x{:X}", pgn).into());
+        }
+        let priority = priority.unwrap_or(self.default_priority);
+        if priority > 7 {
+            return Err(format!("Invalid J1939 priority: {}", priority).into());
+        }
+        // The priority is a socket option: every send sets its own, so a
+        // per-call priority does not outlive the call
+        let mut send_priority = self.send_priority.lock().unwrap();
+        if *send_priority != priority {
+            set_option(
+                &self.fd,
+                libc::SOL_CAN_J1939,
+                libc::SO_J1939_SEND_PRIO,
+                &(priority as libc::c_int),
+            )?;
+            *send_priority = priority;
+        }
+
+        // J1939_NO_NAME: route by address only
+        let dest = j1939_addr(0, 0, pgn, da);
+        let sent = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                data.as_ptr() as *const libc::c_void,
+                data.len(),
+                0,
+                &dest as *const libc::sockaddr_can as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Receive one (possibly multi-packet) message, waiting at most `timeout_ms`
+    pub fn recv(
+        &self,
+        timeout_ms: Option<u64>,
+    ) -> Result<J1939Message, Box<dyn std::error::Error>> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout_ms.map_or(-1, |t| t.min(i32::MAX as u64) as libc::c_int);
+        let ready = unsafe { libc::poll(&mut pfd, 1, timeout) };
+        if ready < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        if ready == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "J1939 receive timed out").into());
+        }
+
+        // Peek the datagram size so ETP messages get an exact buffer
+        let size = unsafe {
+            libc::recv(
+                self.fd.as_raw_fd(),
+                std::ptr::null_mut(),
+                0,
+                libc::MSG_PEEK | libc::MSG_TRUNC,
+            )
+        };
+        if size < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let mut data = vec![0u8; size as usize];
+
+        let mut source: libc::sockaddr_can = unsafe { std::mem::zeroed() };
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut control = [0u64; 16];
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_name = &mut source as *mut libc::sockaddr_can as *mut libc::c_void;
+        msg.msg_namelen = std::mem::size_of::<libc::sockaddr_can>() as libc::socklen_t;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+        let read = unsafe { libc::recvmsg(self.fd.as_raw_fd(), &mut msg, 0) };
+        if read < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        data.truncate(read as usize);
+
+        let mut da = libc::J1939_NO_ADDR;
+        let mut priority = DEFAULT_PRIORITY;
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_CAN_J1939 {
+                    let payload = libc::CMSG_DATA(cmsg);
+                    match (*cmsg).cmsg_type {
+                        libc::SCM_J1939_DEST_ADDR => da = *payload,
+                        libc::SCM_J1939_PRIO => priority = *payload,
+                        _ => {}
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+
+        let source = unsafe { source.can_addr.j1939 };
+        Ok(J1939Message {
+            pgn: source.pgn,
+            sa: source.addr,
+            da,
+            priority,
+            data,
+            src_name: source.name,
+        })
+    }
+}
+
+fn is_timeout(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::TimedOut)
+}
+
+lazy_static::lazy_static! {
+    static ref J1939_REGISTRY: Arc<Mutex<HashMap<u32, Arc<J1939Socket>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+fn get_j1939_socket(socket_id: u32) -> Option<Arc<J1939Socket>> {
+    J1939_REGISTRY.lock().unwrap().get(&socket_id).cloned()
+}
+
+/// Read a 64-bit NAME given either as a number or a bigint
+fn name_from_js(cx: &mut FunctionContext, value: Handle<JsValue>) -> NeonResult<u64> {
+    if let Ok(big) = value.downcast::<JsBigInt, _>(cx) {
+        return big
+            .to_u64(cx)
+            .or_else(|_| cx.throw_range_error("NAME must fit in 64 bits"));
+    }
+    Ok(value.downcast_or_throw::<JsNumber, _>(cx)?.value(cx) as u64)
+}
+
+/// Create a J1939 socket from JavaScript
+pub fn create_j1939_socket(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let interface = cx.argument::<JsString>(0)?.value(&mut cx);
+    let mut options = J1939Options::default();
+    if let Some(obj) = cx
+        .argument_opt(1)
+        .and_then(|v| v.downcast::<JsObject, _>(&mut cx).ok())
+    {
+        if let Some(name) = obj.get_opt::<JsValue, _, _>(&mut cx, "name")? {
+            if !name.is_a::<JsUndefined, _>(&mut cx) {
+                options.name = name_from_js(&mut cx, name)?;
+            }
+        }
+        options.address = obj
+            .get_opt::<JsNumber, _, _>(&mut cx, "address")?
+            .map(|v| v.value(&mut cx) as u8);
+        options.pgn = obj
+            .get_opt::<JsNumber, _, _>(&mut cx, "pgn")?
+            .map(|v| v.value(&mut cx) as u32);
+        options.promiscuous = obj
+            .get_opt::<JsBoolean, _, _>(&mut cx, "promiscuous")?
+            .map(|v| v.value(&mut cx))
+            .unwrap_or(false);
+        options.priority = obj
+            .get_opt::<JsNumber, _, _>(&mut cx, "priority")?
+            .map(|v| v.value(&mut cx) as u8);
+    }
+
+    match J1939Socket::open(&interface, &options) {
+        Ok(socket) => {
+            let id = next_handle_id();
+            J1939_REGISTRY.lock().unwrap().insert(id, Arc::new(socket));
+            Ok(cx.number(id as f64))
+        }
+        Err(e) => cx.throw_error(format!("Failed to create J1939 socket: {}", e)),
+    }
+}
+
+/// Pack NAME fields into a 64-bit NAME from JavaScript
+pub fn j1939_encode_name(mut cx: FunctionContext) -> JsResult<JsBigInt> {
+    let obj = cx.argument::<JsObject>(0)?;
+    let mut field = |key: &str| -> NeonResult<u32> {
+        Ok(obj
+            .get_opt::<JsNumber, _, _>(&mut cx, key)?
+            .map(|v| v.value(&mut cx) as u32)
+            .unwrap_or(0))
+    };
+    let mut name = J1939Name {
+        identity_number: field("identityNumber")?,
+        manufacturer_code: field("manufacturerCode")? as u16,
+        ecu_instance: field("ecuInstance")? as u8,
+        function_instance: field("functionInstance")? as u8,
+        function: field("function")? as u8,
+        vehicle_system: field("vehicleSystem")? as u8,
+        vehicle_system_instance: field("vehicleSystemInstance")? as u8,
+        industry_group: field("industryGroup")? as u8,
+        arbitrary_address_capable: false,
+    };
+    name.arbitrary_address_capable = obj
+        .get_opt::<JsBoolean, _, _>(&mut cx, "arbitraryAddressCapable")?
+        .map(|v| v.value(&mut cx))
+        .unwrap_or(false);
+    Ok(JsBigInt::from_u64(&mut cx, name.to_u64()))
+}
+
+/// Get the claimed source address from JavaScript
+pub fn j1939_get_address(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(socket) = get_j1939_socket(socket_id) else {
+        return cx.throw_error("Invalid J1939 socket ID");
+    };
+    Ok(cx.number(socket.address() as f64))
+}
+
+/// Send a J1939 message from JavaScript
+pub fn j1939_send(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let pgn = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
+    let data = cx.argument::<JsBuffer>(2)?.as_slice(&cx).to_vec();
+    let mut da = libc::J1939_NO_ADDR;
+    let mut priority = None;
+    if let Some(obj) = cx
+        .argument_opt(3)
+        .and_then(|v| v.downcast::<JsObject, _>(&mut cx).ok())
+    {
+        if let Some(v) = obj.get_opt::<JsNumber, _, _>(&mut cx, "da")? {
+            da = v.value(&mut cx) as u8;
+        }
+        priority = obj
+            .get_opt::<JsNumber, _, _>(&mut cx, "priority")?
+            .map(|v| v.value(&mut cx) as u8);
+    }
+
+    let Some(socket) = get_j1939_socket(socket_id) else {
+        return cx.throw_error("Invalid J1939 socket ID");
+    };
+    match socket.send(pgn, &data, da, priority) {
+        Ok(_) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to send J1939 message: {}", e)),
+    }
+}
+
+/// Receive a J1939 message from JavaScript
+pub fn j1939_recv(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let timeout = if cx.len() > 1 {
+        Some(cx.argument::<JsNumber>(1)?.value(&mut cx) as u64)
+    } else {
+        Some(1000)
+    };
+
+    let Some(socket) = get_j1939_socket(socket_id) else {
+        return cx.throw_error("Invalid J1939 socket ID");
+    };
+    match socket.recv(timeout) {
+        Ok(message) => {
+            let obj = cx.empty_object();
+
+            let pgn_val = cx.number(message.pgn as f64);
+            obj.set(&mut cx, "pgn", pgn_val)?;
+
+            let sa_val = cx.number(message.sa as f64);
+            obj.set(&mut cx, "sa", sa_val)?;
+
+            let da_val = cx.number(message.da as f64);
+            obj.set(&mut cx, "da", da_val)?;
+
+            let priority_val = cx.number(message.priority as f64);
+            obj.set(&mut cx, "priority", priority_val)?;
+
+            let data_val = JsBuffer::from_slice(&mut cx, &message.data)?;
+            obj.set(&mut cx, "data", data_val)?;
+
+            if message.src_name != 0 {
+                let name_val = JsBigInt::from_u64(&mut cx, message.src_name);
+                obj.set(&mut cx, "name", name_val)?;
+            }
+
+            Ok(obj)
+        }
+        Err(e) => cx.throw_error(format!("Failed to receive J1939 message: {}", e)),
+    }
+}
+
+/// Close a J1939 socket from JavaScript
+pub fn close_j1939_socket(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match J1939_REGISTRY.lock().unwrap().remove(&socket_id) {
+        Some(_) => Ok(cx.undefined()),
+        None => cx.throw_error("Invalid J1939 socket ID"),
+    }
+}
@@ -0,0 +1,512 @@
+//! J1939 PGN/SPN decoding of raw extended frames returned by `readFrame`
+
+use neon::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::next_handle_id;
+
+/// First PDU format value of the broadcast (PDU2) range
+const PDU2_THRESHOLD: u32 = 240;
+
+/// Fields carried by a 29-bit J1939 identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct J1939Id {
+    pub priority: u8,
+    pub pgn: u32,
+    pub sa: u8,
+    /// Destination address, 0xFF for PDU2 (broadcast) PGNs
+    pub da: u8,
+    /// 1 for destination-specific PGNs, 2 for broadcast PGNs
+    pub pdu_format: u8,
+}
+
+/// Split a 29-bit identifier into priority, PGN and addresses
+pub fn decode_id(id: u32) -> J1939Id {
+    let priority = ((id >> 26) & 0x07) as u8;
+    let edp_dp = (id >> 24) & 0x03;
+    let pf = (id >> 16) & 0xFF;
+    let ps = (id >> 8) & 0xFF;
+    let sa = (id & 0xFF) as u8;
+
+    if pf < PDU2_THRESHOLD {
+        J1939Id {
+            priority,
+            pgn: (edp_dp << 16) | (pf << 8),
+            sa,
+            da: ps as u8,
+            pdu_format: 1,
+        }
+    } else {
+        J1939Id {
+            priority,
+            pgn: (edp_dp << 16) | (pf << 8) | ps,
+            sa,
+            da: 0xFF,
+            pdu_format: 2,
+        }
+    }
+}
+
+/// Build a 29-bit identifier from its J1939 fields
+pub fn encode_id(priority: u8, pgn: u32, sa: u8, da: u8) -> u32 {
+    let pf = (pgn >> 8) & 0xFF;
+    let ps = if pf < PDU2_THRESHOLD {
+        da as u32
+    } else {
+        pgn & 0xFF
+    };
+    ((priority as u32 & 0x07) << 26) | ((pgn & 0x3_0000) << 8) | (pf << 16) | (ps << 8) | sa as u32
+}
+
+/// Largest PGN payload, carried by a multi-packet transport session
+const MAX_PGN_BYTES: u32 = 1785;
+
+/// Suspect parameter definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpnDefinition {
+    pub spn: u32,
+    pub pgn: u32,
+    pub name: String,
+    /// Position of the least significant bit in the PGN data (0-based)
+    pub start_bit: u32,
+    /// Length in bits (1..=32)
+    pub length: u32,
+    pub scale: f64,
+    pub offset: f64,
+    pub unit: String,
+}
+
+impl SpnDefinition {
+    fn validate(&self) -> Result<(), String> {
+        if self.length == 0 || self.length > 32 {
+            return Err(format!("SPN {}: invalid length {}", self.spn, self.length));
+        }
+        if self.start_bit > MAX_PGN_BYTES * 8 - self.length {
+            return Err(format!(
+                "SPN {}: invalid start bit {}",
+                self.spn, self.start_bit
+            ));
+        }
+        if self.pgn > 0x3FFFF {
+            return Err(format!("SPN {}: invalid PGN 0x{:X}", self.spn, self.pgn));
+        }
+        Ok(())
+    }
+}
+
+/// Validity of a decoded SPN value (J1939-71 reserved ranges)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpnStatus {
+    Valid,
+    Error,
+    NotAvailable,
+}
+
+impl SpnStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            SpnStatus::Valid => "valid",
+            SpnStatus::Error => "error",
+            SpnStatus::NotAvailable => "notAvailable",
+        }
+    }
+}
+
+/// Classify a raw value against the error/not-available indicators
+pub fn spn_status(raw: u32, length: u32) -> SpnStatus {
+    match length {
+        1 => SpnStatus::Valid,
+        2 => match raw {
+            2 => SpnStatus::Error,
+            3 => SpnStatus::NotAvailable,
+            _ => SpnStatus::Valid,
+        },
+        3..=7 => {
+            let max = (1u32 << length) - 1;
+            if raw == max {
+                SpnStatus::NotAvailable
+            } else if raw == max - 1 {
+                SpnStatus::Error
+            } else {
+                SpnStatus::Valid
+            }
+        }
+        _ => {
+            // The most significant byte flags error (0xFE) or not available (0xFF)
+            let top = (raw >> (length - 8)) & 0xFF;
+            match top {
+                0xFF => SpnStatus::NotAvailable,
+                0xFE => SpnStatus::Error,
+                _ => SpnStatus::Valid,
+            }
+        }
+    }
+}
+
+/// Decoded SPN value
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpnValue {
+    pub spn: u32,
+    pub name: String,
+    pub raw: u32,
+    pub value: f64,
+    pub unit: String,
+    pub status: SpnStatus,
+}
+
+/// Extract little-endian bits from a PGN payload
+pub fn extract_bits(data: &[u8], start_bit: u32, length: u32) -> Option<u32> {
+    if start_bit.checked_add(length)? > data.len() as u32 * 8 {
+        return None;
+    }
+    let mut raw = 0u64;
+    for i in 0..length {
+        let bit = start_bit + i;
+        let byte = data[(bit / 8) as usize];
+        if byte >> (bit % 8) & 1 != 0 {
+            raw |= 1 << i;
+        }
+    }
+    Some(raw as u32)
+}
+
+/// SPN, PGN, name, start bit, length, scale, offset, unit
+type StandardSpn = (u32, u32, &'static str, u32, u32, f64, f64, &'static str);
+
+#[rustfmt::skip]
+const STANDARD_SPNS: &[StandardSpn] = &[
+    (899, 61444, "Engine Torque Mode", 0, 4, 1.0, 0.0, ""),
+    (512, 61444, "Driver's Demand Engine - Percent Torque", 8, 8, 1.0, -125.0, "%"),
+    (513, 61444, "Actual Engine - Percent Torque", 16, 8, 1.0, -125.0, "%"),
+    (190, 61444, "Engine Speed", 24, 16, 0.125, 0.0, "rpm"),
+    (91, 61443, "Accelerator Pedal Position 1", 8, 8, 0.4, 0.0, "%"),
+    (92, 61443, "Engine Percent Load At Current Speed", 16, 8, 1.0, 0.0, "%"),
+    (110, 65262, "Engine Coolant Temperature", 0, 8, 1.0, -40.0, "°C"),
+    (174, 65262, "Engine Fuel Temperature 1", 8, 8, 1.0, -40.0, "°C"),
+    (175, 65262, "Engine Oil Temperature 1", 16, 16, 0.03125, -273.0, "°C"),
+    (100, 65263, "Engine Oil Pressure", 24, 8, 4.0, 0.0, "kPa"),
+    (84, 65265, "Wheel-Based Vehicle Speed", 8, 16, 1.0 / 256.0, 0.0, "km/h"),
+    (96, 65276, "Fuel Level 1", 8, 8, 0.4, 0.0, "%"),
+    (247, 65253, "Engine Total Hours of Operation", 0, 32, 0.05, 0.0, "h"),
+    (245, 65248, "Total Vehicle Distance", 32, 32, 0.125, 0.0, "km"),
+    (168, 65271, "Battery Potential / Power Input 1", 32, 16, 0.05, 0.0, "V"),
+];
+
+/// SPN definitions indexed by PGN
+#[derive(Debug, Clone, Default)]
+pub struct SpnTable {
+    by_pgn: HashMap<u32, Vec<SpnDefinition>>,
+}
+
+impl SpnTable {
+    /// Table with a few common J1939-71 parameters
+    pub fn standard() -> Self {
+        let mut table = Self::default();
+        for &(spn, pgn, name, start_bit, length, scale, offset, unit) in STANDARD_SPNS {
+            table.insert(SpnDefinition {
+                spn,
+                pgn,
+                name: name.to_string(),
+                start_bit,
+                length,
+                scale,
+                offset,
+                unit: unit.to_string(),
+            });
+        }
+        table
+    }
+
+    /// Add or replace a definition
+    pub fn insert(&mut self, definition: SpnDefinition) {
+        let entries = self.by_pgn.entry(definition.pgn).or_default();
+        entries.retain(|existing| existing.spn != definition.spn);
+        entries.push(definition);
+    }
+
+    /// Parse a CSV table: `spn,pgn,name,start_bit,length,scale,offset,unit`
+    ///
+    /// Blank lines, `#` comments and a header line are skipped.
+    pub fn parse_csv(&mut self, text: &str) -> Result<usize, String> {
+        let mut count = 0;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if index == 0 && fields[0].parse::<u32>().is_err() {
+                continue;
+            }
+            if fields.len() < 7 {
+                return Err(format!("line {}: expected at least 7 fields", index + 1));
+            }
+            let number = |i: usize| -> Result<f64, String> {
+                fields[i]
+                    .parse::<f64>()
+                    .map_err(|_| format!("line {}: invalid number '{}'", index + 1, fields[i]))
+            };
+            let definition = SpnDefinition {
+                spn: number(0)? as u32,
+                pgn: number(1)? as u32,
+                name: fields[2].to_string(),
+                start_bit: number(3)? as u32,
+                length: number(4)? as u32,
+                scale: number(5)?,
+                offset: number(6)?,
+                unit: fields.get(7).copied().unwrap_or("").to_string(),
+            };
+            definition
+                .validate()
+                .map_err(|e| format!("line {}: {}", index + 1, e))?;
+            self.insert(definition);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Decode every known SPN of `pgn` from `data`
+    pub fn decode(&self, pgn: u32, data: &[u8]) -> Vec<SpnValue> {
+        let Some(definitions) = self.by_pgn.get(&pgn) else {
+            return Vec::new();
+        };
+        definitions
+            .iter()
+            .filter_map(|definition| {
+                let raw = extract_bits(data, definition.start_bit, definition.length)?;
+                let status = spn_status(raw, definition.length);
+                Some(SpnValue {
+                    spn: definition.spn,
+                    name: definition.name.clone(),
+                    raw,
+                    value: raw as f64 * definition.scale + definition.offset,
+                    unit: definition.unit.clone(),
+                    status,
+                })
+            })
+            .collect()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SPN_TABLE_REGISTRY: Arc<Mutex<HashMap<u32, Arc<SpnTable>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Read SPN definitions from a JS array
+fn definitions_from_js(
+    cx: &mut FunctionContext,
+    array: Handle<JsArray>,
+) -> NeonResult<Vec<SpnDefinition>> {
+    let mut definitions = Vec::new();
+    for i in 0..array.len(cx) {
+        let obj = array.get::<JsObject, _, _>(cx, i)?;
+        let spn = obj.get::<JsNumber, _, _>(cx, "spn")?.value(cx) as u32;
+        let pgn = obj.get::<JsNumber, _, _>(cx, "pgn")?.value(cx) as u32;
+        let start_bit = obj.get::<JsNumber, _, _>(cx, "startBit")?.value(cx) as u32;
+        let length = obj.get::<JsNumber, _, _>(cx, "length")?.value(cx) as u32;
+        let name = match obj.get_opt::<JsString, _, _>(cx, "name")? {
+            Some(v) => v.value(cx),
+            None => format!("SPN {}", spn),
+        };
+        let scale = match obj.get_opt::<JsNumber, _, _>(cx, "scale")? {
+            Some(v) => v.value(cx),
+            None => 1.0,
+        };
+        let offset = match obj.get_opt::<JsNumber, _, _>(cx, "offset")? {
+            Some(v) => v.value(cx),
+            None => 0.0,
+        };
+        let unit = match obj.get_opt::<JsString, _, _>(cx, "unit")? {
+            Some(v) => v.value(cx),
+            None => String::new(),
+        };
+
+        let definition = SpnDefinition {
+            spn,
+            pgn,
+            name,
+            start_bit,
+            length,
+            scale,
+            offset,
+            unit,
+        };
+        if let Err(e) = definition.validate() {
+            return cx.throw_error(e);
+        }
+        definitions.push(definition);
+    }
+    Ok(definitions)
+}
+
+fn register_table(table: SpnTable) -> u32 {
+    let id = next_handle_id();
+    SPN_TABLE_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(id, Arc::new(table));
+    id
+}
+
+/// Create an SPN table from JavaScript definitions (plus the standard set by default)
+pub fn create_spn_table(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let definitions = match cx.argument_opt(0) {
+        Some(v) if !v.is_a::<JsUndefined, _>(&mut cx) => {
+            let array = v.downcast_or_throw::<JsArray, _>(&mut cx)?;
+            definitions_from_js(&mut cx, array)?
+        }
+        _ => Vec::new(),
+    };
+    let include_standard = match cx.argument_opt(1) {
+        Some(v) => v.downcast_or_throw::<JsBoolean, _>(&mut cx)?.value(&mut cx),
+        None => true,
+    };
+
+    let mut table = if include_standard {
+        SpnTable::standard()
+    } else {
+        SpnTable::default()
+    };
+    for definition in definitions {
+        table.insert(definition);
+    }
+    Ok(cx.number(register_table(table) as f64))
+}
+
+/// Load an SPN table from a CSV file
+pub fn load_spn_table(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let include_standard = match cx.argument_opt(1) {
+        Some(v) => v.downcast_or_throw::<JsBoolean, _>(&mut cx)?.value(&mut cx),
+        None => false,
+    };
+
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => return cx.throw_error(format!("Failed to read SPN table {}: {}", path, e)),
+    };
+    let mut table = if include_standard {
+        SpnTable::standard()
+    } else {
+        SpnTable::default()
+    };
+    if let Err(e) = table.parse_csv(&text) {
+        return cx.throw_error(format!("Failed to parse SPN table: {}", e));
+    }
+    Ok(cx.number(register_table(table) as f64))
+}
+
+/// Release an SPN table
+pub fn close_spn_table(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let table_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match SPN_TABLE_REGISTRY.lock().unwrap().remove(&table_id) {
+        Some(_) => Ok(cx.undefined()),
+        None => cx.throw_error("Invalid SPN table ID"),
+    }
+}
+
+fn set_id_fields<'a>(
+    cx: &mut FunctionContext<'a>,
+    obj: Handle<'a, JsObject>,
+    fields: J1939Id,
+) -> NeonResult<()> {
+    let priority_val = cx.number(fields.priority as f64);
+    obj.set(cx, "priority", priority_val)?;
+
+    let pgn_val = cx.number(fields.pgn as f64);
+    obj.set(cx, "pgn", pgn_val)?;
+
+    let sa_val = cx.number(fields.sa as f64);
+    obj.set(cx, "sa", sa_val)?;
+
+    let da_val = cx.number(fields.da as f64);
+    obj.set(cx, "da", da_val)?;
+
+    let pdu_val = cx.number(fields.pdu_format as f64);
+    obj.set(cx, "pduFormat", pdu_val)?;
+    Ok(())
+}
+
+/// Decode the J1939 fields of a 29-bit identifier from JavaScript
+pub fn decode_j1939_id(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let obj = cx.empty_object();
+    set_id_fields(&mut cx, obj, decode_id(id & 0x1FFF_FFFF))?;
+    Ok(obj)
+}
+
+/// Build a 29-bit identifier from JavaScript: `(priority, pgn, sa, da?)`
+pub fn encode_j1939_id(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let priority = cx.argument::<JsNumber>(0)?.value(&mut cx) as u8;
+    let pgn = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
+    let sa = cx.argument::<JsNumber>(2)?.value(&mut cx) as u8;
+    let da = match cx.argument_opt(3) {
+        Some(v) => v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u8,
+        None => 0xFF,
+    };
+
+    if priority > 7 {
+        return cx.throw_error("J1939 priority must be between 0 and 7");
+    }
+    if pgn > 0x3FFFF {
+        return cx.throw_error("Invalid PGN: must be 18 bits");
+    }
+    Ok(cx.number(encode_id(priority, pgn, sa, da) as f64))
+}
+
+/// Decode a frame object `{ id, data, extended }` against an SPN table
+pub fn decode_j1939_frame(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let table_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let frame = cx.argument::<JsObject>(1)?;
+    let id = frame.get::<JsNumber, _, _>(&mut cx, "id")?.value(&mut cx) as u32;
+    let extended = match frame.get_opt::<JsBoolean, _, _>(&mut cx, "extended")? {
+        Some(v) => v.value(&mut cx),
+        None => true,
+    };
+    let data_array = frame.get::<JsArray, _, _>(&mut cx, "data")?;
+    let mut data = Vec::new();
+    for i in 0..data_array.len(&mut cx) {
+        let val = data_array.get::<JsNumber, _, _>(&mut cx, i)?.value(&mut cx) as u8;
+        data.push(val);
+    }
+
+    if !extended {
+        return cx.throw_error("J1939 frames use 29-bit extended identifiers");
+    }
+    let Some(table) = SPN_TABLE_REGISTRY.lock().unwrap().get(&table_id).cloned() else {
+        return cx.throw_error("Invalid SPN table ID");
+    };
+
+    let fields = decode_id(id);
+    let obj = cx.empty_object();
+    set_id_fields(&mut cx, obj, fields)?;
+
+    let spns = cx.empty_array();
+    for (i, value) in table.decode(fields.pgn, &data).into_iter().enumerate() {
+        let spn_obj = cx.empty_object();
+
+        let spn_val = cx.number(value.spn as f64);
+        spn_obj.set(&mut cx, "spn", spn_val)?;
+
+        let name_val = cx.string(&value.name);
+        spn_obj.set(&mut cx, "name", name_val)?;
+
+        let raw_val = cx.number(value.raw as f64);
+        spn_obj.set(&mut cx, "raw", raw_val)?;
+
+        let value_val = cx.number(value.value);
+        spn_obj.set(&mut cx, "value", value_val)?;
+
+        let unit_val = cx.string(&value.unit);
+        spn_obj.set(&mut cx, "unit", unit_val)?;
+
+        let status_val = cx.string(value.status.as_str());
+        spn_obj.set(&mut cx, "status", status_val)?;
+
+        spns.set(&mut cx, i as u32, spn_obj)?;
+    }
+    obj.set(&mut cx, "spns", spns)?;
+    Ok(obj)
+}
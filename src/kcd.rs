@@ -0,0 +1,212 @@
+//! Kayak KCD network definition parser
+//!
+//! Maps `Bus`/`Message`/`Signal`/`Multiplex` elements onto the same
+//! [`SignalDatabase`] model as DBC files. Messages of every bus are loaded
+//! unless a bus name is given.
+
+use std::collections::{BTreeMap, HashMap};
+
+use roxmltree::{Document, Node};
+
+use crate::signal_db::{
+    ByteOrder, Message, Multiplex, Signal, SignalDatabase, ValueType, MAX_MESSAGE_SIZE,
+    MAX_SIGNAL_BITS,
+};
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+        None => text.parse().ok(),
+    }
+}
+
+fn number_attr(node: Node, name: &str) -> Result<Option<f64>, String> {
+    match node.attribute(name) {
+        Some(text) => parse_number(text)
+            .map(Some)
+            .ok_or_else(|| format!("<{}>: invalid {} '{}'", node.tag_name().name(), name, text)),
+        None => Ok(None),
+    }
+}
+
+fn notes(node: Node) -> Option<String> {
+    child(node, "Notes")
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn node_refs(node: Option<Node>, nodes: &HashMap<String, String>) -> Vec<String> {
+    node.into_iter()
+        .flat_map(|n| children(n, "NodeRef"))
+        .filter_map(|r| nodes.get(r.attribute("id")?).cloned())
+        .collect()
+}
+
+/// Parse a `Signal` or `Multiplex` element
+fn parse_signal(node: Node, nodes: &HashMap<String, String>) -> Result<Signal, String> {
+    let name = node
+        .attribute("name")
+        .ok_or("<Signal>: missing name attribute")?;
+    let offset =
+        number_attr(node, "offset")?.ok_or_else(|| format!("{}: missing offset", name))? as u32;
+    let length = number_attr(node, "length")?.unwrap_or(1.0) as u32;
+    if length == 0 || length > 64 {
+        return Err(format!("{}: invalid length {}", name, length));
+    }
+    if offset >= MAX_SIGNAL_BITS {
+        return Err(format!("{}: invalid offset {}", name, offset));
+    }
+
+    // Big endian : offset du MSB en numérotation séquentielle, converti en bit de départ DBC
+    let (byte_order, start_bit) = match node.attribute("endianess").unwrap_or("little") {
+        "little" => (ByteOrder::LittleEndian, offset),
+        "big" => (ByteOrder::BigEndian, 8 * (offset / 8) + 7 - offset % 8),
+        other => return Err(format!("{}: invalid endianess '{}'", name, other)),
+    };
+
+    let mut signal = Signal::new(name, start_bit, length, byte_order);
+    signal.comment = notes(node);
+    signal.receivers = node_refs(child(node, "Consumer"), nodes);
+
+    if let Some(value) = child(node, "Value") {
+        signal.value_type = match value.attribute("type").unwrap_or("unsigned") {
+            "unsigned" => ValueType::Unsigned,
+            "signed" => ValueType::Signed,
+            "single" => ValueType::Float32,
+            "double" => ValueType::Float64,
+            other => return Err(format!("{}: invalid value type '{}'", name, other)),
+        };
+        signal.factor = number_attr(value, "slope")?.unwrap_or(1.0);
+        signal.offset = number_attr(value, "intercept")?.unwrap_or(0.0);
+        signal.minimum = number_attr(value, "min")?.unwrap_or(0.0);
+        signal.maximum = number_attr(value, "max")?.unwrap_or(0.0);
+        signal.unit = value.attribute("unit").unwrap_or("").to_string();
+    }
+
+    let mut table = BTreeMap::new();
+    if let Some(labels) = child(node, "LabelSet") {
+        for label in children(labels, "Label") {
+            let value = number_attr(label, "value")?.ok_or("<Label>: missing value")?;
+            table.insert(
+                value as i64,
+                label.attribute("name").unwrap_or("").to_string(),
+            );
+        }
+    }
+    signal.value_table = table;
+    Ok(signal)
+}
+
+/// Payload length covering all signals, for `length="auto"`
+fn auto_length(signals: &[Signal]) -> u32 {
+    signals
+        .iter()
+        .map(|s| match s.byte_order {
+            ByteOrder::LittleEndian => (s.start_bit + s.length).div_ceil(8),
+            // Le LSB est dans l'octet du MSB + (bits restants dans les octets suivants)
+            ByteOrder::BigEndian => {
+                let first_bits = s.start_bit % 8 + 1;
+                s.start_bit / 8 + 1 + s.length.saturating_sub(first_bits).div_ceil(8)
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn parse_message(node: Node, nodes: &HashMap<String, String>) -> Result<Message, String> {
+    let name = node
+        .attribute("name")
+        .ok_or("<Message>: missing name attribute")?;
+    let id = number_attr(node, "id")?.ok_or_else(|| format!("{}: missing id", name))? as u32;
+    let extended = node.attribute("format") == Some("extended");
+
+    let mut signals = Vec::new();
+    for element in node.children().filter(|n| n.is_element()) {
+        match element.tag_name().name() {
+            "Signal" => signals.push(parse_signal(element, nodes)?),
+            "Multiplex" => {
+                let mut mux = parse_signal(element, nodes)?;
+                mux.multiplex = Multiplex::Multiplexor;
+                signals.push(mux);
+                for group in children(element, "MuxGroup") {
+                    let count = number_attr(group, "count")?
+                        .ok_or_else(|| format!("{}: MuxGroup without count", name))?;
+                    for node in children(group, "Signal") {
+                        let mut signal = parse_signal(node, nodes)?;
+                        signal.multiplex = Multiplex::Multiplexed(count as u64);
+                        signals.push(signal);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let size = match node.attribute("length") {
+        None | Some("auto") => auto_length(&signals),
+        Some(_) => number_attr(node, "length")?.unwrap_or(0.0) as u32,
+    };
+    if size > MAX_MESSAGE_SIZE {
+        return Err(format!("{}: invalid length {}", name, size));
+    }
+
+    Ok(Message {
+        id,
+        extended,
+        name: name.to_string(),
+        size,
+        transmitter: node_refs(child(node, "Producer"), nodes).into_iter().next(),
+        signals,
+        comment: notes(node),
+        attributes: HashMap::new(),
+    })
+}
+
+/// Parse a KCD document, optionally restricted to one bus
+pub fn parse(text: &str, bus: Option<&str>) -> Result<SignalDatabase, String> {
+    let doc = Document::parse(text).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "NetworkDefinition" {
+        return Err("not a KCD document (missing NetworkDefinition)".to_string());
+    }
+
+    let mut db = SignalDatabase::default();
+    let mut nodes = HashMap::new();
+    for node in children(root, "Node") {
+        if let (Some(id), Some(name)) = (node.attribute("id"), node.attribute("name")) {
+            nodes.insert(id.to_string(), name.to_string());
+            db.nodes.push(name.to_string());
+        }
+    }
+
+    let mut found = false;
+    for bus_node in children(root, "Bus") {
+        if bus.is_some_and(|name| bus_node.attribute("name") != Some(name)) {
+            continue;
+        }
+        found = true;
+        for message in children(bus_node, "Message") {
+            db.messages.push(parse_message(message, &nodes)?);
+        }
+    }
+    if let (Some(name), false) = (bus, found) {
+        return Err(format!("unknown bus {}", name));
+    }
+
+    db.finish();
+    Ok(db)
+}
@@ -7,9 +7,49 @@ use socketcan::{
     CanFdFrame, CanFdSocket, CanFilter, CanFrame, CanSocket, EmbeddedFrame, ExtendedId, Frame, Id,
     Socket, SocketOptions, StandardId,
 };
-#[cfg(target_os = "linux")]
 use std::time::Duration;
 
+mod arxml;
+mod asc;
+mod blf;
+mod bridge;
+mod candump;
+#[cfg(target_os = "linux")]
+mod canopen;
+#[cfg(target_os = "linux")]
+mod canopen_od;
+#[cfg(target_os = "linux")]
+mod controller;
+mod dbc;
+#[cfg(target_os = "linux")]
+mod gateway;
+#[cfg(target_os = "linux")]
+mod interfaces;
+#[cfg(target_os = "linux")]
+mod isotp;
+#[cfg(target_os = "linux")]
+mod isotp_engine;
+#[cfg(target_os = "linux")]
+mod j1939;
+mod j1939_decode;
+mod kcd;
+mod mdf;
+#[cfg(target_os = "linux")]
+mod netlink;
+#[cfg(target_os = "linux")]
+mod obd;
+mod pcap;
+mod replay;
+mod signal_db;
+mod socketcand;
+mod socketcand_server;
+#[cfg(target_os = "linux")]
+mod supervisor;
+mod trace;
+mod trc;
+#[cfg(target_os = "linux")]
+mod uds;
+
 /// Pool de buffers réutilisables pour éviter les allocations répétées
 #[cfg(target_os = "linux")]
 struct BufferPool {
@@ -76,6 +116,8 @@ pub enum CanSocketWrapper {
     Regular(Arc<Mutex<CanSocket>>),
     /// CAN FD socket
     Fd(Arc<Mutex<CanFdSocket>>),
+    /// Bus of a remote socketcand server
+    Remote(Arc<socketcand::SocketcandClient>),
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -83,6 +125,8 @@ pub enum CanSocketWrapper {
 pub struct CanSocketWrapper {
     interface: String,
     is_fd: bool,
+    /// Bus of a remote socketcand server, the only working backend here
+    remote: Option<Arc<socketcand::SocketcandClient>>,
 }
 
 // Global registry to store sockets and buffer pool
@@ -91,11 +135,76 @@ lazy_static::lazy_static! {
     static ref NEXT_ID: Arc<Mutex<u32>> = Arc::new(Mutex::new(1));
 }
 
+/// Allocate a handle ID shared by all native object registries
+pub(crate) fn next_handle_id() -> u32 {
+    let mut next_id = NEXT_ID.lock().unwrap();
+    let id = *next_id;
+    *next_id += 1;
+    id
+}
+
 #[cfg(target_os = "linux")]
 lazy_static::lazy_static! {
     static ref BUFFER_POOL: Arc<Mutex<BufferPool>> = Arc::new(Mutex::new(BufferPool::new(50, 64)));
 }
 
+/// Read timeout applied once poll reported a frame, in case another reader took it first
+///
+/// Only set for that read, the socket keeps its own timeout otherwise.
+#[cfg(target_os = "linux")]
+const CLAIM_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Read a frame, waiting for it without holding the socket lock
+///
+/// The wait happens in `poll` on the raw descriptor, so senders sharing the
+/// wrapper (bridges, socketcand clients) are never stalled by a pending read.
+#[cfg(target_os = "linux")]
+fn read_unlocked<S: Socket>(
+    socket: &Mutex<S>,
+    timeout_ms: Option<u64>,
+) -> Result<S::FrameType, Box<dyn std::error::Error>> {
+    use std::time::Instant;
+
+    let fd = socket.lock().map_err(|_| "Mutex poisoned")?.as_raw_fd();
+    let deadline = timeout_ms.map(|t| Instant::now() + Duration::from_millis(t));
+    loop {
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = deadline.map_or(-1, |d| {
+            let left = d.saturating_duration_since(Instant::now());
+            left.as_millis().min(i32::MAX as u128) as libc::c_int
+        });
+        let ready = unsafe { libc::poll(&mut pfd, 1, timeout) };
+        if ready < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        if ready == 0 {
+            return Err(
+                std::io::Error::new(std::io::ErrorKind::TimedOut, "CAN receive timed out").into(),
+            );
+        }
+
+        let socket = socket.lock().map_err(|_| "Mutex poisoned")?;
+        // Le délai d'origine est rétabli pour les autres lecteurs du socket
+        let previous = socket.read_timeout()?;
+        socket.set_read_timeout(CLAIM_TIMEOUT)?;
+        let read = socket.read_frame();
+        socket.set_read_timeout(previous)?;
+        match read {
+            Ok(frame) => return Ok(frame),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
 #[cfg(target_os = "linux")]
 impl CanSocketWrapper {
     /// Create a new CAN socket (regular)
@@ -110,6 +219,19 @@ impl CanSocketWrapper {
         Ok(CanSocketWrapper::Fd(Arc::new(Mutex::new(socket))))
     }
 
+    /// Wrap a connection to a socketcand bus
+    fn remote(client: socketcand::SocketcandClient) -> Self {
+        CanSocketWrapper::Remote(Arc::new(client))
+    }
+
+    /// socketcand connection of a remote socket
+    fn socketcand(&self) -> Option<Arc<socketcand::SocketcandClient>> {
+        match self {
+            CanSocketWrapper::Remote(client) => Some(Arc::clone(client)),
+            _ => None,
+        }
+    }
+
     /// Send a CAN frame (regular, FD, or remote)
     fn send_frame(
         &self,
@@ -168,6 +290,16 @@ impl CanSocketWrapper {
                     socket.write_frame(&frame)?;
                 }
             }
+            CanSocketWrapper::Remote(client) => {
+                client.send(&trace::TraceFrame {
+                    id,
+                    data,
+                    extended,
+                    fd: is_fd,
+                    remote: is_remote,
+                    ..Default::default()
+                })?;
+            }
         }
         Ok(())
     }
@@ -179,15 +311,7 @@ impl CanSocketWrapper {
     ) -> Result<(u32, Vec<u8>, bool, bool, bool, bool), Box<dyn std::error::Error>> {
         match self {
             CanSocketWrapper::Regular(socket) => {
-                let socket = socket.lock().map_err(|_| "Mutex poisoned")?;
-                if let Some(timeout) = timeout_ms {
-                    socket.set_read_timeout(Duration::from_millis(timeout))?;
-                } else {
-                    // Set to blocking mode if no timeout (very long timeout)
-                    socket.set_read_timeout(Duration::from_secs(3600))?;
-                }
-
-                let frame = socket.read_frame()?;
+                let frame = read_unlocked(socket, timeout_ms)?;
                 let (id, extended) = match frame.id() {
                     Id::Standard(std_id) => (std_id.as_raw() as u32, false),
                     Id::Extended(ext_id) => (ext_id.as_raw(), true),
@@ -227,16 +351,8 @@ impl CanSocketWrapper {
                 Ok((id, data, extended, false, is_remote, is_error)) // Regular CAN frame with flags
             }
             CanSocketWrapper::Fd(socket) => {
-                let socket = socket.lock().map_err(|_| "Mutex poisoned")?;
-                if let Some(timeout) = timeout_ms {
-                    socket.set_read_timeout(Duration::from_millis(timeout))?;
-                } else {
-                    // Set to blocking mode if no timeout (very long timeout)
-                    socket.set_read_timeout(Duration::from_secs(3600))?;
-                }
-
                 // Read any frame (CAN or CAN FD)
-                match socket.read_frame() {
+                match read_unlocked(socket, timeout_ms) {
                     Ok(frame) => {
                         match frame {
                             socketcan::CanAnyFrame::Normal(can_frame) => {
@@ -295,9 +411,20 @@ impl CanSocketWrapper {
                             _ => Err("Unsupported frame type".into()),
                         }
                     }
-                    Err(e) => Err(e.into()),
+                    Err(e) => Err(e),
                 }
             }
+            CanSocketWrapper::Remote(client) => {
+                let frame = client.read_frame(timeout_ms)?;
+                Ok((
+                    frame.id,
+                    frame.data,
+                    frame.extended,
+                    false,
+                    false,
+                    frame.error,
+                ))
+            }
         }
     }
 
@@ -308,8 +435,8 @@ impl CanSocketWrapper {
     ) -> Result<(), Box<dyn std::error::Error>> {
         // Convertir les filtres en format CanFilter
         let can_filters: Vec<CanFilter> = filters
-            .into_iter()
-            .map(|(id, mask, _extended)| {
+            .iter()
+            .map(|&(id, mask, _extended)| {
                 // CanFilter::new prend directement des u32, pas des Id
                 CanFilter::new(id, mask)
             })
@@ -338,6 +465,10 @@ impl CanSocketWrapper {
                     socket.set_filters(&can_filters)?;
                 }
             }
+            CanSocketWrapper::Remote(client) => {
+                // socketcand ne filtre pas en mode brut : filtrage côté client
+                client.set_filters(filters.iter().map(|&(id, mask, _)| (id, mask)).collect());
+            }
         }
         Ok(())
     }
@@ -357,14 +488,58 @@ impl CanSocketWrapper {
                 let accept_all = vec![CanFilter::new(0x00000000, 0x00000000)];
                 socket.set_filters(&accept_all)?;
             }
+            CanSocketWrapper::Remote(client) => client.set_filters(Vec::new()),
         }
         Ok(())
     }
 
+    /// Name of the interface the socket is bound to
+    fn interface_name(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use std::os::fd::AsRawFd;
+
+        let fd = match self {
+            CanSocketWrapper::Regular(socket) => {
+                socket.lock().map_err(|_| "Mutex poisoned")?.as_raw_fd()
+            }
+            CanSocketWrapper::Fd(socket) => {
+                socket.lock().map_err(|_| "Mutex poisoned")?.as_raw_fd()
+            }
+            CanSocketWrapper::Remote(client) => {
+                return Err(format!("Socket is bound to socketcand bus {}", client.bus()).into())
+            }
+        };
+
+        let mut addr: libc::sockaddr_can = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::sockaddr_can>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockname(
+                fd,
+                &mut addr as *mut libc::sockaddr_can as *mut libc::sockaddr,
+                &mut len,
+            )
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+        let ptr =
+            unsafe { libc::if_indextoname(addr.can_ifindex as libc::c_uint, name.as_mut_ptr()) };
+        if ptr.is_null() {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let name = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) };
+        Ok(name.to_string_lossy().into_owned())
+    }
+
     /// Close the socket and cleanup resources
     fn close(&self) -> Result<(), Box<dyn std::error::Error>> {
         // Note: SocketCAN sockets are automatically closed when dropped
         // This method exists for explicit cleanup if needed
+        if let CanSocketWrapper::Remote(client) = self {
+            // Débloque une lecture en cours sur la connexion
+            client.close()?;
+        }
         Ok(())
     }
 }
@@ -376,6 +551,7 @@ impl CanSocketWrapper {
         Ok(CanSocketWrapper {
             interface,
             is_fd: false,
+            remote: None,
         })
     }
 
@@ -384,44 +560,102 @@ impl CanSocketWrapper {
         Ok(CanSocketWrapper {
             interface,
             is_fd: true,
+            remote: None,
         })
     }
 
+    /// Wrap a connection to a socketcand bus
+    fn remote(client: socketcand::SocketcandClient) -> Self {
+        CanSocketWrapper {
+            interface: client.bus().to_string(),
+            is_fd: false,
+            remote: Some(Arc::new(client)),
+        }
+    }
+
+    /// socketcand connection of a remote socket
+    fn socketcand(&self) -> Option<Arc<socketcand::SocketcandClient>> {
+        self.remote.clone()
+    }
+
     /// Send a CAN frame (stub for non-Linux)
     fn send_frame(
         &self,
-        _id: u32,
-        _data: Vec<u8>,
-        _extended: bool,
-        _is_fd: bool,
-        _is_remote: bool,
+        id: u32,
+        data: Vec<u8>,
+        extended: bool,
+        is_fd: bool,
+        is_remote: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        Err("SocketCAN is only supported on Linux".into())
+        match &self.remote {
+            Some(client) => Ok(client.send(&trace::TraceFrame {
+                id,
+                data,
+                extended,
+                fd: is_fd,
+                remote: is_remote,
+                ..Default::default()
+            })?),
+            None => Err("SocketCAN is only supported on Linux".into()),
+        }
     }
 
     /// Receive a CAN frame with timeout (stub for non-Linux)
     fn read_frame(
         &self,
-        _timeout_ms: Option<u64>,
+        timeout_ms: Option<u64>,
     ) -> Result<(u32, Vec<u8>, bool, bool, bool, bool), Box<dyn std::error::Error>> {
-        Err("SocketCAN is only supported on Linux".into())
+        match &self.remote {
+            Some(client) => {
+                let frame = client.read_frame(timeout_ms)?;
+                Ok((
+                    frame.id,
+                    frame.data,
+                    frame.extended,
+                    false,
+                    false,
+                    frame.error,
+                ))
+            }
+            None => Err("SocketCAN is only supported on Linux".into()),
+        }
     }
 
     /// Set CAN filters (stub for non-Linux)
     fn set_filters(
         &self,
-        _filters: Vec<(u32, u32, bool)>,
+        filters: Vec<(u32, u32, bool)>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        Err("SocketCAN is only supported on Linux".into())
+        match &self.remote {
+            Some(client) => {
+                client.set_filters(filters.iter().map(|&(id, mask, _)| (id, mask)).collect());
+                Ok(())
+            }
+            None => Err("SocketCAN is only supported on Linux".into()),
+        }
     }
 
     /// Clear all CAN filters (stub for non-Linux)
     fn clear_filters(&self) -> Result<(), Box<dyn std::error::Error>> {
-        Err("SocketCAN is only supported on Linux".into())
+        match &self.remote {
+            Some(client) => {
+                client.set_filters(Vec::new());
+                Ok(())
+            }
+            None => Err("SocketCAN is only supported on Linux".into()),
+        }
+    }
+
+    /// Name of the interface the socket is bound to (stub for non-Linux)
+    fn interface_name(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.interface.clone())
     }
 
     /// Close the socket (stub for non-Linux)
     fn close(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(client) = &self.remote {
+            client.close()?;
+        }
         Ok(())
     }
 }
@@ -443,10 +677,7 @@ fn create_socket(mut cx: FunctionContext) -> JsResult<JsNumber> {
 
     match wrapper {
         Ok(wrapper) => {
-            let mut next_id = NEXT_ID.lock().unwrap();
-            let id = *next_id;
-            *next_id += 1;
-
+            let id = next_handle_id();
             SOCKET_REGISTRY.lock().unwrap().insert(id, wrapper);
             Ok(cx.number(id as f64))
         }
@@ -454,6 +685,122 @@ fn create_socket(mut cx: FunctionContext) -> JsResult<JsNumber> {
     }
 }
 
+/// Connect to a bus of a socketcand server from JavaScript, returning a socket ID
+fn connect_socketcand(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let host = cx.argument::<JsString>(0)?.value(&mut cx);
+    let port = cx.argument::<JsNumber>(1)?.value(&mut cx) as u16;
+    let bus = cx.argument::<JsString>(2)?.value(&mut cx);
+    let mut mode = socketcand::Mode::Raw;
+    let mut timeout = Duration::from_secs(3);
+    if let Some(options) = cx.argument_opt(3) {
+        if let Ok(options) = options.downcast::<JsObject, _>(&mut cx) {
+            if let Some(name) = options.get_opt::<JsString, _, _>(&mut cx, "mode")? {
+                mode = match name.value(&mut cx).as_str() {
+                    "raw" => socketcand::Mode::Raw,
+                    "bcm" => socketcand::Mode::Bcm,
+                    other => return cx.throw_error(format!("Unknown socketcand mode: {}", other)),
+                };
+            }
+            if let Some(ms) = options.get_opt::<JsNumber, _, _>(&mut cx, "timeout")? {
+                timeout = Duration::from_millis(ms.value(&mut cx).max(1.0) as u64);
+            }
+        }
+    }
+
+    match socketcand::SocketcandClient::connect((host.as_str(), port), &bus, mode, timeout) {
+        Ok(client) => {
+            let id = next_handle_id();
+            SOCKET_REGISTRY
+                .lock()
+                .unwrap()
+                .insert(id, CanSocketWrapper::remote(client));
+            Ok(cx.number(id as f64))
+        }
+        Err(e) => cx.throw_error(format!("Failed to connect to socketcand: {}", e)),
+    }
+}
+
+/// socketcand connection of a socket ID
+fn socketcand_client(
+    cx: &mut FunctionContext,
+    socket_id: u32,
+) -> NeonResult<Arc<socketcand::SocketcandClient>> {
+    let client = match SOCKET_REGISTRY.lock().unwrap().get(&socket_id) {
+        Some(wrapper) => wrapper.socketcand(),
+        None => return cx.throw_error("Invalid socket ID"),
+    };
+    match client {
+        Some(client) => Ok(client),
+        None => cx.throw_error("Socket is not a socketcand connection"),
+    }
+}
+
+/// Switch a socketcand connection between raw and BCM mode from JavaScript
+fn set_socketcand_mode(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let mode = match cx.argument::<JsString>(1)?.value(&mut cx).as_str() {
+        "raw" => socketcand::Mode::Raw,
+        "bcm" => socketcand::Mode::Bcm,
+        other => return cx.throw_error(format!("Unknown socketcand mode: {}", other)),
+    };
+    let client = socketcand_client(&mut cx, socket_id)?;
+    match client.set_mode(mode) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to set socketcand mode: {}", e)),
+    }
+}
+
+/// Add a broadcast manager filter to a socketcand connection from JavaScript
+fn add_socketcand_filter(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let options = cx.argument::<JsObject>(1)?;
+    let id = options.get::<JsNumber, _, _>(&mut cx, "id")?.value(&mut cx) as u32;
+    let extended = options
+        .get_opt::<JsBoolean, _, _>(&mut cx, "extended")?
+        .map_or(id > 0x7FF, |v| v.value(&mut cx));
+    let interval = options
+        .get_opt::<JsNumber, _, _>(&mut cx, "interval")?
+        .map_or(Duration::ZERO, |ms| {
+            Duration::from_secs_f64(ms.value(&mut cx).max(0.0) / 1000.0)
+        });
+    let mask = match options.get_opt::<JsArray, _, _>(&mut cx, "mask")? {
+        Some(array) => {
+            let mut mask = Vec::new();
+            for value in array.to_vec(&mut cx)? {
+                mask.push(
+                    value
+                        .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                        .value(&mut cx) as u8,
+                );
+            }
+            Some(mask)
+        }
+        None => None,
+    };
+
+    let client = socketcand_client(&mut cx, socket_id)?;
+    let filter = socketcand::BcmFilter {
+        id,
+        extended,
+        interval,
+        mask,
+    };
+    match client.filter(&filter) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to add socketcand filter: {}", e)),
+    }
+}
+
+/// Round trip of a socketcand echo in milliseconds from JavaScript
+fn socketcand_echo(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let client = socketcand_client(&mut cx, socket_id)?;
+    match client.echo() {
+        Ok(rtt) => Ok(cx.number(rtt.as_secs_f64() * 1000.0)),
+        Err(e) => cx.throw_error(format!("socketcand echo failed: {}", e)),
+    }
+}
+
 /// Send a CAN frame from JavaScript
 fn send_frame(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
@@ -492,6 +839,225 @@ fn send_frame(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     }
 }
 
+/// Send a message of a loaded DBC from physical signal values
+fn send_message(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let name = cx.argument::<JsString>(1)?.value(&mut cx);
+    let values = cx.argument::<JsObject>(2)?;
+    let db_id = match cx.argument_opt(3) {
+        Some(arg) => match arg.downcast::<JsNumber, _>(&mut cx) {
+            Ok(n) => Some(n.value(&mut cx) as u32),
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    let frame = signal_db::encode_message_values(&mut cx, db_id, &name, values)?;
+
+    let registry = SOCKET_REGISTRY.lock().unwrap();
+    if let Some(wrapper) = registry.get(&socket_id) {
+        match wrapper.send_frame(frame.id, frame.data, frame.extended, frame.fd, false) {
+            Ok(_) => Ok(cx.undefined()),
+            Err(e) => cx.throw_error(format!("Failed to send message: {}", e)),
+        }
+    } else {
+        cx.throw_error("Invalid socket ID")
+    }
+}
+
+/// Log every frame of the socket's interface to a candump or pcapng file from JavaScript
+#[cfg(target_os = "linux")]
+fn start_logging(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let path = cx.argument::<JsString>(1)?.value(&mut cx);
+    let mut format = candump::LogFormat::from_path(&path);
+    if let Some(options) = cx.argument_opt(2) {
+        if let Ok(options) = options.downcast::<JsObject, _>(&mut cx) {
+            let value = options.get_value(&mut cx, "format")?;
+            if let Ok(name) = value.downcast::<JsString, _>(&mut cx) {
+                format = match name.value(&mut cx).as_str() {
+                    "candump" => candump::LogFormat::Candump,
+                    "pcapng" => candump::LogFormat::Pcapng,
+                    other => return cx.throw_error(format!("Unknown log format: {}", other)),
+                };
+            }
+        }
+    }
+
+    let interface = match SOCKET_REGISTRY.lock().unwrap().get(&socket_id) {
+        Some(wrapper) => wrapper.interface_name(),
+        None => return cx.throw_error("Invalid socket ID"),
+    };
+    match interface.and_then(|interface| candump::start_logger(&interface, &path, format)) {
+        Ok(id) => Ok(cx.number(id as f64)),
+        Err(e) => cx.throw_error(format!("Failed to start logging: {}", e)),
+    }
+}
+
+/// Restart the socket's controller with back-off after bus-off from JavaScript
+#[cfg(target_os = "linux")]
+fn supervise_bus_off(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let callback = cx.argument::<JsFunction>(1)?;
+    let policy = supervisor::policy_from_js(&mut cx, 2)?;
+
+    let interface = match SOCKET_REGISTRY.lock().unwrap().get(&socket_id) {
+        Some(wrapper) => wrapper.interface_name(),
+        None => return cx.throw_error("Invalid socket ID"),
+    };
+    let callback = supervisor::js_callback(&mut cx, callback);
+    match interface.and_then(|interface| {
+        supervisor::start_supervisor(&interface, policy, callback).map_err(Into::into)
+    }) {
+        Ok(id) => Ok(cx.number(id as f64)),
+        Err(e) => cx.throw_error(format!("Failed to start supervisor: {}", e)),
+    }
+}
+
+/// Parse an identifier key of the replay `idMap` option ("0x123" or "291")
+fn parse_id_key(key: &str) -> Option<u32> {
+    match key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => key.parse().ok(),
+    }
+}
+
+/// Replay trace frames on sockets with their original timing from JavaScript
+fn start_replay(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let array = cx.argument::<JsArray>(0)?;
+    let frames = trace::frames_from_js(&mut cx, array)?;
+    let options = cx.argument::<JsObject>(1)?;
+
+    let default_socket = options
+        .get_opt::<JsNumber, _, _>(&mut cx, "socketId")?
+        .map(|v| v.value(&mut cx) as u32);
+    let mut channel_sockets = Vec::new();
+    if let Some(channels) = options.get_opt::<JsObject, _, _>(&mut cx, "channels")? {
+        let keys = channels.get_own_property_names(&mut cx)?.to_vec(&mut cx)?;
+        for key in keys {
+            let name = key
+                .downcast_or_throw::<JsString, _>(&mut cx)?
+                .value(&mut cx);
+            let socket_id = channels.get::<JsNumber, _, _>(&mut cx, name.as_str())?;
+            channel_sockets.push((name, socket_id.value(&mut cx) as u32));
+        }
+    }
+
+    let mut replay_options = replay::ReplayOptions::default();
+    if let Some(speed) = options.get_opt::<JsNumber, _, _>(&mut cx, "speed")? {
+        replay_options.speed = speed.value(&mut cx);
+    }
+    let repeat = options.get_value(&mut cx, "loop")?;
+    if let Ok(repeat) = repeat.downcast::<JsBoolean, _>(&mut cx) {
+        replay_options.passes = if repeat.value(&mut cx) { None } else { Some(1) };
+    } else if let Ok(count) = repeat.downcast::<JsNumber, _>(&mut cx) {
+        replay_options.passes = Some(count.value(&mut cx) as u32);
+    }
+    if let Some(id_map) = options.get_opt::<JsObject, _, _>(&mut cx, "idMap")? {
+        let keys = id_map.get_own_property_names(&mut cx)?.to_vec(&mut cx)?;
+        for key in keys {
+            let key = key
+                .downcast_or_throw::<JsString, _>(&mut cx)?
+                .value(&mut cx);
+            let Some(from) = parse_id_key(&key) else {
+                return cx.throw_error(format!("Invalid CAN ID in idMap: {}", key));
+            };
+            let to = id_map.get::<JsNumber, _, _>(&mut cx, key.as_str())?;
+            replay_options.id_map.insert(from, to.value(&mut cx) as u32);
+        }
+    }
+
+    let targets = {
+        let registry = SOCKET_REGISTRY.lock().unwrap();
+        let lookup = |socket_id: u32| registry.get(&socket_id).cloned();
+        let default = match default_socket {
+            Some(socket_id) => match lookup(socket_id) {
+                Some(wrapper) => Some(wrapper),
+                None => return cx.throw_error("Invalid socket ID"),
+            },
+            None => None,
+        };
+        let mut channels = HashMap::new();
+        for (name, socket_id) in channel_sockets {
+            match lookup(socket_id) {
+                Some(wrapper) => channels.insert(name, wrapper),
+                None => return cx.throw_error("Invalid socket ID"),
+            };
+        }
+        replay::ReplayTargets { default, channels }
+    };
+    if targets.default.is_none() && targets.channels.is_empty() {
+        return cx.throw_error("Replay needs a socketId or channels option");
+    }
+
+    match replay::start_replayer(frames, targets, replay_options) {
+        Ok(id) => Ok(cx.number(id as f64)),
+        Err(e) => cx.throw_error(format!("Failed to start replay: {}", e)),
+    }
+}
+
+/// Forward frames between two sockets in a native thread from JavaScript
+fn start_bridge(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let src_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let dst_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
+    let options = match cx.argument_opt(2) {
+        Some(value) => value.downcast::<JsObject, _>(&mut cx).ok(),
+        None => None,
+    };
+    let (bridge_options, hook) = match options {
+        Some(options) => {
+            let hook = options
+                .get_opt::<JsFunction, _, _>(&mut cx, "onFrame")?
+                .map(|callback| bridge::js_hook(&mut cx, callback));
+            (bridge::options_from_js(&mut cx, options)?, hook)
+        }
+        None => (bridge::BridgeOptions::default(), None),
+    };
+
+    let (source, sink) = {
+        let registry = SOCKET_REGISTRY.lock().unwrap();
+        match (registry.get(&src_id), registry.get(&dst_id)) {
+            (Some(source), Some(sink)) => (source.clone(), sink.clone()),
+            _ => return cx.throw_error("Invalid socket ID"),
+        }
+    };
+    let id = bridge::start_bridge(source, sink, bridge_options, hook);
+    Ok(cx.number(id as f64))
+}
+
+/// Start a socketcand-compatible server sharing sockets from JavaScript
+fn start_socketcand_server(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let buses = cx.argument::<JsObject>(0)?;
+    let options = match cx.argument_opt(1) {
+        Some(value) => value.downcast::<JsObject, _>(&mut cx).ok(),
+        None => None,
+    };
+    let (host, port, server_options) = socketcand_server::options_from_js(&mut cx, options)?;
+
+    let names = buses.get_own_property_names(&mut cx)?.to_vec(&mut cx)?;
+    let mut sockets = Vec::new();
+    for name in names {
+        let name = name
+            .downcast_or_throw::<JsString, _>(&mut cx)?
+            .value(&mut cx);
+        let socket_id = buses
+            .get::<JsNumber, _, _>(&mut cx, name.as_str())?
+            .value(&mut cx) as u32;
+        match SOCKET_REGISTRY.lock().unwrap().get(&socket_id) {
+            Some(wrapper) => sockets.push((name, wrapper.clone())),
+            None => return cx.throw_error("Invalid socket ID"),
+        }
+    }
+    if sockets.is_empty() {
+        return cx.throw_error("At least one bus is required");
+    }
+
+    match socketcand_server::start_server((host.as_str(), port), sockets, server_options) {
+        Ok(id) => Ok(cx.number(id as f64)),
+        Err(e) => cx.throw_error(format!("Failed to start socketcand server: {}", e)),
+    }
+}
+
 /// Receive a CAN frame from JavaScript (fonction optimisée)
 fn read_frame(mut cx: FunctionContext) -> JsResult<JsObject> {
     let socket_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
@@ -683,6 +1249,221 @@ fn main(mut cx: ModuleContext) -> NeonResult<()> {
     // Fonction batch optimisée pour réception en lot
     cx.export_function("readFramesBatch", read_frames_batch)?;
 
+    // ISO-TP (ISO 15765-2) via le protocole noyau CAN_ISOTP
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("createIsoTpSocket", isotp::create_isotp_socket)?;
+        cx.export_function("isoTpSend", isotp::isotp_send)?;
+        cx.export_function("isoTpRecv", isotp::isotp_recv)?;
+        cx.export_function("closeIsoTpSocket", isotp::close_isotp_socket)?;
+    }
+
+    // Client de diagnostic UDS (ISO 14229) sur ISO-TP
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("createUdsClient", uds::create_uds_client)?;
+        cx.export_function("udsRequest", uds::uds_request)?;
+        cx.export_function(
+            "udsDiagnosticSessionControl",
+            uds::uds_diagnostic_session_control,
+        )?;
+        cx.export_function("udsSecurityAccessSeed", uds::uds_security_access_seed)?;
+        cx.export_function("udsSecurityAccessKey", uds::uds_security_access_key)?;
+        cx.export_function("udsReadDataByIdentifier", uds::uds_read_data_by_identifier)?;
+        cx.export_function(
+            "udsWriteDataByIdentifier",
+            uds::uds_write_data_by_identifier,
+        )?;
+        cx.export_function("udsRoutineControl", uds::uds_routine_control)?;
+        cx.export_function("udsRequestDownload", uds::uds_request_download)?;
+        cx.export_function("udsTransferData", uds::uds_transfer_data)?;
+        cx.export_function("udsRequestTransferExit", uds::uds_request_transfer_exit)?;
+        cx.export_function("udsTesterPresent", uds::uds_tester_present)?;
+        cx.export_function("closeUdsClient", uds::close_uds_client)?;
+    }
+
+    // SAE J1939 via le protocole noyau CAN_J1939
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("createJ1939Socket", j1939::create_j1939_socket)?;
+        cx.export_function("j1939EncodeName", j1939::j1939_encode_name)?;
+        cx.export_function("j1939GetAddress", j1939::j1939_get_address)?;
+        cx.export_function("j1939Send", j1939::j1939_send)?;
+        cx.export_function("j1939Recv", j1939::j1939_recv)?;
+        cx.export_function("closeJ1939Socket", j1939::close_j1939_socket)?;
+    }
+
+    // CANopen (CiA 301) : NMT, heartbeat et client SDO
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("createCanOpenMaster", canopen::create_canopen_master)?;
+        cx.export_function("canopenNmt", canopen::canopen_nmt)?;
+        cx.export_function(
+            "canopenSetHeartbeatTimeout",
+            canopen::canopen_set_heartbeat_timeout,
+        )?;
+        cx.export_function("canopenGetNodeStates", canopen::canopen_get_node_states)?;
+        cx.export_function("canopenOnNodeEvent", canopen::canopen_on_node_event)?;
+        cx.export_function("canopenSdoUpload", canopen::canopen_sdo_upload)?;
+        cx.export_function("canopenSdoDownload", canopen::canopen_sdo_download)?;
+        cx.export_function("canopenStartSync", canopen::canopen_start_sync)?;
+        cx.export_function("canopenStopSync", canopen::canopen_stop_sync)?;
+        cx.export_function("canopenDecodeEmcy", canopen::canopen_decode_emcy)?;
+        cx.export_function("canopenWritePdoMapping", canopen::canopen_write_pdo_mapping)?;
+        cx.export_function("closeCanOpenMaster", canopen::close_canopen_master)?;
+    }
+
+    // Dictionnaire d'objets CANopen (EDS/DCF) et mapping des PDO
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("loadCanOpenEds", canopen_od::load_canopen_eds)?;
+        cx.export_function("canopenGetObject", canopen_od::canopen_get_object)?;
+        cx.export_function(
+            "canopenGetPdoMappings",
+            canopen_od::canopen_get_pdo_mappings,
+        )?;
+        cx.export_function("canopenSetPdoMapping", canopen_od::canopen_set_pdo_mapping)?;
+        cx.export_function("canopenDecodePdo", canopen_od::canopen_decode_pdo)?;
+        cx.export_function("canopenEncodePdo", canopen_od::canopen_encode_pdo)?;
+        cx.export_function("closeCanOpenEds", canopen_od::close_canopen_eds)?;
+    }
+
+    // Décodage J1939 (PGN/SPN) des trames étendues brutes
+    cx.export_function("decodeJ1939Id", j1939_decode::decode_j1939_id)?;
+    cx.export_function("encodeJ1939Id", j1939_decode::encode_j1939_id)?;
+    cx.export_function("createJ1939SpnTable", j1939_decode::create_spn_table)?;
+    cx.export_function("loadJ1939SpnTable", j1939_decode::load_spn_table)?;
+    cx.export_function("decodeJ1939Frame", j1939_decode::decode_j1939_frame)?;
+    cx.export_function("closeJ1939SpnTable", j1939_decode::close_spn_table)?;
+
+    // Base de signaux (DBC, ARXML, KCD) : décodage et encodage des trames
+    cx.export_function("loadDbc", signal_db::load_dbc)?;
+    cx.export_function("parseDbc", signal_db::parse_dbc)?;
+    cx.export_function("loadSignalDatabase", signal_db::load_signal_database)?;
+    cx.export_function("parseSignalDatabase", signal_db::parse_signal_database)?;
+    cx.export_function("getDbcMessages", signal_db::get_dbc_messages)?;
+    cx.export_function("decodeFrame", signal_db::decode_frame)?;
+    cx.export_function("encodeMessage", signal_db::encode_message)?;
+    cx.export_function("sendMessage", send_message)?;
+    cx.export_function("closeDbc", signal_db::close_dbc)?;
+
+    // Client OBD-II (SAE J1979) : requêtes fonctionnelles et décodage des PID
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("createObdClient", obd::create_obd_client)?;
+        cx.export_function("obdReadPid", obd::obd_read_pid)?;
+        cx.export_function("obdReadFreezeFrame", obd::obd_read_freeze_frame)?;
+        cx.export_function("obdReadDtcs", obd::obd_read_dtcs)?;
+        cx.export_function("obdReadVin", obd::obd_read_vin)?;
+        cx.export_function("obdReadVehicleInfo", obd::obd_read_vehicle_info)?;
+        cx.export_function("obdRequest", obd::obd_request)?;
+        cx.export_function("closeObdClient", obd::close_obd_client)?;
+    }
+
+    // Journaux candump (can-utils) : lecture, écriture et enregistrement natif
+    cx.export_function("parseCandumpLog", candump::parse_candump_log)?;
+    cx.export_function("readCandumpLog", candump::read_candump_log)?;
+    cx.export_function("formatCandumpLog", candump::format_candump_log)?;
+    cx.export_function("writeCandumpLog", candump::write_candump_log)?;
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("startLogging", start_logging)?;
+        cx.export_function("stopLogging", candump::stop_logging)?;
+    }
+
+    // Traces Vector ASC (texte) et BLF (binaire)
+    cx.export_function("parseAscLog", asc::parse_asc_log)?;
+    cx.export_function("readAscLog", asc::read_asc_log)?;
+    cx.export_function("formatAscLog", asc::format_asc_log)?;
+    cx.export_function("writeAscLog", asc::write_asc_log)?;
+    cx.export_function("parseBlfLog", blf::parse_blf_log)?;
+    cx.export_function("readBlfLog", blf::read_blf_log)?;
+    cx.export_function("formatBlfLog", blf::format_blf_log)?;
+    cx.export_function("writeBlfLog", blf::write_blf_log)?;
+
+    // Traces PEAK TRC et fichiers ASAM MDF4 (bus logging)
+    cx.export_function("parseTrcLog", trc::parse_trc_log)?;
+    cx.export_function("readTrcLog", trc::read_trc_log)?;
+    cx.export_function("formatTrcLog", trc::format_trc_log)?;
+    cx.export_function("writeTrcLog", trc::write_trc_log)?;
+    cx.export_function("parseMdfLog", mdf::parse_mdf_log)?;
+    cx.export_function("readMdfLog", mdf::read_mdf_log)?;
+    cx.export_function("formatMdfLog", mdf::format_mdf_log)?;
+    cx.export_function("writeMdfLog", mdf::write_mdf_log)?;
+
+    // Rejeu de traces avec le cadencement d'origine
+    cx.export_function("startReplay", start_replay)?;
+    cx.export_function("pauseReplay", replay::pause_replay)?;
+    cx.export_function("resumeReplay", replay::resume_replay)?;
+    cx.export_function("seekReplay", replay::seek_replay)?;
+    cx.export_function("setReplaySpeed", replay::set_replay_speed)?;
+    cx.export_function("getReplayStatus", replay::get_replay_status)?;
+    cx.export_function("stopReplay", replay::stop_replay)?;
+
+    // Captures pcap/pcapng (LINKTYPE_CAN_SOCKETCAN)
+    cx.export_function("parsePcap", pcap::parse_pcap_log)?;
+    cx.export_function("readPcap", pcap::read_pcap_log)?;
+    cx.export_function("formatPcapng", pcap::format_pcapng_log)?;
+    cx.export_function("writePcapng", pcap::write_pcapng_log)?;
+
+    // Interfaces CAN : énumération, surveillance, création vcan/vxcan (rtnetlink)
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("listInterfaces", interfaces::list_interfaces)?;
+        cx.export_function("watchInterfaces", interfaces::watch_interfaces)?;
+        cx.export_function("unwatchInterfaces", interfaces::unwatch_interfaces)?;
+        cx.export_function("createVcan", interfaces::create_vcan_interface)?;
+        cx.export_function("createVxcan", interfaces::create_vxcan_interface)?;
+        cx.export_function("setInterfaceUp", interfaces::set_interface_state)?;
+        cx.export_function("deleteInterface", interfaces::delete_virtual_interface)?;
+    }
+
+    // Contrôleurs CAN : débit, point d'échantillonnage, modes, statistiques
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("configureInterface", controller::configure_interface)?;
+        cx.export_function("getInterfaceStats", controller::get_interface_stats)?;
+        cx.export_function("restartInterface", controller::restart_interface)?;
+    }
+
+    // Supervision bus-off avec redémarrage automatique du contrôleur
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("superviseBusOff", supervise_bus_off)?;
+        cx.export_function("stopSupervisor", supervisor::stop_supervisor)?;
+    }
+
+    // Passerelle CAN du noyau (can-gw) : routage, filtres et modifications de trames
+    #[cfg(target_os = "linux")]
+    {
+        cx.export_function("addGatewayRule", gateway::add_gateway_rule)?;
+        cx.export_function("listGatewayRules", gateway::list_gateway_rules)?;
+        cx.export_function("deleteGatewayRule", gateway::delete_gateway_rule)?;
+        cx.export_function("flushGatewayRules", gateway::flush_gateway_rules)?;
+    }
+
+    // Client socketcand : bus distants derrière l'API de socket habituelle
+    cx.export_function("connectSocketcand", connect_socketcand)?;
+    cx.export_function("setSocketcandMode", set_socketcand_mode)?;
+    cx.export_function("addSocketcandFilter", add_socketcand_filter)?;
+    cx.export_function("socketcandEcho", socketcand_echo)?;
+
+    // Pont logiciel entre deux sockets avec filtres, réécritures et limitation de débit
+    cx.export_function("bridge", start_bridge)?;
+    cx.export_function("getBridgeStats", bridge::get_bridge_stats)?;
+    cx.export_function("stopBridge", bridge::stop_bridge)?;
+
+    // Serveur compatible socketcand : partage des sockets locales sur TCP
+    cx.export_function("startSocketcandServer", start_socketcand_server)?;
+    cx.export_function(
+        "getSocketcandServerStatus",
+        socketcand_server::get_socketcand_server_status,
+    )?;
+    cx.export_function(
+        "stopSocketcandServer",
+        socketcand_server::stop_socketcand_server,
+    )?;
+
     // Debug: fonction pour consulter les statistiques du pool de buffers
     cx.export_function("getBufferPoolStats", get_buffer_pool_stats)?;
 
@@ -0,0 +1,922 @@
+//! ASAM MDF 4 bus logging files
+//!
+//! Frames are read from the `CAN_DataFrame`, `CAN_RemoteFrame` and
+//! `CAN_ErrorFrame` channel groups of the bus logging standard, with their
+//! `BusChannel`, `ID`, `IDE`, `DLC`, `DataLength`, `DataBytes`, `Dir`,
+//! `EDL`, `BRS` and `ESI` sub-channels. Sorted and unsorted data groups,
+//! data lists, zipped blocks and VLSD data bytes are supported.
+//!
+//! Written files are MDF 4.10 with one uncompressed, sorted data group per
+//! frame kind and a fixed 64-byte `DataBytes` array.
+
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::trace::{dlc_to_len, frames_from_js, frames_to_js, ChannelMap, Direction, TraceFrame};
+
+const ID_BLOCK_SIZE: usize = 64;
+const BLOCK_HEADER_SIZE: usize = 24;
+const VERSION: u16 = 410;
+
+const CN_VLSD: u8 = 1;
+const CN_MASTER: u8 = 2;
+const CN_VIRTUAL_MASTER: u8 = 3;
+const SYNC_TIME: u8 = 1;
+
+const DT_UINT_LE: u8 = 0;
+const DT_UINT_BE: u8 = 1;
+const DT_INT_LE: u8 = 2;
+const DT_INT_BE: u8 = 3;
+const DT_FLOAT_LE: u8 = 4;
+const DT_FLOAT_BE: u8 = 5;
+const DT_BYTE_ARRAY: u8 = 10;
+
+const CG_VLSD: u16 = 0x0001;
+const CG_BUS_EVENT: u16 = 0x0002;
+const CG_PLAIN_BUS_EVENT: u16 = 0x0004;
+
+const CC_IDENTITY: u8 = 0;
+const CC_LINEAR: u8 = 1;
+
+const SI_TYPE_BUS: u8 = 2;
+const SI_BUS_CAN: u8 = 2;
+
+/// Time flag of the header block: start time is local time
+const HD_LOCAL_TIME: u8 = 0x01;
+const HD_TIME_OFFSETS_VALID: u8 = 0x02;
+
+const ZIP_DEFLATE: u8 = 0;
+const ZIP_TRANSPOSE_DEFLATE: u8 = 1;
+
+/// Deepest nesting of data lists and channel compositions
+const MAX_DEPTH: usize = 16;
+
+const DATA_FRAME: &str = "CAN_DataFrame";
+const REMOTE_FRAME: &str = "CAN_RemoteFrame";
+const ERROR_FRAME: &str = "CAN_ErrorFrame";
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+fn f64_at(data: &[u8], pos: usize) -> f64 {
+    f64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+/// Block header (`##XX`), links and data section
+struct Block<'a> {
+    id: &'a [u8],
+    links: Vec<u64>,
+    data: &'a [u8],
+}
+
+impl<'a> Block<'a> {
+    fn link(&self, index: usize) -> u64 {
+        self.links.get(index).copied().unwrap_or(0)
+    }
+}
+
+fn block(file: &[u8], offset: u64) -> Result<Block<'_>, String> {
+    let pos = usize::try_from(offset).unwrap_or(usize::MAX);
+    let header = file
+        .get(pos..pos.saturating_add(BLOCK_HEADER_SIZE))
+        .ok_or_else(|| format!("block at {:#x} is out of the file", offset))?;
+    if &header[..2] != b"##" {
+        return Err(format!("no block at {:#x}", offset));
+    }
+    let invalid = || format!("invalid length of block at {:#x}", offset);
+    let length = usize::try_from(u64_at(header, 8)).map_err(|_| invalid())?;
+    let link_count = usize::try_from(u64_at(header, 16)).map_err(|_| invalid())?;
+    let links_end = link_count
+        .checked_mul(8)
+        .and_then(|size| size.checked_add(BLOCK_HEADER_SIZE))
+        .ok_or_else(invalid)?;
+    if length < links_end || pos.saturating_add(length) > file.len() {
+        return Err(invalid());
+    }
+    let block = &file[pos..pos + length];
+    Ok(Block {
+        id: &block[2..4],
+        links: (0..link_count)
+            .map(|i| u64_at(block, BLOCK_HEADER_SIZE + 8 * i))
+            .collect(),
+        data: &block[links_end..],
+    })
+}
+
+/// Blocks reached by a walk, so that links looping back fail instead of
+/// recursing or iterating forever
+#[derive(Default)]
+struct Visited(HashSet<u64>);
+
+impl Visited {
+    fn enter(&mut self, link: u64) -> Result<(), String> {
+        if self.0.insert(link) {
+            Ok(())
+        } else {
+            Err(format!("block at {:#x} is linked more than once", link))
+        }
+    }
+}
+
+/// Contents of a TX or MD block, empty for a nil link
+fn text(file: &[u8], link: u64) -> Result<String, String> {
+    if link == 0 {
+        return Ok(String::new());
+    }
+    let block = block(file, link)?;
+    let end = block
+        .data
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(block.data.len());
+    Ok(String::from_utf8_lossy(&block.data[..end]).into_owned())
+}
+
+/// Concatenated payload of a DT/SD/RD, DZ, DL or HL block chain
+fn data_stream(file: &[u8], link: u64, out: &mut Vec<u8>) -> Result<(), String> {
+    collect_data(file, link, out, &mut Visited::default(), 0)
+}
+
+fn collect_data(
+    file: &[u8],
+    link: u64,
+    out: &mut Vec<u8>,
+    visited: &mut Visited,
+    depth: usize,
+) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err("data blocks are nested too deeply".to_string());
+    }
+    let mut link = link;
+    while link != 0 {
+        visited.enter(link)?;
+        let block = block(file, link)?;
+        match block.id {
+            b"DT" | b"SD" | b"RD" => out.extend_from_slice(block.data),
+            b"DZ" => out.extend_from_slice(&unzip(block.data)?),
+            b"HL" => collect_data(file, block.link(0), out, visited, depth + 1)?,
+            b"DL" => {
+                for &data in block.links.get(1..).unwrap_or_default() {
+                    collect_data(file, data, out, visited, depth + 1)?;
+                }
+                link = block.link(0);
+                continue;
+            }
+            id => {
+                return Err(format!(
+                    "unexpected ##{} data block",
+                    String::from_utf8_lossy(id)
+                ))
+            }
+        }
+        break;
+    }
+    Ok(())
+}
+
+/// Decompress a DZ block, undoing the transposition of zip type 1
+fn unzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 24 {
+        return Err("truncated ##DZ block".to_string());
+    }
+    let zip_type = data[2];
+    let columns = u32_at(data, 4) as usize;
+    let original = u64_at(data, 8) as usize;
+    let compressed = u64_at(data, 16) as usize;
+    let payload = data
+        .get(24..24usize.saturating_add(compressed))
+        .ok_or("truncated ##DZ block")?;
+    let inflated = decompress_to_vec_zlib_with_limit(payload, original)
+        .map_err(|e| format!("##DZ block: {}", e))?;
+    if inflated.len() != original {
+        return Err("##DZ block length mismatch".to_string());
+    }
+    match zip_type {
+        ZIP_DEFLATE => Ok(inflated),
+        ZIP_TRANSPOSE_DEFLATE if columns > 0 => {
+            let rows = original / columns;
+            let mut out = vec![0; original];
+            for column in 0..columns {
+                for row in 0..rows {
+                    out[row * columns + column] = inflated[column * rows + row];
+                }
+            }
+            out[rows * columns..].copy_from_slice(&inflated[rows * columns..]);
+            Ok(out)
+        }
+        ZIP_TRANSPOSE_DEFLATE => Ok(inflated),
+        other => Err(format!("unsupported zip type {}", other)),
+    }
+}
+
+struct Channel {
+    name: String,
+    kind: u8,
+    sync: u8,
+    data_type: u8,
+    bit_offset: u8,
+    byte_offset: usize,
+    bit_count: u32,
+    /// `cn_data`: signal data of VLSD channels
+    data: u64,
+    /// Linear conversion `a + b * x`
+    conversion: (f64, f64),
+}
+
+impl Channel {
+    fn bytes<'r>(&self, record: &'r [u8]) -> &'r [u8] {
+        let start = self.byte_offset.min(record.len());
+        let end = (start + (self.bit_count as usize).div_ceil(8)).min(record.len());
+        &record[start..end]
+    }
+
+    fn uint(&self, record: &[u8]) -> u64 {
+        let size = (self.bit_offset as usize + self.bit_count as usize).div_ceil(8);
+        let start = self.byte_offset.min(record.len());
+        let bytes = &record[start..(start + size.min(16)).min(record.len())];
+        let value = if matches!(self.data_type, DT_UINT_BE | DT_INT_BE | DT_FLOAT_BE) {
+            bytes.iter().fold(0u128, |acc, &b| acc << 8 | b as u128)
+        } else {
+            bytes
+                .iter()
+                .rev()
+                .fold(0u128, |acc, &b| acc << 8 | b as u128)
+        };
+        let value = value.checked_shr(self.bit_offset as u32).unwrap_or(0);
+        if self.bit_count >= 64 {
+            value as u64
+        } else {
+            value as u64 & ((1 << self.bit_count) - 1)
+        }
+    }
+
+    fn float(&self, record: &[u8]) -> f64 {
+        let raw = self.uint(record);
+        let value = match (self.data_type, self.bit_count) {
+            (DT_FLOAT_LE | DT_FLOAT_BE, 32) => f32::from_bits(raw as u32) as f64,
+            (DT_FLOAT_LE | DT_FLOAT_BE, _) => f64::from_bits(raw),
+            (DT_INT_LE | DT_INT_BE, bits) if bits > 0 && bits < 64 => {
+                let shift = 64 - bits;
+                ((raw << shift) as i64 >> shift) as f64
+            }
+            (DT_INT_LE | DT_INT_BE, _) => raw as i64 as f64,
+            _ => raw as f64,
+        };
+        self.conversion.0 + self.conversion.1 * value
+    }
+
+    /// Sub-channel name without the composition path
+    fn short_name(&self) -> &str {
+        self.name.rsplit('.').next().unwrap_or(&self.name)
+    }
+}
+
+fn conversion(file: &[u8], link: u64) -> Result<(f64, f64), String> {
+    if link == 0 {
+        return Ok((0.0, 1.0));
+    }
+    let block = block(file, link)?;
+    match block.data.first() {
+        Some(&CC_IDENTITY) => Ok((0.0, 1.0)),
+        Some(&CC_LINEAR) if block.data.len() >= 40 => {
+            Ok((f64_at(block.data, 24), f64_at(block.data, 32)))
+        }
+        _ => Err(format!("unsupported conversion at {:#x}", link)),
+    }
+}
+
+fn is_channel(file: &[u8], link: u64) -> Result<bool, String> {
+    Ok(block(file, link)?.id == b"CN")
+}
+
+/// Channels of a chain, structure members flattened after their parent
+fn channels(file: &[u8], first: u64, out: &mut Vec<Channel>) -> Result<(), String> {
+    channel_chain(file, first, out, &mut Visited::default(), 0)
+}
+
+fn channel_chain(
+    file: &[u8],
+    first: u64,
+    out: &mut Vec<Channel>,
+    visited: &mut Visited,
+    depth: usize,
+) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err("channel compositions are nested too deeply".to_string());
+    }
+    let mut link = first;
+    while link != 0 {
+        visited.enter(link)?;
+        let block = block(file, link)?;
+        if block.id != b"CN" || block.data.len() < 24 {
+            return Err(format!("invalid channel block at {:#x}", link));
+        }
+        let data = block.data;
+        let kind = data[0];
+        let master = matches!(kind, CN_MASTER | CN_VIRTUAL_MASTER);
+        out.push(Channel {
+            name: text(file, block.link(2))?,
+            kind,
+            sync: data[1],
+            data_type: data[2],
+            bit_offset: data[3],
+            byte_offset: u32_at(data, 4) as usize,
+            bit_count: u32_at(data, 8),
+            data: block.link(5),
+            // Seule la conversion du temps est utile aux trames
+            conversion: if master {
+                conversion(file, block.link(4))?
+            } else {
+                (0.0, 1.0)
+            },
+        });
+        let composition = block.link(1);
+        if composition != 0 && is_channel(file, composition)? {
+            channel_chain(file, composition, out, visited, depth + 1)?;
+        }
+        link = block.link(0);
+    }
+    Ok(())
+}
+
+struct Group {
+    offset: u64,
+    record_id: u64,
+    flags: u16,
+    record_size: usize,
+    acq_name: String,
+    channels: Vec<Channel>,
+}
+
+impl Group {
+    /// Frame kind of a bus logging group, from its name or channel names
+    fn frame_kind(&self) -> Option<&'static str> {
+        let names = std::iter::once(self.acq_name.as_str())
+            .chain(self.channels.iter().map(|c| c.name.as_str()));
+        for name in names {
+            match name.split('.').next() {
+                Some(DATA_FRAME) => return Some(DATA_FRAME),
+                Some(REMOTE_FRAME) => return Some(REMOTE_FRAME),
+                Some(ERROR_FRAME) => return Some(ERROR_FRAME),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn channel(&self, name: &str) -> Option<&Channel> {
+        self.channels
+            .iter()
+            .find(|c| c.kind != CN_MASTER && c.short_name() == name)
+    }
+}
+
+fn groups(file: &[u8], first: u64) -> Result<Vec<Group>, String> {
+    let mut groups = Vec::new();
+    let mut visited = Visited::default();
+    let mut link = first;
+    while link != 0 {
+        visited.enter(link)?;
+        let block = block(file, link)?;
+        if block.id != b"CG" || block.data.len() < 32 {
+            return Err(format!("invalid channel group block at {:#x}", link));
+        }
+        let mut group = Group {
+            offset: link,
+            record_id: u64_at(block.data, 0),
+            flags: u16_at(block.data, 16),
+            record_size: u32_at(block.data, 24) as usize + u32_at(block.data, 28) as usize,
+            acq_name: text(file, block.link(2))?,
+            channels: Vec::new(),
+        };
+        channels(file, block.link(1), &mut group.channels)?;
+        groups.push(group);
+        link = block.link(0);
+    }
+    Ok(groups)
+}
+
+/// Records of each group of a data group, and VLSD group data
+struct Records<'a> {
+    fixed: Vec<Vec<&'a [u8]>>,
+    vlsd: Vec<Vec<u8>>,
+}
+
+fn split_records<'a>(
+    data: &'a [u8],
+    groups: &[Group],
+    id_size: usize,
+) -> Result<Records<'a>, String> {
+    let mut records = Records {
+        fixed: vec![Vec::new(); groups.len()],
+        vlsd: vec![Vec::new(); groups.len()],
+    };
+    if id_size == 0 {
+        let Some(group) = groups.first() else {
+            return Ok(records);
+        };
+        if group.record_size > 0 {
+            records.fixed[0] = data.chunks_exact(group.record_size).collect();
+        }
+        return Ok(records);
+    }
+    if !matches!(id_size, 1 | 2 | 4 | 8) {
+        return Err(format!("invalid record ID size {}", id_size));
+    }
+
+    let mut pos = 0;
+    while pos + id_size <= data.len() {
+        let mut id = [0u8; 8];
+        id[..id_size].copy_from_slice(&data[pos..pos + id_size]);
+        let id = u64::from_le_bytes(id);
+        pos += id_size;
+        let index = groups
+            .iter()
+            .position(|g| g.record_id == id)
+            .ok_or_else(|| format!("unknown record ID {}", id))?;
+        if groups[index].flags & CG_VLSD != 0 {
+            let len = data
+                .get(pos..pos + 4)
+                .map(|b| u32_at(b, 0) as usize)
+                .ok_or("truncated VLSD record")?;
+            let value = data
+                .get(pos..pos + 4 + len)
+                .ok_or("truncated VLSD record")?;
+            records.vlsd[index].extend_from_slice(value);
+            pos += 4 + len;
+        } else {
+            let size = groups[index].record_size;
+            let record = data.get(pos..pos + size).ok_or("truncated record")?;
+            records.fixed[index].push(record);
+            pos += size;
+        }
+    }
+    Ok(records)
+}
+
+/// Value of a signal data stream (`u32` length then bytes) at `offset`
+fn signal_value(stream: &[u8], offset: u64) -> Option<&[u8]> {
+    let pos = usize::try_from(offset).ok()?;
+    let len = stream
+        .get(pos..pos.checked_add(4)?)
+        .map(|b| u32_at(b, 0) as usize)?;
+    stream.get(pos + 4..(pos + 4).checked_add(len)?)
+}
+
+fn start_time(header: &Block) -> Result<Duration, String> {
+    if header.id != b"HD" || header.data.len() < 14 {
+        return Err("invalid header block".to_string());
+    }
+    let nanos = u64_at(header.data, 0);
+    let flags = header.data[12];
+    let mut start = Duration::from_nanos(nanos);
+    // Heure locale : on revient en UTC quand le décalage est connu
+    if flags & HD_LOCAL_TIME != 0 && flags & HD_TIME_OFFSETS_VALID != 0 {
+        let minutes = u16_at(header.data, 8) as i16 as i64 + u16_at(header.data, 10) as i16 as i64;
+        let offset = minutes * 60;
+        start = if offset >= 0 {
+            start.saturating_sub(Duration::from_secs(offset as u64))
+        } else {
+            start + Duration::from_secs(offset.unsigned_abs())
+        };
+    }
+    Ok(start)
+}
+
+fn decode_group(
+    file: &[u8],
+    group: &Group,
+    kind: &str,
+    records: &Records,
+    groups: &[Group],
+    start: Duration,
+    frames: &mut Vec<TraceFrame>,
+) -> Result<(), String> {
+    let index = groups
+        .iter()
+        .position(|g| g.offset == group.offset)
+        .unwrap();
+    let master = group
+        .channels
+        .iter()
+        .find(|c| matches!(c.kind, CN_MASTER | CN_VIRTUAL_MASTER) && c.sync == SYNC_TIME);
+    let bus = group.channel("BusChannel");
+    let id = group.channel("ID");
+    let ide = group.channel("IDE");
+    let dlc = group.channel("DLC");
+    let length = group.channel("DataLength");
+    let bytes = group.channel("DataBytes");
+    let dir = group.channel("Dir");
+    let edl = group.channel("EDL");
+    let brs = group.channel("BRS");
+    let esi = group.channel("ESI");
+
+    // Octets des trames en VLSD : bloc SD ou groupe VLSD du même DG
+    let mut signal_data = Vec::new();
+    if let Some(channel) = bytes.filter(|c| c.kind == CN_VLSD) {
+        match groups.iter().position(|g| g.offset == channel.data) {
+            Some(vlsd) => signal_data = records.vlsd[vlsd].clone(),
+            None => data_stream(file, channel.data, &mut signal_data)?,
+        }
+    }
+
+    for (row, record) in records.fixed[index].iter().enumerate() {
+        let seconds = match master {
+            Some(m) if m.kind == CN_VIRTUAL_MASTER => m.conversion.0 + m.conversion.1 * row as f64,
+            Some(m) => m.float(record),
+            None => 0.0,
+        };
+        let flag = |c: Option<&Channel>| c.is_some_and(|c| c.uint(record) != 0);
+        let fd = flag(edl);
+        let dlc_value = dlc.map(|c| c.uint(record) as u8);
+        let len = length
+            .map(|c| c.uint(record) as usize)
+            .or(dlc_value.map(|d| dlc_to_len(d, fd)))
+            .unwrap_or(0);
+
+        let mut frame = TraceFrame {
+            timestamp: start + Duration::from_nanos((seconds.max(0.0) * 1e9).round() as u64),
+            interface: bus.map_or(String::new(), |c| c.uint(record).to_string()),
+            direction: dir.map(|c| {
+                if c.uint(record) != 0 {
+                    Direction::Tx
+                } else {
+                    Direction::Rx
+                }
+            }),
+            ..Default::default()
+        };
+        match kind {
+            ERROR_FRAME => frame.error = true,
+            _ => {
+                frame.id = id.map_or(0, |c| c.uint(record) as u32 & 0x1FFF_FFFF);
+                frame.extended = flag(ide);
+                if kind == REMOTE_FRAME {
+                    frame.remote = true;
+                    frame.remote_len = dlc_value.unwrap_or(len as u8).min(8);
+                } else {
+                    frame.fd = fd;
+                    frame.brs = fd && flag(brs);
+                    frame.esi = fd && flag(esi);
+                    let data = match bytes {
+                        Some(c) if c.kind == CN_VLSD => {
+                            signal_value(&signal_data, c.uint(record)).unwrap_or_default()
+                        }
+                        Some(c) if c.data_type == DT_BYTE_ARRAY => c.bytes(record),
+                        _ => &[],
+                    };
+                    frame.data = data[..len.min(data.len())].to_vec();
+                }
+            }
+        }
+        frames.push(frame);
+    }
+    Ok(())
+}
+
+/// Parse an MDF 4 file, frames ordered by timestamp
+///
+/// Bus channels become the interface names ("1", "2"...).
+pub fn parse(file: &[u8]) -> Result<Vec<TraceFrame>, String> {
+    if file.len() < ID_BLOCK_SIZE + BLOCK_HEADER_SIZE || &file[..3] != b"MDF" {
+        return Err("not an MDF file".to_string());
+    }
+    let version = u16_at(file, 28);
+    if version < 400 {
+        return Err(format!("MDF version {} is not supported", version));
+    }
+
+    let header = block(file, ID_BLOCK_SIZE as u64)?;
+    let start = start_time(&header)?;
+    let mut frames = Vec::new();
+    let mut link = header.link(0);
+    let mut data = Vec::new();
+    let mut visited = Visited::default();
+    while link != 0 {
+        visited.enter(link)?;
+        let data_group = block(file, link)?;
+        if data_group.id != b"DG" {
+            return Err(format!("invalid data group block at {:#x}", link));
+        }
+        let groups = groups(file, data_group.link(1))?;
+        if groups.iter().any(|g| g.frame_kind().is_some()) {
+            data.clear();
+            data_stream(file, data_group.link(2), &mut data)?;
+            let id_size = data_group.data.first().copied().unwrap_or(0) as usize;
+            let records = split_records(&data, &groups, id_size)?;
+            for group in &groups {
+                if let Some(kind) = group.frame_kind() {
+                    decode_group(file, group, kind, &records, &groups, start, &mut frames)?;
+                }
+            }
+        }
+        link = data_group.link(0);
+    }
+    frames.sort_by_key(|f| f.timestamp);
+    Ok(frames)
+}
+
+/// Block-by-block file builder
+struct Builder {
+    out: Vec<u8>,
+}
+
+impl Builder {
+    fn block(&mut self, id: &[u8; 2], links: &[u64], data: &[u8]) -> u64 {
+        let offset = self.out.len() as u64;
+        let length = BLOCK_HEADER_SIZE + 8 * links.len() + data.len().div_ceil(8) * 8;
+        self.out.extend_from_slice(b"##");
+        self.out.extend_from_slice(id);
+        self.out.extend_from_slice(&[0; 4]);
+        self.out.extend_from_slice(&(length as u64).to_le_bytes());
+        self.out
+            .extend_from_slice(&(links.len() as u64).to_le_bytes());
+        for link in links {
+            self.out.extend_from_slice(&link.to_le_bytes());
+        }
+        self.out.extend_from_slice(data);
+        self.out.resize(offset as usize + length, 0);
+        offset
+    }
+
+    fn text(&mut self, id: &[u8; 2], text: &str) -> u64 {
+        let mut data = text.as_bytes().to_vec();
+        data.push(0);
+        self.block(id, &[], &data)
+    }
+
+    fn set_link(&mut self, block: u64, index: usize, target: u64) {
+        let pos = block as usize + BLOCK_HEADER_SIZE + 8 * index;
+        self.out[pos..pos + 8].copy_from_slice(&target.to_le_bytes());
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn channel(
+        &mut self,
+        name: &str,
+        kind: u8,
+        data_type: u8,
+        byte_offset: u32,
+        bit_offset: u8,
+        bit_count: u32,
+        composition: u64,
+        unit: u64,
+    ) -> u64 {
+        let name = self.text(b"TX", name);
+        let mut data = vec![
+            kind,
+            if kind == CN_MASTER { SYNC_TIME } else { 0 },
+            data_type,
+            bit_offset,
+        ];
+        data.extend_from_slice(&byte_offset.to_le_bytes());
+        data.extend_from_slice(&bit_count.to_le_bytes());
+        data.resize(72, 0);
+        self.block(b"CN", &[0, composition, name, 0, 0, 0, unit, 0], &data)
+    }
+}
+
+/// Record layout shared by the three groups: timestamp, bus, ID with IDE
+/// in bit 31, DLC, length, flags (Dir, EDL, BRS, ESI) and data bytes
+const RECORD_HEADER_SIZE: usize = 16;
+const DATA_BYTES_SIZE: usize = 64;
+
+fn encode_record(
+    frame: &TraceFrame,
+    start: Duration,
+    bus: u16,
+    with_data: bool,
+) -> Result<Vec<u8>, String> {
+    if frame.data.len() > DATA_BYTES_SIZE {
+        return Err(format!(
+            "frame 0x{:X}: {} data bytes exceed the {} of an MDF record",
+            frame.id,
+            frame.data.len(),
+            DATA_BYTES_SIZE
+        ));
+    }
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + DATA_BYTES_SIZE);
+    let seconds = frame.timestamp.saturating_sub(start).as_secs_f64();
+    record.extend_from_slice(&seconds.to_le_bytes());
+    record.push(bus.min(255) as u8);
+    let id = if frame.error {
+        0
+    } else {
+        frame.id & 0x1FFF_FFFF | if frame.extended { 0x8000_0000 } else { 0 }
+    };
+    record.extend_from_slice(&id.to_le_bytes());
+    let (dlc, len) = if frame.remote {
+        (frame.remote_len, frame.remote_len)
+    } else {
+        (frame.dlc(), frame.data.len() as u8)
+    };
+    record.extend_from_slice(&[if frame.error { 0 } else { dlc }, len]);
+    record.push(
+        (frame.direction == Some(Direction::Tx)) as u8
+            | (frame.fd as u8) << 1
+            | (frame.brs as u8) << 2
+            | (frame.esi as u8) << 3,
+    );
+    if with_data {
+        record.extend_from_slice(&frame.data);
+        record.resize(RECORD_HEADER_SIZE + DATA_BYTES_SIZE, 0);
+    }
+    Ok(record)
+}
+
+/// Format frames as an MDF 4.10 file, timestamps relative to the first frame
+///
+/// Interfaces follow the channel numbering of the Vector formats. CAN XL
+/// frames are skipped; payloads longer than 64 bytes are rejected.
+pub fn format(frames: &[TraceFrame]) -> Result<Vec<u8>, String> {
+    let start = frames.first().map_or(Duration::ZERO, |f| f.timestamp);
+    let mut builder = Builder {
+        out: Vec::with_capacity(4096 + frames.len() * 80),
+    };
+
+    let mut id = Vec::with_capacity(ID_BLOCK_SIZE);
+    id.extend_from_slice(b"MDF     4.10    cansockt");
+    id.resize(28, 0);
+    id.extend_from_slice(&VERSION.to_le_bytes());
+    id.resize(ID_BLOCK_SIZE, 0);
+    builder.out.extend_from_slice(&id);
+
+    let mut header = Vec::with_capacity(32);
+    header.extend_from_slice(&(start.as_nanos() as u64).to_le_bytes());
+    header.resize(32, 0);
+    let header = builder.block(b"HD", &[0; 6], &header);
+
+    let comment = builder.text(
+        b"MD",
+        "<FHcomment><TX>created</TX><tool_id>can-socket</tool_id>\
+         <tool_vendor>can-socket</tool_vendor><tool_version>1.0</tool_version></FHcomment>",
+    );
+    let mut history = Vec::with_capacity(16);
+    history.extend_from_slice(&(start.as_nanos() as u64).to_le_bytes());
+    history.resize(16, 0);
+    let history = builder.block(b"FH", &[0, comment], &history);
+    builder.set_link(header, 1, history);
+
+    let mut channels = ChannelMap::default();
+    let mut kinds: [(&str, Vec<u8>, u64); 3] = [
+        (DATA_FRAME, Vec::new(), 0),
+        (REMOTE_FRAME, Vec::new(), 0),
+        (ERROR_FRAME, Vec::new(), 0),
+    ];
+    for frame in frames.iter().filter(|f| f.xl.is_none()) {
+        let index = if frame.error {
+            2
+        } else if frame.remote {
+            1
+        } else {
+            0
+        };
+        let bus = channels.channel(&frame.interface);
+        let record = encode_record(frame, start, bus, index == 0)?;
+        kinds[index].1.extend_from_slice(&record);
+        kinds[index].2 += 1;
+    }
+
+    let mut previous = None;
+    for (name, records, count) in &kinds {
+        if *count == 0 {
+            continue;
+        }
+        let with_data = *name == DATA_FRAME;
+        let record_size = RECORD_HEADER_SIZE + if with_data { DATA_BYTES_SIZE } else { 0 };
+        let data = builder.block(b"DT", &[], records);
+
+        #[rustfmt::skip]
+        let mut members: Vec<(&str, u8, u32, u8, u32)> = vec![
+            ("BusChannel", DT_UINT_LE, 8, 0, 8),
+            ("ID", DT_UINT_LE, 9, 0, 29),
+            ("IDE", DT_UINT_LE, 12, 7, 1),
+            ("DLC", DT_UINT_LE, 13, 0, 4),
+            ("DataLength", DT_UINT_LE, 14, 0, 8),
+            ("Dir", DT_UINT_LE, 15, 0, 1),
+            ("EDL", DT_UINT_LE, 15, 1, 1),
+            ("BRS", DT_UINT_LE, 15, 2, 1),
+            ("ESI", DT_UINT_LE, 15, 3, 1),
+        ];
+        if with_data {
+            members.push((
+                "DataBytes",
+                DT_BYTE_ARRAY,
+                16,
+                0,
+                8 * DATA_BYTES_SIZE as u32,
+            ));
+        }
+        let mut member_links = Vec::with_capacity(members.len());
+        for (member, data_type, byte_offset, bit_offset, bit_count) in members {
+            member_links.push(builder.channel(
+                &format!("{}.{}", name, member),
+                0,
+                data_type,
+                byte_offset,
+                bit_offset,
+                bit_count,
+                0,
+                0,
+            ));
+        }
+        for pair in member_links.windows(2) {
+            builder.set_link(pair[0], 0, pair[1]);
+        }
+
+        let frame_channel = builder.channel(
+            name,
+            0,
+            DT_BYTE_ARRAY,
+            8,
+            0,
+            8 * (record_size as u32 - 8),
+            member_links[0],
+            0,
+        );
+        let unit = builder.text(b"TX", "s");
+        let master = builder.channel("Timestamp", CN_MASTER, DT_FLOAT_LE, 0, 0, 64, 0, unit);
+        builder.set_link(master, 0, frame_channel);
+
+        let acq_name = builder.text(b"TX", name);
+        let source_name = builder.text(b"TX", "CAN");
+        let source = builder.block(
+            b"SI",
+            &[source_name, 0, 0],
+            &[SI_TYPE_BUS, SI_BUS_CAN, 0, 0, 0, 0, 0, 0],
+        );
+        let mut group = Vec::with_capacity(32);
+        group.extend_from_slice(&0u64.to_le_bytes());
+        group.extend_from_slice(&count.to_le_bytes());
+        group.extend_from_slice(&(CG_BUS_EVENT | CG_PLAIN_BUS_EVENT).to_le_bytes());
+        group.extend_from_slice(&(b'.' as u16).to_le_bytes());
+        group.extend_from_slice(&[0; 4]);
+        group.extend_from_slice(&(record_size as u32).to_le_bytes());
+        group.extend_from_slice(&0u32.to_le_bytes());
+        let group = builder.block(b"CG", &[0, master, acq_name, source, 0, 0], &group);
+
+        let data_group = builder.block(b"DG", &[0, group, data, 0], &[0; 8]);
+        match previous {
+            Some(previous) => builder.set_link(previous, 0, data_group),
+            None => builder.set_link(header, 0, data_group),
+        }
+        previous = Some(data_group);
+    }
+    Ok(builder.out)
+}
+
+/// Parse an MDF 4 buffer from JavaScript
+pub fn parse_mdf_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let data = cx.argument::<JsBuffer>(0)?.as_slice(&cx).to_vec();
+    match parse(&data) {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to parse MDF file: {}", e)),
+    }
+}
+
+/// Read an MDF 4 file from JavaScript
+pub fn read_mdf_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let result = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| parse(&data));
+    match result {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+/// Format frames as an MDF 4 buffer from JavaScript
+pub fn format_mdf_log(mut cx: FunctionContext) -> JsResult<JsBuffer> {
+    let array = cx.argument::<JsArray>(0)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    match format(&frames) {
+        Ok(data) => JsBuffer::from_slice(&mut cx, &data),
+        Err(e) => cx.throw_error(format!("Failed to format MDF file: {}", e)),
+    }
+}
+
+/// Write frames to an MDF 4 file from JavaScript
+pub fn write_mdf_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let array = cx.argument::<JsArray>(1)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    let result =
+        format(&frames).and_then(|data| std::fs::write(&path, data).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to write {}: {}", path, e)),
+    }
+}
@@ -0,0 +1,445 @@
+//! Minimal rtnetlink (`NETLINK_ROUTE`) client for network links and CAN
+//! gateway rules
+//!
+//! Requests are built as raw messages: `nlmsghdr`, the family header
+//! (`ifinfomsg`, `rtcanmsg`) and netlink attributes, nested where needed.
+//! Replies are checked for `NLMSG_ERROR` and split back into attributes.
+
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+pub const RTM_NEWLINK: u16 = 16;
+pub const RTM_DELLINK: u16 = 17;
+pub const RTM_GETLINK: u16 = 18;
+
+pub const NLM_F_REQUEST: u16 = 0x001;
+pub const NLM_F_ACK: u16 = 0x004;
+pub const NLM_F_EXCL: u16 = 0x200;
+pub const NLM_F_CREATE: u16 = 0x400;
+pub const NLM_F_DUMP: u16 = 0x300;
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLMSG_HDRLEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+
+const NLA_F_NESTED: u16 = 0x8000;
+const NLA_TYPE_MASK: u16 = 0x3FFF;
+
+/// Multicast group of link notifications
+pub const RTMGRP_LINK: u32 = 0x1;
+
+pub const IFLA_IFNAME: u16 = 3;
+pub const IFLA_MTU: u16 = 4;
+pub const IFLA_OPERSTATE: u16 = 16;
+pub const IFLA_LINKINFO: u16 = 18;
+pub const IFLA_STATS64: u16 = 23;
+pub const IFLA_INFO_KIND: u16 = 1;
+pub const IFLA_INFO_DATA: u16 = 2;
+pub const IFLA_INFO_XSTATS: u16 = 3;
+
+/// Link type of CAN devices (`ARPHRD_CAN`)
+pub const ARPHRD_CAN: u16 = 280;
+
+const RECV_BUFFER_SIZE: usize = 64 * 1024;
+
+fn align(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// Netlink request under construction
+pub struct Message {
+    buf: Vec<u8>,
+    nests: Vec<usize>,
+    /// Family header length, to find the attributes in tests
+    #[cfg(test)]
+    header_len: usize,
+}
+
+impl Message {
+    /// Request of type `kind` with the family header `header`
+    pub fn new(kind: u16, flags: u16, header: &[u8]) -> Self {
+        let mut buf = vec![0; NLMSG_HDRLEN];
+        buf[4..6].copy_from_slice(&kind.to_ne_bytes());
+        buf[6..8].copy_from_slice(&(flags | NLM_F_REQUEST).to_ne_bytes());
+        buf.extend_from_slice(header);
+        buf.resize(align(buf.len()), 0);
+        Self {
+            buf,
+            nests: Vec::new(),
+            #[cfg(test)]
+            header_len: header.len(),
+        }
+    }
+
+    /// Link request (`ifinfomsg`) for interface `index`, 0 for none
+    pub fn link(kind: u16, flags: u16, index: u32) -> Self {
+        let mut header = [0; IFINFOMSG_LEN];
+        header[4..8].copy_from_slice(&index.to_ne_bytes());
+        Self::new(kind, flags, &header)
+    }
+
+    /// Set the `ifi_flags` bits selected by `change`
+    pub fn link_flags(&mut self, flags: u32, change: u32) -> &mut Self {
+        let pos = NLMSG_HDRLEN + 8;
+        self.buf[pos..pos + 4].copy_from_slice(&flags.to_ne_bytes());
+        self.buf[pos + 4..pos + 8].copy_from_slice(&change.to_ne_bytes());
+        self
+    }
+
+    /// Append an empty `ifinfomsg`, as nested in vxcan peer attributes
+    pub fn ifinfomsg(&mut self) -> &mut Self {
+        self.buf.extend_from_slice(&[0; IFINFOMSG_LEN]);
+        self
+    }
+
+    pub fn attr(&mut self, kind: u16, data: &[u8]) -> &mut Self {
+        self.buf
+            .extend_from_slice(&((4 + data.len()) as u16).to_ne_bytes());
+        self.buf.extend_from_slice(&kind.to_ne_bytes());
+        self.buf.extend_from_slice(data);
+        self.buf.resize(align(self.buf.len()), 0);
+        self
+    }
+
+    pub fn attr_u32(&mut self, kind: u16, value: u32) -> &mut Self {
+        self.attr(kind, &value.to_ne_bytes())
+    }
+
+    /// NUL-terminated string attribute
+    pub fn attr_str(&mut self, kind: u16, value: &str) -> &mut Self {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        self.attr(kind, &data)
+    }
+
+    /// Open a nested attribute, closed by [`Message::end`]
+    pub fn nest(&mut self, kind: u16) -> &mut Self {
+        self.nests.push(self.buf.len());
+        self.attr(kind | NLA_F_NESTED, &[])
+    }
+
+    pub fn end(&mut self) -> &mut Self {
+        if let Some(start) = self.nests.pop() {
+            let len = (self.buf.len() - start) as u16;
+            self.buf[start..start + 2].copy_from_slice(&len.to_ne_bytes());
+        }
+        self
+    }
+
+    /// Family header and attributes, as in a reply message
+    #[cfg(test)]
+    pub fn body(&self) -> &[u8] {
+        &self.buf[NLMSG_HDRLEN..]
+    }
+
+    /// Attributes after the family header
+    #[cfg(test)]
+    pub fn payload(&self) -> &[u8] {
+        &self.buf[NLMSG_HDRLEN + align(self.header_len)..]
+    }
+
+    fn flags(&self) -> u16 {
+        u16::from_ne_bytes([self.buf[6], self.buf[7]])
+    }
+
+    fn finish(mut self, seq: u32) -> Vec<u8> {
+        let len = self.buf.len() as u32;
+        self.buf[0..4].copy_from_slice(&len.to_ne_bytes());
+        self.buf[8..12].copy_from_slice(&seq.to_ne_bytes());
+        self.buf
+    }
+}
+
+/// Attributes of a netlink payload as `(type, value)` pairs
+pub fn attributes(data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attrs = Vec::new();
+    let mut pos = 0;
+    while pos + 4 <= data.len() {
+        let len = u16::from_ne_bytes([data[pos], data[pos + 1]]) as usize;
+        let kind = u16::from_ne_bytes([data[pos + 2], data[pos + 3]]) & NLA_TYPE_MASK;
+        if len < 4 || pos + len > data.len() {
+            break;
+        }
+        attrs.push((kind, &data[pos + 4..pos + len]));
+        pos += align(len);
+    }
+    attrs
+}
+
+pub fn attr_u32(value: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(value.get(..4)?.try_into().ok()?))
+}
+
+pub fn attr_string(value: &[u8]) -> String {
+    let end = value.iter().position(|&b| b == 0).unwrap_or(value.len());
+    String::from_utf8_lossy(&value[..end]).into_owned()
+}
+
+/// Generic interface counters (head of `struct rtnl_link_stats64`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+}
+
+impl LinkStats {
+    fn parse(value: &[u8]) -> Option<Self> {
+        let field = |i: usize| -> Option<u64> {
+            Some(u64::from_ne_bytes(
+                value.get(i * 8..i * 8 + 8)?.try_into().ok()?,
+            ))
+        };
+        Some(LinkStats {
+            rx_packets: field(0)?,
+            tx_packets: field(1)?,
+            rx_bytes: field(2)?,
+            tx_bytes: field(3)?,
+            rx_errors: field(4)?,
+            tx_errors: field(5)?,
+            rx_dropped: field(6)?,
+            tx_dropped: field(7)?,
+        })
+    }
+}
+
+/// Link message (`RTM_NEWLINK`) decoded
+#[derive(Debug, Clone, Default)]
+pub struct Link {
+    pub index: u32,
+    /// `ifi_type` (ARPHRD_*)
+    pub link_type: u16,
+    /// `ifi_flags` (IFF_*)
+    pub flags: u32,
+    pub name: String,
+    pub mtu: u32,
+    /// `IFLA_OPERSTATE` (RFC 2863 state)
+    pub operstate: u8,
+    /// Link kind of `IFLA_LINKINFO` ("can", "vcan"...)
+    pub kind: Option<String>,
+    /// Kind-specific `IFLA_INFO_DATA` attributes
+    pub info_data: Vec<u8>,
+    /// Kind-specific statistics (`IFLA_INFO_XSTATS`)
+    pub xstats: Vec<u8>,
+    pub stats: Option<LinkStats>,
+}
+
+/// Decode the payload of a link message
+pub fn parse_link(payload: &[u8]) -> Option<Link> {
+    let header = payload.get(..IFINFOMSG_LEN)?;
+    let mut link = Link {
+        link_type: u16::from_ne_bytes([header[2], header[3]]),
+        index: u32::from_ne_bytes(header[4..8].try_into().ok()?),
+        flags: u32::from_ne_bytes(header[8..12].try_into().ok()?),
+        ..Default::default()
+    };
+    for (kind, value) in attributes(&payload[IFINFOMSG_LEN..]) {
+        match kind {
+            IFLA_IFNAME => link.name = attr_string(value),
+            IFLA_MTU => link.mtu = attr_u32(value).unwrap_or(0),
+            IFLA_OPERSTATE => link.operstate = value.first().copied().unwrap_or(0),
+            IFLA_STATS64 => link.stats = LinkStats::parse(value),
+            IFLA_LINKINFO => {
+                for (info, value) in attributes(value) {
+                    match info {
+                        IFLA_INFO_KIND => link.kind = Some(attr_string(value)),
+                        IFLA_INFO_DATA => link.info_data = value.to_vec(),
+                        IFLA_INFO_XSTATS => link.xstats = value.to_vec(),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(link)
+}
+
+/// Route netlink socket
+pub struct Netlink {
+    fd: OwnedFd,
+    seq: u32,
+}
+
+impl Netlink {
+    /// Socket for requests
+    pub fn open() -> io::Result<Self> {
+        Self::bind(0)
+    }
+
+    /// Socket receiving the notifications of multicast `groups`
+    pub fn subscribe(groups: u32) -> io::Result<Self> {
+        Self::bind(groups)
+    }
+
+    fn bind(groups: u32) -> io::Result<Self> {
+        let raw = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = groups;
+        let ret = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, seq: 0 })
+    }
+
+    pub fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        let tv = libc::timeval {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+        };
+        let ret = unsafe {
+            libc::setsockopt(
+                self.fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &tv as *const libc::timeval as *const libc::c_void,
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Receive one datagram and split it into `(type, seq, payload)` messages
+    fn recv_messages(&self) -> io::Result<Vec<(u16, u32, Vec<u8>)>> {
+        let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+        let len = unsafe {
+            libc::recv(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let data = &buf[..len as usize];
+
+        let mut messages = Vec::new();
+        let mut pos = 0;
+        while pos + NLMSG_HDRLEN <= data.len() {
+            let header = &data[pos..pos + NLMSG_HDRLEN];
+            let len = u32::from_ne_bytes(header[0..4].try_into().unwrap()) as usize;
+            if len < NLMSG_HDRLEN || pos + len > data.len() {
+                break;
+            }
+            let kind = u16::from_ne_bytes([header[4], header[5]]);
+            let seq = u32::from_ne_bytes(header[8..12].try_into().unwrap());
+            messages.push((kind, seq, data[pos + NLMSG_HDRLEN..pos + len].to_vec()));
+            pos += align(len);
+        }
+        Ok(messages)
+    }
+
+    /// Receive notifications as `(type, payload)` messages
+    pub fn recv(&self) -> io::Result<Vec<(u16, Vec<u8>)>> {
+        Ok(self
+            .recv_messages()?
+            .into_iter()
+            .map(|(kind, _, payload)| (kind, payload))
+            .collect())
+    }
+
+    /// Send a request and collect the reply messages
+    ///
+    /// Dumps end with `NLMSG_DONE`; other requests ask for an
+    /// acknowledgement, and kernel errors are returned as OS errors.
+    pub fn request(&mut self, message: Message) -> io::Result<Vec<(u16, Vec<u8>)>> {
+        self.seq = self.seq.wrapping_add(1);
+        let seq = self.seq;
+        let dump = message.flags() & NLM_F_DUMP == NLM_F_DUMP;
+        let mut message = message;
+        if !dump {
+            let flags = message.flags() | NLM_F_ACK;
+            message.buf[6..8].copy_from_slice(&flags.to_ne_bytes());
+        }
+        let request = message.finish(seq);
+
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        let sent = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                request.as_ptr() as *const libc::c_void,
+                request.len(),
+                0,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut replies = Vec::new();
+        loop {
+            for (kind, reply_seq, payload) in self.recv_messages()? {
+                if reply_seq != seq {
+                    continue;
+                }
+                match kind {
+                    NLMSG_DONE => return Ok(replies),
+                    NLMSG_ERROR => {
+                        let errno = payload
+                            .get(..4)
+                            .map_or(0, |b| i32::from_ne_bytes(b.try_into().unwrap()));
+                        if errno != 0 {
+                            return Err(io::Error::from_raw_os_error(-errno));
+                        }
+                        return Ok(replies);
+                    }
+                    _ => replies.push((kind, payload)),
+                }
+            }
+        }
+    }
+
+    /// All links of the network namespace
+    pub fn links(&mut self) -> io::Result<Vec<Link>> {
+        let replies = self.request(Message::link(RTM_GETLINK, NLM_F_DUMP, 0))?;
+        Ok(replies
+            .iter()
+            .filter(|(kind, _)| *kind == RTM_NEWLINK)
+            .filter_map(|(_, payload)| parse_link(payload))
+            .collect())
+    }
+
+    /// Link of interface `index`
+    pub fn link(&mut self, index: u32) -> io::Result<Link> {
+        let replies = self.request(Message::link(RTM_GETLINK, 0, index))?;
+        replies
+            .iter()
+            .find(|(kind, _)| *kind == RTM_NEWLINK)
+            .and_then(|(_, payload)| parse_link(payload))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no link in reply"))
+    }
+}
@@ -0,0 +1,797 @@
+//! OBD-II (SAE J1979 / ISO 15765-4) client
+//!
+//! Requests are sent functionally on 0x7DF (or 0x18DB33F1 with 29-bit
+//! identifiers) and every ECU answering within the response window is
+//! collected. Multi-frame responses are reassembled per ECU, with flow
+//! control sent to the physical address of each responder.
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::isotp_engine::{decode_frame, CanLink, IsoTpFrame};
+use crate::uds::nrc_name;
+use crate::{next_handle_id, CanSocketWrapper};
+
+const FUNCTIONAL_ID: u32 = 0x7DF;
+const FUNCTIONAL_ID_EXTENDED: u32 = 0x18DB_33F1;
+const RESPONSE_ID_FIRST: u32 = 0x7E8;
+const RESPONSE_ID_LAST: u32 = 0x7EF;
+const RESPONSE_ID_EXTENDED: u32 = 0x18DA_F100;
+const PHYSICAL_ID_EXTENDED: u32 = 0x18DA_00F1;
+
+const NEGATIVE_RESPONSE: u8 = 0x7F;
+const NRC_RESPONSE_PENDING: u8 = 0x78;
+const PAD_BYTE: u8 = 0xCC;
+
+/// Flow control: continue to send, no block limit, no separation time
+const FLOW_CONTROL: [u8; 3] = [0x30, 0x00, 0x00];
+/// Maximum wait for the next consecutive frame
+const N_CR: Duration = Duration::from_millis(1000);
+/// Extended wait after a responsePending negative response
+const P2_STAR: Duration = Duration::from_millis(5000);
+
+pub const MODE_CURRENT_DATA: u8 = 0x01;
+pub const MODE_FREEZE_FRAME: u8 = 0x02;
+pub const MODE_VEHICLE_INFO: u8 = 0x09;
+pub const PID_VIN: u8 = 0x02;
+
+/// Errors raised by OBD requests
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ObdError {
+    #[error("Negative response 0x{code:02X} ({}) to service 0x{service:02X}", nrc_name(*code))]
+    NegativeResponse { service: u8, code: u8 },
+    #[error("No ECU responded to service 0x{0:02X}")]
+    NoResponse(u8),
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("Transport error: {0}")]
+    Transport(String),
+}
+
+/// CAN identifier layout of ISO 15765-4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObdAddressing {
+    /// 11-bit: request 0x7DF, responses 0x7E8-0x7EF
+    Standard,
+    /// 29-bit: request 0x18DB33F1, responses 0x18DAF1xx
+    Extended,
+}
+
+impl ObdAddressing {
+    fn functional_id(self) -> u32 {
+        match self {
+            ObdAddressing::Standard => FUNCTIONAL_ID,
+            ObdAddressing::Extended => FUNCTIONAL_ID_EXTENDED,
+        }
+    }
+
+    fn is_response(self, id: u32, extended: bool) -> bool {
+        match self {
+            ObdAddressing::Standard => {
+                !extended && (RESPONSE_ID_FIRST..=RESPONSE_ID_LAST).contains(&id)
+            }
+            ObdAddressing::Extended => extended && id & 0xFFFF_FF00 == RESPONSE_ID_EXTENDED,
+        }
+    }
+
+    /// Physical request identifier of the ECU answering on `response_id`
+    fn physical_id(self, response_id: u32) -> u32 {
+        match self {
+            ObdAddressing::Standard => response_id - 8,
+            ObdAddressing::Extended => PHYSICAL_ID_EXTENDED | (response_id & 0xFF) << 8,
+        }
+    }
+}
+
+/// Value of a decoded PID
+#[derive(Debug, Clone, PartialEq)]
+pub enum PidValue {
+    Number(f64),
+    /// PIDs 0x00, 0x20, 0x40...: supported PIDs of the next range
+    Supported(Vec<u8>),
+    /// PID 0x01: MIL state and number of confirmed DTCs
+    MonitorStatus {
+        mil: bool,
+        dtc_count: u8,
+        compression_ignition: bool,
+    },
+    /// Freeze frame PID 0x02: DTC that stored the freeze frame
+    Dtc(String),
+    /// PID without a known formula
+    Raw(Vec<u8>),
+}
+
+/// PID value with its description
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidReading {
+    pub pid: u8,
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub value: PidValue,
+}
+
+/// Response of one ECU
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcuResponse<T> {
+    /// CAN identifier the ECU answered on
+    pub ecu: u32,
+    pub result: Result<T, ObdError>,
+}
+
+/// Vehicle information (mode 09) record
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleInfo {
+    pub pid: u8,
+    /// Number of data items announced by the ECU
+    pub count: u8,
+    pub data: Vec<u8>,
+}
+
+/// DTC memory read by modes 03/07/0A
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtcKind {
+    Stored,
+    Pending,
+    Permanent,
+}
+
+impl DtcKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stored" => Some(DtcKind::Stored),
+            "pending" => Some(DtcKind::Pending),
+            "permanent" => Some(DtcKind::Permanent),
+            _ => None,
+        }
+    }
+
+    pub fn mode(self) -> u8 {
+        match self {
+            DtcKind::Stored => 0x03,
+            DtcKind::Pending => 0x07,
+            DtcKind::Permanent => 0x0A,
+        }
+    }
+}
+
+type Formula = fn(&[u8]) -> f64;
+
+struct PidInfo {
+    pid: u8,
+    name: &'static str,
+    unit: &'static str,
+    length: usize,
+    formula: Formula,
+}
+
+fn word(d: &[u8]) -> f64 {
+    (d[0] as f64) * 256.0 + d[1] as f64
+}
+
+#[rustfmt::skip]
+const PIDS: &[PidInfo] = &[
+    PidInfo { pid: 0x04, name: "Calculated engine load", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x05, name: "Engine coolant temperature", unit: "°C", length: 1, formula: |d| d[0] as f64 - 40.0 },
+    PidInfo { pid: 0x06, name: "Short term fuel trim bank 1", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 128.0 - 100.0 },
+    PidInfo { pid: 0x07, name: "Long term fuel trim bank 1", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 128.0 - 100.0 },
+    PidInfo { pid: 0x08, name: "Short term fuel trim bank 2", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 128.0 - 100.0 },
+    PidInfo { pid: 0x09, name: "Long term fuel trim bank 2", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 128.0 - 100.0 },
+    PidInfo { pid: 0x0A, name: "Fuel pressure", unit: "kPa", length: 1, formula: |d| d[0] as f64 * 3.0 },
+    PidInfo { pid: 0x0B, name: "Intake manifold absolute pressure", unit: "kPa", length: 1, formula: |d| d[0] as f64 },
+    PidInfo { pid: 0x0C, name: "Engine speed", unit: "rpm", length: 2, formula: |d| word(d) / 4.0 },
+    PidInfo { pid: 0x0D, name: "Vehicle speed", unit: "km/h", length: 1, formula: |d| d[0] as f64 },
+    PidInfo { pid: 0x0E, name: "Timing advance", unit: "°", length: 1, formula: |d| d[0] as f64 / 2.0 - 64.0 },
+    PidInfo { pid: 0x0F, name: "Intake air temperature", unit: "°C", length: 1, formula: |d| d[0] as f64 - 40.0 },
+    PidInfo { pid: 0x10, name: "Mass air flow rate", unit: "g/s", length: 2, formula: |d| word(d) / 100.0 },
+    PidInfo { pid: 0x11, name: "Throttle position", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x1F, name: "Run time since engine start", unit: "s", length: 2, formula: word },
+    PidInfo { pid: 0x21, name: "Distance traveled with MIL on", unit: "km", length: 2, formula: word },
+    PidInfo { pid: 0x22, name: "Fuel rail pressure (relative to vacuum)", unit: "kPa", length: 2, formula: |d| word(d) * 0.079 },
+    PidInfo { pid: 0x23, name: "Fuel rail gauge pressure", unit: "kPa", length: 2, formula: |d| word(d) * 10.0 },
+    PidInfo { pid: 0x2C, name: "Commanded EGR", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x2D, name: "EGR error", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 128.0 - 100.0 },
+    PidInfo { pid: 0x2E, name: "Commanded evaporative purge", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x2F, name: "Fuel tank level input", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x30, name: "Warm-ups since codes cleared", unit: "", length: 1, formula: |d| d[0] as f64 },
+    PidInfo { pid: 0x31, name: "Distance traveled since codes cleared", unit: "km", length: 2, formula: word },
+    PidInfo { pid: 0x33, name: "Absolute barometric pressure", unit: "kPa", length: 1, formula: |d| d[0] as f64 },
+    PidInfo { pid: 0x42, name: "Control module voltage", unit: "V", length: 2, formula: |d| word(d) / 1000.0 },
+    PidInfo { pid: 0x43, name: "Absolute load value", unit: "%", length: 2, formula: |d| word(d) * 100.0 / 255.0 },
+    PidInfo { pid: 0x44, name: "Commanded air-fuel equivalence ratio", unit: "", length: 2, formula: |d| word(d) * 2.0 / 65536.0 },
+    PidInfo { pid: 0x45, name: "Relative throttle position", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x46, name: "Ambient air temperature", unit: "°C", length: 1, formula: |d| d[0] as f64 - 40.0 },
+    PidInfo { pid: 0x47, name: "Absolute throttle position B", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x49, name: "Accelerator pedal position D", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x4A, name: "Accelerator pedal position E", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x4C, name: "Commanded throttle actuator", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x4D, name: "Time run with MIL on", unit: "min", length: 2, formula: word },
+    PidInfo { pid: 0x4E, name: "Time since trouble codes cleared", unit: "min", length: 2, formula: word },
+    PidInfo { pid: 0x51, name: "Fuel type", unit: "", length: 1, formula: |d| d[0] as f64 },
+    PidInfo { pid: 0x52, name: "Ethanol fuel percentage", unit: "%", length: 1, formula: |d| d[0] as f64 * 100.0 / 255.0 },
+    PidInfo { pid: 0x5C, name: "Engine oil temperature", unit: "°C", length: 1, formula: |d| d[0] as f64 - 40.0 },
+    PidInfo { pid: 0x5D, name: "Fuel injection timing", unit: "°", length: 2, formula: |d| word(d) / 128.0 - 210.0 },
+    PidInfo { pid: 0x5E, name: "Engine fuel rate", unit: "L/h", length: 2, formula: |d| word(d) / 20.0 },
+    PidInfo { pid: 0x61, name: "Driver's demand engine torque", unit: "%", length: 1, formula: |d| d[0] as f64 - 125.0 },
+    PidInfo { pid: 0x62, name: "Actual engine torque", unit: "%", length: 1, formula: |d| d[0] as f64 - 125.0 },
+    PidInfo { pid: 0x63, name: "Engine reference torque", unit: "Nm", length: 2, formula: word },
+];
+
+/// Decode a DTC from its two-byte encoding (e.g. `01 33` -> `P0133`)
+pub fn decode_dtc(high: u8, low: u8) -> String {
+    let system = ['P', 'C', 'B', 'U'][(high >> 6) as usize];
+    format!(
+        "{}{}{:01X}{:02X}",
+        system,
+        (high >> 4) & 0x03,
+        high & 0x0F,
+        low
+    )
+}
+
+/// Decode the data bytes of a mode 01/02 PID
+pub fn decode_pid(mode: u8, pid: u8, data: &[u8]) -> Result<PidReading, ObdError> {
+    let too_short = || ObdError::InvalidResponse(format!("PID 0x{:02X}: data too short", pid));
+    let (name, unit, value) = match pid {
+        _ if pid.is_multiple_of(0x20) => {
+            let bitmap = data.get(..4).ok_or_else(too_short)?;
+            let supported = (0..32u8)
+                .filter(|bit| bitmap[(bit / 8) as usize] & (0x80 >> (bit % 8)) != 0)
+                .filter_map(|bit| pid.checked_add(bit + 1))
+                .collect();
+            ("PIDs supported", "", PidValue::Supported(supported))
+        }
+        0x01 => {
+            let d = data.get(..2).ok_or_else(too_short)?;
+            let status = PidValue::MonitorStatus {
+                mil: d[0] & 0x80 != 0,
+                dtc_count: d[0] & 0x7F,
+                compression_ignition: d[1] & 0x08 != 0,
+            };
+            ("Monitor status since DTCs cleared", "", status)
+        }
+        0x02 if mode == MODE_FREEZE_FRAME => {
+            let d = data.get(..2).ok_or_else(too_short)?;
+            (
+                "DTC that caused freeze frame",
+                "",
+                PidValue::Dtc(decode_dtc(d[0], d[1])),
+            )
+        }
+        _ => match PIDS.iter().find(|info| info.pid == pid) {
+            Some(info) => {
+                let d = data.get(..info.length).ok_or_else(too_short)?;
+                (info.name, info.unit, PidValue::Number((info.formula)(d)))
+            }
+            None => ("Unknown", "", PidValue::Raw(data.to_vec())),
+        },
+    };
+    Ok(PidReading {
+        pid,
+        name,
+        unit,
+        value,
+    })
+}
+
+/// Decode a mode 03/07/0A response payload (after the service byte)
+pub fn decode_dtc_list(data: &[u8]) -> Vec<String> {
+    // ISO 15765-4 : le premier octet donne le nombre de DTC
+    let pairs = match data.len() % 2 {
+        1 => &data[1..],
+        _ => data,
+    };
+    pairs
+        .chunks_exact(2)
+        .filter(|pair| pair != &[0, 0])
+        .map(|pair| decode_dtc(pair[0], pair[1]))
+        .collect()
+}
+
+/// Printable text of a vehicle information record (VIN, ECU name...)
+pub fn vehicle_info_text(data: &[u8]) -> String {
+    data.iter()
+        .filter(|b| b.is_ascii_graphic() || **b == b' ')
+        .map(|&b| b as char)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+struct Reassembly {
+    total: usize,
+    data: Vec<u8>,
+    next_sequence: u8,
+}
+
+/// OBD-II client over a raw CAN link
+pub struct ObdClient<L: CanLink = CanSocketWrapper> {
+    link: L,
+    addressing: ObdAddressing,
+    response_window: Duration,
+    request_lock: Mutex<()>,
+}
+
+impl ObdClient<CanSocketWrapper> {
+    /// Open a raw socket on `interface`
+    pub fn open(
+        interface: &str,
+        addressing: ObdAddressing,
+        response_window: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let socket = CanSocketWrapper::new(interface.to_string())?;
+        Ok(Self::with_link(socket, addressing, response_window))
+    }
+}
+
+impl<L: CanLink> ObdClient<L> {
+    /// Run the client over an existing link
+    pub fn with_link(link: L, addressing: ObdAddressing, response_window: Duration) -> Self {
+        Self {
+            link,
+            addressing,
+            response_window,
+            request_lock: Mutex::new(()),
+        }
+    }
+
+    fn send_padded(&self, id: u32, payload: &[u8]) -> Result<(), ObdError> {
+        let mut data = payload.to_vec();
+        data.resize(8, PAD_BYTE);
+        self.link
+            .send(id, data, self.addressing == ObdAddressing::Extended, false)
+            .map_err(|e| ObdError::Transport(e.to_string()))
+    }
+
+    /// Send a functional request and collect the responses of every ECU
+    ///
+    /// Waits for the whole response window, extended while multi-frame
+    /// responses are in progress. Returns `(ecu, response)` pairs in arrival
+    /// order, negative responses included.
+    pub fn request(&self, request: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, ObdError> {
+        let service = *request
+            .first()
+            .ok_or_else(|| ObdError::InvalidResponse("empty request".to_string()))?;
+        if request.len() > 7 {
+            return Err(ObdError::InvalidResponse(
+                "functional requests must fit in a single frame".to_string(),
+            ));
+        }
+
+        let _guard = self.request_lock.lock().unwrap();
+        let mut pci = vec![request.len() as u8];
+        pci.extend_from_slice(request);
+        self.send_padded(self.addressing.functional_id(), &pci)?;
+
+        let mut responses = Vec::new();
+        let mut pending: HashMap<u32, Reassembly> = HashMap::new();
+        let mut deadline = Instant::now() + self.response_window;
+        // Échéance du prochain CF, prise en compte tant qu'un transfert est en cours
+        let mut transfer_deadline = Instant::now();
+        loop {
+            let limit = if pending.is_empty() {
+                deadline
+            } else {
+                deadline.max(transfer_deadline)
+            };
+            let remaining = limit.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            let Some((id, data, extended)) = self
+                .link
+                .recv(remaining)
+                .map_err(|e| ObdError::Transport(e.to_string()))?
+            else {
+                continue;
+            };
+            if !self.addressing.is_response(id, extended) {
+                continue;
+            }
+
+            let complete = match decode_frame(&data) {
+                Ok(IsoTpFrame::Single(payload)) => Some(payload.to_vec()),
+                Ok(IsoTpFrame::First { total, data }) => {
+                    pending.insert(
+                        id,
+                        Reassembly {
+                            total,
+                            data: data.to_vec(),
+                            next_sequence: 1,
+                        },
+                    );
+                    self.send_padded(self.addressing.physical_id(id), &FLOW_CONTROL)?;
+                    transfer_deadline = Instant::now() + N_CR;
+                    None
+                }
+                Ok(IsoTpFrame::Consecutive { sequence, data }) => {
+                    match pending.get_mut(&id) {
+                        Some(transfer) if transfer.next_sequence == sequence => {
+                            transfer.data.extend_from_slice(data);
+                            transfer.next_sequence = (sequence + 1) & 0x0F;
+                            if transfer.data.len() >= transfer.total {
+                                let mut transfer = pending.remove(&id).unwrap();
+                                transfer.data.truncate(transfer.total);
+                                Some(transfer.data)
+                            } else {
+                                transfer_deadline = Instant::now() + N_CR;
+                                None
+                            }
+                        }
+                        // Séquence rompue : la réponse de cet ECU est abandonnée
+                        Some(_) => {
+                            pending.remove(&id);
+                            None
+                        }
+                        None => None,
+                    }
+                }
+                _ => None,
+            };
+
+            match complete.as_deref() {
+                Some([NEGATIVE_RESPONSE, sid, NRC_RESPONSE_PENDING, ..]) if *sid == service => {
+                    deadline = deadline.max(Instant::now() + P2_STAR);
+                }
+                Some([NEGATIVE_RESPONSE, sid, ..]) if *sid == service => {
+                    responses.push((id, complete.unwrap()));
+                }
+                Some([sid, ..]) if *sid == service.wrapping_add(0x40) => {
+                    responses.push((id, complete.unwrap()));
+                }
+                _ => {}
+            }
+        }
+
+        if responses.is_empty() {
+            return Err(ObdError::NoResponse(service));
+        }
+        Ok(responses)
+    }
+
+    /// Send `request` and parse each positive response after its echo bytes
+    fn collect<T>(
+        &self,
+        request: &[u8],
+        parse: impl Fn(&[u8]) -> Result<T, ObdError>,
+    ) -> Result<Vec<EcuResponse<T>>, ObdError> {
+        let service = request[0];
+        let responses = self.request(request)?;
+        Ok(responses
+            .into_iter()
+            .map(|(ecu, response)| {
+                let result = match response.as_slice() {
+                    [NEGATIVE_RESPONSE, _, code, ..] => Err(ObdError::NegativeResponse {
+                        service,
+                        code: *code,
+                    }),
+                    [NEGATIVE_RESPONSE, ..] => Err(ObdError::InvalidResponse(
+                        "truncated negative response".to_string(),
+                    )),
+                    _ => {
+                        let echo = &request[1..];
+                        if response.len() < request.len() || response[1..request.len()] != *echo {
+                            Err(ObdError::InvalidResponse(format!(
+                                "unexpected response {:02X?}",
+                                response
+                            )))
+                        } else {
+                            parse(&response[request.len()..])
+                        }
+                    }
+                };
+                EcuResponse { ecu, result }
+            })
+            .collect())
+    }
+
+    /// Read a mode 01 PID from every ECU
+    pub fn read_pid(&self, pid: u8) -> Result<Vec<EcuResponse<PidReading>>, ObdError> {
+        self.collect(&[MODE_CURRENT_DATA, pid], |data| {
+            decode_pid(MODE_CURRENT_DATA, pid, data)
+        })
+    }
+
+    /// Read a mode 02 PID from freeze frame `frame`
+    pub fn read_freeze_frame(
+        &self,
+        pid: u8,
+        frame: u8,
+    ) -> Result<Vec<EcuResponse<PidReading>>, ObdError> {
+        self.collect(&[MODE_FREEZE_FRAME, pid, frame], |data| {
+            decode_pid(MODE_FREEZE_FRAME, pid, data)
+        })
+    }
+
+    /// Read stored, pending or permanent DTCs
+    pub fn read_dtcs(&self, kind: DtcKind) -> Result<Vec<EcuResponse<Vec<String>>>, ObdError> {
+        self.collect(&[kind.mode()], |data| Ok(decode_dtc_list(data)))
+    }
+
+    /// Read a mode 09 vehicle information record
+    pub fn read_vehicle_info(&self, pid: u8) -> Result<Vec<EcuResponse<VehicleInfo>>, ObdError> {
+        self.collect(&[MODE_VEHICLE_INFO, pid], |data| match data.split_first() {
+            Some((&count, data)) => Ok(VehicleInfo {
+                pid,
+                count,
+                data: data.to_vec(),
+            }),
+            None => Err(ObdError::InvalidResponse(
+                "missing data item count".to_string(),
+            )),
+        })
+    }
+
+    /// Read the vehicle identification number (mode 09, PID 02)
+    pub fn read_vin(&self) -> Result<Vec<EcuResponse<String>>, ObdError> {
+        Ok(self
+            .read_vehicle_info(PID_VIN)?
+            .into_iter()
+            .map(|r| EcuResponse {
+                ecu: r.ecu,
+                result: r.result.map(|info| vehicle_info_text(&info.data)),
+            })
+            .collect())
+    }
+}
+
+/// Decoded reply of one ECU, for the JavaScript side
+enum ObdReply {
+    Pid(PidReading),
+    Dtcs(Vec<String>),
+    VehicleInfo(VehicleInfo),
+    Vin(String),
+    Raw(Vec<u8>),
+}
+
+type ObdReplies = Vec<EcuResponse<ObdReply>>;
+
+fn map_replies<T>(
+    responses: Result<Vec<EcuResponse<T>>, ObdError>,
+    wrap: fn(T) -> ObdReply,
+) -> Result<ObdReplies, ObdError> {
+    Ok(responses?
+        .into_iter()
+        .map(|r| EcuResponse {
+            ecu: r.ecu,
+            result: r.result.map(wrap),
+        })
+        .collect())
+}
+
+lazy_static::lazy_static! {
+    static ref OBD_REGISTRY: Arc<Mutex<HashMap<u32, Arc<ObdClient>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+fn get_obd_client(client_id: u32) -> Option<Arc<ObdClient>> {
+    OBD_REGISTRY.lock().unwrap().get(&client_id).cloned()
+}
+
+fn set_error<'a>(
+    cx: &mut TaskContext<'a>,
+    obj: Handle<'a, JsObject>,
+    e: &ObdError,
+) -> NeonResult<()> {
+    let v = cx.string(e.to_string());
+    obj.set(cx, "error", v)?;
+    if let ObdError::NegativeResponse { code, .. } = e {
+        let v = cx.number(*code as f64);
+        obj.set(cx, "nrc", v)?;
+        let v = cx.string(nrc_name(*code));
+        obj.set(cx, "nrcName", v)?;
+    }
+    Ok(())
+}
+
+fn reply_to_js<'a>(
+    cx: &mut TaskContext<'a>,
+    reply: EcuResponse<ObdReply>,
+) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let v = cx.number(reply.ecu as f64);
+    obj.set(cx, "ecu", v)?;
+
+    match reply.result {
+        Err(e) => set_error(cx, obj, &e)?,
+        Ok(ObdReply::Pid(reading)) => {
+            let v = cx.number(reading.pid as f64);
+            obj.set(cx, "pid", v)?;
+            let v = cx.string(reading.name);
+            obj.set(cx, "name", v)?;
+            let v = cx.string(reading.unit);
+            obj.set(cx, "unit", v)?;
+            let value: Handle<JsValue> = match reading.value {
+                PidValue::Number(n) => cx.number(n).upcast(),
+                PidValue::Supported(pids) => {
+                    let array = cx.empty_array();
+                    for (i, pid) in pids.iter().enumerate() {
+                        let v = cx.number(*pid as f64);
+                        array.set(cx, i as u32, v)?;
+                    }
+                    array.upcast()
+                }
+                PidValue::MonitorStatus {
+                    mil,
+                    dtc_count,
+                    compression_ignition,
+                } => {
+                    let status = cx.empty_object();
+                    let v = cx.boolean(mil);
+                    status.set(cx, "mil", v)?;
+                    let v = cx.number(dtc_count as f64);
+                    status.set(cx, "dtcCount", v)?;
+                    let v = cx.boolean(compression_ignition);
+                    status.set(cx, "compressionIgnition", v)?;
+                    status.upcast()
+                }
+                PidValue::Dtc(dtc) => cx.string(dtc).upcast(),
+                PidValue::Raw(data) => JsBuffer::from_slice(cx, &data)?.upcast(),
+            };
+            obj.set(cx, "value", value)?;
+        }
+        Ok(ObdReply::Dtcs(dtcs)) => {
+            let array = cx.empty_array();
+            for (i, dtc) in dtcs.iter().enumerate() {
+                let v = cx.string(dtc);
+                array.set(cx, i as u32, v)?;
+            }
+            obj.set(cx, "dtcs", array)?;
+        }
+        Ok(ObdReply::VehicleInfo(info)) => {
+            let v = cx.number(info.pid as f64);
+            obj.set(cx, "pid", v)?;
+            let v = cx.number(info.count as f64);
+            obj.set(cx, "count", v)?;
+            let v = cx.string(vehicle_info_text(&info.data));
+            obj.set(cx, "text", v)?;
+            let v = JsBuffer::from_slice(cx, &info.data)?;
+            obj.set(cx, "data", v)?;
+        }
+        Ok(ObdReply::Vin(vin)) => {
+            let v = cx.string(vin);
+            obj.set(cx, "vin", v)?;
+        }
+        Ok(ObdReply::Raw(data)) => {
+            let v = JsBuffer::from_slice(cx, &data)?;
+            obj.set(cx, "data", v)?;
+        }
+    }
+    Ok(obj)
+}
+
+/// Run an OBD call on the libuv thread pool and resolve the per-ECU replies
+fn obd_promise<'a, F>(cx: &mut FunctionContext<'a>, call: F) -> JsResult<'a, JsPromise>
+where
+    F: FnOnce(&ObdClient) -> Result<ObdReplies, ObdError> + Send + 'static,
+{
+    let client_id = cx.argument::<JsNumber>(0)?.value(cx) as u32;
+    let Some(client) = get_obd_client(client_id) else {
+        return cx.throw_error("Invalid OBD client ID");
+    };
+
+    let promise = cx
+        .task(move || call(&client))
+        .promise(move |mut cx, result| match result {
+            Ok(replies) => {
+                let array = cx.empty_array();
+                for (i, reply) in replies.into_iter().enumerate() {
+                    let obj = reply_to_js(&mut cx, reply)?;
+                    array.set(&mut cx, i as u32, obj)?;
+                }
+                Ok(array)
+            }
+            Err(e) => cx.throw_error(e.to_string()),
+        });
+    Ok(promise)
+}
+
+/// Create an OBD-II client on `interface`
+pub fn create_obd_client(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let interface = cx.argument::<JsString>(0)?.value(&mut cx);
+    let mut addressing = ObdAddressing::Standard;
+    let mut window = Duration::from_millis(100);
+    if let Some(options) = cx
+        .argument_opt(1)
+        .and_then(|v| v.downcast::<JsObject, _>(&mut cx).ok())
+    {
+        if let Some(flag) = options.get_opt::<JsBoolean, _, _>(&mut cx, "extended")? {
+            if flag.value(&mut cx) {
+                addressing = ObdAddressing::Extended;
+            }
+        }
+        if let Some(timeout) = options.get_opt::<JsNumber, _, _>(&mut cx, "timeout")? {
+            window = Duration::from_millis(timeout.value(&mut cx) as u64);
+        }
+    }
+
+    match ObdClient::open(&interface, addressing, window) {
+        Ok(client) => {
+            let id = next_handle_id();
+            OBD_REGISTRY.lock().unwrap().insert(id, Arc::new(client));
+            Ok(cx.number(id as f64))
+        }
+        Err(e) => cx.throw_error(format!("Failed to create OBD client: {}", e)),
+    }
+}
+
+/// Mode 01 PID from JavaScript
+pub fn obd_read_pid(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let pid = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    obd_promise(&mut cx, move |client| {
+        map_replies(client.read_pid(pid), ObdReply::Pid)
+    })
+}
+
+/// Mode 02 freeze frame PID from JavaScript
+pub fn obd_read_freeze_frame(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let pid = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let frame = match cx.argument_opt(2) {
+        Some(v) => v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u8,
+        None => 0,
+    };
+    obd_promise(&mut cx, move |client| {
+        map_replies(client.read_freeze_frame(pid, frame), ObdReply::Pid)
+    })
+}
+
+/// Modes 03/07/0A from JavaScript
+pub fn obd_read_dtcs(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let kind = match cx.argument_opt(1) {
+        Some(v) => {
+            let name = v.downcast_or_throw::<JsString, _>(&mut cx)?.value(&mut cx);
+            match DtcKind::from_name(&name) {
+                Some(kind) => kind,
+                None => return cx.throw_error(format!("Invalid DTC kind: {}", name)),
+            }
+        }
+        None => DtcKind::Stored,
+    };
+    obd_promise(&mut cx, move |client| {
+        map_replies(client.read_dtcs(kind), ObdReply::Dtcs)
+    })
+}
+
+/// Mode 09 record from JavaScript (PID 0x02 is the VIN)
+pub fn obd_read_vehicle_info(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let pid = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    obd_promise(&mut cx, move |client| {
+        map_replies(client.read_vehicle_info(pid), ObdReply::VehicleInfo)
+    })
+}
+
+/// VIN of every responding ECU from JavaScript
+pub fn obd_read_vin(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    obd_promise(&mut cx, |client| {
+        map_replies(client.read_vin(), ObdReply::Vin)
+    })
+}
+
+/// Arbitrary functional request from JavaScript, resolving to raw responses
+pub fn obd_request(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let request = cx.argument::<JsBuffer>(1)?.as_slice(&cx).to_vec();
+    if request.is_empty() {
+        return cx.throw_error("Empty OBD request");
+    }
+    obd_promise(&mut cx, move |client| {
+        Ok(client
+            .request(&request)?
+            .into_iter()
+            .map(|(ecu, data)| EcuResponse {
+                ecu,
+                result: Ok(ObdReply::Raw(data)),
+            })
+            .collect())
+    })
+}
+
+/// Close an OBD-II client from JavaScript
+pub fn close_obd_client(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match OBD_REGISTRY.lock().unwrap().remove(&client_id) {
+        Some(_) => Ok(cx.undefined()),
+        None => cx.throw_error("Invalid OBD client ID"),
+    }
+}
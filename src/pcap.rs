@@ -0,0 +1,524 @@
+//! pcap / pcapng captures with `LINKTYPE_CAN_SOCKETCAN`
+//!
+//! Packets carry the SocketCAN pseudo-header: big-endian CAN ID word with
+//! EFF/RTR/ERR flags, payload length, FD flags, then the payload. CAN XL
+//! packets start with the priority/VCID word and are recognised by the XLF
+//! flag, with little-endian length and acceptance field.
+//!
+//! Written captures are pcapng with one interface block per interface and
+//! nanosecond timestamps; reading accepts pcap (µs or ns) and pcapng.
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::trace::{frames_from_js, frames_to_js, Direction, TraceFrame, XlHeader};
+
+pub const LINKTYPE_CAN_SOCKETCAN: u16 = 227;
+
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+
+const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const BLOCK_INTERFACE: u32 = 0x0000_0001;
+const BLOCK_SIMPLE_PACKET: u32 = 0x0000_0003;
+const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_IF_NAME: u16 = 2;
+const OPT_IF_TSRESOL: u16 = 9;
+const OPT_IF_TSOFFSET: u16 = 14;
+const OPT_EPB_FLAGS: u16 = 2;
+const EPB_INBOUND: u32 = 1;
+const EPB_OUTBOUND: u32 = 2;
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_EFF_MASK: u32 = 0x1FFF_FFFF;
+const CAN_SFF_MASK: u32 = 0x7FF;
+const CANFD_BRS: u8 = 0x01;
+const CANFD_ESI: u8 = 0x02;
+const CANFD_FDF: u8 = 0x04;
+const CANFD_MTU: usize = 72;
+const CANXL_XLF: u8 = 0x80;
+const CANXL_SEC: u8 = 0x01;
+const CANXL_HEADER_SIZE: usize = 12;
+const CANXL_MAX_DLEN: usize = 2048;
+
+/// Encode a frame as a `LINKTYPE_CAN_SOCKETCAN` packet
+///
+/// Classic and FD frames are padded to the kernel `can_frame` and
+/// `canfd_frame` sizes, as in live captures.
+pub fn encode_packet(frame: &TraceFrame) -> Vec<u8> {
+    if let Some(xl) = frame.xl {
+        let prio = (frame.id & CAN_SFF_MASK) | (xl.vcid as u32) << 16;
+        let flags = CANXL_XLF | if xl.sec { CANXL_SEC } else { 0 };
+        let mut packet = Vec::with_capacity(CANXL_HEADER_SIZE + frame.data.len());
+        packet.extend_from_slice(&prio.to_be_bytes());
+        packet.extend_from_slice(&[flags, xl.sdt]);
+        packet.extend_from_slice(&(frame.data.len() as u16).to_le_bytes());
+        packet.extend_from_slice(&xl.af.to_le_bytes());
+        packet.extend_from_slice(&frame.data);
+        return packet;
+    }
+
+    let word = if frame.error {
+        frame.id & CAN_EFF_MASK | CAN_ERR_FLAG
+    } else if frame.extended {
+        frame.id & CAN_EFF_MASK | CAN_EFF_FLAG
+    } else {
+        frame.id & CAN_SFF_MASK
+    } | if frame.remote { CAN_RTR_FLAG } else { 0 };
+    let (len, flags, size) = if frame.fd {
+        let flags = CANFD_FDF
+            | if frame.brs { CANFD_BRS } else { 0 }
+            | if frame.esi { CANFD_ESI } else { 0 };
+        (frame.data.len(), flags, CANFD_MTU)
+    } else if frame.remote {
+        (frame.remote_len as usize, 0, 16)
+    } else {
+        (frame.data.len(), 0, 16)
+    };
+
+    let mut packet = Vec::with_capacity(size);
+    packet.extend_from_slice(&word.to_be_bytes());
+    packet.extend_from_slice(&[len as u8, flags, 0, 0]);
+    if !frame.remote {
+        packet.extend_from_slice(&frame.data);
+    }
+    packet.resize(size, 0);
+    packet
+}
+
+/// Decode a `LINKTYPE_CAN_SOCKETCAN` packet
+pub fn decode_packet(packet: &[u8]) -> Result<TraceFrame, String> {
+    if packet.len() < 8 {
+        return Err(format!("packet too short ({} bytes)", packet.len()));
+    }
+    let word = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+
+    if packet[4] & CANXL_XLF != 0 {
+        if packet.len() < CANXL_HEADER_SIZE {
+            return Err("truncated CAN XL header".to_string());
+        }
+        let len = u16::from_le_bytes([packet[6], packet[7]]) as usize;
+        if len > CANXL_MAX_DLEN {
+            return Err(format!("invalid CAN XL length {}", len));
+        }
+        let data = packet
+            .get(CANXL_HEADER_SIZE..CANXL_HEADER_SIZE + len)
+            .ok_or("truncated CAN XL payload")?;
+        return Ok(TraceFrame {
+            id: word & CAN_SFF_MASK,
+            xl: Some(XlHeader {
+                sdt: packet[5],
+                vcid: (word >> 16) as u8,
+                af: u32::from_le_bytes([packet[8], packet[9], packet[10], packet[11]]),
+                sec: packet[4] & CANXL_SEC != 0,
+            }),
+            data: data.to_vec(),
+            ..Default::default()
+        });
+    }
+
+    let len = packet[4] as usize;
+    let flags = packet[5];
+    let error = word & CAN_ERR_FLAG != 0;
+    let extended = word & CAN_EFF_FLAG != 0 && !error;
+    let remote = word & CAN_RTR_FLAG != 0;
+    let fd = flags & CANFD_FDF != 0 || packet.len() == CANFD_MTU;
+    if len > if fd { 64 } else { 8 } {
+        return Err(format!("invalid payload length {}", len));
+    }
+
+    let mut frame = TraceFrame {
+        id: if extended || error {
+            word & CAN_EFF_MASK
+        } else {
+            word & CAN_SFF_MASK
+        },
+        extended,
+        fd,
+        brs: fd && flags & CANFD_BRS != 0,
+        esi: fd && flags & CANFD_ESI != 0,
+        remote,
+        error,
+        ..Default::default()
+    };
+    if remote {
+        frame.remote_len = len as u8;
+    } else {
+        frame.data = packet.get(8..8 + len).ok_or("truncated payload")?.to_vec();
+    }
+    Ok(frame)
+}
+
+/// Byte order of a pcap file or pcapng section
+#[derive(Clone, Copy)]
+struct Endian(bool);
+
+impl Endian {
+    fn u16(self, data: &[u8], pos: usize) -> u16 {
+        let bytes = [data[pos], data[pos + 1]];
+        if self.0 {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(self, data: &[u8], pos: usize) -> u32 {
+        let bytes = data[pos..pos + 4].try_into().unwrap();
+        if self.0 {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+/// Interface Description Block of a pcapng section
+struct Interface {
+    linktype: u16,
+    name: String,
+    /// `if_tsresol`: decimal or (high bit set) binary exponent
+    resolution: u8,
+    offset: i64,
+}
+
+impl Interface {
+    /// Time of a packet, `None` beyond the range of `Duration`
+    fn timestamp(&self, ticks: u64) -> Option<Duration> {
+        let exponent = (self.resolution & 0x7F) as u32;
+        let time = if self.resolution & 0x80 != 0 {
+            let nanos = ((ticks as u128) * 1_000_000_000) >> exponent.min(127);
+            Duration::new(
+                u64::try_from(nanos / 1_000_000_000).ok()?,
+                (nanos % 1_000_000_000) as u32,
+            )
+        } else if exponent <= 9 {
+            let scale = 10u64.pow(exponent);
+            Duration::new(
+                ticks / scale,
+                ((ticks % scale) * 10u64.pow(9 - exponent)) as u32,
+            )
+        } else {
+            Duration::from_nanos(ticks / 10u64.pow((exponent - 9).min(19)))
+        };
+        match self.offset {
+            0 => Some(time),
+            offset if offset > 0 => time.checked_add(Duration::from_secs(offset as u64)),
+            offset => Some(time.saturating_sub(Duration::from_secs(offset.unsigned_abs()))),
+        }
+    }
+}
+
+/// Options of a pcapng block as `(code, value)` pairs
+fn options(endian: Endian, data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut options = Vec::new();
+    let mut pos = 0;
+    while pos + 4 <= data.len() {
+        let code = endian.u16(data, pos);
+        let len = endian.u16(data, pos + 2) as usize;
+        if code == OPT_END {
+            break;
+        }
+        let Some(value) = data.get(pos + 4..pos + 4 + len) else {
+            break;
+        };
+        options.push((code, value));
+        pos += 4 + len.div_ceil(4) * 4;
+    }
+    options
+}
+
+fn parse_pcapng(data: &[u8]) -> Result<Vec<TraceFrame>, String> {
+    let mut frames = Vec::new();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut endian = Endian(false);
+    let mut pos = 0;
+    while pos + 12 <= data.len() {
+        let kind = endian.u32(data, pos);
+        if kind == BLOCK_SECTION_HEADER {
+            let magic = &data[pos + 8..pos + 12];
+            endian = match u32::from_le_bytes(magic.try_into().unwrap()) {
+                BYTE_ORDER_MAGIC => Endian(false),
+                m if m.swap_bytes() == BYTE_ORDER_MAGIC => Endian(true),
+                _ => return Err(format!("invalid byte-order magic at offset {}", pos)),
+            };
+            interfaces.clear();
+        }
+        let len = endian.u32(data, pos + 4) as usize;
+        if len < 12 || !len.is_multiple_of(4) || pos + len > data.len() {
+            return Err(format!("invalid block length at offset {}", pos));
+        }
+        let body = &data[pos + 8..pos + len - 4];
+
+        match kind {
+            BLOCK_INTERFACE if body.len() >= 8 => {
+                let mut interface = Interface {
+                    linktype: endian.u16(body, 0),
+                    name: String::new(),
+                    resolution: 6,
+                    offset: 0,
+                };
+                for (code, value) in options(endian, &body[8..]) {
+                    match (code, value.len()) {
+                        (OPT_IF_NAME, _) => {
+                            interface.name = String::from_utf8_lossy(value)
+                                .trim_end_matches('\0')
+                                .to_string()
+                        }
+                        (OPT_IF_TSRESOL, 1) => interface.resolution = value[0],
+                        (OPT_IF_TSOFFSET, 8) => {
+                            let bytes = value.try_into().unwrap();
+                            interface.offset = if endian.0 {
+                                i64::from_be_bytes(bytes)
+                            } else {
+                                i64::from_le_bytes(bytes)
+                            };
+                        }
+                        _ => {}
+                    }
+                }
+                interfaces.push(interface);
+            }
+            BLOCK_ENHANCED_PACKET if body.len() >= 20 => {
+                let index = endian.u32(body, 0) as usize;
+                let interface = interfaces
+                    .get(index)
+                    .ok_or_else(|| format!("unknown interface {} at offset {}", index, pos))?;
+                let captured = endian.u32(body, 12) as usize;
+                let packet = body
+                    .get(20..20 + captured)
+                    .ok_or_else(|| format!("truncated packet at offset {}", pos))?;
+                if interface.linktype == LINKTYPE_CAN_SOCKETCAN {
+                    let ticks = (endian.u32(body, 4) as u64) << 32 | endian.u32(body, 8) as u64;
+                    let mut frame =
+                        decode_packet(packet).map_err(|e| format!("offset {}: {}", pos, e))?;
+                    frame.timestamp = interface
+                        .timestamp(ticks)
+                        .ok_or_else(|| format!("timestamp out of range at offset {}", pos))?;
+                    frame.interface = interface.name.clone();
+                    let options_start = (20 + captured.div_ceil(4) * 4).min(body.len());
+                    for (code, value) in options(endian, &body[options_start..]) {
+                        if code == OPT_EPB_FLAGS && value.len() == 4 {
+                            frame.direction = match endian.u32(value, 0) & 0x3 {
+                                EPB_INBOUND => Some(Direction::Rx),
+                                EPB_OUTBOUND => Some(Direction::Tx),
+                                _ => None,
+                            };
+                        }
+                    }
+                    frames.push(frame);
+                }
+            }
+            BLOCK_SIMPLE_PACKET if body.len() >= 4 => {
+                let interface = interfaces
+                    .first()
+                    .ok_or_else(|| format!("simple packet without interface at offset {}", pos))?;
+                if interface.linktype == LINKTYPE_CAN_SOCKETCAN {
+                    let original = endian.u32(body, 0) as usize;
+                    let packet = &body[4..(4 + original).min(body.len())];
+                    let mut frame =
+                        decode_packet(packet).map_err(|e| format!("offset {}: {}", pos, e))?;
+                    frame.interface = interface.name.clone();
+                    frames.push(frame);
+                }
+            }
+            _ => {}
+        }
+        pos += len;
+    }
+    Ok(frames)
+}
+
+fn parse_pcap(data: &[u8]) -> Result<Vec<TraceFrame>, String> {
+    if data.len() < 24 {
+        return Err("truncated pcap header".to_string());
+    }
+    let magic = u32::from_le_bytes(data[..4].try_into().unwrap());
+    let (endian, nanos) = match magic {
+        PCAP_MAGIC_MICROS => (Endian(false), false),
+        PCAP_MAGIC_NANOS => (Endian(false), true),
+        m if m.swap_bytes() == PCAP_MAGIC_MICROS => (Endian(true), false),
+        m if m.swap_bytes() == PCAP_MAGIC_NANOS => (Endian(true), true),
+        _ => return Err("not a pcap or pcapng file".to_string()),
+    };
+    let linktype = endian.u32(data, 20) & 0xFFFF;
+    if linktype != LINKTYPE_CAN_SOCKETCAN as u32 {
+        return Err(format!(
+            "unsupported link type {} (expected LINKTYPE_CAN_SOCKETCAN)",
+            linktype
+        ));
+    }
+
+    let mut frames = Vec::new();
+    let mut pos = 24;
+    while pos + 16 <= data.len() {
+        let secs = endian.u32(data, pos) as u64;
+        let fraction = endian.u32(data, pos + 4);
+        let captured = endian.u32(data, pos + 8) as usize;
+        let packet = data
+            .get(pos + 16..pos + 16 + captured)
+            .ok_or_else(|| format!("truncated packet at offset {}", pos))?;
+        let mut frame = decode_packet(packet).map_err(|e| format!("offset {}: {}", pos, e))?;
+        frame.timestamp = if nanos {
+            Duration::new(secs, fraction)
+        } else {
+            Duration::new(secs, 0) + Duration::from_micros(fraction as u64)
+        };
+        frames.push(frame);
+        pos += 16 + captured;
+    }
+    Ok(frames)
+}
+
+/// Parse a pcap or pcapng capture
+pub fn parse(data: &[u8]) -> Result<Vec<TraceFrame>, String> {
+    if data.len() >= 4 && data[..4] == BLOCK_SECTION_HEADER.to_le_bytes() {
+        parse_pcapng(data)
+    } else {
+        parse_pcap(data)
+    }
+}
+
+/// Streaming pcapng writer, adding an interface block per new interface
+pub struct PcapngWriter<W: Write> {
+    out: W,
+    interfaces: Vec<String>,
+}
+
+fn push_option(block: &mut Vec<u8>, code: u16, value: &[u8]) {
+    block.extend_from_slice(&code.to_le_bytes());
+    block.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    block.extend_from_slice(value);
+    block.resize(block.len().div_ceil(4) * 4, 0);
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Write the section header block
+    pub fn new(mut out: W) -> io::Result<Self> {
+        let mut body = Vec::with_capacity(16);
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&(-1i64).to_le_bytes()); // longueur de section inconnue
+        Self::write_block(&mut out, BLOCK_SECTION_HEADER, &body)?;
+        Ok(Self {
+            out,
+            interfaces: Vec::new(),
+        })
+    }
+
+    fn write_block(out: &mut W, kind: u32, body: &[u8]) -> io::Result<()> {
+        let len = (12 + body.len()) as u32;
+        out.write_all(&kind.to_le_bytes())?;
+        out.write_all(&len.to_le_bytes())?;
+        out.write_all(body)?;
+        out.write_all(&len.to_le_bytes())
+    }
+
+    fn interface_id(&mut self, name: &str) -> io::Result<u32> {
+        if let Some(index) = self.interfaces.iter().position(|i| i == name) {
+            return Ok(index as u32);
+        }
+        let mut body = Vec::with_capacity(32);
+        body.extend_from_slice(&LINKTYPE_CAN_SOCKETCAN.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&65535u32.to_le_bytes());
+        if !name.is_empty() {
+            push_option(&mut body, OPT_IF_NAME, name.as_bytes());
+        }
+        push_option(&mut body, OPT_IF_TSRESOL, &[9]);
+        push_option(&mut body, OPT_END, &[]);
+        Self::write_block(&mut self.out, BLOCK_INTERFACE, &body)?;
+        self.interfaces.push(name.to_string());
+        Ok(self.interfaces.len() as u32 - 1)
+    }
+
+    /// Write one frame as an enhanced packet block
+    pub fn write_frame(&mut self, frame: &TraceFrame) -> io::Result<()> {
+        let interface = self.interface_id(&frame.interface)?;
+        let packet = encode_packet(frame);
+        let ticks = frame.timestamp.as_nanos() as u64;
+
+        let mut body = Vec::with_capacity(32 + packet.len());
+        body.extend_from_slice(&interface.to_le_bytes());
+        body.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ticks as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&packet);
+        body.resize(body.len().div_ceil(4) * 4, 0);
+        if let Some(direction) = frame.direction {
+            let flags = match direction {
+                Direction::Rx => EPB_INBOUND,
+                Direction::Tx => EPB_OUTBOUND,
+            };
+            push_option(&mut body, OPT_EPB_FLAGS, &flags.to_le_bytes());
+            push_option(&mut body, OPT_END, &[]);
+        }
+        Self::write_block(&mut self.out, BLOCK_ENHANCED_PACKET, &body)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Format frames as a pcapng capture
+pub fn format(frames: &[TraceFrame]) -> Vec<u8> {
+    // L'écriture dans un Vec ne peut pas échouer
+    let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+    for frame in frames {
+        writer.write_frame(frame).unwrap();
+    }
+    writer.into_inner()
+}
+
+/// Parse a pcap or pcapng buffer from JavaScript
+pub fn parse_pcap_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let data = cx.argument::<JsBuffer>(0)?.as_slice(&cx).to_vec();
+    match parse(&data) {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to parse capture: {}", e)),
+    }
+}
+
+/// Read a pcap or pcapng file from JavaScript
+pub fn read_pcap_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let result = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| parse(&data));
+    match result {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+/// Format frames as a pcapng buffer from JavaScript
+pub fn format_pcapng_log(mut cx: FunctionContext) -> JsResult<JsBuffer> {
+    let array = cx.argument::<JsArray>(0)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    JsBuffer::from_slice(&mut cx, &format(&frames))
+}
+
+/// Write frames to a pcapng file from JavaScript
+pub fn write_pcapng_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let array = cx.argument::<JsArray>(1)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    match std::fs::write(&path, format(&frames)) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to write {}: {}", path, e)),
+    }
+}
@@ -0,0 +1,482 @@
+//! Timing-accurate trace replay
+//!
+//! A replayer thread sends a recorded frame sequence with its original
+//! inter-frame timing, scaled by a speed factor. It sleeps on a condition
+//! variable until shortly before each frame, so that pause, resume, seek
+//! and speed changes apply immediately, then busy-waits the last stretch.
+
+use neon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::trace::TraceFrame;
+use crate::CanSocketWrapper;
+
+/// Remaining time below which the thread busy-waits instead of sleeping
+const SPIN: Duration = Duration::from_micros(1500);
+/// How long a full transmit queue is retried before giving up
+const QUEUE_FULL_TIMEOUT: Duration = Duration::from_secs(1);
+/// Accepted speed factors
+const SPEED_RANGE: std::ops::RangeInclusive<f64> = 1e-3..=1e3;
+
+#[cfg(target_os = "linux")]
+const ENOBUFS: Option<i32> = Some(libc::ENOBUFS);
+#[cfg(not(target_os = "linux"))]
+const ENOBUFS: Option<i32> = None;
+
+/// Destination of replayed frames
+pub trait ReplaySink: Send + 'static {
+    fn send(&self, frame: &TraceFrame) -> Result<(), Box<dyn Error>>;
+}
+
+impl ReplaySink for CanSocketWrapper {
+    fn send(&self, frame: &TraceFrame) -> Result<(), Box<dyn Error>> {
+        // La longueur demandée d'une trame distante passe par la taille des données
+        let data = if frame.remote {
+            vec![0; frame.remote_len as usize]
+        } else {
+            frame.data.clone()
+        };
+        self.send_frame(frame.id, data, frame.extended, frame.fd, frame.remote)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Playback speed factor, 2.0 plays twice as fast
+    pub speed: f64,
+    /// Number of passes over the trace, `None` to loop until stopped
+    pub passes: Option<u32>,
+    /// Identifiers to rewrite before sending
+    pub id_map: HashMap<u32, u32>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            passes: Some(1),
+            id_map: HashMap::new(),
+        }
+    }
+}
+
+/// Where frames go: by trace interface name, else to the default sink
+///
+/// Frames without a destination are skipped.
+pub struct ReplayTargets<S> {
+    pub default: Option<S>,
+    pub channels: HashMap<String, S>,
+}
+
+impl<S> ReplayTargets<S> {
+    fn route(&self, interface: &str) -> Option<&S> {
+        self.channels.get(interface).or(self.default.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayState {
+    Running,
+    Paused,
+    Finished,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayStatus {
+    pub state: ReplayState,
+    /// Trace time of the last sent frame, from the first frame
+    pub position: Duration,
+    /// Index of the next frame
+    pub index: usize,
+    pub sent: u64,
+    /// Error, CAN XL and unrouted frames
+    pub skipped: u64,
+    /// Completed passes
+    pub passes: u32,
+    pub error: Option<String>,
+}
+
+struct Control {
+    paused: bool,
+    stop: bool,
+    seek: Option<Duration>,
+    speed: f64,
+    status: ReplayStatus,
+}
+
+struct Shared {
+    control: Mutex<Control>,
+    wake: Condvar,
+}
+
+/// Replay thread and its controls
+pub struct Replayer {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+fn check_speed(speed: f64) -> Result<(), String> {
+    if SPEED_RANGE.contains(&speed) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid replay speed {} (expected {} to {})",
+            speed,
+            SPEED_RANGE.start(),
+            SPEED_RANGE.end()
+        ))
+    }
+}
+
+fn is_queue_full(error: &(dyn Error + 'static)) -> bool {
+    error.downcast_ref::<io::Error>().is_some_and(|e| {
+        e.kind() == io::ErrorKind::WouldBlock
+            || (e.raw_os_error().is_some() && e.raw_os_error() == ENOBUFS)
+    })
+}
+
+impl Replayer {
+    /// Start replaying `frames`, ordered by timestamp, in a background thread
+    pub fn start<S: ReplaySink>(
+        mut frames: Vec<TraceFrame>,
+        targets: ReplayTargets<S>,
+        options: ReplayOptions,
+    ) -> Result<Self, String> {
+        check_speed(options.speed)?;
+        if options.passes == Some(0) {
+            return Err("Replay needs at least one pass".to_string());
+        }
+        frames.sort_by_key(|f| f.timestamp);
+
+        let shared = Arc::new(Shared {
+            control: Mutex::new(Control {
+                paused: false,
+                stop: false,
+                seek: None,
+                speed: options.speed,
+                status: ReplayStatus {
+                    state: ReplayState::Running,
+                    position: Duration::ZERO,
+                    index: 0,
+                    sent: 0,
+                    skipped: 0,
+                    passes: 0,
+                    error: None,
+                },
+            }),
+            wake: Condvar::new(),
+        });
+        let thread_shared = Arc::clone(&shared);
+        let handle = thread::spawn(move || run(frames, targets, options, &thread_shared));
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Control)) {
+        f(&mut self.shared.control.lock().unwrap());
+        self.shared.wake.notify_all();
+    }
+
+    pub fn pause(&self) {
+        self.update(|c| c.paused = true);
+    }
+
+    pub fn resume(&self) {
+        self.update(|c| c.paused = false);
+    }
+
+    /// Continue from the first frame at or after `position` in the trace
+    pub fn seek(&self, position: Duration) {
+        self.update(|c| c.seek = Some(position));
+    }
+
+    pub fn set_speed(&self, speed: f64) -> Result<(), String> {
+        check_speed(speed)?;
+        self.update(|c| c.speed = speed);
+        Ok(())
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        self.shared.control.lock().unwrap().status.clone()
+    }
+
+    /// Wait for the replay to finish on its own
+    #[cfg(test)]
+    pub fn wait(mut self) -> ReplayStatus {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        self.status()
+    }
+
+    /// Stop the thread and return the final status
+    pub fn stop(mut self) -> ReplayStatus {
+        self.halt();
+        self.status()
+    }
+
+    fn halt(&mut self) {
+        self.update(|c| c.stop = true);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Replayer {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+fn run<S: ReplaySink>(
+    frames: Vec<TraceFrame>,
+    targets: ReplayTargets<S>,
+    options: ReplayOptions,
+    shared: &Shared,
+) {
+    let first = frames.first().map_or(Duration::ZERO, |f| f.timestamp);
+    let offsets: Vec<Duration> = frames
+        .iter()
+        .map(|f| f.timestamp.saturating_sub(first))
+        .collect();
+
+    // Instant de référence et position correspondante dans la trace
+    let mut speed = options.speed;
+    let mut anchor = (Instant::now(), Duration::ZERO);
+    let position_at = |anchor: (Instant, Duration), speed: f64, now: Instant| {
+        anchor
+            .1
+            .saturating_add(now.saturating_duration_since(anchor.0).mul_f64(speed))
+    };
+    let mut index = 0;
+
+    let mut control = shared.control.lock().unwrap();
+    loop {
+        if control.stop {
+            break;
+        }
+        let now = Instant::now();
+        if let Some(seek) = control.seek.take() {
+            index = offsets.partition_point(|&o| o < seek);
+            anchor = (now, seek);
+            control.status.index = index;
+            control.status.position = seek;
+        }
+        if control.speed != speed {
+            anchor = (now, position_at(anchor, speed, now));
+            speed = control.speed;
+        }
+        if control.paused {
+            let position = position_at(anchor, speed, now);
+            control.status.state = ReplayState::Paused;
+            while control.paused && !control.stop && control.seek.is_none() {
+                control = shared.wake.wait(control).unwrap();
+            }
+            control.status.state = ReplayState::Running;
+            anchor = (Instant::now(), position);
+            continue;
+        }
+
+        if index >= frames.len() {
+            control.status.passes += 1;
+            if options.passes.is_some_and(|p| control.status.passes >= p) || frames.is_empty() {
+                break;
+            }
+            index = 0;
+            anchor = (now, Duration::ZERO);
+            control.status.index = 0;
+            continue;
+        }
+
+        // Décalages de trace démesurés : attente sans fin plutôt que débordement
+        let wait = Duration::try_from_secs_f64(
+            offsets[index].saturating_sub(anchor.1).as_secs_f64() / speed,
+        )
+        .unwrap_or(Duration::MAX);
+        let remaining = anchor.0.checked_add(wait).map_or(Duration::MAX, |target| {
+            target.saturating_duration_since(now)
+        });
+        if remaining > SPIN {
+            control = shared
+                .wake
+                .wait_timeout(control, remaining - SPIN)
+                .unwrap()
+                .0;
+            continue;
+        }
+        drop(control);
+        let target = now + remaining;
+        while Instant::now() < target {
+            std::hint::spin_loop();
+        }
+
+        let frame = &frames[index];
+        let result = match targets.route(&frame.interface) {
+            Some(sink) if !frame.error && frame.xl.is_none() => match options.id_map.get(&frame.id)
+            {
+                Some(&id) => send(
+                    sink,
+                    &TraceFrame {
+                        id,
+                        ..frame.clone()
+                    },
+                ),
+                None => send(sink, frame),
+            }
+            .map(|()| true),
+            _ => Ok(false),
+        };
+
+        control = shared.control.lock().unwrap();
+        match result {
+            Ok(sent) => {
+                if sent {
+                    control.status.sent += 1;
+                } else {
+                    control.status.skipped += 1;
+                }
+            }
+            Err(e) => {
+                control.status.error = Some(e);
+                break;
+            }
+        }
+        index += 1;
+        control.status.index = index;
+        control.status.position = offsets[index - 1];
+    }
+    control.status.state = ReplayState::Finished;
+}
+
+/// Send a frame, waiting while the transmit queue is full
+pub(crate) fn send<S: ReplaySink>(sink: &S, frame: &TraceFrame) -> Result<(), String> {
+    let deadline = Instant::now() + QUEUE_FULL_TIMEOUT;
+    loop {
+        match sink.send(frame) {
+            Ok(()) => return Ok(()),
+            Err(e) if is_queue_full(e.as_ref()) && Instant::now() < deadline => {
+                thread::sleep(Duration::from_micros(100))
+            }
+            Err(e) => return Err(format!("Failed to send frame 0x{:X}: {}", frame.id, e)),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref REPLAY_REGISTRY: Arc<Mutex<HashMap<u32, Replayer>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Start a replayer on sockets and register it, returning its handle
+pub fn start_replayer(
+    frames: Vec<TraceFrame>,
+    targets: ReplayTargets<CanSocketWrapper>,
+    options: ReplayOptions,
+) -> Result<u32, String> {
+    let replayer = Replayer::start(frames, targets, options)?;
+    let id = crate::next_handle_id();
+    REPLAY_REGISTRY.lock().unwrap().insert(id, replayer);
+    Ok(id)
+}
+
+fn status_to_js<'a, C: Context<'a>>(cx: &mut C, status: &ReplayStatus) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let state = cx.string(match status.state {
+        ReplayState::Running => "running",
+        ReplayState::Paused => "paused",
+        ReplayState::Finished => "finished",
+    });
+    obj.set(cx, "state", state)?;
+    let position = cx.number(status.position.as_secs_f64());
+    obj.set(cx, "position", position)?;
+    let index = cx.number(status.index as f64);
+    obj.set(cx, "index", index)?;
+    let sent = cx.number(status.sent as f64);
+    obj.set(cx, "sent", sent)?;
+    let skipped = cx.number(status.skipped as f64);
+    obj.set(cx, "skipped", skipped)?;
+    let passes = cx.number(status.passes);
+    obj.set(cx, "passes", passes)?;
+    if let Some(error) = &status.error {
+        let error = cx.string(error);
+        obj.set(cx, "error", error)?;
+    }
+    Ok(obj)
+}
+
+/// Run `f` on a registered replayer from JavaScript
+fn with_replayer<'a, T>(
+    cx: &mut FunctionContext<'a>,
+    f: impl FnOnce(&mut FunctionContext<'a>, &Replayer) -> NeonResult<T>,
+) -> NeonResult<T> {
+    let replay_id = cx.argument::<JsNumber>(0)?.value(cx) as u32;
+    let registry = REPLAY_REGISTRY.lock().unwrap();
+    match registry.get(&replay_id) {
+        Some(replayer) => f(cx, replayer),
+        None => cx.throw_error("Invalid replay ID"),
+    }
+}
+
+/// Pause a replay from JavaScript
+pub fn pause_replay(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    with_replayer(&mut cx, |_, replayer| {
+        replayer.pause();
+        Ok(())
+    })?;
+    Ok(cx.undefined())
+}
+
+/// Resume a paused replay from JavaScript
+pub fn resume_replay(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    with_replayer(&mut cx, |_, replayer| {
+        replayer.resume();
+        Ok(())
+    })?;
+    Ok(cx.undefined())
+}
+
+/// Move a replay to a trace time in seconds from JavaScript
+pub fn seek_replay(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let seconds = cx.argument::<JsNumber>(1)?.value(&mut cx);
+    let Ok(position) = Duration::try_from_secs_f64(seconds) else {
+        return cx.throw_error(format!("Invalid replay position {}", seconds));
+    };
+    with_replayer(&mut cx, |_, replayer| {
+        replayer.seek(position);
+        Ok(())
+    })?;
+    Ok(cx.undefined())
+}
+
+/// Change the speed factor of a replay from JavaScript
+pub fn set_replay_speed(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let speed = cx.argument::<JsNumber>(1)?.value(&mut cx);
+    with_replayer(&mut cx, |cx, replayer| match replayer.set_speed(speed) {
+        Ok(()) => Ok(()),
+        Err(e) => cx.throw_error(e),
+    })?;
+    Ok(cx.undefined())
+}
+
+/// Get the progress of a replay from JavaScript
+pub fn get_replay_status(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let status = with_replayer(&mut cx, |_, replayer| Ok(replayer.status()))?;
+    status_to_js(&mut cx, &status)
+}
+
+/// Stop a replay from JavaScript, returning its final status
+pub fn stop_replay(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let replay_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(replayer) = REPLAY_REGISTRY.lock().unwrap().remove(&replay_id) else {
+        return cx.throw_error("Invalid replay ID");
+    };
+    let status = replayer.stop();
+    status_to_js(&mut cx, &status)
+}
@@ -0,0 +1,903 @@
+//! Signal database model shared by the DBC, ARXML and KCD loaders, with
+//! frame decoding and encoding
+//!
+//! Messages and signals follow the DBC semantics: Intel/Motorola bit
+//! numbering, factor/offset scaling and (extended) multiplexing. The other
+//! formats are converted to it on load.
+
+use neon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::next_handle_id;
+
+/// Bit order of a signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Intel: start bit is the least significant bit
+    LittleEndian,
+    /// Motorola: start bit is the most significant bit (DBC sawtooth numbering)
+    BigEndian,
+}
+
+/// Encoding of the raw signal value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Unsigned,
+    Signed,
+    Float32,
+    Float64,
+}
+
+/// Role of a signal in a multiplexed message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplex {
+    None,
+    /// Multiplexor switch (`M`)
+    Multiplexor,
+    /// Present when the multiplexor equals the value (`m<n>`)
+    Multiplexed(u64),
+    /// Multiplexed signal that is itself a multiplexor (`m<n>M`)
+    MultiplexedMultiplexor(u64),
+}
+
+/// Extended multiplexing condition (`SG_MUL_VAL_`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MuxCondition {
+    pub multiplexor: String,
+    pub ranges: Vec<(u64, u64)>,
+}
+
+/// Attribute value (`BA_`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Number(f64),
+    Text(String),
+}
+
+impl AttributeValue {
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            AttributeValue::Number(v) => Some(*v),
+            AttributeValue::Text(v) => v.parse().ok(),
+        }
+    }
+}
+
+/// Attribute holding the raw value sent when a signal is not given
+pub const GEN_SIG_START_VALUE: &str = "GenSigStartValue";
+
+/// Bytes of the largest CAN FD payload, the largest message size
+pub const MAX_MESSAGE_SIZE: u32 = 64;
+
+/// Bits of the largest CAN FD payload, signals start below it
+pub const MAX_SIGNAL_BITS: u32 = MAX_MESSAGE_SIZE * 8;
+
+/// Signal definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub name: String,
+    pub start_bit: u32,
+    pub length: u32,
+    pub byte_order: ByteOrder,
+    pub value_type: ValueType,
+    pub factor: f64,
+    pub offset: f64,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub unit: String,
+    pub receivers: Vec<String>,
+    pub multiplex: Multiplex,
+    /// Extended multiplexing conditions, replacing the simple `m<n>` rule
+    pub mux_conditions: Vec<MuxCondition>,
+    pub value_table: BTreeMap<i64, String>,
+    pub comment: Option<String>,
+    pub attributes: HashMap<String, AttributeValue>,
+}
+
+impl Signal {
+    /// Signal with default scaling, for loaders that fill fields one by one
+    pub fn new(name: &str, start_bit: u32, length: u32, byte_order: ByteOrder) -> Self {
+        Self {
+            name: name.to_string(),
+            start_bit,
+            length,
+            byte_order,
+            value_type: ValueType::Unsigned,
+            factor: 1.0,
+            offset: 0.0,
+            minimum: 0.0,
+            maximum: 0.0,
+            unit: String::new(),
+            receivers: Vec::new(),
+            multiplex: Multiplex::None,
+            mux_conditions: Vec::new(),
+            value_table: BTreeMap::new(),
+            comment: None,
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Raw value as stored in the frame, `None` if the frame is too short
+    pub fn raw_bits(&self, data: &[u8]) -> Option<u64> {
+        if self.length == 0 || self.length > 64 {
+            return None;
+        }
+        match self.byte_order {
+            ByteOrder::LittleEndian => {
+                if self.start_bit.checked_add(self.length)? > data.len() as u32 * 8 {
+                    return None;
+                }
+                let mut raw = 0u64;
+                for i in 0..self.length {
+                    let bit = self.start_bit + i;
+                    if data[(bit / 8) as usize] >> (bit % 8) & 1 != 0 {
+                        raw |= 1 << i;
+                    }
+                }
+                Some(raw)
+            }
+            ByteOrder::BigEndian => {
+                let mut raw = 0u64;
+                let mut pos = self.start_bit;
+                for i in 0..self.length {
+                    let byte = *data.get((pos / 8) as usize)?;
+                    raw = (raw << 1) | (byte >> (pos % 8) & 1) as u64;
+                    if i + 1 < self.length {
+                        pos = if pos.is_multiple_of(8) {
+                            pos + 15
+                        } else {
+                            pos - 1
+                        };
+                    }
+                }
+                Some(raw)
+            }
+        }
+    }
+
+    /// Raw value interpreted according to the value type
+    pub fn raw_value(&self, bits: u64) -> f64 {
+        match self.value_type {
+            ValueType::Unsigned => bits as f64,
+            ValueType::Signed => {
+                let shift = 64 - self.length;
+                (((bits << shift) as i64) >> shift) as f64
+            }
+            ValueType::Float32 => f32::from_bits(bits as u32) as f64,
+            ValueType::Float64 => f64::from_bits(bits),
+        }
+    }
+
+    /// Physical value of a raw value
+    pub fn physical(&self, raw: f64) -> f64 {
+        raw * self.factor + self.offset
+    }
+
+    /// Whether the signal is a multiplexor switch
+    pub fn is_multiplexor(&self) -> bool {
+        matches!(
+            self.multiplex,
+            Multiplex::Multiplexor | Multiplex::MultiplexedMultiplexor(_)
+        )
+    }
+
+    fn mask(&self) -> u64 {
+        if self.length >= 64 {
+            u64::MAX
+        } else {
+            (1 << self.length) - 1
+        }
+    }
+
+    /// Raw bits of a physical value, checked against the signal range
+    pub fn encode_raw(&self, physical: f64) -> Result<u64, String> {
+        if !physical.is_finite() {
+            return Err(format!("{}: value must be a finite number", self.name));
+        }
+        // [0|0] signifie "pas de plage" dans les DBC
+        if self.minimum != 0.0 || self.maximum != 0.0 {
+            let tolerance = 1e-9 * self.minimum.abs().max(self.maximum.abs()).max(1.0);
+            if physical < self.minimum - tolerance || physical > self.maximum + tolerance {
+                return Err(format!(
+                    "{}: value {} out of range [{}, {}]",
+                    self.name, physical, self.minimum, self.maximum
+                ));
+            }
+        }
+        if self.factor == 0.0 {
+            return Err(format!("{}: factor is zero", self.name));
+        }
+
+        let raw = (physical - self.offset) / self.factor;
+        match self.value_type {
+            ValueType::Float32 => Ok((raw as f32).to_bits() as u64),
+            ValueType::Float64 => Ok(raw.to_bits()),
+            ValueType::Unsigned => {
+                let raw = raw.round();
+                if raw < 0.0 || raw > self.mask() as f64 {
+                    return Err(format!(
+                        "{}: value {} does not fit in {} unsigned bits",
+                        self.name, physical, self.length
+                    ));
+                }
+                Ok(raw as u64)
+            }
+            ValueType::Signed => {
+                let raw = raw.round();
+                let half = 2f64.powi(self.length as i32 - 1);
+                if raw < -half || raw > half - 1.0 {
+                    return Err(format!(
+                        "{}: value {} does not fit in {} signed bits",
+                        self.name, physical, self.length
+                    ));
+                }
+                Ok(raw as i64 as u64 & self.mask())
+            }
+        }
+    }
+
+    /// Raw bits sent when no value is given (`GenSigStartValue`, else 0)
+    pub fn start_raw(&self, defaults: &HashMap<String, AttributeValue>) -> u64 {
+        let start = self
+            .attributes
+            .get(GEN_SIG_START_VALUE)
+            .or_else(|| defaults.get(GEN_SIG_START_VALUE))
+            .and_then(AttributeValue::as_number)
+            .unwrap_or(0.0);
+        match self.value_type {
+            ValueType::Float32 => (start as f32).to_bits() as u64,
+            ValueType::Float64 => start.to_bits(),
+            ValueType::Unsigned | ValueType::Signed => start as i64 as u64 & self.mask(),
+        }
+    }
+
+    /// Store raw bits in `data`, the reverse of [`Signal::raw_bits`]
+    pub fn write_bits(&self, data: &mut [u8], raw: u64) -> Result<(), String> {
+        let too_short = || format!("{}: signal does not fit in the message", self.name);
+        match self.byte_order {
+            ByteOrder::LittleEndian => {
+                let end = self
+                    .start_bit
+                    .checked_add(self.length)
+                    .ok_or_else(too_short)?;
+                if end > data.len() as u32 * 8 {
+                    return Err(too_short());
+                }
+                for i in 0..self.length {
+                    let bit = self.start_bit + i;
+                    let mask = 1 << (bit % 8);
+                    if raw >> i & 1 != 0 {
+                        data[(bit / 8) as usize] |= mask;
+                    } else {
+                        data[(bit / 8) as usize] &= !mask;
+                    }
+                }
+            }
+            ByteOrder::BigEndian => {
+                let mut pos = self.start_bit;
+                for i in 0..self.length {
+                    let byte = data.get_mut((pos / 8) as usize).ok_or_else(too_short)?;
+                    let mask = 1 << (pos % 8);
+                    if raw >> (self.length - 1 - i) & 1 != 0 {
+                        *byte |= mask;
+                    } else {
+                        *byte &= !mask;
+                    }
+                    if i + 1 < self.length {
+                        pos = if pos.is_multiple_of(8) {
+                            pos + 15
+                        } else {
+                            pos - 1
+                        };
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Message definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// Identifier without the DBC extended flag
+    pub id: u32,
+    pub extended: bool,
+    pub name: String,
+    /// Payload length in bytes
+    pub size: u32,
+    pub transmitter: Option<String>,
+    pub signals: Vec<Signal>,
+    pub comment: Option<String>,
+    pub attributes: HashMap<String, AttributeValue>,
+}
+
+impl Message {
+    pub fn signal(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|s| s.name == name)
+    }
+
+    /// Whether `signal` is present given the raw multiplexor values of the frame
+    fn is_active(&self, signal: &Signal, raw: &HashMap<&str, u64>, depth: u32) -> bool {
+        if depth > 16 {
+            return false;
+        }
+        if !signal.mux_conditions.is_empty() {
+            return signal.mux_conditions.iter().all(|condition| {
+                let Some(mux) = self.signal(&condition.multiplexor) else {
+                    return false;
+                };
+                let Some(&value) = raw.get(mux.name.as_str()) else {
+                    return false;
+                };
+                self.is_active(mux, raw, depth + 1)
+                    && condition
+                        .ranges
+                        .iter()
+                        .any(|&(low, high)| (low..=high).contains(&value))
+            });
+        }
+        match signal.multiplex {
+            Multiplex::None | Multiplex::Multiplexor => true,
+            Multiplex::Multiplexed(value) | Multiplex::MultiplexedMultiplexor(value) => self
+                .signals
+                .iter()
+                .find(|s| s.multiplex == Multiplex::Multiplexor)
+                .and_then(|mux| raw.get(mux.name.as_str()))
+                .is_some_and(|&raw| raw == value),
+        }
+    }
+
+    /// Build the payload from physical values
+    ///
+    /// Signals that are not given use their start value. Multiplexor values
+    /// select which multiplexed signals are written.
+    pub fn encode(
+        &self,
+        values: &HashMap<String, f64>,
+        defaults: &HashMap<String, AttributeValue>,
+    ) -> Result<Vec<u8>, String> {
+        if let Some(name) = values.keys().find(|name| self.signal(name).is_none()) {
+            return Err(format!("message {} has no signal {}", self.name, name));
+        }
+
+        let mut raw_values = HashMap::new();
+        for signal in &self.signals {
+            let raw = match values.get(&signal.name) {
+                Some(&value) => signal.encode_raw(value)?,
+                None => signal.start_raw(defaults),
+            };
+            raw_values.insert(signal.name.as_str(), raw);
+        }
+        let muxes: HashMap<&str, u64> = self
+            .signals
+            .iter()
+            .filter(|s| s.is_multiplexor())
+            .map(|s| (s.name.as_str(), raw_values[s.name.as_str()]))
+            .collect();
+
+        let mut data = vec![0u8; self.size as usize];
+        for signal in &self.signals {
+            if !self.is_active(signal, &muxes, 0) {
+                if values.contains_key(&signal.name) {
+                    return Err(format!(
+                        "{}: signal is not present for the selected multiplexor value",
+                        signal.name
+                    ));
+                }
+                continue;
+            }
+            signal.write_bits(&mut data, raw_values[signal.name.as_str()])?;
+        }
+        Ok(data)
+    }
+
+    /// Decode the signals present in `data`
+    pub fn decode(&self, data: &[u8]) -> Vec<DecodedSignal> {
+        let raw: HashMap<&str, u64> = self
+            .signals
+            .iter()
+            .filter(|s| s.is_multiplexor())
+            .filter_map(|s| Some((s.name.as_str(), s.raw_bits(data)?)))
+            .collect();
+
+        self.signals
+            .iter()
+            .filter(|signal| self.is_active(signal, &raw, 0))
+            .filter_map(|signal| {
+                let raw = signal.raw_value(signal.raw_bits(data)?);
+                let label = signal.value_table.get(&(raw as i64)).cloned();
+                Some(DecodedSignal {
+                    name: signal.name.clone(),
+                    raw,
+                    value: signal.physical(raw),
+                    unit: signal.unit.clone(),
+                    label,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Decoded signal value
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedSignal {
+    pub name: String,
+    pub raw: f64,
+    pub value: f64,
+    pub unit: String,
+    /// Value table description of the raw value
+    pub label: Option<String>,
+}
+
+/// Signal database (messages by identifier)
+#[derive(Debug, Clone, Default)]
+pub struct SignalDatabase {
+    pub version: String,
+    pub nodes: Vec<String>,
+    pub messages: Vec<Message>,
+    /// Attribute defaults (`BA_DEF_DEF_`)
+    pub attribute_defaults: HashMap<String, AttributeValue>,
+    index: HashMap<(u32, bool), usize>,
+}
+
+impl SignalDatabase {
+    /// Rebuild the identifier index after messages were added
+    pub fn finish(&mut self) {
+        self.index = self
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| ((m.id, m.extended), i))
+            .collect();
+    }
+
+    pub fn message_by_id(&self, id: u32, extended: bool) -> Option<&Message> {
+        self.index.get(&(id, extended)).map(|&i| &self.messages[i])
+    }
+
+    pub fn message_by_name(&self, name: &str) -> Option<&Message> {
+        self.messages.iter().find(|m| m.name == name)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SIGNAL_DB_REGISTRY: Arc<Mutex<HashMap<u32, Arc<SignalDatabase>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+pub(crate) fn get_signal_db(db_id: u32) -> Option<Arc<SignalDatabase>> {
+    SIGNAL_DB_REGISTRY.lock().unwrap().get(&db_id).cloned()
+}
+
+fn register_db(db: SignalDatabase) -> u32 {
+    let id = next_handle_id();
+    SIGNAL_DB_REGISTRY.lock().unwrap().insert(id, Arc::new(db));
+    id
+}
+
+/// Frame built from a database message
+pub(crate) struct EncodedFrame {
+    pub id: u32,
+    pub extended: bool,
+    pub fd: bool,
+    pub data: Vec<u8>,
+}
+
+/// Find a message by name in `db_id`, or in the loaded database defining it
+fn find_message(db_id: Option<u32>, name: &str) -> Result<(Arc<SignalDatabase>, usize), String> {
+    let candidates: Vec<Arc<SignalDatabase>> = match db_id {
+        Some(db_id) => vec![get_signal_db(db_id).ok_or("Invalid DBC handle")?],
+        None => SIGNAL_DB_REGISTRY
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|db| db.message_by_name(name).is_some())
+            .cloned()
+            .collect(),
+    };
+    if candidates.len() > 1 {
+        return Err(format!(
+            "message {} is defined in several databases, pass a DBC handle",
+            name
+        ));
+    }
+    candidates
+        .into_iter()
+        .find_map(|db| {
+            let index = db.messages.iter().position(|m| m.name == name)?;
+            Some((db, index))
+        })
+        .ok_or_else(|| format!("unknown message {}", name))
+}
+
+/// Encode a JS object of signal values (numbers, booleans or value table
+/// labels) for a message, throwing on errors
+pub(crate) fn encode_message_values<'a, C: Context<'a>>(
+    cx: &mut C,
+    db_id: Option<u32>,
+    name: &str,
+    values: Handle<JsObject>,
+) -> NeonResult<EncodedFrame> {
+    let (db, index) = match find_message(db_id, name) {
+        Ok(found) => found,
+        Err(e) => return cx.throw_error(format!("Failed to encode message: {}", e)),
+    };
+    let message = &db.messages[index];
+
+    let mut physical = HashMap::new();
+    let keys = values.get_own_property_names(cx)?;
+    for i in 0..keys.len(cx) {
+        let key = keys.get::<JsString, _, _>(cx, i)?.value(cx);
+        let value = values.get_value(cx, key.as_str())?;
+        let number = if let Ok(number) = value.downcast::<JsNumber, _>(cx) {
+            number.value(cx)
+        } else if let Ok(flag) = value.downcast::<JsBoolean, _>(cx) {
+            if flag.value(cx) {
+                1.0
+            } else {
+                0.0
+            }
+        } else if let Ok(label) = value.downcast::<JsString, _>(cx) {
+            let label = label.value(cx);
+            let signal = message.signal(&key);
+            let raw = signal.and_then(|signal| {
+                signal
+                    .value_table
+                    .iter()
+                    .find(|(_, l)| **l == label)
+                    .map(|(raw, _)| signal.physical(*raw as f64))
+            });
+            match raw {
+                Some(raw) => raw,
+                None => {
+                    return cx.throw_error(format!(
+                        "Failed to encode message: {}: unknown value '{}'",
+                        key, label
+                    ))
+                }
+            }
+        } else {
+            return cx.throw_error(format!(
+                "Failed to encode message: {}: expected a number, boolean or label",
+                key
+            ));
+        };
+        physical.insert(key, number);
+    }
+
+    match message.encode(&physical, &db.attribute_defaults) {
+        Ok(data) => Ok(EncodedFrame {
+            id: message.id,
+            extended: message.extended,
+            fd: data.len() > 8,
+            data,
+        }),
+        Err(e) => cx.throw_error(format!("Failed to encode message: {}", e)),
+    }
+}
+
+/// Build the frame of a message from physical signal values
+pub fn encode_message(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let db_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let name = cx.argument::<JsString>(1)?.value(&mut cx);
+    let values = cx.argument::<JsObject>(2)?;
+
+    let frame = encode_message_values(&mut cx, Some(db_id), &name, values)?;
+
+    let obj = cx.empty_object();
+    let id_val = cx.number(frame.id as f64);
+    obj.set(&mut cx, "id", id_val)?;
+
+    let data = cx.empty_array();
+    for (i, byte) in frame.data.iter().enumerate() {
+        let byte_val = cx.number(*byte as f64);
+        data.set(&mut cx, i as u32, byte_val)?;
+    }
+    obj.set(&mut cx, "data", data)?;
+
+    let extended_val = cx.boolean(frame.extended);
+    obj.set(&mut cx, "extended", extended_val)?;
+
+    let fd_val = cx.boolean(frame.fd);
+    obj.set(&mut cx, "fd", fd_val)?;
+    Ok(obj)
+}
+
+/// Read a database file, tolerating non UTF-8 content
+fn read_text(path: &str) -> Result<String, String> {
+    // Les DBC sont souvent en Windows-1252 : lecture tolérante
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(String::from_utf8(bytes)
+            .unwrap_or_else(|e| e.into_bytes().iter().map(|&b| b as char).collect())),
+        Err(e) => Err(format!("{}: {}", path, e)),
+    }
+}
+
+/// Parse a database in the given format (`dbc`, `arxml` or `kcd`)
+fn parse_database(text: &str, format: &str, bus: Option<&str>) -> Result<SignalDatabase, String> {
+    match format {
+        "dbc" => crate::dbc::parse(text),
+        "arxml" => crate::arxml::parse(text),
+        "kcd" => crate::kcd::parse(text, bus),
+        other => Err(format!("unknown database format '{}'", other)),
+    }
+}
+
+/// Read the optional `{ format, bus }` options argument
+fn database_options(
+    cx: &mut FunctionContext,
+    index: usize,
+) -> NeonResult<(Option<String>, Option<String>)> {
+    let Some(options) = cx
+        .argument_opt(index)
+        .and_then(|arg| arg.downcast::<JsObject, _>(cx).ok())
+    else {
+        return Ok((None, None));
+    };
+    let format = options
+        .get_opt::<JsString, _, _>(cx, "format")?
+        .map(|v| v.value(cx));
+    let bus = options
+        .get_opt::<JsString, _, _>(cx, "bus")?
+        .map(|v| v.value(cx));
+    Ok((format, bus))
+}
+
+/// Load a DBC file
+pub fn load_dbc(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    let text = match read_text(&path) {
+        Ok(text) => text,
+        Err(e) => return cx.throw_error(format!("Failed to read DBC file {}", e)),
+    };
+    match crate::dbc::parse(&text) {
+        Ok(db) => Ok(cx.number(register_db(db) as f64)),
+        Err(e) => cx.throw_error(format!("Failed to parse DBC file: {}", e)),
+    }
+}
+
+/// Parse DBC content from a string
+pub fn parse_dbc(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let text = cx.argument::<JsString>(0)?.value(&mut cx);
+    match crate::dbc::parse(&text) {
+        Ok(db) => Ok(cx.number(register_db(db) as f64)),
+        Err(e) => cx.throw_error(format!("Failed to parse DBC: {}", e)),
+    }
+}
+
+/// Load a DBC, ARXML or KCD file (format taken from the extension by default)
+pub fn load_signal_database(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let (format, bus) = database_options(&mut cx, 1)?;
+
+    let format = format.unwrap_or_else(|| {
+        std::path::Path::new(&path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    });
+    let text = match read_text(&path) {
+        Ok(text) => text,
+        Err(e) => return cx.throw_error(format!("Failed to read signal database {}", e)),
+    };
+    match parse_database(&text, &format, bus.as_deref()) {
+        Ok(db) => Ok(cx.number(register_db(db) as f64)),
+        Err(e) => cx.throw_error(format!("Failed to parse signal database: {}", e)),
+    }
+}
+
+/// Parse a DBC, ARXML or KCD database from a string
+pub fn parse_signal_database(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let text = cx.argument::<JsString>(0)?.value(&mut cx);
+    let (format, bus) = database_options(&mut cx, 1)?;
+
+    let Some(format) = format else {
+        return cx.throw_error("Failed to parse signal database: missing format");
+    };
+    match parse_database(&text, &format, bus.as_deref()) {
+        Ok(db) => Ok(cx.number(register_db(db) as f64)),
+        Err(e) => cx.throw_error(format!("Failed to parse signal database: {}", e)),
+    }
+}
+
+fn byte_order_name(order: ByteOrder) -> &'static str {
+    match order {
+        ByteOrder::LittleEndian => "little_endian",
+        ByteOrder::BigEndian => "big_endian",
+    }
+}
+
+fn value_type_name(value_type: ValueType) -> &'static str {
+    match value_type {
+        ValueType::Unsigned => "unsigned",
+        ValueType::Signed => "signed",
+        ValueType::Float32 => "float32",
+        ValueType::Float64 => "float64",
+    }
+}
+
+fn signal_to_js<'a>(cx: &mut FunctionContext<'a>, signal: &Signal) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+
+    let name_val = cx.string(&signal.name);
+    obj.set(cx, "name", name_val)?;
+
+    let start_val = cx.number(signal.start_bit as f64);
+    obj.set(cx, "startBit", start_val)?;
+
+    let length_val = cx.number(signal.length as f64);
+    obj.set(cx, "length", length_val)?;
+
+    let order_val = cx.string(byte_order_name(signal.byte_order));
+    obj.set(cx, "byteOrder", order_val)?;
+
+    let type_val = cx.string(value_type_name(signal.value_type));
+    obj.set(cx, "valueType", type_val)?;
+
+    let factor_val = cx.number(signal.factor);
+    obj.set(cx, "factor", factor_val)?;
+
+    let offset_val = cx.number(signal.offset);
+    obj.set(cx, "offset", offset_val)?;
+
+    let min_val = cx.number(signal.minimum);
+    obj.set(cx, "minimum", min_val)?;
+
+    let max_val = cx.number(signal.maximum);
+    obj.set(cx, "maximum", max_val)?;
+
+    let unit_val = cx.string(&signal.unit);
+    obj.set(cx, "unit", unit_val)?;
+
+    let receivers = cx.empty_array();
+    for (i, receiver) in signal.receivers.iter().enumerate() {
+        let receiver_val = cx.string(receiver);
+        receivers.set(cx, i as u32, receiver_val)?;
+    }
+    obj.set(cx, "receivers", receivers)?;
+
+    match signal.multiplex {
+        Multiplex::None => {}
+        Multiplex::Multiplexor => {
+            let flag = cx.boolean(true);
+            obj.set(cx, "isMultiplexor", flag)?;
+        }
+        Multiplex::Multiplexed(value) => {
+            let value_val = cx.number(value as f64);
+            obj.set(cx, "multiplexerValue", value_val)?;
+        }
+        Multiplex::MultiplexedMultiplexor(value) => {
+            let flag = cx.boolean(true);
+            obj.set(cx, "isMultiplexor", flag)?;
+            let value_val = cx.number(value as f64);
+            obj.set(cx, "multiplexerValue", value_val)?;
+        }
+    }
+
+    if !signal.value_table.is_empty() {
+        let table = cx.empty_object();
+        for (value, label) in &signal.value_table {
+            let label_val = cx.string(label);
+            table.set(cx, value.to_string().as_str(), label_val)?;
+        }
+        obj.set(cx, "choices", table)?;
+    }
+
+    if let Some(comment) = &signal.comment {
+        let comment_val = cx.string(comment);
+        obj.set(cx, "comment", comment_val)?;
+    }
+    Ok(obj)
+}
+
+/// Describe the messages and signals of a database
+pub fn get_dbc_messages(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let db_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(db) = get_signal_db(db_id) else {
+        return cx.throw_error("Invalid DBC handle");
+    };
+
+    let result = cx.empty_array();
+    for (i, message) in db.messages.iter().enumerate() {
+        let obj = cx.empty_object();
+
+        let name_val = cx.string(&message.name);
+        obj.set(&mut cx, "name", name_val)?;
+
+        let id_val = cx.number(message.id as f64);
+        obj.set(&mut cx, "id", id_val)?;
+
+        let extended_val = cx.boolean(message.extended);
+        obj.set(&mut cx, "extended", extended_val)?;
+
+        let size_val = cx.number(message.size as f64);
+        obj.set(&mut cx, "size", size_val)?;
+
+        if let Some(transmitter) = &message.transmitter {
+            let transmitter_val = cx.string(transmitter);
+            obj.set(&mut cx, "transmitter", transmitter_val)?;
+        }
+
+        if let Some(comment) = &message.comment {
+            let comment_val = cx.string(comment);
+            obj.set(&mut cx, "comment", comment_val)?;
+        }
+
+        let signals = cx.empty_array();
+        for (j, signal) in message.signals.iter().enumerate() {
+            let signal_obj = signal_to_js(&mut cx, signal)?;
+            signals.set(&mut cx, j as u32, signal_obj)?;
+        }
+        obj.set(&mut cx, "signals", signals)?;
+
+        result.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(result)
+}
+
+/// Decode a frame `{ id, data, extended? }` into physical signal values
+///
+/// Returns `null` when the database has no message with this identifier.
+pub fn decode_frame(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let db_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let frame = cx.argument::<JsObject>(1)?;
+    let id = frame.get::<JsNumber, _, _>(&mut cx, "id")?.value(&mut cx) as u32;
+    let extended = match frame.get_opt::<JsBoolean, _, _>(&mut cx, "extended")? {
+        Some(v) => v.value(&mut cx),
+        None => id > 0x7FF,
+    };
+    let data_array = frame.get::<JsArray, _, _>(&mut cx, "data")?;
+    let mut data = Vec::new();
+    for i in 0..data_array.len(&mut cx) {
+        let val = data_array.get::<JsNumber, _, _>(&mut cx, i)?.value(&mut cx) as u8;
+        data.push(val);
+    }
+
+    let Some(db) = get_signal_db(db_id) else {
+        return cx.throw_error("Invalid DBC handle");
+    };
+    let Some(message) = db.message_by_id(id, extended) else {
+        return Ok(cx.null().upcast());
+    };
+
+    let obj = cx.empty_object();
+    let name_val = cx.string(&message.name);
+    obj.set(&mut cx, "name", name_val)?;
+    let id_val = cx.number(message.id as f64);
+    obj.set(&mut cx, "id", id_val)?;
+
+    let signals = cx.empty_object();
+    for decoded in message.decode(&data) {
+        let signal_obj = cx.empty_object();
+
+        let value_val = cx.number(decoded.value);
+        signal_obj.set(&mut cx, "value", value_val)?;
+
+        let raw_val = cx.number(decoded.raw);
+        signal_obj.set(&mut cx, "raw", raw_val)?;
+
+        let unit_val = cx.string(&decoded.unit);
+        signal_obj.set(&mut cx, "unit", unit_val)?;
+
+        if let Some(label) = &decoded.label {
+            let label_val = cx.string(label);
+            signal_obj.set(&mut cx, "label", label_val)?;
+        }
+
+        signals.set(&mut cx, decoded.name.as_str(), signal_obj)?;
+    }
+    obj.set(&mut cx, "signals", signals)?;
+    Ok(obj.upcast())
+}
+
+/// Release a database
+pub fn close_dbc(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let db_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match SIGNAL_DB_REGISTRY.lock().unwrap().remove(&db_id) {
+        Some(_) => Ok(cx.undefined()),
+        None => cx.throw_error("Invalid DBC handle"),
+    }
+}
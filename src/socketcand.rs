@@ -0,0 +1,441 @@
+//! socketcand protocol client
+//!
+//! socketcand exposes remote CAN buses over TCP with an ASCII protocol of
+//! `< command args >` messages. The client opens a bus, then either
+//! receives every frame (raw mode) or only the frames selected by
+//! broadcast manager filters (BCM mode, the server default). It backs the
+//! remote variant of `CanSocketWrapper`, so the frame API is the same as
+//! for local sockets.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::trace::TraceFrame;
+
+const CAN_EFF_MASK: u32 = 0x1FFF_FFFF;
+/// Longest unterminated message accepted from the server
+const MAX_PENDING: usize = 4096;
+/// Longest wait of `recv` on the connection, so that commands get the
+/// reader in between
+const RECV_POLL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SocketcandError {
+    #[error("socketcand error: {0}")]
+    Server(String),
+    #[error("Unexpected socketcand reply: {0}")]
+    Protocol(String),
+    #[error("No reply from the socketcand server")]
+    Timeout,
+    #[error("{0} is not supported by socketcand")]
+    Unsupported(&'static str),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Reception mode after `open`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// All frames of the bus
+    Raw,
+    /// Frames selected by `filter`/`subscribe` commands
+    Bcm,
+}
+
+/// Broadcast manager filter (BCM mode)
+///
+/// With a data `mask`, the server reports the frames whose masked content
+/// changed (`filter`); without, every frame of the identifier (`subscribe`).
+/// A non-zero `interval` throttles the reports.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BcmFilter {
+    pub id: u32,
+    pub extended: bool,
+    pub interval: Duration,
+    pub mask: Option<Vec<u8>>,
+}
+
+/// Server message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Hi,
+    Ok,
+    Echo,
+    /// Received data or error frame
+    Frame(TraceFrame),
+    /// Error report of the server
+    Error(String),
+    Other(String),
+}
+
+/// Split the next `< ... >` message off `buf`, without the brackets
+pub fn take_message(buf: &mut Vec<u8>) -> Option<String> {
+    let Some(start) = buf.iter().position(|&b| b == b'<') else {
+        buf.clear();
+        return None;
+    };
+    let end = start + buf[start..].iter().position(|&b| b == b'>')?;
+    let text = String::from_utf8_lossy(&buf[start + 1..end])
+        .trim()
+        .to_string();
+    buf.drain(..=end);
+    Some(text)
+}
+
+fn parse_timestamp(text: &str) -> Option<Duration> {
+    let (secs, micros) = text.split_once('.')?;
+    Duration::from_secs(secs.parse().ok()?).checked_add(Duration::from_micros(micros.parse().ok()?))
+}
+
+/// `ID SECS.USECS DATA` of a `frame` or `error` message
+fn parse_frame(tokens: &[&str], error: bool) -> Option<TraceFrame> {
+    let (&id_text, rest) = tokens.split_first()?;
+    let (&timestamp, data) = rest.split_first()?;
+    let id = u32::from_str_radix(id_text, 16).ok()?;
+    // Les données sont envoyées d'un bloc, certaines versions les séparent
+    let digits: Vec<u8> = data.iter().flat_map(|token| token.bytes()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    let data = digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    Some(TraceFrame {
+        timestamp: parse_timestamp(timestamp)?,
+        id: id & CAN_EFF_MASK,
+        extended: !error && (id_text.len() > 3 || id > 0x7FF),
+        error,
+        data,
+        ..Default::default()
+    })
+}
+
+pub fn parse_message(text: &str) -> Message {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    match tokens.split_first() {
+        Some((&"hi", _)) => Message::Hi,
+        Some((&"ok", _)) => Message::Ok,
+        Some((&"echo", _)) => Message::Echo,
+        Some((&"frame", rest)) => match parse_frame(rest, false) {
+            Some(frame) => Message::Frame(frame),
+            None => Message::Other(text.to_string()),
+        },
+        // Trames d'erreur et rapports d'erreur partagent le mot-clé
+        Some((&"error", rest)) => match parse_frame(rest, true) {
+            Some(frame) => Message::Frame(frame),
+            None => Message::Error(rest.join(" ")),
+        },
+        _ => Message::Other(text.to_string()),
+    }
+}
+
+/// Identifier as socketcand expects it: 8 hex digits mark extended frames
+fn format_id(id: u32, extended: bool) -> String {
+    if extended {
+        format!("{:08X}", id)
+    } else {
+        format!("{:03X}", id)
+    }
+}
+
+/// `frame` message of a received frame, `error` message of an error frame
+pub fn format_frame(frame: &TraceFrame) -> String {
+    let (keyword, id) = if frame.error {
+        ("error", format!("{:03X}", frame.id))
+    } else {
+        ("frame", format_id(frame.id, frame.extended))
+    };
+    let mut text = format!(
+        "< {} {} {}.{:06} ",
+        keyword,
+        id,
+        frame.timestamp.as_secs(),
+        frame.timestamp.subsec_micros()
+    );
+    for byte in &frame.data {
+        let _ = write!(text, "{:02X}", byte);
+    }
+    text.push_str(" >");
+    text
+}
+
+/// `send` command of a classic data frame
+pub fn format_send(frame: &TraceFrame) -> Result<String, SocketcandError> {
+    if frame.fd || frame.xl.is_some() {
+        return Err(SocketcandError::Unsupported("CAN FD"));
+    }
+    if frame.remote {
+        return Err(SocketcandError::Unsupported("Sending remote frames"));
+    }
+    if frame.error {
+        return Err(SocketcandError::Unsupported("Sending error frames"));
+    }
+    if frame.data.len() > 8 {
+        return Err(SocketcandError::Protocol(
+            "Data too long for regular CAN frame (max 8 bytes)".to_string(),
+        ));
+    }
+    let mut text = format!(
+        "< send {} {}",
+        format_id(frame.id, frame.extended),
+        frame.data.len()
+    );
+    for byte in &frame.data {
+        let _ = write!(text, " {:02X}", byte);
+    }
+    text.push_str(" >");
+    Ok(text)
+}
+
+/// `filter` or `subscribe` command of a BCM filter
+pub fn format_filter(filter: &BcmFilter) -> String {
+    let id = format_id(filter.id, filter.extended);
+    let (secs, micros) = (filter.interval.as_secs(), filter.interval.subsec_micros());
+    match &filter.mask {
+        Some(mask) => {
+            let mut text = format!("< filter {} {} {} {}", secs, micros, id, mask.len());
+            for byte in mask {
+                let _ = write!(text, " {:02X}", byte);
+            }
+            text.push_str(" >");
+            text
+        }
+        None => format!("< subscribe {} {} {} >", secs, micros, id),
+    }
+}
+
+struct Reader {
+    stream: TcpStream,
+    buf: Vec<u8>,
+    /// Frames received while waiting for a reply
+    frames: VecDeque<TraceFrame>,
+}
+
+impl Reader {
+    /// Next message, `None` once `deadline` has passed
+    fn next_message(&mut self, deadline: Option<Instant>) -> io::Result<Option<Message>> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(text) = take_message(&mut self.buf) {
+                return Ok(Some(parse_message(&text)));
+            }
+            // Le tampon ne contient plus qu'un message inachevé
+            if self.buf.len() > MAX_PENDING {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "socketcand message too long",
+                ));
+            }
+            let timeout = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) if !remaining.is_zero() => Some(remaining),
+                    _ => return Ok(None),
+                },
+                None => None,
+            };
+            self.stream.set_read_timeout(timeout)?;
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "socketcand server closed the connection",
+                    ))
+                }
+                Ok(len) => self.buf.extend_from_slice(&chunk[..len]),
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    return Ok(None)
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Wait for the reply to a command, queueing the frames received meanwhile
+    fn reply(&mut self, timeout: Duration) -> Result<Message, SocketcandError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.next_message(Some(deadline))? {
+                Some(Message::Frame(frame)) => self.frames.push_back(frame),
+                Some(message) => return Ok(message),
+                None => return Err(SocketcandError::Timeout),
+            }
+        }
+    }
+}
+
+fn expect_ok(reply: Message) -> Result<(), SocketcandError> {
+    match reply {
+        Message::Ok => Ok(()),
+        Message::Error(message) => Err(SocketcandError::Server(message)),
+        other => Err(SocketcandError::Protocol(format!("{:?}", other))),
+    }
+}
+
+/// Connection to one bus of a socketcand server
+pub struct SocketcandClient {
+    bus: String,
+    writer: Mutex<TcpStream>,
+    reader: Mutex<Reader>,
+    /// (id, mask) filters applied to received frames, all frames if empty
+    filters: Mutex<Vec<(u32, u32)>>,
+    /// How long commands wait for their reply
+    timeout: Duration,
+}
+
+impl SocketcandClient {
+    /// Connect to `addr`, open `bus` and switch to `mode`
+    pub fn connect(
+        addr: impl ToSocketAddrs,
+        bus: &str,
+        mode: Mode,
+        timeout: Duration,
+    ) -> Result<Self, SocketcandError> {
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "No address for the server")
+        })?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_nodelay(true)?;
+        let client = SocketcandClient {
+            bus: bus.to_string(),
+            writer: Mutex::new(stream.try_clone()?),
+            reader: Mutex::new(Reader {
+                stream,
+                buf: Vec::new(),
+                frames: VecDeque::new(),
+            }),
+            filters: Mutex::new(Vec::new()),
+            timeout,
+        };
+
+        match client.reader.lock().unwrap().reply(timeout)? {
+            Message::Hi => {}
+            Message::Error(message) => return Err(SocketcandError::Server(message)),
+            other => return Err(SocketcandError::Protocol(format!("{:?}", other))),
+        }
+        client.command(&format!("< open {} >", bus))?;
+        if mode == Mode::Raw {
+            client.set_mode(Mode::Raw)?;
+        }
+        Ok(client)
+    }
+
+    pub fn bus(&self) -> &str {
+        &self.bus
+    }
+
+    fn write(&self, text: &str) -> io::Result<()> {
+        self.writer.lock().unwrap().write_all(text.as_bytes())
+    }
+
+    /// Send a command acknowledged by `< ok >`
+    fn command(&self, text: &str) -> Result<(), SocketcandError> {
+        let mut reader = self.reader.lock().unwrap();
+        self.write(text)?;
+        expect_ok(reader.reply(self.timeout)?)
+    }
+
+    /// Switch between raw (`rawmode`) and BCM (`bcmmode`) reception
+    pub fn set_mode(&self, mode: Mode) -> Result<(), SocketcandError> {
+        self.command(match mode {
+            Mode::Raw => "< rawmode >",
+            Mode::Bcm => "< bcmmode >",
+        })
+    }
+
+    /// Round trip of an `echo` command
+    pub fn echo(&self) -> Result<Duration, SocketcandError> {
+        let mut reader = self.reader.lock().unwrap();
+        let start = Instant::now();
+        self.write("< echo >")?;
+        match reader.reply(self.timeout)? {
+            Message::Echo => Ok(start.elapsed()),
+            Message::Error(message) => Err(SocketcandError::Server(message)),
+            other => Err(SocketcandError::Protocol(format!("{:?}", other))),
+        }
+    }
+
+    /// Send a classic data frame on the bus
+    pub fn send(&self, frame: &TraceFrame) -> Result<(), SocketcandError> {
+        self.write(&format_send(frame)?)?;
+        Ok(())
+    }
+
+    /// Add a broadcast manager filter, effective in BCM mode
+    pub fn filter(&self, filter: &BcmFilter) -> Result<(), SocketcandError> {
+        self.write(&format_filter(filter))?;
+        Ok(())
+    }
+
+    pub fn set_filters(&self, filters: Vec<(u32, u32)>) {
+        *self.filters.lock().unwrap() = filters;
+    }
+
+    fn accepts(&self, frame: &TraceFrame) -> bool {
+        let filters = self.filters.lock().unwrap();
+        filters.is_empty()
+            || filters
+                .iter()
+                .any(|&(id, mask)| frame.id & mask == id & mask)
+    }
+
+    /// Next frame passing the filters, `None` when `timeout` expires first
+    pub fn recv(&self, timeout: Option<Duration>) -> Result<Option<TraceFrame>, SocketcandError> {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        loop {
+            // Attente par tranches : une commande prend le lecteur entre deux
+            let poll = Instant::now() + RECV_POLL;
+            let mut reader = self.reader.lock().unwrap();
+            let message = match reader.frames.pop_front() {
+                Some(frame) => Message::Frame(frame),
+                None => match reader.next_message(Some(deadline.map_or(poll, |d| d.min(poll))))? {
+                    Some(message) => message,
+                    None if deadline.is_some_and(|d| Instant::now() >= d) => return Ok(None),
+                    None => continue,
+                },
+            };
+            drop(reader);
+            match message {
+                Message::Frame(frame) if self.accepts(&frame) => return Ok(Some(frame)),
+                Message::Error(message) => return Err(SocketcandError::Server(message)),
+                Message::Other(text) if text.starts_with("frame") => {
+                    return Err(SocketcandError::Protocol(text))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Next frame like `CanSocketWrapper::read_frame`: an expired timeout
+    /// is a `WouldBlock` error, as with local sockets
+    pub fn read_frame(&self, timeout_ms: Option<u64>) -> io::Result<TraceFrame> {
+        match self.recv(timeout_ms.map(Duration::from_millis)) {
+            Ok(Some(frame)) => Ok(frame),
+            Ok(None) => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "No frame received before the timeout",
+            )),
+            Err(SocketcandError::Io(e)) => Err(e),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+
+    pub fn close(&self) -> io::Result<()> {
+        match self.writer.lock().unwrap().shutdown(Shutdown::Both) {
+            Err(e) if e.kind() == io::ErrorKind::NotConnected => Ok(()),
+            result => result,
+        }
+    }
+}
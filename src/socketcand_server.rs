@@ -0,0 +1,826 @@
+//! socketcand-compatible server
+//!
+//! Shares local buses with remote tools speaking the socketcand protocol
+//! (Kayak, python-can's socketcand interface). Each client opens one bus,
+//! then receives its frames in raw mode or through broadcast manager
+//! subscriptions, and may send frames. Access rules matched on the client
+//! address restrict the buses, the received identifiers and the sent
+//! identifiers of each client.
+
+use neon::prelude::*;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::bridge::BridgeSource;
+use crate::replay::{self, ReplaySink};
+use crate::socketcand::{format_frame, take_message, BcmFilter, Mode};
+use crate::trace::TraceFrame;
+use crate::CanSocketWrapper;
+
+pub const DEFAULT_PORT: u16 = 29536;
+
+/// Longest wait of the server threads, bounds the stop latency
+const SERVER_POLL: Duration = Duration::from_millis(20);
+/// A client not reading its frames for this long is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Longest unterminated message kept from a client, which is disconnected
+/// beyond it
+const MAX_PENDING: usize = 4096;
+
+/// Client network, an address with a prefix length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Network {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl Network {
+    /// Parse `addr` or `addr/prefix`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (addr, prefix) = match text.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (text, None),
+        };
+        let addr: IpAddr = addr.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Network { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Les clients IPv4 d'une socket double pile arrivent en IPv6 mappé
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            ip => ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// What a client may do
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientPolicy {
+    /// Buses the client may open, all if `None`
+    pub buses: Option<Vec<String>>,
+    pub read_only: bool,
+    /// (id, mask) filters of the frames the client receives, all if empty
+    pub rx_filters: Vec<(u32, u32)>,
+    /// (id, mask) filters of the frames the client may send, all if empty
+    pub tx_filters: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessRule {
+    pub network: Network,
+    pub policy: ClientPolicy,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// The first rule matching the client address applies and clients
+    /// matching none are refused; without rules every client has full access
+    pub rules: Vec<AccessRule>,
+    pub max_clients: Option<usize>,
+}
+
+impl ServerOptions {
+    pub fn policy(&self, ip: IpAddr) -> Option<ClientPolicy> {
+        if self.rules.is_empty() {
+            return Some(ClientPolicy::default());
+        }
+        self.rules
+            .iter()
+            .find(|rule| rule.network.contains(ip))
+            .map(|rule| rule.policy.clone())
+    }
+}
+
+fn passes(filters: &[(u32, u32)], id: u32) -> bool {
+    filters.is_empty() || filters.iter().any(|&(fid, mask)| id & mask == fid & mask)
+}
+
+/// Client command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Open(String),
+    RawMode,
+    BcmMode,
+    Echo,
+    Send(TraceFrame),
+    /// `subscribe`, or `filter` with a data mask
+    Subscribe(BcmFilter),
+    Unsubscribe {
+        id: u32,
+        extended: bool,
+    },
+}
+
+/// Identifier argument: 8 hex digits mark extended frames
+fn parse_id(text: Option<&&str>) -> Result<(u32, bool), String> {
+    let text = text.ok_or("missing CAN ID")?;
+    let id = u32::from_str_radix(text, 16).map_err(|_| format!("invalid CAN ID {}", text))?;
+    Ok((id, text.len() == 8))
+}
+
+fn parse_number<T: std::str::FromStr>(text: Option<&&str>, name: &str) -> Result<T, String> {
+    text.and_then(|text| text.parse().ok())
+        .ok_or_else(|| format!("invalid {}", name))
+}
+
+/// `dlc` hex bytes following the length argument
+fn parse_bytes(tokens: &[&str], dlc: usize) -> Result<Vec<u8>, String> {
+    if dlc > 8 || tokens.len() < dlc {
+        return Err("invalid data length".to_string());
+    }
+    tokens[..dlc]
+        .iter()
+        .map(|text| u8::from_str_radix(text, 16).map_err(|_| format!("invalid byte {}", text)))
+        .collect()
+}
+
+/// Parse a client message without its brackets
+pub fn parse_command(text: &str) -> Result<Command, String> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let args = tokens.get(1..).unwrap_or_default();
+    match tokens.first().copied() {
+        Some("open") => match args {
+            [bus] => Ok(Command::Open(bus.to_string())),
+            _ => Err("missing bus name".to_string()),
+        },
+        Some("rawmode") => Ok(Command::RawMode),
+        Some("bcmmode") => Ok(Command::BcmMode),
+        Some("echo") => Ok(Command::Echo),
+        Some("send") => {
+            let (id, extended) = parse_id(args.first())?;
+            let dlc: usize = parse_number(args.get(1), "data length")?;
+            Ok(Command::Send(TraceFrame {
+                id,
+                extended,
+                data: parse_bytes(args.get(2..).unwrap_or_default(), dlc)?,
+                ..Default::default()
+            }))
+        }
+        Some(command @ ("filter" | "subscribe")) => {
+            let secs: u64 = parse_number(args.first(), "interval")?;
+            let micros: u64 = parse_number(args.get(1), "interval")?;
+            let interval = Duration::from_secs(secs)
+                .checked_add(Duration::from_micros(micros))
+                .ok_or("invalid interval")?;
+            let (id, extended) = parse_id(args.get(2))?;
+            let mask = if command == "filter" {
+                let dlc: usize = parse_number(args.get(3), "data length")?;
+                Some(parse_bytes(args.get(4..).unwrap_or_default(), dlc)?)
+            } else {
+                None
+            };
+            Ok(Command::Subscribe(BcmFilter {
+                id,
+                extended,
+                interval,
+                mask,
+            }))
+        }
+        Some("unsubscribe") => {
+            let (id, extended) = parse_id(args.first())?;
+            Ok(Command::Unsubscribe { id, extended })
+        }
+        Some(other) => Err(format!("unknown command {}", other)),
+        None => Err("empty command".to_string()),
+    }
+}
+
+/// Broadcast manager subscription of a client
+struct Subscription {
+    filter: BcmFilter,
+    last_report: Option<Instant>,
+    /// Length and masked content of the last reported frame
+    last_data: Option<(usize, Vec<u8>)>,
+}
+
+impl Subscription {
+    /// Whether `frame` is reported: at most once per interval, and only on
+    /// content changes with a data mask
+    fn report(&mut self, frame: &TraceFrame, now: Instant) -> bool {
+        if self
+            .last_report
+            .is_some_and(|at| now.saturating_duration_since(at) < self.filter.interval)
+        {
+            return false;
+        }
+        if let Some(mask) = &self.filter.mask {
+            let masked = frame
+                .data
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte & mask.get(i).copied().unwrap_or(0))
+                .collect();
+            let content = Some((frame.data.len(), masked));
+            if self.last_data == content {
+                return false;
+            }
+            self.last_data = content;
+        }
+        self.last_report = Some(now);
+        true
+    }
+}
+
+struct Session {
+    bus: Option<String>,
+    mode: Mode,
+    subscriptions: Vec<Subscription>,
+}
+
+struct Client {
+    address: SocketAddr,
+    policy: ClientPolicy,
+    writer: Mutex<TcpStream>,
+    session: Mutex<Session>,
+    /// Frames sent to the client
+    received: AtomicU64,
+    /// Frames the client sent on its bus
+    sent: AtomicU64,
+    closed: AtomicBool,
+}
+
+impl Client {
+    fn write(&self, text: &str) -> bool {
+        if self.closed.load(Ordering::Relaxed) {
+            return false;
+        }
+        let mut writer = self.writer.lock().unwrap();
+        if writer.write_all(text.as_bytes()).is_err() {
+            self.close_with(&writer);
+            return false;
+        }
+        true
+    }
+
+    fn close_with(&self, stream: &TcpStream) {
+        self.closed.store(true, Ordering::Relaxed);
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+
+    fn close(&self) {
+        self.close_with(&self.writer.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    /// Forward a frame of `bus` if the client's session selects it
+    fn deliver(&self, bus: &str, frame: &TraceFrame, now: Instant) {
+        let selected = {
+            // Une session empoisonnée a paniqué et se termine
+            let Ok(mut session) = self.session.lock() else {
+                return;
+            };
+            if session.bus.as_deref() != Some(bus) {
+                return;
+            }
+            match session.mode {
+                // Les classes d'erreur ne sont pas des identifiants filtrables
+                Mode::Raw if frame.error => self.policy.rx_filters.is_empty(),
+                Mode::Raw => passes(&self.policy.rx_filters, frame.id),
+                Mode::Bcm => {
+                    !frame.error
+                        && passes(&self.policy.rx_filters, frame.id)
+                        && session
+                            .subscriptions
+                            .iter_mut()
+                            .find(|s| {
+                                s.filter.id == frame.id && s.filter.extended == frame.extended
+                            })
+                            .is_some_and(|s| s.report(frame, now))
+                }
+            }
+        };
+        if selected && self.write(&format_frame(frame)) {
+            self.received.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Connected client as reported by the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientStatus {
+    pub address: SocketAddr,
+    pub bus: Option<String>,
+    pub mode: Mode,
+    pub received: u64,
+    pub sent: u64,
+}
+
+struct Shared {
+    options: ServerOptions,
+    clients: Mutex<HashMap<u32, Arc<Client>>>,
+    next_client: Mutex<u32>,
+    /// Read errors that stopped a bus
+    bus_errors: Mutex<HashMap<String, String>>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn clients(&self) -> Vec<Arc<Client>> {
+        self.clients.lock().unwrap().values().cloned().collect()
+    }
+
+    fn broadcast(&self, bus: &str, frame: &TraceFrame, except: Option<&Arc<Client>>) {
+        let now = Instant::now();
+        for client in self.clients() {
+            if except.is_none_or(|except| !Arc::ptr_eq(except, &client)) {
+                client.deliver(bus, frame, now);
+            }
+        }
+    }
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Answer a client command, `None` for commands without reply
+fn handle<D: ReplaySink + Sync>(
+    shared: &Shared,
+    sinks: &HashMap<String, D>,
+    client: &Arc<Client>,
+    text: &str,
+) -> Option<String> {
+    let error = |message: &str| Some(format!("< error {} >", message));
+    let command = match parse_command(text) {
+        Ok(command) => command,
+        Err(e) => return error(&e),
+    };
+    let mut session = client.session.lock().unwrap();
+    let bus = match (&command, &session.bus) {
+        (Command::Open(name), None) => {
+            let allowed = client
+                .policy
+                .buses
+                .as_ref()
+                .is_none_or(|buses| buses.contains(name));
+            if !allowed || !sinks.contains_key(name) {
+                return error("could not open bus");
+            }
+            session.bus = Some(name.clone());
+            session.mode = Mode::Bcm;
+            return Some("< ok >".to_string());
+        }
+        (Command::Open(_), Some(_)) => return error("bus already open"),
+        (Command::Echo, _) => return Some("< echo >".to_string()),
+        (_, None) => return error("no bus open"),
+        (_, Some(bus)) => bus.clone(),
+    };
+
+    match command {
+        Command::RawMode => {
+            session.mode = Mode::Raw;
+            Some("< ok >".to_string())
+        }
+        Command::BcmMode => {
+            session.mode = Mode::Bcm;
+            Some("< ok >".to_string())
+        }
+        Command::Subscribe(filter) => {
+            session
+                .subscriptions
+                .retain(|s| (s.filter.id, s.filter.extended) != (filter.id, filter.extended));
+            session.subscriptions.push(Subscription {
+                filter,
+                last_report: None,
+                last_data: None,
+            });
+            None
+        }
+        Command::Unsubscribe { id, extended } => {
+            session
+                .subscriptions
+                .retain(|s| (s.filter.id, s.filter.extended) != (id, extended));
+            None
+        }
+        Command::Send(mut frame) => {
+            drop(session);
+            if client.policy.read_only || !passes(&client.policy.tx_filters, frame.id) {
+                return error(&format!("send not permitted for {:X}", frame.id));
+            }
+            if let Err(e) = replay::send(&sinks[&bus], &frame) {
+                return error(&e);
+            }
+            client.sent.fetch_add(1, Ordering::Relaxed);
+            // La socket partagée ne reçoit pas ses propres trames : les autres
+            // clients du bus les voient comme avec socketcand
+            frame.timestamp = unix_now();
+            shared.broadcast(&bus, &frame, Some(client));
+            None
+        }
+        Command::Open(_) | Command::Echo => unreachable!(),
+    }
+}
+
+/// Client registration, released when its session ends even by a panic
+struct Registration<'a> {
+    shared: &'a Shared,
+    id: u32,
+    client: Arc<Client>,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.shared
+            .clients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.id);
+        self.client.close();
+    }
+}
+
+fn serve<D: ReplaySink + Sync>(
+    shared: &Shared,
+    sinks: &HashMap<String, D>,
+    mut stream: TcpStream,
+    address: SocketAddr,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let Some(policy) = shared.options.policy(address.ip()) else {
+        return stream.write_all(b"< error access denied >");
+    };
+    let registration = {
+        let mut clients = shared.clients.lock().unwrap();
+        if shared
+            .options
+            .max_clients
+            .is_some_and(|max| clients.len() >= max)
+        {
+            return stream.write_all(b"< error too many clients >");
+        }
+        let client = Arc::new(Client {
+            address,
+            policy,
+            writer: Mutex::new(stream.try_clone()?),
+            session: Mutex::new(Session {
+                bus: None,
+                mode: Mode::Bcm,
+                subscriptions: Vec::new(),
+            }),
+            received: AtomicU64::new(0),
+            sent: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        });
+        let mut next = shared.next_client.lock().unwrap();
+        *next += 1;
+        clients.insert(*next, Arc::clone(&client));
+        Registration {
+            shared,
+            id: *next,
+            client,
+        }
+    };
+    let client = &registration.client;
+
+    client.write("< hi >");
+    stream.set_read_timeout(Some(SERVER_POLL))?;
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    while !shared.stop.load(Ordering::Relaxed) && !client.closed.load(Ordering::Relaxed) {
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(len) => buf.extend_from_slice(&chunk[..len]),
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock
+                    || e.kind() == io::ErrorKind::TimedOut
+                    || e.kind() == io::ErrorKind::Interrupted =>
+            {
+                continue
+            }
+            Err(_) => break,
+        }
+        while let Some(text) = take_message(&mut buf) {
+            if let Some(reply) = handle(shared, sinks, client, &text) {
+                client.write(&reply);
+            }
+        }
+        if buf.len() > MAX_PENDING {
+            client.write("< error message too long >");
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Server threads: connection acceptance, one reader per bus, one per client
+pub struct SocketcandServer {
+    shared: Arc<Shared>,
+    local_addr: SocketAddr,
+    buses: Vec<String>,
+}
+
+impl SocketcandServer {
+    pub fn start<S, D>(
+        listener: TcpListener,
+        buses: Vec<(String, S, D)>,
+        options: ServerOptions,
+    ) -> io::Result<Self>
+    where
+        S: BridgeSource,
+        D: ReplaySink + Sync,
+    {
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            options,
+            clients: Mutex::new(HashMap::new()),
+            next_client: Mutex::new(0),
+            bus_errors: Mutex::new(HashMap::new()),
+            threads: Mutex::new(Vec::new()),
+            stop: AtomicBool::new(false),
+        });
+
+        let names: Vec<String> = buses.iter().map(|(name, _, _)| name.clone()).collect();
+        let mut sinks = HashMap::new();
+        let mut threads = Vec::new();
+        for (name, mut source, sink) in buses {
+            sinks.insert(name.clone(), sink);
+            let shared = Arc::clone(&shared);
+            threads.push(thread::spawn(move || {
+                while !shared.stop.load(Ordering::Relaxed) {
+                    match source.recv(SERVER_POLL) {
+                        Ok(Some(mut frame)) => {
+                            frame.timestamp = unix_now();
+                            shared.broadcast(&name, &frame, None);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            shared
+                                .bus_errors
+                                .lock()
+                                .unwrap()
+                                .insert(name, e.to_string());
+                            break;
+                        }
+                    }
+                }
+            }));
+        }
+
+        let sinks = Arc::new(sinks);
+        let accept_shared = Arc::clone(&shared);
+        threads.push(thread::spawn(move || {
+            let shared = accept_shared;
+            while !shared.stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, address)) => {
+                        let client_shared = Arc::clone(&shared);
+                        let sinks = Arc::clone(&sinks);
+                        let handle = thread::spawn(move || {
+                            let _ = serve(&client_shared, &sinks, stream, address);
+                        });
+                        let mut threads = shared.threads.lock().unwrap();
+                        threads.retain(|handle| !handle.is_finished());
+                        threads.push(handle);
+                    }
+                    Err(_) => thread::sleep(SERVER_POLL),
+                }
+            }
+        }));
+        shared.threads.lock().unwrap().extend(threads);
+
+        Ok(SocketcandServer {
+            shared,
+            local_addr,
+            buses: names,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn clients(&self) -> Vec<ClientStatus> {
+        let mut clients: Vec<(u32, Arc<Client>)> = self
+            .shared
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&id, client)| (id, Arc::clone(client)))
+            .collect();
+        clients.sort_by_key(|(id, _)| *id);
+        clients
+            .into_iter()
+            .map(|(_, client)| {
+                let session = client.session.lock().unwrap();
+                ClientStatus {
+                    address: client.address,
+                    bus: session.bus.clone(),
+                    mode: session.mode,
+                    received: client.received.load(Ordering::Relaxed),
+                    sent: client.sent.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
+    /// Read error that stopped `bus`, if any
+    pub fn bus_error(&self, bus: &str) -> Option<String> {
+        self.shared.bus_errors.lock().unwrap().get(bus).cloned()
+    }
+
+    pub fn stop(mut self) {
+        self.halt();
+    }
+
+    fn halt(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        for client in self.shared.clients() {
+            client.close();
+        }
+        // Les threads ajoutés par le thread d'acceptation pendant l'arrêt sont
+        // repris au tour suivant
+        loop {
+            let threads: Vec<JoinHandle<()>> =
+                self.shared.threads.lock().unwrap().drain(..).collect();
+            if threads.is_empty() {
+                break;
+            }
+            for handle in threads {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl Drop for SocketcandServer {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SERVER_REGISTRY: Arc<Mutex<HashMap<u32, SocketcandServer>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Start a server sharing sockets under bus names, returning its handle
+pub fn start_server(
+    addr: (&str, u16),
+    buses: Vec<(String, CanSocketWrapper)>,
+    options: ServerOptions,
+) -> io::Result<u32> {
+    let listener = TcpListener::bind(addr)?;
+    let buses = buses
+        .into_iter()
+        .map(|(name, wrapper)| (name, wrapper.clone(), wrapper))
+        .collect();
+    let server = SocketcandServer::start(listener, buses, options)?;
+    let id = crate::next_handle_id();
+    SERVER_REGISTRY.lock().unwrap().insert(id, server);
+    Ok(id)
+}
+
+fn filters_from_js(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+    key: &str,
+) -> NeonResult<Vec<(u32, u32)>> {
+    let mut filters = Vec::new();
+    if let Some(array) = obj.get_opt::<JsArray, _, _>(cx, key)? {
+        for value in array.to_vec(cx)? {
+            let filter = value.downcast_or_throw::<JsObject, _>(cx)?;
+            let id = filter.get::<JsNumber, _, _>(cx, "id")?.value(cx) as u32;
+            let mask = filter
+                .get_opt::<JsNumber, _, _>(cx, "mask")?
+                .map_or(u32::MAX, |mask| mask.value(cx) as u32);
+            filters.push((id, mask));
+        }
+    }
+    Ok(filters)
+}
+
+/// Read `{ host, port, maxClients, clients }`, returning the listen address
+pub fn options_from_js(
+    cx: &mut FunctionContext,
+    obj: Option<Handle<JsObject>>,
+) -> NeonResult<(String, u16, ServerOptions)> {
+    let mut host = "127.0.0.1".to_string();
+    let mut port = DEFAULT_PORT;
+    let mut options = ServerOptions::default();
+    let Some(obj) = obj else {
+        return Ok((host, port, options));
+    };
+
+    if let Some(value) = obj.get_opt::<JsString, _, _>(cx, "host")? {
+        host = value.value(cx);
+    }
+    if let Some(value) = obj.get_opt::<JsNumber, _, _>(cx, "port")? {
+        port = value.value(cx) as u16;
+    }
+    if let Some(value) = obj.get_opt::<JsNumber, _, _>(cx, "maxClients")? {
+        options.max_clients = Some(value.value(cx) as usize);
+    }
+    if let Some(rules) = obj.get_opt::<JsArray, _, _>(cx, "clients")? {
+        for value in rules.to_vec(cx)? {
+            let rule = value.downcast_or_throw::<JsObject, _>(cx)?;
+            let address = rule.get::<JsString, _, _>(cx, "address")?.value(cx);
+            let Some(network) = Network::parse(&address) else {
+                return cx.throw_error(format!("Invalid client address: {}", address));
+            };
+            let buses = match rule.get_opt::<JsArray, _, _>(cx, "buses")? {
+                Some(array) => {
+                    let mut buses = Vec::new();
+                    for bus in array.to_vec(cx)? {
+                        buses.push(bus.downcast_or_throw::<JsString, _>(cx)?.value(cx));
+                    }
+                    Some(buses)
+                }
+                None => None,
+            };
+            let read_only = rule
+                .get_opt::<JsBoolean, _, _>(cx, "readOnly")?
+                .is_some_and(|value| value.value(cx));
+            let policy = ClientPolicy {
+                buses,
+                read_only,
+                rx_filters: filters_from_js(cx, rule, "rxFilters")?,
+                tx_filters: filters_from_js(cx, rule, "txFilters")?,
+            };
+            options.rules.push(AccessRule { network, policy });
+        }
+    }
+    Ok((host, port, options))
+}
+
+/// Get the address, buses and clients of a server from JavaScript
+pub fn get_socketcand_server_status(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let server_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let registry = SERVER_REGISTRY.lock().unwrap();
+    let Some(server) = registry.get(&server_id) else {
+        return cx.throw_error("Invalid server ID");
+    };
+
+    let obj = cx.empty_object();
+    let port = cx.number(server.local_addr().port());
+    obj.set(&mut cx, "port", port)?;
+    let buses = cx.empty_array();
+    for (i, name) in server.buses.iter().enumerate() {
+        let bus = cx.empty_object();
+        let value = cx.string(name);
+        bus.set(&mut cx, "name", value)?;
+        if let Some(error) = server.bus_error(name) {
+            let error = cx.string(error);
+            bus.set(&mut cx, "error", error)?;
+        }
+        buses.set(&mut cx, i as u32, bus)?;
+    }
+    obj.set(&mut cx, "buses", buses)?;
+
+    let clients = cx.empty_array();
+    for (i, status) in server.clients().iter().enumerate() {
+        let client = cx.empty_object();
+        let address = cx.string(status.address.to_string());
+        client.set(&mut cx, "address", address)?;
+        let bus = match &status.bus {
+            Some(bus) => cx.string(bus).upcast::<JsValue>(),
+            None => cx.null().upcast(),
+        };
+        client.set(&mut cx, "bus", bus)?;
+        let mode = cx.string(match status.mode {
+            Mode::Raw => "raw",
+            Mode::Bcm => "bcm",
+        });
+        client.set(&mut cx, "mode", mode)?;
+        let received = cx.number(status.received as f64);
+        client.set(&mut cx, "received", received)?;
+        let sent = cx.number(status.sent as f64);
+        client.set(&mut cx, "sent", sent)?;
+        clients.set(&mut cx, i as u32, client)?;
+    }
+    obj.set(&mut cx, "clients", clients)?;
+    Ok(obj)
+}
+
+/// Stop a server and disconnect its clients from JavaScript
+pub fn stop_socketcand_server(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let server_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let Some(server) = SERVER_REGISTRY.lock().unwrap().remove(&server_id) else {
+        return cx.throw_error("Invalid server ID");
+    };
+    server.stop();
+    Ok(cx.undefined())
+}
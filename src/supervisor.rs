@@ -0,0 +1,409 @@
+//! Bus-off supervision with automatic controller restart
+//!
+//! A raw socket on the interface receives only the bus-off and restarted
+//! error frames. On bus-off the controller is restarted after a delay that
+//! doubles with each attempt following a recent restart, and every step is
+//! reported to a callback.
+
+use neon::prelude::*;
+use socketcan::{CanFrame, CanSocket, Socket, SocketOptions};
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::controller;
+use crate::interfaces::InterfaceError;
+use crate::next_handle_id;
+
+const CAN_ERR_BUSOFF: u32 = 0x0000_0040;
+const CAN_ERR_RESTARTED: u32 = 0x0000_0100;
+
+/// Longest wait for an error frame, bounds the stop latency
+const SUPERVISE_POLL: Duration = Duration::from_millis(100);
+
+/// Longest delay accepted in a back-off policy from JavaScript
+const MAX_POLICY_DELAY: Duration = Duration::from_secs(24 * 3600);
+
+/// Source of error frame classes (`can_id & CAN_ERR_MASK`)
+pub trait ErrorSource: Send + 'static {
+    /// Next error class, `None` when `timeout` expires first
+    fn next_error(&mut self, timeout: Duration) -> io::Result<Option<u32>>;
+}
+
+impl ErrorSource for CanSocket {
+    fn next_error(&mut self, timeout: Duration) -> io::Result<Option<u32>> {
+        self.set_read_timeout(timeout)?;
+        match self.read_frame() {
+            Ok(CanFrame::Error(frame)) => Ok(Some(frame.error_bits())),
+            Ok(_) => Ok(None),
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock
+                    || e.kind() == io::ErrorKind::TimedOut
+                    || e.kind() == io::ErrorKind::Interrupted =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Socket receiving only the bus-off and restarted error frames of `interface`
+pub fn error_socket(interface: &str) -> io::Result<CanSocket> {
+    let socket = CanSocket::open(interface)?;
+    socket.set_filter_drop_all()?;
+    socket.set_error_filter(CAN_ERR_BUSOFF | CAN_ERR_RESTARTED)?;
+    Ok(socket)
+}
+
+/// Restart delays of the supervisor
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackoffPolicy {
+    /// Delay before the first restart
+    pub initial: Duration,
+    /// Upper bound of the doubled delays
+    pub max: Duration,
+    /// Attempts before giving up, `None` to retry forever
+    pub max_attempts: Option<u32>,
+    /// A bus-off later than this after the last restart starts over at
+    /// the first attempt
+    pub reset_after: Duration,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        BackoffPolicy {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            max_attempts: None,
+            reset_after: Duration::from_secs(10),
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Delay before restart `attempt` (starting at 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Step of a bus-off recovery
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecoveryEvent {
+    /// Bus-off detected, restart scheduled after `delay`
+    BusOff { attempt: u32, delay: Duration },
+    /// Restart requested, `error` if the request failed
+    Restart { attempt: u32, error: Option<String> },
+    /// The controller is error-active again
+    Recovered { attempt: u32 },
+    /// `max_attempts` restarts did not recover the controller
+    GaveUp { attempts: u32 },
+}
+
+pub type RecoveryCallback = Box<dyn Fn(RecoveryEvent) + Send>;
+
+/// Recovery state machine of the supervisor thread
+struct Recovery<R> {
+    restart: R,
+    policy: BackoffPolicy,
+    callback: RecoveryCallback,
+    attempt: u32,
+    /// Deadline of the scheduled restart
+    pending: Option<Instant>,
+    recovering: bool,
+    gave_up: bool,
+    last_restart: Option<Instant>,
+}
+
+impl<R> Recovery<R>
+where
+    R: FnMut() -> Result<(), InterfaceError>,
+{
+    fn schedule(&mut self) {
+        if self
+            .policy
+            .max_attempts
+            .is_some_and(|max| self.attempt > max)
+        {
+            self.gave_up = true;
+            (self.callback)(RecoveryEvent::GaveUp {
+                attempts: self.attempt - 1,
+            });
+            return;
+        }
+        let delay = self.policy.delay(self.attempt);
+        let Some(deadline) = Instant::now().checked_add(delay) else {
+            // A deadline the clock cannot represent would never fire
+            self.gave_up = true;
+            (self.callback)(RecoveryEvent::GaveUp {
+                attempts: self.attempt - 1,
+            });
+            return;
+        };
+        self.pending = Some(deadline);
+        (self.callback)(RecoveryEvent::BusOff {
+            attempt: self.attempt,
+            delay,
+        });
+    }
+
+    fn on_error(&mut self, class: u32) {
+        if class & CAN_ERR_RESTARTED != 0 && self.recovering {
+            self.recovering = false;
+            self.gave_up = false;
+            self.pending = None;
+            (self.callback)(RecoveryEvent::Recovered {
+                attempt: self.attempt,
+            });
+        }
+        if class & CAN_ERR_BUSOFF != 0 && self.pending.is_none() && !self.gave_up {
+            let recent = self
+                .last_restart
+                .is_some_and(|at| at.elapsed() < self.policy.reset_after);
+            self.attempt = if recent { self.attempt + 1 } else { 1 };
+            self.recovering = true;
+            self.schedule();
+        }
+    }
+
+    fn on_deadline(&mut self) {
+        if self.pending.is_none_or(|at| Instant::now() < at) {
+            return;
+        }
+        self.pending = None;
+        let result = (self.restart)();
+        self.last_restart = Some(Instant::now());
+        match result {
+            Ok(()) => (self.callback)(RecoveryEvent::Restart {
+                attempt: self.attempt,
+                error: None,
+            }),
+            // Restarted meanwhile by the kernel (restart-ms) or another process
+            Err(InterfaceError::NotBusOff(_)) => {
+                self.recovering = false;
+                (self.callback)(RecoveryEvent::Recovered {
+                    attempt: self.attempt,
+                });
+            }
+            // The kernel restarts the controller itself: no retry, its
+            // restarted error frame reports the recovery
+            Err(e @ InterfaceError::AutoRestart(_)) => {
+                (self.callback)(RecoveryEvent::Restart {
+                    attempt: self.attempt,
+                    error: Some(e.to_string()),
+                });
+            }
+            Err(e) => {
+                (self.callback)(RecoveryEvent::Restart {
+                    attempt: self.attempt,
+                    error: Some(e.to_string()),
+                });
+                self.attempt += 1;
+                self.schedule();
+            }
+        }
+    }
+
+    /// Wait for the next error frame, never zero as that blocks sockets
+    fn timeout(&self) -> Duration {
+        match self.pending {
+            Some(at) => at
+                .saturating_duration_since(Instant::now())
+                .clamp(Duration::from_millis(1), SUPERVISE_POLL),
+            None => SUPERVISE_POLL,
+        }
+    }
+}
+
+/// Background thread restarting a controller after bus-off
+pub struct Supervisor {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Supervisor {
+    pub fn start<S, R>(
+        mut source: S,
+        restart: R,
+        policy: BackoffPolicy,
+        callback: RecoveryCallback,
+    ) -> Self
+    where
+        S: ErrorSource,
+        R: FnMut() -> Result<(), InterfaceError> + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut recovery = Recovery {
+                restart,
+                policy,
+                callback,
+                attempt: 0,
+                pending: None,
+                recovering: false,
+                gave_up: false,
+                last_restart: None,
+            };
+            while !stop_flag.load(Ordering::Relaxed) {
+                match source.next_error(recovery.timeout()) {
+                    Ok(Some(class)) => recovery.on_error(class),
+                    Ok(None) => {}
+                    Err(_) => break,
+                }
+                recovery.on_deadline();
+            }
+        });
+
+        Supervisor {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stop(mut self) {
+        self.halt();
+    }
+
+    fn halt(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SUPERVISOR_REGISTRY: Arc<Mutex<HashMap<u32, Supervisor>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+/// Supervise the controller of `interface`, returns the supervisor ID
+pub fn start_supervisor(
+    interface: &str,
+    policy: BackoffPolicy,
+    callback: RecoveryCallback,
+) -> io::Result<u32> {
+    let source = error_socket(interface)?;
+    let interface = interface.to_string();
+    let supervisor = Supervisor::start(
+        source,
+        move || controller::restart(&interface),
+        policy,
+        callback,
+    );
+    let id = next_handle_id();
+    SUPERVISOR_REGISTRY.lock().unwrap().insert(id, supervisor);
+    Ok(id)
+}
+
+/// Read `{ initialDelay, maxDelay, maxAttempts, resetAfter }` (milliseconds)
+pub fn policy_from_js(cx: &mut FunctionContext, index: usize) -> NeonResult<BackoffPolicy> {
+    let mut policy = BackoffPolicy::default();
+    let Some(obj) = cx
+        .argument_opt(index)
+        .and_then(|v| v.downcast::<JsObject, _>(cx).ok())
+    else {
+        return Ok(policy);
+    };
+    if let Some(delay) = delay_from_js(cx, obj, "initialDelay")? {
+        policy.initial = delay;
+    }
+    if let Some(delay) = delay_from_js(cx, obj, "maxDelay")? {
+        policy.max = delay;
+    }
+    if let Some(count) = obj.get_opt::<JsNumber, _, _>(cx, "maxAttempts")? {
+        policy.max_attempts = Some(count.value(cx) as u32);
+    }
+    if let Some(delay) = delay_from_js(cx, obj, "resetAfter")? {
+        policy.reset_after = delay;
+    }
+    Ok(policy)
+}
+
+/// Optional delay `key` of `obj` in milliseconds, at most `MAX_POLICY_DELAY`
+fn delay_from_js(
+    cx: &mut FunctionContext,
+    obj: Handle<JsObject>,
+    key: &str,
+) -> NeonResult<Option<Duration>> {
+    let Some(ms) = obj.get_opt::<JsNumber, _, _>(cx, key)? else {
+        return Ok(None);
+    };
+    let ms = ms.value(cx);
+    if !(0.0..=MAX_POLICY_DELAY.as_millis() as f64).contains(&ms) {
+        return cx.throw_range_error(format!(
+            "{} must be between 0 and {} ms, got {}",
+            key,
+            MAX_POLICY_DELAY.as_millis(),
+            ms
+        ));
+    }
+    Ok(Some(Duration::from_millis(ms as u64)))
+}
+
+/// Callback forwarding recovery events to a JS function
+pub fn js_callback(cx: &mut FunctionContext, callback: Handle<JsFunction>) -> RecoveryCallback {
+    let callback = Arc::new(callback.root(cx));
+    let js_channel = cx.channel();
+    Box::new(move |event| {
+        let callback = Arc::clone(&callback);
+        js_channel.send(move |mut cx| {
+            let obj = cx.empty_object();
+            let (kind, attempt) = match &event {
+                RecoveryEvent::BusOff { attempt, .. } => ("bus-off", *attempt),
+                RecoveryEvent::Restart { attempt, .. } => ("restart", *attempt),
+                RecoveryEvent::Recovered { attempt } => ("recovered", *attempt),
+                RecoveryEvent::GaveUp { attempts } => ("gave-up", *attempts),
+            };
+            let kind = cx.string(kind);
+            obj.set(&mut cx, "type", kind)?;
+            let attempt = cx.number(attempt);
+            obj.set(&mut cx, "attempt", attempt)?;
+            match &event {
+                RecoveryEvent::BusOff { delay, .. } => {
+                    let delay = cx.number(delay.as_millis() as f64);
+                    obj.set(&mut cx, "delay", delay)?;
+                }
+                RecoveryEvent::Restart {
+                    error: Some(error), ..
+                } => {
+                    let error = cx.string(error);
+                    obj.set(&mut cx, "error", error)?;
+                }
+                _ => {}
+            }
+            callback
+                .to_inner(&mut cx)
+                .call_with(&cx)
+                .arg(obj)
+                .exec(&mut cx)
+        });
+    })
+}
+
+/// Stop a bus-off supervisor
+pub fn stop_supervisor(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let supervisor_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    let supervisor = SUPERVISOR_REGISTRY.lock().unwrap().remove(&supervisor_id);
+    match supervisor {
+        Some(supervisor) => {
+            supervisor.stop();
+            Ok(cx.undefined())
+        }
+        None => cx.throw_error("Invalid supervisor ID"),
+    }
+}
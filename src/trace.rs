@@ -0,0 +1,396 @@
+//! Trace frame model shared by the log file formats
+//!
+//! Readers of each format produce [`TraceFrame`]s and writers consume them,
+//! so a trace can be converted between formats or replayed on a bus.
+
+use neon::prelude::*;
+use std::time::Duration;
+
+/// Direction of a logged frame, when the format records it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rx,
+    Tx,
+}
+
+/// CAN XL header fields; the priority is the frame `id`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XlHeader {
+    /// SDU type
+    pub sdt: u8,
+    /// Virtual CAN network ID
+    pub vcid: u8,
+    /// Acceptance field
+    pub af: u32,
+    /// Simple extended content
+    pub sec: bool,
+}
+
+/// One frame of a trace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceFrame {
+    /// Absolute (UNIX epoch) or trace-relative timestamp
+    pub timestamp: Duration,
+    /// Interface or channel name (e.g. "can0")
+    pub interface: String,
+    /// Identifier, or error class bits for error frames
+    pub id: u32,
+    pub extended: bool,
+    pub fd: bool,
+    /// CAN FD bit rate switch
+    pub brs: bool,
+    /// CAN FD error state indicator
+    pub esi: bool,
+    pub remote: bool,
+    pub error: bool,
+    /// Requested length of remote frames
+    pub remote_len: u8,
+    pub direction: Option<Direction>,
+    /// CAN XL frame, with up to 2048 data bytes
+    pub xl: Option<XlHeader>,
+    pub data: Vec<u8>,
+}
+
+impl TraceFrame {
+    /// Data length code of the frame
+    pub fn dlc(&self) -> u8 {
+        if self.remote {
+            return self.remote_len;
+        }
+        len_to_dlc(self.data.len())
+    }
+}
+
+/// CAN FD payload lengths indexed by DLC
+pub const DLC_TO_LEN: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+/// Smallest DLC covering `len` bytes
+pub fn len_to_dlc(len: usize) -> u8 {
+    DLC_TO_LEN.iter().position(|&l| l >= len).unwrap_or(15) as u8
+}
+
+/// Payload length of a DLC, capped at 8 bytes for classic frames
+pub fn dlc_to_len(dlc: u8, fd: bool) -> usize {
+    if fd {
+        DLC_TO_LEN[dlc.min(15) as usize]
+    } else {
+        dlc.min(8) as usize
+    }
+}
+
+/// Numbering of interfaces as the 1-based channels of Vector formats
+///
+/// Numeric names ("1", "2"...) are kept, other interfaces are numbered in
+/// order of appearance.
+#[derive(Default)]
+pub struct ChannelMap {
+    channels: Vec<String>,
+}
+
+impl ChannelMap {
+    pub fn channel(&mut self, interface: &str) -> u16 {
+        if let Ok(channel) = interface.parse::<u16>() {
+            return channel;
+        }
+        let index = match self.channels.iter().position(|c| c == interface) {
+            Some(index) => index,
+            None => {
+                self.channels.push(interface.to_string());
+                self.channels.len() - 1
+            }
+        };
+        index as u16 + 1
+    }
+}
+
+/// Calendar date and time, as found in trace file headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millisecond: u32,
+}
+
+impl DateTime {
+    /// Whether the fields are in their calendar and clock ranges
+    pub fn is_valid(self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+            && self.millisecond < 1000
+    }
+
+    /// Time since the UNIX epoch (UTC), `None` before 1970 or when a field
+    /// is out of range
+    pub fn to_unix(self) -> Option<Duration> {
+        if !self.is_valid() {
+            return None;
+        }
+        // Algorithme « days from civil » de H. Hinnant
+        let y = self.year as i64 - (self.month <= 2) as i64;
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let mp = (self.month as i64 + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        let secs =
+            days * 86_400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        u64::try_from(secs)
+            .ok()
+            .map(|secs| Duration::new(secs, self.millisecond * 1_000_000))
+    }
+
+    /// Calendar date of a time since the UNIX epoch (UTC)
+    pub fn from_unix(time: Duration) -> Self {
+        let secs = time.as_secs();
+        let days = (secs / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let doe = days - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400) as i32 + (month <= 2) as i32;
+        let rem = secs % 86_400;
+        Self {
+            year,
+            month,
+            day,
+            hour: (rem / 3600) as u32,
+            minute: (rem / 60 % 60) as u32,
+            second: (rem % 60) as u32,
+            millisecond: time.subsec_millis(),
+        }
+    }
+
+    /// Day of week, 0 for Sunday
+    pub fn weekday(self) -> u32 {
+        let days = self.to_unix().map_or(0, |t| t.as_secs() / 86_400);
+        ((days + 4) % 7) as u32
+    }
+}
+
+fn get_bool<'a, C: Context<'a>>(
+    cx: &mut C,
+    obj: Handle<'a, JsObject>,
+    key: &str,
+) -> NeonResult<bool> {
+    Ok(obj
+        .get_opt::<JsBoolean, _, _>(cx, key)?
+        .map(|v| v.value(cx))
+        .unwrap_or(false))
+}
+
+/// Convert a JavaScript frame object into a trace frame
+///
+/// Accepts the objects returned by `readFrame` with an optional `timestamp`
+/// in seconds, `interface`, `brs`, `esi` and `direction`, plus `sdt`,
+/// `vcid`, `af` and `sec` for CAN XL frames (`xl: true`).
+pub fn frame_from_js<'a, C: Context<'a>>(
+    cx: &mut C,
+    obj: Handle<'a, JsObject>,
+) -> NeonResult<TraceFrame> {
+    let id = obj.get::<JsNumber, _, _>(cx, "id")?.value(cx) as u32;
+    let data_array = obj
+        .get_opt::<JsArray, _, _>(cx, "data")?
+        .map(|a| a.to_vec(cx))
+        .transpose()?
+        .unwrap_or_default();
+    let mut data = Vec::with_capacity(data_array.len());
+    for value in data_array {
+        data.push(value.downcast_or_throw::<JsNumber, _>(cx)?.value(cx) as u8);
+    }
+
+    let timestamp = match obj.get_opt::<JsNumber, _, _>(cx, "timestamp")? {
+        Some(ts) => Duration::try_from_secs_f64(ts.value(cx))
+            .or_else(|e| cx.throw_error(format!("Invalid timestamp: {}", e)))?,
+        None => Duration::ZERO,
+    };
+    let interface = obj
+        .get_opt::<JsString, _, _>(cx, "interface")?
+        .map(|s| s.value(cx))
+        .unwrap_or_default();
+    let direction = match obj.get_opt::<JsString, _, _>(cx, "direction")? {
+        Some(s) => match s.value(cx).as_str() {
+            "rx" => Some(Direction::Rx),
+            "tx" => Some(Direction::Tx),
+            other => return cx.throw_error(format!("Invalid direction: {}", other)),
+        },
+        None => None,
+    };
+    let remote = get_bool(cx, obj, "remote")?;
+    let remote_len = match obj.get_opt::<JsNumber, _, _>(cx, "dlc")? {
+        Some(dlc) => dlc.value(cx) as u8,
+        None if remote => data.len() as u8,
+        None => 0,
+    };
+    let xl = if get_bool(cx, obj, "xl")? {
+        let mut number = |key: &str| -> NeonResult<u32> {
+            Ok(obj
+                .get_opt::<JsNumber, _, _>(cx, key)?
+                .map(|v| v.value(cx) as u32)
+                .unwrap_or(0))
+        };
+        Some(XlHeader {
+            sdt: number("sdt")? as u8,
+            vcid: number("vcid")? as u8,
+            af: number("af")?,
+            sec: get_bool(cx, obj, "sec")?,
+        })
+    } else {
+        None
+    };
+
+    Ok(TraceFrame {
+        timestamp,
+        interface,
+        id,
+        extended: xl.is_none() && (get_bool(cx, obj, "extended")? || id > 0x7FF),
+        fd: get_bool(cx, obj, "fd")?,
+        brs: get_bool(cx, obj, "brs")?,
+        esi: get_bool(cx, obj, "esi")?,
+        remote,
+        error: get_bool(cx, obj, "error")?,
+        remote_len,
+        direction,
+        xl,
+        data: if remote { Vec::new() } else { data },
+    })
+}
+
+/// Convert a JavaScript array of frame objects
+pub fn frames_from_js<'a, C: Context<'a>>(
+    cx: &mut C,
+    array: Handle<'a, JsArray>,
+) -> NeonResult<Vec<TraceFrame>> {
+    let mut frames = Vec::new();
+    for value in array.to_vec(cx)? {
+        let obj = value.downcast_or_throw::<JsObject, _>(cx)?;
+        frames.push(frame_from_js(cx, obj)?);
+    }
+    Ok(frames)
+}
+
+/// Convert a trace frame into a JavaScript object shaped like `readFrame` results
+pub fn frame_to_js<'a, C: Context<'a>>(cx: &mut C, frame: &TraceFrame) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let v = cx.number(frame.timestamp.as_secs_f64());
+    obj.set(cx, "timestamp", v)?;
+    let v = cx.string(&frame.interface);
+    obj.set(cx, "interface", v)?;
+    let v = cx.number(frame.id as f64);
+    obj.set(cx, "id", v)?;
+
+    let data = cx.empty_array();
+    for (i, &byte) in frame.data.iter().enumerate() {
+        let v = cx.number(byte as f64);
+        data.set(cx, i as u32, v)?;
+    }
+    obj.set(cx, "data", data)?;
+
+    let flags = [
+        ("extended", frame.extended),
+        ("fd", frame.fd),
+        ("remote", frame.remote),
+        ("error", frame.error),
+    ];
+    for (key, value) in flags {
+        let v = cx.boolean(value);
+        obj.set(cx, key, v)?;
+    }
+    if frame.fd {
+        let v = cx.boolean(frame.brs);
+        obj.set(cx, "brs", v)?;
+        let v = cx.boolean(frame.esi);
+        obj.set(cx, "esi", v)?;
+    }
+    if frame.remote {
+        let v = cx.number(frame.remote_len as f64);
+        obj.set(cx, "dlc", v)?;
+    }
+    if let Some(xl) = frame.xl {
+        let v = cx.boolean(true);
+        obj.set(cx, "xl", v)?;
+        let v = cx.number(xl.sdt as f64);
+        obj.set(cx, "sdt", v)?;
+        let v = cx.number(xl.vcid as f64);
+        obj.set(cx, "vcid", v)?;
+        let v = cx.number(xl.af as f64);
+        obj.set(cx, "af", v)?;
+        let v = cx.boolean(xl.sec);
+        obj.set(cx, "sec", v)?;
+    }
+    if let Some(direction) = frame.direction {
+        let v = cx.string(match direction {
+            Direction::Rx => "rx",
+            Direction::Tx => "tx",
+        });
+        obj.set(cx, "direction", v)?;
+    }
+    Ok(obj)
+}
+
+/// Convert trace frames into a JavaScript array
+pub fn frames_to_js<'a, C: Context<'a>>(
+    cx: &mut C,
+    frames: &[TraceFrame],
+) -> JsResult<'a, JsArray> {
+    let array = cx.empty_array();
+    for (i, frame) in frames.iter().enumerate() {
+        let obj = frame_to_js(cx, frame)?;
+        array.set(cx, i as u32, obj)?;
+    }
+    Ok(array)
+}
+
+#[cfg(target_os = "linux")]
+impl TraceFrame {
+    /// Build a trace frame from a frame read on a SocketCAN socket
+    pub fn from_socket_frame(
+        frame: &socketcan::CanAnyFrame,
+        timestamp: Duration,
+        interface: &str,
+    ) -> Self {
+        use socketcan::{CanAnyFrame, EmbeddedFrame, Frame};
+
+        let word = frame.id_word();
+        let extended = word & libc::CAN_EFF_FLAG != 0;
+        let error = word & libc::CAN_ERR_FLAG != 0;
+        let id = if extended || error {
+            word & libc::CAN_EFF_MASK
+        } else {
+            word & libc::CAN_SFF_MASK
+        };
+
+        let mut trace = TraceFrame {
+            timestamp,
+            interface: interface.to_string(),
+            id,
+            extended: extended && !error,
+            error,
+            ..Default::default()
+        };
+        match frame {
+            CanAnyFrame::Remote(remote) => {
+                trace.remote = true;
+                trace.remote_len = remote.dlc() as u8;
+            }
+            CanAnyFrame::Fd(fd) => {
+                trace.fd = true;
+                trace.brs = fd.is_brs();
+                trace.esi = fd.is_esi();
+                trace.data = fd.data().to_vec();
+            }
+            _ => trace.data = frame.data().to_vec(),
+        }
+        trace
+    }
+}
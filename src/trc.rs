@@ -0,0 +1,397 @@
+//! PEAK PCAN-View `.trc` trace format, versions 1.1 to 2.1
+//!
+//! Version 1.x lines have fixed columns (`1)  1841.0  Rx  0123  8  ...`,
+//! with a bus column from 1.2); version 2.x lines follow the `$COLUMNS`
+//! header and carry CAN FD frames. Timestamps are millisecond offsets from
+//! `$STARTTIME`, a day count since 1899-12-30.
+
+use neon::prelude::*;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::trace::{
+    dlc_to_len, frames_from_js, frames_to_js, ChannelMap, DateTime, Direction, TraceFrame,
+};
+
+/// `$STARTTIME` day of the UNIX epoch
+const UNIX_EPOCH_DAYS: f64 = 25_569.0;
+
+/// Default columns of version 2.0 and 2.1 files
+const COLUMNS_2_0: &str = "N,O,T,I,d,l,D";
+const COLUMNS_2_1: &str = "N,O,T,B,I,d,R,L,D";
+
+fn parse_start_time(text: &str) -> Result<Duration, String> {
+    let days: f64 = text
+        .trim()
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| format!("invalid start time '{}'", text.trim()))?;
+    // Le jour est écrit avec 10 décimales : on arrondit à la milliseconde
+    let millis = ((days - UNIX_EPOCH_DAYS) * 86_400_000.0).round().max(0.0);
+    if millis >= u64::MAX as f64 {
+        return Err(format!("invalid start time '{}'", text.trim()));
+    }
+    Ok(Duration::from_millis(millis as u64))
+}
+
+fn parse_offset(text: &str) -> Result<Duration, String> {
+    let millis: f64 = text
+        .parse()
+        .map_err(|_| format!("invalid time offset '{}'", text))?;
+    let nanos = (millis * 1_000_000.0).round();
+    if !(0.0..u64::MAX as f64).contains(&nanos) {
+        return Err(format!("invalid time offset '{}'", text));
+    }
+    Ok(Duration::from_nanos(nanos as u64))
+}
+
+/// Identifier column: 8 hex digits mark an extended frame
+fn parse_id(text: &str) -> Result<(u32, bool), String> {
+    let id = u32::from_str_radix(text, 16).map_err(|_| format!("invalid ID '{}'", text))?;
+    Ok((id, text.len() > 4))
+}
+
+fn parse_data(fields: &[&str], len: usize) -> Result<Vec<u8>, String> {
+    if fields.len() < len {
+        return Err(format!(
+            "expected {} data bytes, found {}",
+            len,
+            fields.len()
+        ));
+    }
+    fields[..len]
+        .iter()
+        .map(|b| u8::from_str_radix(b, 16).map_err(|_| format!("invalid data byte '{}'", b)))
+        .collect()
+}
+
+fn parse_direction(text: &str) -> Option<Direction> {
+    match text {
+        "Rx" => Some(Direction::Rx),
+        "Tx" => Some(Direction::Tx),
+        _ => None,
+    }
+}
+
+/// Version 1.x line; `minor` selects the bus and reserved columns
+fn parse_line_v1(fields: &[&str], minor: u32) -> Result<Option<TraceFrame>, String> {
+    let bus = minor >= 2;
+    let typed = minor >= 1;
+    let reserved = minor >= 3;
+    let header = 2 + bus as usize + typed as usize + 1 + reserved as usize + 1;
+    if fields.len() < header {
+        return Err("truncated line".to_string());
+    }
+
+    let mut pos = 1;
+    let timestamp = parse_offset(fields[pos])?;
+    pos += 1;
+    let interface = if bus {
+        pos += 1;
+        fields[pos - 1].to_string()
+    } else {
+        String::new()
+    };
+    let kind = if typed {
+        pos += 1;
+        fields[pos - 1]
+    } else {
+        ""
+    };
+    match kind {
+        "Rx" | "Tx" | "" => {}
+        "Error" => {
+            return Ok(Some(TraceFrame {
+                timestamp,
+                interface,
+                error: true,
+                ..Default::default()
+            }))
+        }
+        _ => return Ok(None),
+    }
+
+    let (id, extended) = parse_id(fields[pos])?;
+    pos += 1 + reserved as usize;
+    let dlc: u8 = fields[pos]
+        .parse()
+        .map_err(|_| format!("invalid DLC '{}'", fields[pos]))?;
+    let rest = &fields[pos + 1..];
+    let remote = rest.first() == Some(&"RTR");
+
+    Ok(Some(TraceFrame {
+        timestamp,
+        interface,
+        id,
+        extended,
+        remote,
+        remote_len: if remote { dlc.min(8) } else { 0 },
+        direction: parse_direction(kind),
+        data: if remote {
+            Vec::new()
+        } else {
+            parse_data(rest, dlc_to_len(dlc, false))?
+        },
+        ..Default::default()
+    }))
+}
+
+/// Version 2.x line, laid out by the `$COLUMNS` header
+fn parse_line_v2(fields: &[&str], columns: &[char]) -> Result<Option<TraceFrame>, String> {
+    let mut frame = TraceFrame::default();
+    let mut dlc = None;
+    let mut len = None;
+    let mut pos = 0;
+    let mut next = |name: &str| {
+        pos += 1;
+        fields
+            .get(pos - 1)
+            .copied()
+            .ok_or(format!("missing {} column", name))
+    };
+
+    for &column in columns {
+        match column {
+            'N' => {
+                next("number")?;
+            }
+            'O' => frame.timestamp = parse_offset(next("time offset")?)?,
+            'T' => match next("type")? {
+                "DT" => {}
+                "FD" => frame.fd = true,
+                "FB" => (frame.fd, frame.brs) = (true, true),
+                "FE" => (frame.fd, frame.esi) = (true, true),
+                "BI" => (frame.fd, frame.brs, frame.esi) = (true, true, true),
+                "RR" => frame.remote = true,
+                "ER" => frame.error = true,
+                // Statut, compteurs d'erreurs et événements
+                _ => return Ok(None),
+            },
+            'B' => frame.interface = next("bus")?.to_string(),
+            'I' => {
+                let id = next("ID")?;
+                if !frame.error {
+                    (frame.id, frame.extended) = parse_id(id)?;
+                }
+            }
+            'd' => frame.direction = parse_direction(next("direction")?),
+            'R' => {
+                next("reserved")?;
+            }
+            'L' => {
+                let text = next("DLC")?;
+                dlc = Some(
+                    text.parse::<u8>()
+                        .map_err(|_| format!("invalid DLC '{}'", text))?,
+                );
+            }
+            'l' => {
+                let text = next("data length")?;
+                len = Some(
+                    text.parse::<usize>()
+                        .map_err(|_| format!("invalid data length '{}'", text))?,
+                );
+            }
+            'D' => {
+                // Les octets de l'erreur (type, compteurs) ne sont pas conservés
+                if frame.error {
+                    break;
+                }
+                let count = match (len, dlc) {
+                    (Some(len), _) => len,
+                    (None, Some(dlc)) => dlc_to_len(dlc, frame.fd),
+                    (None, None) => 0,
+                };
+                if frame.remote {
+                    frame.remote_len = dlc.unwrap_or(count as u8).min(8);
+                } else {
+                    frame.data = parse_data(&fields[pos.min(fields.len())..], count)?;
+                }
+                break;
+            }
+            _ => return Err(format!("unknown column '{}'", column)),
+        }
+    }
+    Ok(Some(frame))
+}
+
+/// Parse a TRC trace
+///
+/// Files without `$FILEVERSION` are read as version 1.1, and without
+/// `$STARTTIME` timestamps stay relative to the start of the trace.
+pub fn parse(text: &str) -> Result<Vec<TraceFrame>, String> {
+    let mut version = (1, 1);
+    let mut start = Duration::ZERO;
+    let mut columns: Option<Vec<char>> = None;
+    let mut frames = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let error = |e: String| format!("line {}: {}", number + 1, e);
+        if let Some(header) = line.strip_prefix(";$") {
+            let (key, value) = header.split_once('=').unwrap_or((header, ""));
+            match key.trim() {
+                "FILEVERSION" => {
+                    let (major, minor) = value.trim().split_once('.').unwrap_or((value, "0"));
+                    version = (
+                        major.parse().map_err(|_| error("invalid version".into()))?,
+                        minor.parse().map_err(|_| error("invalid version".into()))?,
+                    );
+                }
+                "STARTTIME" => start = parse_start_time(value).map_err(error)?,
+                "COLUMNS" => {
+                    columns = Some(
+                        value
+                            .split(',')
+                            .filter_map(|c| c.trim().chars().next())
+                            .collect(),
+                    )
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let frame = match version {
+            (1, minor) => parse_line_v1(&fields, minor),
+            (2, minor) => {
+                let columns = columns.get_or_insert_with(|| {
+                    let default = if minor == 0 { COLUMNS_2_0 } else { COLUMNS_2_1 };
+                    default
+                        .split(',')
+                        .filter_map(|c| c.chars().next())
+                        .collect()
+                });
+                parse_line_v2(&fields, columns)
+            }
+            (major, minor) => Err(format!("unsupported version {}.{}", major, minor)),
+        }
+        .map_err(error)?;
+        if let Some(frame) = frame {
+            let timestamp = start
+                .checked_add(frame.timestamp)
+                .ok_or_else(|| error("timestamp out of range".to_string()))?;
+            frames.push(TraceFrame { timestamp, ..frame });
+        }
+    }
+    Ok(frames)
+}
+
+/// Format frames as a version 2.1 TRC trace, offsets from the first frame
+///
+/// Buses follow the channel numbering of the Vector formats. CAN XL frames
+/// have no TRC representation and are skipped.
+pub fn format(frames: &[TraceFrame]) -> String {
+    let first = frames.first().map_or(Duration::ZERO, |f| f.timestamp);
+    let start = Duration::from_millis(first.as_millis() as u64);
+    let date = DateTime::from_unix(start);
+    let days = UNIX_EPOCH_DAYS + start.as_millis() as f64 / 86_400_000.0;
+
+    let mut out = String::new();
+    out.push_str(";$FILEVERSION=2.1\n");
+    let _ = writeln!(out, ";$STARTTIME={:.10}", days);
+    let _ = writeln!(out, ";$COLUMNS={}", COLUMNS_2_1);
+    out.push_str(";\n");
+    let _ = writeln!(
+        out,
+        ";   Start time: {:02}/{:02}/{} {:02}:{:02}:{:02}.{:03}.0",
+        date.month, date.day, date.year, date.hour, date.minute, date.second, date.millisecond
+    );
+    out.push_str(";   Generated by can-socket\n");
+    out.push_str(
+        ";-------------------------------------------------------------------------------\n\
+         ;   Message   Time    Type    ID     Rx/Tx\n\
+         ;   Number    Offset  |  Bus  [hex]  |  Reserved\n\
+         ;   |         [ms]    |  |    |      |  |  Data Length Code\n\
+         ;   |         |       |  |    |      |  |  |    Data [hex] ...\n\
+         ;   |         |       |  |    |      |  |  |    |\n\
+         ;---+-- ------+------ +- +- --+----- +- +- +--- +- -- -- -- -- -- -- --\n",
+    );
+
+    let mut channels = ChannelMap::default();
+    for (number, frame) in frames.iter().filter(|f| f.xl.is_none()).enumerate() {
+        let offset = frame.timestamp.saturating_sub(start).as_nanos() as f64 / 1_000_000.0;
+        let kind = match (frame.fd, frame.brs, frame.esi) {
+            _ if frame.error => "ER",
+            _ if frame.remote => "RR",
+            (false, ..) => "DT",
+            (true, false, false) => "FD",
+            (true, true, false) => "FB",
+            (true, false, true) => "FE",
+            (true, true, true) => "BI",
+        };
+        let id = if frame.error {
+            "-".to_string()
+        } else if frame.extended {
+            format!("{:08X}", frame.id)
+        } else {
+            format!("{:04X}", frame.id)
+        };
+        let direction = match frame.direction {
+            Some(Direction::Tx) => "Tx",
+            _ => "Rx",
+        };
+        let dlc = if frame.error { 0 } else { frame.dlc() };
+        let _ = write!(
+            out,
+            "{:>7} {:>13.3} {} {:>2} {:>8} {} -  {:<4}",
+            number + 1,
+            offset,
+            kind,
+            channels.channel(&frame.interface),
+            id,
+            direction,
+            dlc
+        );
+        if !frame.error && !frame.remote {
+            for byte in &frame.data {
+                let _ = write!(out, " {:02X}", byte);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Parse a TRC trace from JavaScript
+pub fn parse_trc_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let text = cx.argument::<JsString>(0)?.value(&mut cx);
+    match parse(&text) {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to parse TRC trace: {}", e)),
+    }
+}
+
+/// Read a TRC trace file from JavaScript
+pub fn read_trc_log(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let result = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| parse(&String::from_utf8_lossy(&bytes)));
+    match result {
+        Ok(frames) => frames_to_js(&mut cx, &frames),
+        Err(e) => cx.throw_error(format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+/// Format frames as a TRC trace from JavaScript
+pub fn format_trc_log(mut cx: FunctionContext) -> JsResult<JsString> {
+    let array = cx.argument::<JsArray>(0)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    Ok(cx.string(format(&frames)))
+}
+
+/// Write frames to a TRC trace file from JavaScript
+pub fn write_trc_log(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let path = cx.argument::<JsString>(0)?.value(&mut cx);
+    let array = cx.argument::<JsArray>(1)?;
+    let frames = frames_from_js(&mut cx, array)?;
+    match std::fs::write(&path, format(&frames)) {
+        Ok(()) => Ok(cx.undefined()),
+        Err(e) => cx.throw_error(format!("Failed to write {}: {}", path, e)),
+    }
+}
@@ -0,0 +1,770 @@
+//! UDS (ISO 14229) diagnostic client running over an ISO-TP channel
+
+use neon::prelude::*;
+use neon::types::buffer::TypedArray;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::isotp::{get_isotp_socket, IsoTpChannel};
+use crate::next_handle_id;
+
+const NEGATIVE_RESPONSE: u8 = 0x7F;
+const NRC_RESPONSE_PENDING: u8 = 0x78;
+const SUPPRESS_POSITIVE_RESPONSE: u8 = 0x80;
+
+const SID_DIAGNOSTIC_SESSION_CONTROL: u8 = 0x10;
+const SID_SECURITY_ACCESS: u8 = 0x27;
+const SID_TESTER_PRESENT: u8 = 0x3E;
+const SID_READ_DATA_BY_IDENTIFIER: u8 = 0x22;
+const SID_WRITE_DATA_BY_IDENTIFIER: u8 = 0x2E;
+const SID_ROUTINE_CONTROL: u8 = 0x31;
+const SID_REQUEST_DOWNLOAD: u8 = 0x34;
+const SID_TRANSFER_DATA: u8 = 0x36;
+const SID_REQUEST_TRANSFER_EXIT: u8 = 0x37;
+
+/// Errors raised by UDS requests
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum UdsError {
+    #[error("Negative response 0x{code:02X} ({}) to service 0x{service:02X}", nrc_name(*code))]
+    NegativeResponse { service: u8, code: u8 },
+    #[error("Timeout waiting for response to service 0x{0:02X}")]
+    Timeout(u8),
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("Transport error: {0}")]
+    Transport(String),
+}
+
+/// Human-readable name of a negative response code
+pub fn nrc_name(code: u8) -> &'static str {
+    match code {
+        0x10 => "generalReject",
+        0x11 => "serviceNotSupported",
+        0x12 => "subFunctionNotSupported",
+        0x13 => "incorrectMessageLengthOrInvalidFormat",
+        0x14 => "responseTooLong",
+        0x21 => "busyRepeatRequest",
+        0x22 => "conditionsNotCorrect",
+        0x24 => "requestSequenceError",
+        0x25 => "noResponseFromSubnetComponent",
+        0x26 => "failurePreventsExecutionOfRequestedAction",
+        0x31 => "requestOutOfRange",
+        0x33 => "securityAccessDenied",
+        0x35 => "invalidKey",
+        0x36 => "exceedNumberOfAttempts",
+        0x37 => "requiredTimeDelayNotExpired",
+        0x70 => "uploadDownloadNotAccepted",
+        0x71 => "transferDataSuspended",
+        0x72 => "generalProgrammingFailure",
+        0x73 => "wrongBlockSequenceCounter",
+        0x78 => "requestCorrectlyReceivedResponsePending",
+        0x7E => "subFunctionNotSupportedInActiveSession",
+        0x7F => "serviceNotSupportedInActiveSession",
+        0x81 => "rpmTooHigh",
+        0x82 => "rpmTooLow",
+        0x83 => "engineIsRunning",
+        0x84 => "engineIsNotRunning",
+        0x85 => "engineRunTimeTooLow",
+        0x86 => "temperatureTooHigh",
+        0x87 => "temperatureTooLow",
+        0x88 => "vehicleSpeedTooHigh",
+        0x89 => "vehicleSpeedTooLow",
+        0x8A => "throttlePedalTooHigh",
+        0x8B => "throttlePedalTooLow",
+        0x8C => "transmissionRangeNotInNeutral",
+        0x8D => "transmissionRangeNotInGear",
+        0x8F => "brakeSwitchNotClosed",
+        0x90 => "shifterLeverNotInPark",
+        0x91 => "torqueConverterClutchLocked",
+        0x92 => "voltageTooHigh",
+        0x93 => "voltageTooLow",
+        _ => "unknown",
+    }
+}
+
+/// Message transport used by the UDS client
+pub trait UdsTransport: Send + Sync {
+    /// Send one complete diagnostic message
+    fn send(&self, data: &[u8]) -> Result<(), String>;
+
+    /// Receive one complete diagnostic message, `None` on timeout
+    fn recv(&self, timeout: Duration) -> Result<Option<Vec<u8>>, String>;
+}
+
+impl UdsTransport for IsoTpChannel {
+    fn send(&self, data: &[u8]) -> Result<(), String> {
+        IsoTpChannel::send(self, data).map_err(|e| e.to_string())
+    }
+
+    fn recv(&self, timeout: Duration) -> Result<Option<Vec<u8>>, String> {
+        let timeout_ms = (timeout.as_millis() as u64).max(1);
+        match IsoTpChannel::recv(self, Some(timeout_ms)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) => match e.downcast_ref::<io::Error>() {
+                Some(io_err)
+                    if matches!(
+                        io_err.kind(),
+                        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                    ) =>
+                {
+                    Ok(None)
+                }
+                _ => Err(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Client-side application timing parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UdsTiming {
+    /// Time allowed for the first response
+    pub p2: Duration,
+    /// Time allowed after each responsePending (NRC 0x78)
+    pub p2_star: Duration,
+    /// Adopt the P2/P2* values reported by DiagnosticSessionControl
+    pub use_server_timing: bool,
+    /// Network delay (ΔP2) added to the server values
+    pub p2_margin: Duration,
+}
+
+impl Default for UdsTiming {
+    fn default() -> Self {
+        Self {
+            p2: Duration::from_millis(1000),
+            p2_star: Duration::from_millis(5000),
+            use_server_timing: true,
+            p2_margin: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Shortest timeout adopted from a server, whatever it reports
+const MIN_TIMEOUT: Duration = Duration::from_millis(10);
+
+impl UdsTiming {
+    /// Timing after the server reported P2server/P2*server: the server
+    /// values plus the network margin, never below the configured values
+    pub fn adopt(&self, p2_ms: u16, p2_star_ms: u32) -> UdsTiming {
+        let client = |server_ms: u64, configured: Duration| {
+            (Duration::from_millis(server_ms) + self.p2_margin).max(configured.max(MIN_TIMEOUT))
+        };
+        UdsTiming {
+            p2: client(p2_ms as u64, self.p2),
+            p2_star: client(p2_star_ms as u64, self.p2_star),
+            ..*self
+        }
+    }
+}
+
+/// Decoded positive responses
+#[derive(Debug, Clone, PartialEq)]
+pub enum UdsResponse {
+    Session {
+        session: u8,
+        p2_ms: u16,
+        p2_star_ms: u32,
+    },
+    Seed {
+        level: u8,
+        seed: Vec<u8>,
+    },
+    KeyAccepted {
+        level: u8,
+    },
+    Data {
+        did: u16,
+        data: Vec<u8>,
+    },
+    Written {
+        did: u16,
+    },
+    Routine {
+        control: u8,
+        routine_id: u16,
+        status: Vec<u8>,
+    },
+    Download {
+        max_block_length: usize,
+    },
+    Transfer {
+        block_sequence_counter: u8,
+        data: Vec<u8>,
+    },
+    TransferExit {
+        data: Vec<u8>,
+    },
+    Raw(Vec<u8>),
+    NoResponse,
+}
+
+struct KeepAlive {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// UDS client bound to one ISO-TP channel
+pub struct UdsClient<T: UdsTransport = IsoTpChannel> {
+    transport: Arc<T>,
+    /// Timing given at creation, the floor of adopted server timings
+    configured: UdsTiming,
+    timing: Mutex<UdsTiming>,
+    request_lock: Mutex<()>,
+    keep_alive: Mutex<Option<KeepAlive>>,
+}
+
+impl<T: UdsTransport + 'static> UdsClient<T> {
+    /// Create a client over `transport`
+    pub fn new(transport: Arc<T>, timing: UdsTiming) -> Self {
+        Self {
+            transport,
+            configured: timing,
+            timing: Mutex::new(timing),
+            request_lock: Mutex::new(()),
+            keep_alive: Mutex::new(None),
+        }
+    }
+
+    /// Current timing parameters
+    pub fn timing(&self) -> UdsTiming {
+        *self.timing.lock().unwrap()
+    }
+
+    /// Send a raw request and wait for its final response
+    ///
+    /// Negative responses become `UdsError::NegativeResponse`, except
+    /// responsePending which extends the wait by P2*. Returns an empty
+    /// vector when the suppress-positive-response bit is set and no
+    /// negative response arrives within P2.
+    pub fn request(&self, request: &[u8]) -> Result<Vec<u8>, UdsError> {
+        let service = *request
+            .first()
+            .ok_or_else(|| UdsError::InvalidResponse("empty request".to_string()))?;
+        let suppress = request.len() > 1
+            && has_sub_function(service)
+            && request[1] & SUPPRESS_POSITIVE_RESPONSE != 0;
+        let timing = self.timing();
+
+        let _guard = self.request_lock.lock().unwrap();
+        self.transport.send(request).map_err(UdsError::Transport)?;
+
+        let mut deadline = Instant::now() + timing.p2;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return if suppress {
+                    Ok(Vec::new())
+                } else {
+                    Err(UdsError::Timeout(service))
+                };
+            }
+            let Some(response) = self
+                .transport
+                .recv(remaining)
+                .map_err(UdsError::Transport)?
+            else {
+                continue;
+            };
+
+            match response.as_slice() {
+                [NEGATIVE_RESPONSE, sid, NRC_RESPONSE_PENDING, ..] if *sid == service => {
+                    deadline = Instant::now() + timing.p2_star;
+                }
+                [NEGATIVE_RESPONSE, sid, code, ..] if *sid == service => {
+                    return Err(UdsError::NegativeResponse {
+                        service,
+                        code: *code,
+                    });
+                }
+                [sid, ..] if *sid == service.wrapping_add(0x40) => return Ok(response),
+                // Late responses to earlier requests are dropped
+                _ => {}
+            }
+        }
+    }
+
+    /// DiagnosticSessionControl (0x10)
+    pub fn diagnostic_session_control(&self, session: u8) -> Result<UdsResponse, UdsError> {
+        let response = self.request(&[SID_DIAGNOSTIC_SESSION_CONTROL, session])?;
+        if response.is_empty() {
+            return Ok(UdsResponse::NoResponse);
+        }
+        expect_echo(&response, 1, session & !SUPPRESS_POSITIVE_RESPONSE)?;
+        let (p2_ms, p2_star_ms) = if response.len() >= 6 {
+            (
+                u16::from_be_bytes([response[2], response[3]]),
+                u16::from_be_bytes([response[4], response[5]]) as u32 * 10,
+            )
+        } else {
+            (0, 0)
+        };
+
+        if self.configured.use_server_timing && response.len() >= 6 {
+            *self.timing.lock().unwrap() = self.configured.adopt(p2_ms, p2_star_ms);
+        }
+        Ok(UdsResponse::Session {
+            session: session & 0x7F,
+            p2_ms,
+            p2_star_ms,
+        })
+    }
+
+    /// SecurityAccess requestSeed (odd level)
+    pub fn security_access_seed(&self, level: u8) -> Result<UdsResponse, UdsError> {
+        if level & 0x01 == 0 {
+            return Err(UdsError::InvalidResponse(format!(
+                "seed levels are odd, got 0x{:02X}",
+                level
+            )));
+        }
+        let response = self.request(&[SID_SECURITY_ACCESS, level])?;
+        if response.is_empty() {
+            return Ok(UdsResponse::NoResponse);
+        }
+        expect_echo(&response, 1, level & !SUPPRESS_POSITIVE_RESPONSE)?;
+        Ok(UdsResponse::Seed {
+            level,
+            seed: response[2..].to_vec(),
+        })
+    }
+
+    /// SecurityAccess sendKey for the seed obtained at `level`
+    pub fn security_access_key(&self, level: u8, key: &[u8]) -> Result<UdsResponse, UdsError> {
+        let key_level = level.wrapping_add(1);
+        let mut request = vec![SID_SECURITY_ACCESS, key_level];
+        request.extend_from_slice(key);
+        let response = self.request(&request)?;
+        if response.is_empty() {
+            return Ok(UdsResponse::NoResponse);
+        }
+        expect_echo(&response, 1, key_level & !SUPPRESS_POSITIVE_RESPONSE)?;
+        Ok(UdsResponse::KeyAccepted { level: key_level })
+    }
+
+    /// ReadDataByIdentifier (0x22) for a single DID
+    pub fn read_data_by_identifier(&self, did: u16) -> Result<UdsResponse, UdsError> {
+        let [hi, lo] = did.to_be_bytes();
+        let response = self.request(&[SID_READ_DATA_BY_IDENTIFIER, hi, lo])?;
+        expect_echo(&response, 2, lo)?;
+        if response[1] != hi {
+            return Err(UdsError::InvalidResponse(format!(
+                "expected DID 0x{:04X}",
+                did
+            )));
+        }
+        Ok(UdsResponse::Data {
+            did,
+            data: response[3..].to_vec(),
+        })
+    }
+
+    /// WriteDataByIdentifier (0x2E)
+    pub fn write_data_by_identifier(&self, did: u16, data: &[u8]) -> Result<UdsResponse, UdsError> {
+        let [hi, lo] = did.to_be_bytes();
+        let mut request = vec![SID_WRITE_DATA_BY_IDENTIFIER, hi, lo];
+        request.extend_from_slice(data);
+        let response = self.request(&request)?;
+        expect_echo(&response, 2, lo)?;
+        Ok(UdsResponse::Written { did })
+    }
+
+    /// RoutineControl (0x31): 1 = start, 2 = stop, 3 = request results
+    pub fn routine_control(
+        &self,
+        control: u8,
+        routine_id: u16,
+        data: &[u8],
+    ) -> Result<UdsResponse, UdsError> {
+        let [hi, lo] = routine_id.to_be_bytes();
+        let mut request = vec![SID_ROUTINE_CONTROL, control, hi, lo];
+        request.extend_from_slice(data);
+        let response = self.request(&request)?;
+        if response.is_empty() {
+            return Ok(UdsResponse::NoResponse);
+        }
+        expect_echo(&response, 1, control & !SUPPRESS_POSITIVE_RESPONSE)?;
+        expect_echo(&response, 2, hi)?;
+        expect_echo(&response, 3, lo)?;
+        Ok(UdsResponse::Routine {
+            control,
+            routine_id,
+            status: response[4..].to_vec(),
+        })
+    }
+
+    /// RequestDownload (0x34) with 4-byte address and size fields
+    pub fn request_download(
+        &self,
+        address: u32,
+        size: u32,
+        data_format: u8,
+    ) -> Result<UdsResponse, UdsError> {
+        let mut request = vec![SID_REQUEST_DOWNLOAD, data_format, 0x44];
+        request.extend_from_slice(&address.to_be_bytes());
+        request.extend_from_slice(&size.to_be_bytes());
+        let response = self.request(&request)?;
+
+        let length_len = (*response.get(1).ok_or_else(|| {
+            UdsError::InvalidResponse("missing lengthFormatIdentifier".to_string())
+        })? >> 4) as usize;
+        if length_len == 0 || length_len > 8 || response.len() < 2 + length_len {
+            return Err(UdsError::InvalidResponse(
+                "invalid maxNumberOfBlockLength".to_string(),
+            ));
+        }
+        let max_block_length = response[2..2 + length_len]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        Ok(UdsResponse::Download { max_block_length })
+    }
+
+    /// TransferData (0x36)
+    pub fn transfer_data(
+        &self,
+        block_sequence_counter: u8,
+        data: &[u8],
+    ) -> Result<UdsResponse, UdsError> {
+        let mut request = vec![SID_TRANSFER_DATA, block_sequence_counter];
+        request.extend_from_slice(data);
+        let response = self.request(&request)?;
+        expect_echo(&response, 1, block_sequence_counter)?;
+        Ok(UdsResponse::Transfer {
+            block_sequence_counter,
+            data: response[2..].to_vec(),
+        })
+    }
+
+    /// RequestTransferExit (0x37)
+    pub fn request_transfer_exit(&self, data: &[u8]) -> Result<UdsResponse, UdsError> {
+        let mut request = vec![SID_REQUEST_TRANSFER_EXIT];
+        request.extend_from_slice(data);
+        let response = self.request(&request)?;
+        Ok(UdsResponse::TransferExit {
+            data: response[1..].to_vec(),
+        })
+    }
+
+    /// Send TesterPresent (suppressed) every `interval` until stopped
+    pub fn start_tester_present(self: &Arc<Self>, interval: Duration) {
+        self.stop_tester_present();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let client = Arc::clone(self);
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let step = Duration::from_millis(10).min(interval);
+            let mut next = Instant::now() + interval;
+            while !thread_stop.load(Ordering::Relaxed) {
+                if Instant::now() >= next {
+                    let _guard = client.request_lock.lock().unwrap();
+                    let _ = client
+                        .transport
+                        .send(&[SID_TESTER_PRESENT, SUPPRESS_POSITIVE_RESPONSE]);
+                    next = Instant::now() + interval;
+                }
+                thread::sleep(step);
+            }
+        });
+        *self.keep_alive.lock().unwrap() = Some(KeepAlive { stop, handle });
+    }
+
+    /// Stop the TesterPresent keep-alive thread, if running
+    pub fn stop_tester_present(&self) {
+        if let Some(keep_alive) = self.keep_alive.lock().unwrap().take() {
+            keep_alive.stop.store(true, Ordering::Relaxed);
+            let _ = keep_alive.handle.join();
+        }
+    }
+}
+
+/// Services whose second byte is a sub-function carrying the SPRMIB bit
+fn has_sub_function(service: u8) -> bool {
+    matches!(
+        service,
+        0x10 | 0x11 | 0x19 | 0x27 | 0x28 | 0x31 | 0x3E | 0x85
+    )
+}
+
+/// Check that `response[index]` echoes `expected`
+fn expect_echo(response: &[u8], index: usize, expected: u8) -> Result<(), UdsError> {
+    match response.get(index) {
+        Some(&value) if value == expected => Ok(()),
+        Some(&value) => Err(UdsError::InvalidResponse(format!(
+            "expected 0x{:02X} at byte {}, got 0x{:02X}",
+            expected, index, value
+        ))),
+        None => Err(UdsError::InvalidResponse(format!(
+            "response too short ({} bytes)",
+            response.len()
+        ))),
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref UDS_REGISTRY: Arc<Mutex<HashMap<u32, Arc<UdsClient>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
+fn get_uds_client(client_id: u32) -> Option<Arc<UdsClient>> {
+    UDS_REGISTRY.lock().unwrap().get(&client_id).cloned()
+}
+
+/// Convert a decoded response into a JS object
+fn response_to_js<'a>(cx: &mut TaskContext<'a>, response: UdsResponse) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    match response {
+        UdsResponse::Session {
+            session,
+            p2_ms,
+            p2_star_ms,
+        } => {
+            let v = cx.number(session as f64);
+            obj.set(cx, "session", v)?;
+            let v = cx.number(p2_ms as f64);
+            obj.set(cx, "p2", v)?;
+            let v = cx.number(p2_star_ms as f64);
+            obj.set(cx, "p2Star", v)?;
+        }
+        UdsResponse::Seed { level, seed } => {
+            let v = cx.number(level as f64);
+            obj.set(cx, "level", v)?;
+            let v = JsBuffer::from_slice(cx, &seed)?;
+            obj.set(cx, "seed", v)?;
+        }
+        UdsResponse::KeyAccepted { level } => {
+            let v = cx.number(level as f64);
+            obj.set(cx, "level", v)?;
+        }
+        UdsResponse::Data { did, data } => {
+            let v = cx.number(did as f64);
+            obj.set(cx, "did", v)?;
+            let v = JsBuffer::from_slice(cx, &data)?;
+            obj.set(cx, "data", v)?;
+        }
+        UdsResponse::Written { did } => {
+            let v = cx.number(did as f64);
+            obj.set(cx, "did", v)?;
+        }
+        UdsResponse::Routine {
+            control,
+            routine_id,
+            status,
+        } => {
+            let v = cx.number(control as f64);
+            obj.set(cx, "control", v)?;
+            let v = cx.number(routine_id as f64);
+            obj.set(cx, "routineId", v)?;
+            let v = JsBuffer::from_slice(cx, &status)?;
+            obj.set(cx, "status", v)?;
+        }
+        UdsResponse::Download { max_block_length } => {
+            let v = cx.number(max_block_length as f64);
+            obj.set(cx, "maxBlockLength", v)?;
+        }
+        UdsResponse::Transfer {
+            block_sequence_counter,
+            data,
+        } => {
+            let v = cx.number(block_sequence_counter as f64);
+            obj.set(cx, "blockSequenceCounter", v)?;
+            let v = JsBuffer::from_slice(cx, &data)?;
+            obj.set(cx, "data", v)?;
+        }
+        UdsResponse::TransferExit { data } | UdsResponse::Raw(data) => {
+            let v = JsBuffer::from_slice(cx, &data)?;
+            obj.set(cx, "data", v)?;
+        }
+        UdsResponse::NoResponse => {}
+    }
+    Ok(obj)
+}
+
+/// Run a UDS call on the libuv thread pool and resolve its decoded response
+fn uds_promise<'a, F>(cx: &mut FunctionContext<'a>, call: F) -> JsResult<'a, JsPromise>
+where
+    F: FnOnce(&UdsClient) -> Result<UdsResponse, UdsError> + Send + 'static,
+{
+    let client_id = cx.argument::<JsNumber>(0)?.value(cx) as u32;
+    let Some(client) = get_uds_client(client_id) else {
+        return cx.throw_error("Invalid UDS client ID");
+    };
+
+    let promise = cx
+        .task(move || call(&client))
+        .promise(move |mut cx, result| match result {
+            Ok(response) => response_to_js(&mut cx, response),
+            Err(e) => {
+                let err = cx.error(e.to_string())?;
+                if let UdsError::NegativeResponse { service, code } = e {
+                    let v = cx.number(service as f64);
+                    err.set(&mut cx, "service", v)?;
+                    let v = cx.number(code as f64);
+                    err.set(&mut cx, "nrc", v)?;
+                    let v = cx.string(nrc_name(code));
+                    err.set(&mut cx, "nrcName", v)?;
+                }
+                cx.throw(err)
+            }
+        });
+    Ok(promise)
+}
+
+fn buffer_arg(cx: &mut FunctionContext, index: usize) -> NeonResult<Vec<u8>> {
+    match cx.argument_opt(index) {
+        Some(value) if !value.is_a::<JsUndefined, _>(cx) => Ok(value
+            .downcast_or_throw::<JsBuffer, _>(cx)?
+            .as_slice(cx)
+            .to_vec()),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Create a UDS client over an existing ISO-TP socket
+pub fn create_uds_client(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let isotp_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let mut timing = UdsTiming::default();
+    if let Some(options) = cx
+        .argument_opt(1)
+        .and_then(|v| v.downcast::<JsObject, _>(&mut cx).ok())
+    {
+        if let Some(p2) = options.get_opt::<JsNumber, _, _>(&mut cx, "p2")? {
+            timing.p2 = Duration::from_millis(p2.value(&mut cx) as u64);
+        }
+        if let Some(p2_star) = options.get_opt::<JsNumber, _, _>(&mut cx, "p2Star")? {
+            timing.p2_star = Duration::from_millis(p2_star.value(&mut cx) as u64);
+        }
+        if let Some(flag) = options.get_opt::<JsBoolean, _, _>(&mut cx, "useServerTiming")? {
+            timing.use_server_timing = flag.value(&mut cx);
+        }
+        if let Some(margin) = options.get_opt::<JsNumber, _, _>(&mut cx, "p2Margin")? {
+            timing.p2_margin = Duration::from_millis(margin.value(&mut cx) as u64);
+        }
+    }
+
+    let Some(channel) = get_isotp_socket(isotp_id) else {
+        return cx.throw_error("Invalid ISO-TP socket ID");
+    };
+    let id = next_handle_id();
+    UDS_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(id, Arc::new(UdsClient::new(channel, timing)));
+    Ok(cx.number(id as f64))
+}
+
+/// DiagnosticSessionControl from JavaScript
+pub fn uds_diagnostic_session_control(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let session = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    uds_promise(&mut cx, move |client| {
+        client.diagnostic_session_control(session)
+    })
+}
+
+/// SecurityAccess requestSeed from JavaScript
+pub fn uds_security_access_seed(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let level = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    uds_promise(&mut cx, move |client| client.security_access_seed(level))
+}
+
+/// SecurityAccess sendKey from JavaScript
+pub fn uds_security_access_key(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let level = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let key = buffer_arg(&mut cx, 2)?;
+    uds_promise(&mut cx, move |client| {
+        client.security_access_key(level, &key)
+    })
+}
+
+/// ReadDataByIdentifier from JavaScript
+pub fn uds_read_data_by_identifier(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let did = cx.argument::<JsNumber>(1)?.value(&mut cx) as u16;
+    uds_promise(&mut cx, move |client| client.read_data_by_identifier(did))
+}
+
+/// WriteDataByIdentifier from JavaScript
+pub fn uds_write_data_by_identifier(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let did = cx.argument::<JsNumber>(1)?.value(&mut cx) as u16;
+    let data = buffer_arg(&mut cx, 2)?;
+    uds_promise(&mut cx, move |client| {
+        client.write_data_by_identifier(did, &data)
+    })
+}
+
+/// RoutineControl from JavaScript
+pub fn uds_routine_control(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let control = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let routine_id = cx.argument::<JsNumber>(2)?.value(&mut cx) as u16;
+    let data = buffer_arg(&mut cx, 3)?;
+    uds_promise(&mut cx, move |client| {
+        client.routine_control(control, routine_id, &data)
+    })
+}
+
+/// RequestDownload from JavaScript
+pub fn uds_request_download(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let address = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
+    let size = cx.argument::<JsNumber>(2)?.value(&mut cx) as u32;
+    let data_format = match cx.argument_opt(3) {
+        Some(v) => v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u8,
+        None => 0x00,
+    };
+    uds_promise(&mut cx, move |client| {
+        client.request_download(address, size, data_format)
+    })
+}
+
+/// TransferData from JavaScript
+pub fn uds_transfer_data(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let counter = cx.argument::<JsNumber>(1)?.value(&mut cx) as u8;
+    let data = buffer_arg(&mut cx, 2)?;
+    uds_promise(&mut cx, move |client| client.transfer_data(counter, &data))
+}
+
+/// RequestTransferExit from JavaScript
+pub fn uds_request_transfer_exit(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let data = buffer_arg(&mut cx, 1)?;
+    uds_promise(&mut cx, move |client| client.request_transfer_exit(&data))
+}
+
+/// Arbitrary UDS request from JavaScript, resolving to the raw positive response
+pub fn uds_request(mut cx: FunctionContext) -> JsResult<JsPromise> {
+    let request = buffer_arg(&mut cx, 1)?;
+    uds_promise(&mut cx, move |client| {
+        client.request(&request).map(UdsResponse::Raw)
+    })
+}
+
+/// Start or stop the TesterPresent keep-alive from JavaScript
+pub fn uds_tester_present(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let interval_ms = match cx.argument_opt(1) {
+        Some(v) => v.downcast_or_throw::<JsNumber, _>(&mut cx)?.value(&mut cx) as u64,
+        None => 2000,
+    };
+
+    let Some(client) = get_uds_client(client_id) else {
+        return cx.throw_error("Invalid UDS client ID");
+    };
+    if interval_ms == 0 {
+        client.stop_tester_present();
+    } else {
+        client.start_tester_present(Duration::from_millis(interval_ms));
+    }
+    Ok(cx.undefined())
+}
+
+/// Close a UDS client from JavaScript (the ISO-TP socket stays open)
+pub fn close_uds_client(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    match UDS_REGISTRY.lock().unwrap().remove(&client_id) {
+        Some(client) => {
+            client.stop_tester_present();
+            Ok(cx.undefined())
+        }
+        None => cx.throw_error("Invalid UDS client ID"),
+    }
+}
@@ -174,6 +174,1095 @@ export interface NativeSocketCAN {
    * @param socketId Socket ID
    */
   closeSocket(socketId: number): void;
+
+  /**
+   * Create an ISO-TP (ISO 15765-2) socket, kernel-backed unless
+   * `options.userspace` selects the userspace engine
+   * @param interfaceName CAN interface name (e.g., 'can0', 'vcan0')
+   * @param txId CAN ID used for transmitted frames
+   * @param rxId CAN ID of received frames
+   * @param options ISO-TP options (optional)
+   * @returns Created ISO-TP socket ID
+   */
+  createIsoTpSocket(
+    interfaceName: string,
+    txId: number,
+    rxId: number,
+    options?: IsoTpOptions
+  ): number;
+
+  /**
+   * Send a complete ISO-TP message (up to 4095 bytes, more with FD)
+   * @param socketId ISO-TP socket ID
+   * @param data Message payload
+   */
+  isoTpSend(socketId: number, data: Buffer): void;
+
+  /**
+   * Receive a complete ISO-TP message
+   * @param socketId ISO-TP socket ID
+   * @param timeout Timeout in milliseconds (optional, default 1000)
+   * @returns Received message payload
+   */
+  isoTpRecv(socketId: number, timeout?: number): Buffer;
+
+  /**
+   * Close an ISO-TP socket
+   * @param socketId ISO-TP socket ID
+   */
+  closeIsoTpSocket(socketId: number): void;
+
+  /**
+   * Create a UDS (ISO 14229) client over an ISO-TP socket
+   * @param isoTpSocketId ISO-TP socket ID from createIsoTpSocket
+   * @param options Client timing options (optional)
+   * @returns Created UDS client ID
+   */
+  createUdsClient(isoTpSocketId: number, options?: UdsClientOptions): number;
+
+  /**
+   * Send a raw UDS request and resolve with the positive response
+   * @param clientId UDS client ID
+   * @param request Request bytes, starting with the service ID
+   */
+  udsRequest(clientId: number, request: Buffer): Promise<{ data: Buffer }>;
+
+  /** DiagnosticSessionControl (0x10) */
+  udsDiagnosticSessionControl(
+    clientId: number,
+    session: number
+  ): Promise<{ session: number; p2: number; p2Star: number }>;
+
+  /** SecurityAccess requestSeed (odd level) */
+  udsSecurityAccessSeed(
+    clientId: number,
+    level: number
+  ): Promise<{ level: number; seed: Buffer }>;
+
+  /** SecurityAccess sendKey for the seed obtained at `level` */
+  udsSecurityAccessKey(
+    clientId: number,
+    level: number,
+    key: Buffer
+  ): Promise<{ level: number }>;
+
+  /** ReadDataByIdentifier (0x22) */
+  udsReadDataByIdentifier(
+    clientId: number,
+    did: number
+  ): Promise<{ did: number; data: Buffer }>;
+
+  /** WriteDataByIdentifier (0x2E) */
+  udsWriteDataByIdentifier(
+    clientId: number,
+    did: number,
+    data: Buffer
+  ): Promise<{ did: number }>;
+
+  /** RoutineControl (0x31): 1 = start, 2 = stop, 3 = results */
+  udsRoutineControl(
+    clientId: number,
+    control: number,
+    routineId: number,
+    data?: Buffer
+  ): Promise<{ control: number; routineId: number; status: Buffer }>;
+
+  /** RequestDownload (0x34) with 32-bit address and size */
+  udsRequestDownload(
+    clientId: number,
+    address: number,
+    size: number,
+    dataFormat?: number
+  ): Promise<{ maxBlockLength: number }>;
+
+  /** TransferData (0x36) */
+  udsTransferData(
+    clientId: number,
+    blockSequenceCounter: number,
+    data: Buffer
+  ): Promise<{ blockSequenceCounter: number; data: Buffer }>;
+
+  /** RequestTransferExit (0x37) */
+  udsRequestTransferExit(
+    clientId: number,
+    data?: Buffer
+  ): Promise<{ data: Buffer }>;
+
+  /**
+   * Start a suppressed TesterPresent keep-alive (interval 0 stops it)
+   * @param clientId UDS client ID
+   * @param intervalMs Keep-alive period in milliseconds (default 2000)
+   */
+  udsTesterPresent(clientId: number, intervalMs?: number): void;
+
+  /**
+   * Close a UDS client (the ISO-TP socket stays open)
+   * @param clientId UDS client ID
+   */
+  closeUdsClient(clientId: number): void;
+
+  /**
+   * Create a kernel J1939 socket, claiming an address when a NAME is given
+   * @param interfaceName CAN interface name (e.g., 'can0', 'vcan0')
+   * @param options J1939 options (optional)
+   * @returns Created J1939 socket ID
+   */
+  createJ1939Socket(interfaceName: string, options?: J1939Options): number;
+
+  /**
+   * Pack J1939 NAME fields into a 64-bit NAME
+   * @param fields NAME fields
+   */
+  j1939EncodeName(fields: J1939NameFields): bigint;
+
+  /**
+   * Get the source address currently used by a J1939 socket
+   * @param socketId J1939 socket ID
+   */
+  j1939GetAddress(socketId: number): number;
+
+  /**
+   * Send a J1939 message (TP/ETP is used automatically above 8 bytes)
+   * @param socketId J1939 socket ID
+   * @param pgn Parameter group number
+   * @param data Message payload
+   * @param options Destination address (default 0xFF) and priority
+   */
+  j1939Send(
+    socketId: number,
+    pgn: number,
+    data: Buffer,
+    options?: { da?: number; priority?: number }
+  ): void;
+
+  /**
+   * Receive a J1939 message
+   * @param socketId J1939 socket ID
+   * @param timeout Timeout in milliseconds (optional, default 1000)
+   */
+  j1939Recv(socketId: number, timeout?: number): J1939Message;
+
+  /**
+   * Close a J1939 socket
+   * @param socketId J1939 socket ID
+   */
+  closeJ1939Socket(socketId: number): void;
+
+  /**
+   * Decode the J1939 fields of a 29-bit identifier
+   * @param id Extended CAN identifier
+   */
+  decodeJ1939Id(id: number): J1939IdFields;
+
+  /**
+   * Build a 29-bit identifier from J1939 fields
+   * @param priority Priority (0-7)
+   * @param pgn Parameter group number
+   * @param sa Source address
+   * @param da Destination address for PDU1 PGNs (default 0xFF)
+   */
+  encodeJ1939Id(priority: number, pgn: number, sa: number, da?: number): number;
+
+  /**
+   * Create an SPN definition table
+   * @param definitions Additional SPN definitions (override standard ones)
+   * @param includeStandard Include common J1939-71 SPNs (default true)
+   * @returns Table ID
+   */
+  createJ1939SpnTable(
+    definitions?: SpnDefinition[],
+    includeStandard?: boolean
+  ): number;
+
+  /**
+   * Load an SPN table from a CSV file (spn,pgn,name,start_bit,length,scale,offset,unit)
+   * @param path CSV file path
+   * @param includeStandard Include common J1939-71 SPNs (default false)
+   * @returns Table ID
+   */
+  loadJ1939SpnTable(path: string, includeStandard?: boolean): number;
+
+  /**
+   * Decode a raw extended frame into PGN, addresses and SPN values
+   * @param tableId SPN table ID
+   * @param frame Frame returned by readFrame
+   */
+  decodeJ1939Frame(tableId: number, frame: CanFrame): J1939DecodedFrame;
+
+  /**
+   * Release an SPN table
+   * @param tableId SPN table ID
+   */
+  closeJ1939SpnTable(tableId: number): void;
+
+  /**
+   * Create a CANopen master on an interface
+   * @param interfaceName CAN interface name (e.g., 'can0')
+   * @returns Master ID
+   */
+  createCanOpenMaster(interfaceName: string): number;
+
+  /**
+   * Send an NMT command
+   * @param masterId CANopen master ID
+   * @param command Command name or command specifier
+   * @param nodeId Target node (0 = all nodes, default 0)
+   */
+  canopenNmt(masterId: number, command: NmtCommand | number, nodeId?: number): void;
+
+  /**
+   * Supervise a node's heartbeat
+   * @param masterId CANopen master ID
+   * @param nodeId Node ID
+   * @param timeoutMs Heartbeat consumer time (0 disables supervision)
+   */
+  canopenSetHeartbeatTimeout(masterId: number, nodeId: number, timeoutMs: number): void;
+
+  /**
+   * List known nodes and their NMT state
+   * @param masterId CANopen master ID
+   */
+  canopenGetNodeStates(masterId: number): CanOpenNodeStatus[];
+
+  /**
+   * Register a callback for boot-up, state change and heartbeat timeout events
+   * @param masterId CANopen master ID
+   * @param callback Event handler (omit to unregister)
+   */
+  canopenOnNodeEvent(masterId: number, callback?: (event: CanOpenNodeEvent) => void): void;
+
+  /**
+   * Read an object through SDO
+   * @param masterId CANopen master ID
+   * @param nodeId Node ID
+   * @param index Object index
+   * @param subindex Object sub-index
+   * @param options Transfer options
+   * @returns Promise rejected with a SdoAbortError on abort
+   */
+  canopenSdoUpload(
+    masterId: number,
+    nodeId: number,
+    index: number,
+    subindex: number,
+    options?: SdoOptions
+  ): Promise<Buffer>;
+
+  /**
+   * Write an object through SDO
+   * @param masterId CANopen master ID
+   * @param nodeId Node ID
+   * @param index Object index
+   * @param subindex Object sub-index
+   * @param data Object value
+   * @param options Transfer options
+   */
+  canopenSdoDownload(
+    masterId: number,
+    nodeId: number,
+    index: number,
+    subindex: number,
+    data: Buffer,
+    options?: SdoOptions
+  ): Promise<void>;
+
+  /**
+   * Stop a CANopen master
+   * @param masterId CANopen master ID
+   */
+  closeCanOpenMaster(masterId: number): void;
+
+  /**
+   * Produce SYNC messages
+   * @param masterId CANopen master ID
+   * @param periodMs Communication cycle period in milliseconds
+   * @param counterOverflow Include a counter wrapping after this value (2-240)
+   */
+  canopenStartSync(masterId: number, periodMs: number, counterOverflow?: number): void;
+
+  /**
+   * Stop producing SYNC messages
+   * @param masterId CANopen master ID
+   */
+  canopenStopSync(masterId: number): void;
+
+  /**
+   * Decode an EMCY frame
+   * @param frame Frame returned by readFrame
+   * @returns Decoded emergency, or null if the frame is not an EMCY
+   */
+  canopenDecodeEmcy(frame: CanFrame): CanOpenEmergency | null;
+
+  /**
+   * Write a PDO mapping to a node through SDO
+   * @param masterId CANopen master ID
+   * @param nodeId Node ID
+   * @param dictionaryId Object dictionary ID
+   * @param pdo Inline mapping, or the name of a mapping of the dictionary (e.g., 'tpdo1')
+   * @param options SDO options
+   */
+  canopenWritePdoMapping(
+    masterId: number,
+    nodeId: number,
+    dictionaryId: number,
+    pdo: PdoMappingSpec | string,
+    options?: SdoOptions
+  ): Promise<void>;
+
+  /**
+   * Load an EDS or DCF object dictionary
+   * @param path File path
+   * @param nodeId Node ID used to resolve $NODEID (default: DCF NodeID)
+   * @returns Object dictionary ID
+   */
+  loadCanOpenEds(path: string, nodeId?: number): number;
+
+  /**
+   * Describe a dictionary object
+   * @param dictionaryId Object dictionary ID
+   * @param key Object name or index
+   * @param subindex Sub-index when key is an index (default 0)
+   */
+  canopenGetObject(
+    dictionaryId: number,
+    key: string | number,
+    subindex?: number
+  ): CanOpenObject | undefined;
+
+  /**
+   * List the RPDO/TPDO mappings of a dictionary
+   * @param dictionaryId Object dictionary ID
+   */
+  canopenGetPdoMappings(dictionaryId: number): PdoMappingInfo[];
+
+  /**
+   * Add or replace a PDO mapping
+   * @param dictionaryId Object dictionary ID
+   * @param mapping Mapping definition
+   */
+  canopenSetPdoMapping(dictionaryId: number, mapping: PdoMappingSpec): void;
+
+  /**
+   * Decode a received PDO frame into named object values
+   * @param dictionaryId Object dictionary ID
+   * @param frame Frame returned by readFrame
+   * @returns Decoded PDO, or null if no enabled PDO uses this identifier
+   */
+  canopenDecodePdo(dictionaryId: number, frame: CanFrame): DecodedPdo | null;
+
+  /**
+   * Encode a PDO frame from named object values
+   * @param dictionaryId Object dictionary ID
+   * @param kind PDO direction, seen from the device
+   * @param number PDO number (starting at 1)
+   * @param values Object values by name (missing ones use the dictionary value)
+   * @returns Frame ready for sendFrame
+   */
+  canopenEncodePdo(
+    dictionaryId: number,
+    kind: PdoKind,
+    number: number,
+    values: Record<string, CanOpenValue>
+  ): { id: number; data: number[] };
+
+  /**
+   * Release an object dictionary
+   * @param dictionaryId Object dictionary ID
+   */
+  closeCanOpenEds(dictionaryId: number): void;
+
+  /**
+   * Load a DBC file
+   * @param path Path to the .dbc file
+   * @returns Database handle
+   */
+  loadDbc(path: string): number;
+
+  /**
+   * Parse DBC content from a string
+   * @param text DBC file content
+   * @returns Database handle
+   */
+  parseDbc(text: string): number;
+
+  /**
+   * Load a DBC, ARXML (AUTOSAR 4) or KCD file into the shared signal database model
+   * @param path Path to the file
+   * @param options Format (taken from the file extension by default) and KCD bus name
+   * @returns Database handle, usable with decodeFrame/encodeMessage/sendMessage
+   */
+  loadSignalDatabase(path: string, options?: SignalDatabaseOptions): number;
+
+  /**
+   * Parse a DBC, ARXML or KCD database from a string
+   * @param text File content
+   * @param options Format (required) and KCD bus name
+   * @returns Database handle
+   */
+  parseSignalDatabase(text: string, options: SignalDatabaseOptions & { format: SignalDatabaseFormat }): number;
+
+  /**
+   * Describe the messages and signals of a database
+   * @param dbcHandle Database handle
+   */
+  getDbcMessages(dbcHandle: number): DbcMessage[];
+
+  /**
+   * Decode a frame into physical signal values
+   * @param dbcHandle Database handle
+   * @param frame Frame returned by readFrame (extended defaults to id > 0x7FF)
+   * @returns Decoded message, or null if the database has no such identifier
+   */
+  decodeFrame(
+    dbcHandle: number,
+    frame: { id: number; data: number[]; extended?: boolean }
+  ): DecodedMessage | null;
+
+  /**
+   * Build the frame of a DBC message from physical signal values
+   *
+   * Signals that are not given use their GenSigStartValue (or 0). Values
+   * can also be value table labels.
+   * @param dbcHandle Database handle
+   * @param messageName Message name
+   * @param values Physical values by signal name
+   */
+  encodeMessage(
+    dbcHandle: number,
+    messageName: string,
+    values: Record<string, SignalInput>
+  ): { id: number; data: number[]; extended: boolean; fd: boolean };
+
+  /**
+   * Encode and send a DBC message
+   * @param socketId Socket ID
+   * @param messageName Message name
+   * @param values Physical values by signal name
+   * @param dbcHandle Database handle (optional if only one loaded database defines the message)
+   */
+  sendMessage(
+    socketId: number,
+    messageName: string,
+    values: Record<string, SignalInput>,
+    dbcHandle?: number
+  ): void;
+
+  /**
+   * Release a database
+   * @param dbcHandle Database handle
+   */
+  closeDbc(dbcHandle: number): void;
+
+  /**
+   * Create an OBD-II client sending functional requests on 0x7DF
+   * (or 0x18DB33F1 with 29-bit addressing)
+   * @param interfaceName CAN interface name
+   * @param options Addressing and response window
+   * @returns Client ID
+   */
+  createObdClient(interfaceName: string, options?: ObdClientOptions): number;
+
+  /**
+   * Read a mode 01 PID from every responding ECU
+   * @param clientId OBD client ID
+   * @param pid PID number
+   */
+  obdReadPid(clientId: number, pid: number): Promise<Array<ObdPidResponse | ObdErrorResponse>>;
+
+  /**
+   * Read a mode 02 freeze frame PID
+   * @param clientId OBD client ID
+   * @param pid PID number (0x02 gives the DTC that stored the frame)
+   * @param frame Freeze frame number (default 0)
+   */
+  obdReadFreezeFrame(
+    clientId: number,
+    pid: number,
+    frame?: number
+  ): Promise<Array<ObdPidResponse | ObdErrorResponse>>;
+
+  /**
+   * Read DTCs (mode 03, 07 or 0A)
+   * @param clientId OBD client ID
+   * @param kind DTC memory (default 'stored')
+   */
+  obdReadDtcs(
+    clientId: number,
+    kind?: ObdDtcKind
+  ): Promise<Array<ObdDtcResponse | ObdErrorResponse>>;
+
+  /**
+   * Read the VIN (mode 09, PID 02)
+   * @param clientId OBD client ID
+   */
+  obdReadVin(clientId: number): Promise<Array<{ ecu: number; vin: string } | ObdErrorResponse>>;
+
+  /**
+   * Read a mode 09 vehicle information record
+   * @param clientId OBD client ID
+   * @param pid Info type (0x02 VIN, 0x04 calibration ID, 0x0A ECU name...)
+   */
+  obdReadVehicleInfo(
+    clientId: number,
+    pid: number
+  ): Promise<Array<ObdVehicleInfoResponse | ObdErrorResponse>>;
+
+  /**
+   * Send a raw functional request (service byte first, at most 7 bytes)
+   * @param clientId OBD client ID
+   * @param request Request payload
+   * @returns Reassembled responses, negative responses included
+   */
+  obdRequest(clientId: number, request: Buffer): Promise<Array<{ ecu: number; data: Buffer }>>;
+
+  /**
+   * Close an OBD-II client
+   * @param clientId OBD client ID
+   */
+  closeObdClient(clientId: number): void;
+
+  /**
+   * Parse a can-utils `candump -l` log
+   * @param text Log contents
+   */
+  parseCandumpLog(text: string): TraceFrame[];
+
+  /**
+   * Read a can-utils `candump -l` log file
+   * @param path File path
+   */
+  readCandumpLog(path: string): TraceFrame[];
+
+  /**
+   * Format frames as a `candump -l` log
+   * @param frames Frames (interface defaults to 'can0', timestamp to 0)
+   */
+  formatCandumpLog(frames: TraceFrame[]): string;
+
+  /**
+   * Write frames to a `candump -l` log file
+   * @param path File path
+   * @param frames Frames to write
+   */
+  writeCandumpLog(path: string, frames: TraceFrame[]): void;
+
+  /**
+   * Log every frame of the socket's interface to a candump or pcapng file
+   *
+   * Frames are written from a native thread with kernel timestamps. The
+   * logger has its own socket: filters of the socket do not apply and
+   * error frames are included.
+   * @param socketId Socket ID
+   * @param path Log file path (truncated)
+   * @param options Log format, pcapng by default for .pcap/.pcapng paths
+   * @returns Logger ID
+   */
+  startLogging(socketId: number, path: string, options?: LoggingOptions): number;
+
+  /**
+   * Stop a logger and flush its file
+   * @param loggerId Logger ID
+   * @returns Number of logged frames
+   */
+  stopLogging(loggerId: number): number;
+
+  /**
+   * Parse a Vector ASC trace
+   *
+   * Interfaces are the ASC channel numbers ('1', '2'...).
+   * @param text Trace contents
+   */
+  parseAscLog(text: string): TraceFrame[];
+
+  /**
+   * Read a Vector ASC trace file
+   * @param path File path
+   */
+  readAscLog(path: string): TraceFrame[];
+
+  /**
+   * Format frames as a Vector ASC trace
+   *
+   * Numeric interfaces are kept as channels, other interfaces are numbered
+   * from 1 in order of appearance.
+   * @param frames Frames to write
+   */
+  formatAscLog(frames: TraceFrame[]): string;
+
+  /**
+   * Write frames to a Vector ASC trace file
+   * @param path File path
+   * @param frames Frames to write
+   */
+  writeAscLog(path: string, frames: TraceFrame[]): void;
+
+  /**
+   * Parse a Vector BLF trace (uncompressed or zlib containers)
+   * @param data File contents
+   */
+  parseBlfLog(data: Buffer): TraceFrame[];
+
+  /**
+   * Read a Vector BLF trace file
+   * @param path File path
+   */
+  readBlfLog(path: string): TraceFrame[];
+
+  /**
+   * Format frames as a Vector BLF trace, with the channel numbering of formatAscLog
+   * @param frames Frames to write
+   */
+  formatBlfLog(frames: TraceFrame[]): Buffer;
+
+  /**
+   * Write frames to a Vector BLF trace file
+   * @param path File path
+   * @param frames Frames to write
+   */
+  writeBlfLog(path: string, frames: TraceFrame[]): void;
+
+  /**
+   * Parse a PEAK TRC trace (versions 1.1 to 2.1)
+   *
+   * Interfaces are the bus numbers of files with a bus column.
+   * @param text Trace contents
+   */
+  parseTrcLog(text: string): TraceFrame[];
+
+  /**
+   * Read a PEAK TRC trace file
+   * @param path File path
+   */
+  readTrcLog(path: string): TraceFrame[];
+
+  /**
+   * Format frames as a version 2.1 TRC trace, with the bus numbering of formatAscLog
+   * @param frames Frames to write
+   */
+  formatTrcLog(frames: TraceFrame[]): string;
+
+  /**
+   * Write frames to a TRC trace file
+   * @param path File path
+   * @param frames Frames to write
+   */
+  writeTrcLog(path: string, frames: TraceFrame[]): void;
+
+  /**
+   * Parse an ASAM MDF 4 bus logging file (CAN_DataFrame, CAN_RemoteFrame
+   * and CAN_ErrorFrame channel groups)
+   *
+   * Interfaces are the bus channel numbers.
+   * @param data File contents
+   */
+  parseMdfLog(data: Buffer): TraceFrame[];
+
+  /**
+   * Read an ASAM MDF 4 bus logging file
+   * @param path File path
+   */
+  readMdfLog(path: string): TraceFrame[];
+
+  /**
+   * Format frames as an MDF 4.10 bus logging file, with the channel numbering of formatAscLog
+   * @param frames Frames to write
+   */
+  formatMdfLog(frames: TraceFrame[]): Buffer;
+
+  /**
+   * Write frames to an MDF 4 bus logging file
+   * @param path File path
+   * @param frames Frames to write
+   */
+  writeMdfLog(path: string, frames: TraceFrame[]): void;
+
+  /**
+   * Replay frames with their original inter-frame timing
+   *
+   * Frames are sent from a native thread, sleeping until shortly before
+   * each frame then busy-waiting. Error and CAN XL frames, and frames
+   * without a destination socket, are skipped.
+   * @param frames Frames to replay, ordered by timestamp
+   * @param options Destination sockets and playback options
+   * @returns Replay ID
+   */
+  startReplay(frames: TraceFrame[], options: ReplayOptions): number;
+
+  /**
+   * Pause a replay
+   * @param replayId Replay ID
+   */
+  pauseReplay(replayId: number): void;
+
+  /**
+   * Resume a paused replay
+   * @param replayId Replay ID
+   */
+  resumeReplay(replayId: number): void;
+
+  /**
+   * Continue a replay from the first frame at or after a trace time
+   * @param replayId Replay ID
+   * @param position Seconds from the first frame
+   */
+  seekReplay(replayId: number, position: number): void;
+
+  /**
+   * Change the speed factor of a replay
+   * @param replayId Replay ID
+   * @param speed Speed factor (2 plays twice as fast)
+   */
+  setReplaySpeed(replayId: number, speed: number): void;
+
+  /**
+   * Get the progress of a replay
+   * @param replayId Replay ID
+   */
+  getReplayStatus(replayId: number): ReplayStatus;
+
+  /**
+   * Stop a replay
+   * @param replayId Replay ID
+   * @returns Final status
+   */
+  stopReplay(replayId: number): ReplayStatus;
+
+  /**
+   * Parse a pcap or pcapng capture with LINKTYPE_CAN_SOCKETCAN packets
+   *
+   * Interfaces are the pcapng interface names; pcap frames have none.
+   * @param data File contents
+   */
+  parsePcap(data: Buffer): TraceFrame[];
+
+  /**
+   * Read a pcap or pcapng capture file
+   * @param path File path
+   */
+  readPcap(path: string): TraceFrame[];
+
+  /**
+   * Format frames as a pcapng capture, one interface block per interface
+   * and nanosecond timestamps
+   * @param frames Frames to write
+   */
+  formatPcapng(frames: TraceFrame[]): Buffer;
+
+  /**
+   * Write frames to a pcapng capture file
+   * @param path File path
+   * @param frames Frames to write
+   */
+  writePcapng(path: string, frames: TraceFrame[]): void;
+
+  /**
+   * List the CAN, vcan, vxcan and slcan interfaces (Linux only)
+   */
+  listInterfaces(): InterfaceInfo[];
+
+  /**
+   * Watch CAN interfaces being added, removed, brought up or down
+   * @param callback Called on each link-state change
+   * @returns Watcher ID
+   */
+  watchInterfaces(callback: (event: InterfaceEvent) => void): number;
+
+  /**
+   * Stop an interface watcher
+   * @param watcherId Watcher ID
+   */
+  unwatchInterfaces(watcherId: number): void;
+
+  /**
+   * Create a vcan interface (Linux only, needs CAP_NET_ADMIN)
+   * @param name Interface name
+   * @param options MTU and initial state
+   */
+  createVcan(name: string, options?: VirtualInterfaceOptions): InterfaceInfo;
+
+  /**
+   * Create a pair of connected vxcan interfaces
+   * @param name Interface name
+   * @param peer Name of the other end
+   * @param options MTU of both ends and initial state
+   */
+  createVxcan(name: string, peer: string, options?: VirtualInterfaceOptions): [InterfaceInfo, InterfaceInfo];
+
+  /**
+   * Bring an interface up or down
+   * @param name Interface name
+   * @param up True to bring the interface up
+   */
+  setInterfaceUp(name: string, up: boolean): void;
+
+  /**
+   * Delete a vcan or vxcan interface (deleting a vxcan end removes its peer);
+   * other links, physical or virtual, are refused
+   * @param name Interface name
+   */
+  deleteInterface(name: string): void;
+
+  /**
+   * Configure bit timing and control modes of a CAN controller (Linux only)
+   *
+   * The interface must be down and the caller needs CAP_NET_ADMIN. Bit
+   * rates are checked against the controller clock and bit timing limits.
+   * @param name Interface name
+   * @param options Settings to change, others are left unchanged
+   */
+  configureInterface(name: string, options: InterfaceConfig): void;
+
+  /**
+   * Read the controller state, error counters and traffic counters of a CAN
+   * interface (Linux only)
+   * @param name Interface name
+   */
+  getInterfaceStats(name: string): InterfaceStats;
+
+  /**
+   * Restart a CAN controller in bus-off state (Linux only). Fails when the
+   * controller is down or restarts by itself (restartMs is set).
+   * @param name Interface name
+   */
+  restartInterface(name: string): void;
+
+  /**
+   * Watch the socket's controller for bus-off and restart it with
+   * exponential back-off
+   * @param socketId Socket ID
+   * @param callback Called on each recovery step
+   * @param options Back-off delays
+   * @returns Supervisor ID
+   */
+  superviseBusOff(socketId: number, callback: (event: RecoveryEvent) => void, options?: BusOffSupervisorOptions): number;
+
+  /**
+   * Stop a bus-off supervisor
+   * @param supervisorId Supervisor ID
+   */
+  stopSupervisor(supervisorId: number): void;
+
+  /**
+   * Add a kernel CAN gateway (can-gw) rule (Linux only, needs CAP_NET_ADMIN)
+   * @param rule Source and destination interfaces, filter and modifications
+   */
+  addGatewayRule(rule: GatewayRule): void;
+
+  /**
+   * List the kernel CAN gateway rules with their counters
+   */
+  listGatewayRules(): GatewayRuleInfo[];
+
+  /**
+   * Delete the gateway rule with the same interfaces and modifications, or the same uid
+   * @param rule Rule to delete
+   */
+  deleteGatewayRule(rule: GatewayRule): void;
+
+  /**
+   * Delete all kernel CAN gateway rules
+   */
+  flushGatewayRules(): void;
+
+  /**
+   * Forward frames from one socket to another in a native thread
+   *
+   * Frames pass the ID filters, ID remapping, payload rewrites, the
+   * `onFrame` callback and the rate limit, in that order. Error frames are
+   * not forwarded. To bridge both directions, reuse the same two sockets so
+   * that forwarded frames are not read back.
+   * @param srcSocketId Socket frames are read from
+   * @param dstSocketId Socket frames are sent on
+   * @param options Filtering, rewriting and rate limiting options
+   * @returns Bridge ID
+   */
+  bridge(srcSocketId: number, dstSocketId: number, options?: BridgeOptions): number;
+
+  /**
+   * Get the counters and latency histogram of a bridge
+   * @param bridgeId Bridge ID
+   */
+  getBridgeStats(bridgeId: number): BridgeStats;
+
+  /**
+   * Stop a bridge
+   * @param bridgeId Bridge ID
+   * @returns Final counters
+   */
+  stopBridge(bridgeId: number): BridgeStats;
+
+  /**
+   * Connect to a bus of a socketcand server
+   *
+   * The returned socket ID works with sendFrame, readFrame, setFilters,
+   * clearFilters and closeSocket like a local socket. Only classic data
+   * frames can be sent; filters are applied on the client side.
+   * @param host Server host name or address
+   * @param port Server port (socketcand defaults to 29536)
+   * @param bus Bus name on the server (e.g. 'can0')
+   * @param options Reception mode and command timeout
+   * @returns Socket ID
+   */
+  connectSocketcand(host: string, port: number, bus: string, options?: SocketcandOptions): number;
+
+  /**
+   * Switch a socketcand connection between raw and broadcast manager reception
+   * @param socketId Socket ID returned by connectSocketcand
+   * @param mode 'raw' receives all frames, 'bcm' only the filtered identifiers
+   */
+  setSocketcandMode(socketId: number, mode: 'raw' | 'bcm'): void;
+
+  /**
+   * Add a broadcast manager filter, effective in 'bcm' mode
+   * @param socketId Socket ID returned by connectSocketcand
+   * @param filter Identifier, throttling interval and content mask
+   */
+  addSocketcandFilter(socketId: number, filter: SocketcandFilter): void;
+
+  /**
+   * Send an echo to the socketcand server
+   * @param socketId Socket ID returned by connectSocketcand
+   * @returns Round trip time in milliseconds
+   */
+  socketcandEcho(socketId: number): number;
+
+  /**
+   * Share local sockets with socketcand clients (Kayak, python-can...)
+   *
+   * Frames received on a socket are forwarded to the clients that opened
+   * its bus; frames sent by a client go out on the socket and to the other
+   * clients of the bus.
+   * @param buses Socket ID per bus name, e.g. { can0: socketId }
+   * @param options Listen address, client limit and access rules
+   * @returns Server ID
+   */
+  startSocketcandServer(buses: Record<string, number>, options?: SocketcandServerOptions): number;
+
+  /**
+   * Get the buses and connected clients of a socketcand server
+   * @param serverId Server ID
+   */
+  getSocketcandServerStatus(serverId: number): SocketcandServerStatus;
+
+  /**
+   * Stop a socketcand server and disconnect its clients
+   * @param serverId Server ID
+   */
+  stopSocketcandServer(serverId: number): void;
+}
+
+/**
+ * J1939 socket options
+ */
+export interface J1939Options {
+  /** 64-bit NAME used for address claiming */
+  name?: bigint | number;
+  /** Preferred source address (omit for receive-only sockets) */
+  address?: number;
+  /** Only receive this PGN */
+  pgn?: number;
+  /** Receive all bus traffic, not only messages addressed to us */
+  promiscuous?: boolean;
+  /** Default send priority (0 = highest, 7 = lowest) */
+  priority?: number;
+}
+
+/**
+ * J1939 NAME fields (J1939-81)
+ */
+export interface J1939NameFields {
+  identityNumber?: number;
+  manufacturerCode?: number;
+  ecuInstance?: number;
+  functionInstance?: number;
+  function?: number;
+  vehicleSystem?: number;
+  vehicleSystemInstance?: number;
+  industryGroup?: number;
+  arbitraryAddressCapable?: boolean;
+}
+
+/**
+ * Received J1939 message
+ */
+export interface J1939Message {
+  /** Parameter group number */
+  pgn: number;
+  /** Source address */
+  sa: number;
+  /** Destination address (0xFF for broadcast) */
+  da: number;
+  /** Message priority */
+  priority: number;
+  /** Message payload */
+  data: Buffer;
+  /** Source NAME, when known to the kernel */
+  name?: bigint;
+}
+
+/**
+ * UDS client timing options
+ */
+export interface UdsClientOptions {
+  /** P2 client timeout in milliseconds (default 1000) */
+  p2?: number;
+  /** P2* timeout after responsePending, in milliseconds (default 5000) */
+  p2Star?: number;
+  /**
+   * Adopt P2/P2* reported by DiagnosticSessionControl plus p2Margin, never
+   * below the p2/p2Star given here (default true)
+   */
+  useServerTiming?: boolean;
+  /** Network delay added to the server P2/P2* values, in milliseconds (default 50) */
+  p2Margin?: number;
+}
+
+/**
+ * Error thrown by UDS calls; negative responses carry the NRC
+ */
+export interface UdsNegativeResponseError extends Error {
+  /** Request service ID */
+  service?: number;
+  /** Negative response code */
+  nrc?: number;
+  /** ISO 14229 name of the negative response code */
+  nrcName?: string;
+}
+
+/**
+ * ISO-TP socket options
+ */
+export interface IsoTpOptions {
+  /** Use 29-bit tx/rx IDs */
+  extended?: boolean;
+  /** Padding byte for transmitted frames */
+  txPadding?: number;
+  /** Padding byte expected on received frames */
+  rxPadding?: number;
+  /** Block size sent in flow control frames (0 = unlimited) */
+  blockSize?: number;
+  /** Raw STmin byte sent in flow control frames */
+  stMin?: number;
+  /** Maximum number of FC.WAIT frames before aborting */
+  wftMax?: number;
+  /** Extended addressing: address byte for transmitted frames */
+  extAddress?: number;
+  /** Extended addressing: address byte of received frames */
+  rxExtAddress?: number;
+  /** Use CAN FD as link layer */
+  fd?: boolean;
+  /** FD transmit data length (8..64, default 64 with FD) */
+  txDataLength?: number;
+  /** Enable bit rate switch on FD frames (kernel engine only) */
+  brs?: boolean;
+  /** Use the userspace ISO-TP engine instead of the kernel module */
+  userspace?: boolean;
+  /** Userspace engine: N_As timeout in milliseconds (default 1000) */
+  nAs?: number;
+  /** Userspace engine: N_Bs timeout in milliseconds (default 1000) */
+  nBs?: number;
+  /** Userspace engine: N_Cr timeout in milliseconds (default 1000) */
+  nCr?: number;
 }
 
 /**
@@ -252,3 +1341,674 @@ export const CAN_CONSTANTS = {
  */
 export type CanData = number[];
 export type SocketId = number;
+
+/**
+ * Fields carried by a 29-bit J1939 identifier
+ */
+export interface J1939IdFields {
+  /** Message priority */
+  priority: number;
+  /** Parameter group number */
+  pgn: number;
+  /** Source address */
+  sa: number;
+  /** Destination address (0xFF for PDU2 broadcast PGNs) */
+  da: number;
+  /** 1 for destination-specific PGNs, 2 for broadcast PGNs */
+  pduFormat: 1 | 2;
+}
+
+/**
+ * SPN definition (bits are counted little-endian from the first data byte)
+ */
+export interface SpnDefinition {
+  spn: number;
+  pgn: number;
+  name?: string;
+  /** Position of the least significant bit (0-based) */
+  startBit: number;
+  /** Length in bits (1-32) */
+  length: number;
+  /** Resolution per bit (default 1) */
+  scale?: number;
+  /** Physical offset (default 0) */
+  offset?: number;
+  unit?: string;
+}
+
+/**
+ * Decoded SPN value
+ */
+export interface SpnValue {
+  spn: number;
+  name: string;
+  raw: number;
+  /** Physical value (raw * scale + offset) */
+  value: number;
+  unit: string;
+  /** J1939-71 error / not-available indicators */
+  status: "valid" | "error" | "notAvailable";
+}
+
+/**
+ * J1939 frame decoded against an SPN table
+ */
+export interface J1939DecodedFrame extends J1939IdFields {
+  spns: SpnValue[];
+}
+
+/**
+ * NMT command names
+ */
+export type NmtCommand =
+  | "start"
+  | "stop"
+  | "preOperational"
+  | "resetNode"
+  | "resetCommunication";
+
+/**
+ * NMT state reported by heartbeats
+ */
+export type NmtState =
+  | "unknown"
+  | "bootUp"
+  | "stopped"
+  | "operational"
+  | "preOperational";
+
+/**
+ * Monitored CANopen node
+ */
+export interface CanOpenNodeStatus {
+  nodeId: number;
+  state: NmtState;
+  /** Milliseconds since the last heartbeat (absent if never seen) */
+  lastSeenMs?: number;
+  /** Supervision timeout in milliseconds, if enabled */
+  heartbeatTimeout?: number;
+  timedOut: boolean;
+}
+
+/**
+ * CANopen node, SYNC or EMCY event
+ */
+export type CanOpenNodeEvent =
+  | {
+      type: "bootUp" | "stateChanged" | "heartbeatTimeout";
+      nodeId: number;
+      state?: NmtState;
+    }
+  | { type: "sync"; counter?: number }
+  | ({ type: "emergency" } & CanOpenEmergency);
+
+/**
+ * Decoded EMCY message
+ */
+export interface CanOpenEmergency {
+  nodeId: number;
+  errorCode: number;
+  errorRegister: number;
+  manufacturerData: Buffer;
+  /** Error code class description */
+  description: string;
+}
+
+/**
+ * SDO transfer options
+ */
+export interface SdoOptions {
+  /** Response timeout in milliseconds (default 1000) */
+  timeout?: number;
+  /** Use block transfer (default false) */
+  block?: boolean;
+  /** Segments per block, 1-127 (default 127) */
+  blockSize?: number;
+}
+
+/**
+ * Error rejected by SDO calls when the server aborts the transfer
+ */
+export interface SdoAbortError extends Error {
+  abortCode: number;
+  abortName: string;
+}
+
+/**
+ * PDO direction, seen from the device owning the dictionary
+ */
+export type PdoKind = "rpdo" | "tpdo";
+
+/**
+ * Value of a CANopen object
+ */
+export type CanOpenValue = number | bigint | boolean | string | Buffer;
+
+/**
+ * Object dictionary entry
+ */
+export interface CanOpenObject {
+  index: number;
+  subindex: number;
+  /** Parameter name, "Record.Parameter" for sub-objects */
+  name: string;
+  dataType: number;
+  access: string;
+  pdoMappable: boolean;
+  /** DCF ParameterValue or EDS DefaultValue */
+  value?: CanOpenValue;
+}
+
+/**
+ * PDO mapping definition
+ */
+export interface PdoMappingSpec {
+  kind: PdoKind;
+  /** PDO number (1-512) */
+  number: number;
+  /** COB-ID (bit 31 set = disabled) */
+  cobId: number;
+  /** Transmission type (default 0xFF) */
+  transmissionType?: number;
+  /** Object names, or index/sub-index with an optional length in bits */
+  entries: (string | { index: number; subindex?: number; bits?: number })[];
+}
+
+/**
+ * PDO mapping of a dictionary
+ */
+export interface PdoMappingInfo {
+  kind: PdoKind;
+  number: number;
+  cobId: number;
+  enabled: boolean;
+  transmissionType: number;
+  entries: { index: number; subindex: number; bits: number; name: string }[];
+}
+
+/**
+ * Decoded PDO frame
+ */
+export interface DecodedPdo {
+  kind: PdoKind;
+  number: number;
+  cobId: number;
+  values: Record<string, CanOpenValue>;
+}
+
+/**
+ * Signal database file format
+ */
+export type SignalDatabaseFormat = 'dbc' | 'arxml' | 'kcd';
+
+/**
+ * Options for loading a signal database
+ */
+export interface SignalDatabaseOptions {
+  format?: SignalDatabaseFormat;
+  /** KCD only: load the messages of this bus only */
+  bus?: string;
+}
+
+/**
+ * Signal definition of a DBC message
+ */
+export interface DbcSignal {
+  name: string;
+  startBit: number;
+  length: number;
+  byteOrder: 'little_endian' | 'big_endian';
+  valueType: 'unsigned' | 'signed' | 'float32' | 'float64';
+  factor: number;
+  offset: number;
+  minimum: number;
+  maximum: number;
+  unit: string;
+  receivers: string[];
+  /** Set on multiplexor switches */
+  isMultiplexor?: boolean;
+  /** Multiplexor value for which the signal is present */
+  multiplexerValue?: number;
+  /** Value table (raw value to description) */
+  choices?: Record<string, string>;
+  comment?: string;
+}
+
+/**
+ * Message definition of a DBC database
+ */
+export interface DbcMessage {
+  name: string;
+  id: number;
+  extended: boolean;
+  size: number;
+  transmitter?: string;
+  comment?: string;
+  signals: DbcSignal[];
+}
+
+/**
+ * Decoded signal value
+ */
+export interface DecodedSignal {
+  /** Physical value (raw * factor + offset) */
+  value: number;
+  raw: number;
+  unit: string;
+  /** Value table description of the raw value */
+  label?: string;
+}
+
+/**
+ * Signal value for encoding: physical value, boolean or value table label
+ */
+export type SignalInput = number | boolean | string;
+
+/**
+ * Frame decoded with a DBC database
+ */
+export interface DecodedMessage {
+  name: string;
+  id: number;
+  /** Signals present in the frame, by name */
+  signals: Record<string, DecodedSignal>;
+}
+
+/**
+ * OBD-II client options
+ */
+export interface ObdClientOptions {
+  /** Use 29-bit identifiers (default false) */
+  extended?: boolean;
+  /** Time to collect responses after a request, in ms (default 100) */
+  timeout?: number;
+}
+
+/**
+ * DTC memory read by obdReadDtcs
+ */
+export type ObdDtcKind = 'stored' | 'pending' | 'permanent';
+
+/**
+ * Decoded PID of one ECU
+ */
+export interface ObdPidResponse {
+  /** CAN identifier the ECU answered on */
+  ecu: number;
+  pid: number;
+  name: string;
+  unit: string;
+  /**
+   * Engineering value, supported PID list (PIDs 0x00, 0x20...), monitor
+   * status (PID 0x01), DTC (freeze frame PID 0x02) or raw bytes
+   */
+  value:
+    | number
+    | number[]
+    | { mil: boolean; dtcCount: number; compressionIgnition: boolean }
+    | string
+    | Buffer;
+}
+
+/**
+ * DTCs of one ECU (e.g. 'P0133')
+ */
+export interface ObdDtcResponse {
+  ecu: number;
+  dtcs: string[];
+}
+
+/**
+ * Vehicle information record of one ECU
+ */
+export interface ObdVehicleInfoResponse {
+  ecu: number;
+  pid: number;
+  /** Number of data items announced by the ECU */
+  count: number;
+  /** Printable text of the record */
+  text: string;
+  data: Buffer;
+}
+
+/**
+ * Failed response of one ECU
+ */
+export interface ObdErrorResponse {
+  ecu: number;
+  error: string;
+  /** Negative response code, if the ECU refused the request */
+  nrc?: number;
+  nrcName?: string;
+}
+
+/**
+ * Frame of a trace file
+ */
+export interface TraceFrame {
+  /** Timestamp in seconds */
+  timestamp?: number;
+  /** Interface or channel name */
+  interface?: string;
+  id: number;
+  data: number[];
+  extended?: boolean;
+  fd?: boolean;
+  /** CAN FD bit rate switch */
+  brs?: boolean;
+  /** CAN FD error state indicator */
+  esi?: boolean;
+  remote?: boolean;
+  /** Requested length of a remote frame */
+  dlc?: number;
+  /** Error frame, `id` holds the error class */
+  error?: boolean;
+  direction?: 'rx' | 'tx';
+  /** CAN XL frame, `id` holds the 11-bit priority */
+  xl?: boolean;
+  /** CAN XL SDU type */
+  sdt?: number;
+  /** CAN XL virtual CAN network ID */
+  vcid?: number;
+  /** CAN XL acceptance field */
+  af?: number;
+  /** CAN XL simple extended content */
+  sec?: boolean;
+}
+
+export interface LoggingOptions {
+  format?: 'candump' | 'pcapng';
+}
+
+export interface ReplayOptions {
+  /** Socket for frames whose interface is not in `channels` */
+  socketId?: number;
+  /** Socket per trace interface or channel name */
+  channels?: Record<string, number>;
+  /** Speed factor (default 1) */
+  speed?: number;
+  /** Loop until stopped (true) or play a number of passes */
+  loop?: boolean | number;
+  /** Identifier rewrites, keyed by decimal or '0x' hex identifier */
+  idMap?: Record<string, number>;
+}
+
+export interface ReplayStatus {
+  state: 'running' | 'paused' | 'finished';
+  /** Trace time of the last sent frame, in seconds from the first frame */
+  position: number;
+  /** Index of the next frame */
+  index: number;
+  sent: number;
+  /** Error, CAN XL and unrouted frames */
+  skipped: number;
+  /** Completed passes */
+  passes: number;
+  /** Send error that ended the replay */
+  error?: string;
+}
+
+export interface InterfaceInfo {
+  name: string;
+  index: number;
+  type: 'can' | 'vcan' | 'vxcan' | 'slcan' | string;
+  mtu: number;
+  /** Frame format derived from the MTU, null if not a CAN MTU */
+  mode: 'classic' | 'fd' | 'xl' | null;
+  /** Operational state (RFC 2863) */
+  state: 'unknown' | 'notpresent' | 'down' | 'lowerlayerdown' | 'testing' | 'dormant' | 'up';
+  /** Administratively up with carrier */
+  up: boolean;
+  /** Interface flags, e.g. ['UP', 'RUNNING', 'NOARP', 'ECHO'] */
+  flags: string[];
+}
+
+export interface InterfaceEvent {
+  type: 'added' | 'removed' | 'up' | 'down';
+  interface: InterfaceInfo;
+}
+
+export interface InterfaceConfig {
+  /** Nominal bitrate in bit/s */
+  bitrate?: number;
+  /** Nominal sample point as a fraction (0.875) */
+  samplePoint?: number;
+  /** CAN FD data bitrate in bit/s, requires `fd: true` */
+  dbitrate?: number;
+  /** Data sample point as a fraction */
+  dsamplePoint?: number;
+  fd?: boolean;
+  listenOnly?: boolean;
+  loopback?: boolean;
+  oneShot?: boolean;
+  berrReporting?: boolean;
+  /** Automatic bus-off restart delay in ms, 0 to disable */
+  restartMs?: number;
+  /** Termination resistor in Ohm, 0 to disable */
+  termination?: number;
+}
+
+export interface VirtualInterfaceOptions {
+  /** CAN MTU: 16, 72, 76..2060 or the frame format (default: kernel default) */
+  mtu?: number | 'classic' | 'fd' | 'xl';
+  /** Bring the interface up after creation (default true) */
+  up?: boolean;
+}
+
+export interface TrafficCounters {
+  packets: number;
+  bytes: number;
+  errors: number;
+  dropped: number;
+}
+
+export interface InterfaceStats {
+  /** Controller error state, null for virtual interfaces */
+  state: 'error-active' | 'error-warning' | 'error-passive' | 'bus-off' | 'stopped' | 'sleeping' | null;
+  /** TEC/REC, null when the driver does not report them */
+  errorCounters: { tx: number; rx: number } | null;
+  /** Controller event counters, null for virtual interfaces */
+  controller: {
+    busError: number;
+    errorWarning: number;
+    errorPassive: number;
+    busOff: number;
+    arbitrationLost: number;
+    restarts: number;
+  } | null;
+  rx: TrafficCounters;
+  tx: TrafficCounters;
+}
+
+export interface BusOffSupervisorOptions {
+  /** Delay before the first restart in ms (default 100) */
+  initialDelay?: number;
+  /** Upper bound of the doubling delay in ms (default 10000) */
+  maxDelay?: number;
+  /** Failed attempts before giving up (default: retry forever) */
+  maxAttempts?: number;
+  /** A bus-off this long after the last restart starts over at attempt 1, in ms (default 10000) */
+  resetAfter?: number;
+}
+
+export interface RecoveryEvent {
+  type: 'bus-off' | 'restart' | 'recovered' | 'gave-up';
+  /** Restart attempt, or the number of attempts for 'gave-up' */
+  attempt: number;
+  /** Delay before the restart in ms ('bus-off') */
+  delay?: number;
+  /** Restart failure ('restart') */
+  error?: string;
+}
+
+export interface FrameModification {
+  /** Applied by the kernel in the order and, or, xor, set */
+  op: 'and' | 'or' | 'xor' | 'set';
+  /** Raw CAN ID including the EFF/RTR flag bits */
+  id?: number;
+  /** DLC, or length of CAN FD frames */
+  dlc?: number;
+  /** CAN FD flags (BRS, ESI), fd rules only */
+  flags?: number;
+  /** Up to 8 bytes, 64 for fd rules */
+  data?: number[];
+}
+
+export interface XorChecksum {
+  /** First byte, negative indexes count from the end */
+  from: number;
+  /** Last byte */
+  to: number;
+  /** Byte receiving the checksum */
+  result: number;
+  init?: number;
+}
+
+export interface Crc8Checksum extends XorChecksum {
+  finalXor?: number;
+  /** Polynomial used to compute the table */
+  polynomial?: number;
+  /** 256-entry lookup table, instead of polynomial */
+  table?: number[];
+  profile?: 'none' | '1u8' | '16u8' | 'sffid-xor';
+  /** Up to 20 bytes of profile data */
+  profileData?: number[];
+}
+
+export interface GatewayRule {
+  src: string;
+  dst: string;
+  /** Receive filter, all frames by default */
+  filter?: { id: number; mask: number };
+  /** Keep the routed frame visible as local echo */
+  echo?: boolean;
+  /** Keep the source timestamp */
+  srcTimestamp?: boolean;
+  /** Allow sending back on the source interface */
+  allowIifTx?: boolean;
+  /** Route CAN FD frames */
+  fd?: boolean;
+  /** Hop limit */
+  hops?: number;
+  /** Identifier used to delete the rule */
+  uid?: number;
+  /** At most one modification per operation */
+  modifications?: FrameModification[];
+  xorChecksum?: XorChecksum;
+  crc8?: Crc8Checksum;
+}
+
+export interface GatewayRuleInfo extends GatewayRule {
+  handled: number;
+  dropped: number;
+  deleted: number;
+}
+
+export interface BridgeRewriteRule {
+  /** Frames whose `id & mask` equals `id & mask` (after remapping) */
+  id: number;
+  /** Default: exact match */
+  mask?: number;
+  /** First payload byte the operands apply to (default 0) */
+  offset?: number;
+  /** Operands applied in the order and, or, xor, set; bytes past the payload are ignored */
+  and?: number[];
+  or?: number[];
+  xor?: number[];
+  set?: number[];
+}
+
+export interface BridgeOptions {
+  /** Forward frames matching any filter, all frames if empty */
+  filters?: { id: number; mask?: number }[];
+  /** Identifier rewrites, keyed by decimal or '0x' hex identifier */
+  idMap?: Record<string, number>;
+  rewrite?: BridgeRewriteRule[];
+  /** Token bucket: `rate` frames per second, up to `burst` at once (default 1) */
+  rateLimit?: { rate: number; burst?: number };
+  /**
+   * Called with each frame: return false or null to drop it, a frame to
+   * forward instead, anything else to forward it unchanged
+   */
+  onFrame?: (frame: TraceFrame) => TraceFrame | boolean | null | undefined;
+}
+
+export interface BridgeStats {
+  received: number;
+  forwarded: number;
+  /** Sum of the drop reasons below */
+  dropped: number;
+  filtered: number;
+  rateLimited: number;
+  droppedByCallback: number;
+  sendErrors: number;
+  /** Forwarding latency in microseconds */
+  latency: {
+    min: number;
+    max: number;
+    mean: number;
+    /** Counts of latencies up to `le` microseconds, the last bucket is unbounded */
+    buckets: { le: number; count: number }[];
+  };
+  running: boolean;
+  /** Last send error, or the read error that stopped the bridge */
+  error?: string;
+}
+
+export interface SocketcandOptions {
+  /** Reception mode after opening the bus (default 'raw') */
+  mode?: 'raw' | 'bcm';
+  /** Connection and command reply timeout in ms (default 3000) */
+  timeout?: number;
+}
+
+export interface SocketcandFilter {
+  id: number;
+  /** Default: true for identifiers above 0x7FF */
+  extended?: boolean;
+  /** Minimum time between reports in ms (default 0) */
+  interval?: number;
+  /** Report only content changes under this data mask, instead of every frame */
+  mask?: number[];
+}
+
+export interface SocketcandClientRule {
+  /** Client address or network, e.g. '192.168.1.0/24' */
+  address: string;
+  /** Buses the client may open (default: all) */
+  buses?: string[];
+  /** Refuse frames sent by the client */
+  readOnly?: boolean;
+  /** Identifiers forwarded to the client (default: all) */
+  rxFilters?: { id: number; mask?: number }[];
+  /** Identifiers the client may send (default: all) */
+  txFilters?: { id: number; mask?: number }[];
+}
+
+export interface SocketcandServerOptions {
+  /** Listen address (default '127.0.0.1') */
+  host?: string;
+  /** Listen port (default 29536, 0 for any) */
+  port?: number;
+  maxClients?: number;
+  /**
+   * The first rule matching a client address applies; clients matching
+   * none are refused. Without rules every client has full access.
+   */
+  clients?: SocketcandClientRule[];
+}
+
+export interface SocketcandServerStatus {
+  port: number;
+  /** `error` is the read error that stopped the bus */
+  buses: { name: string; error?: string }[];
+  clients: {
+    address: string;
+    bus: string | null;
+    mode: 'raw' | 'bcm';
+    /** Frames forwarded to the client */
+    received: number;
+    /** Frames sent by the client */
+    sent: number;
+  }[];
+}